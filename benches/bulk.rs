@@ -0,0 +1,51 @@
+//! Benchmarks estimating the RTT to many peers at once.
+//!
+//! ```text
+//! cargo bench --bench bulk
+//! ```
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use vivaldi::{
+    estimate_rtt_many,
+    vector::{Dimension3, DimensionN, Vector},
+    Coordinate, PeerTable,
+};
+
+const PEERS: &[usize] = &[100, 10_000];
+
+fn bench_bulk<V: Vector + std::fmt::Debug>(c: &mut Criterion, name: &str) {
+    let mut rng = StdRng::seed_from_u64(42);
+    let coordinate = |rng: &mut StdRng| {
+        Coordinate::try_new(V::random(rng), 0.5, rng.gen_range(0.0..0.01))
+            .expect("random coordinates are valid")
+    };
+    let local = coordinate(&mut rng);
+
+    let mut group = c.benchmark_group(name);
+    for &n in PEERS {
+        let remotes = (0..n).map(|_| coordinate(&mut rng)).collect::<Vec<_>>();
+        let mut table = PeerTable::new();
+        for (i, c) in remotes.iter().enumerate() {
+            table.insert(i, c);
+        }
+
+        group.bench_with_input(
+            BenchmarkId::new("estimate_rtt_many", n),
+            &remotes,
+            |b, r| b.iter(|| estimate_rtt_many(black_box(&local), r)),
+        );
+        group.bench_with_input(BenchmarkId::new("estimate_all", n), &table, |b, t| {
+            b.iter(|| t.estimate_all(black_box(&local)).count())
+        });
+    }
+    group.finish();
+}
+
+fn bulk(c: &mut Criterion) {
+    bench_bulk::<Dimension3>(c, "Dimension3");
+    bench_bulk::<DimensionN<8>>(c, "DimensionN<8>");
+}
+
+criterion_group!(benches, bulk);
+criterion_main!(benches);
@@ -0,0 +1,62 @@
+//! Benchmarks the vector arithmetic of the model.
+//!
+//! Compare runs with and without the `simd` feature to measure the speedup:
+//!
+//! ```text
+//! cargo bench --bench vector -- --save-baseline scalar
+//! cargo bench --bench vector --features simd -- --baseline scalar
+//! ```
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use std::time::Duration;
+use vivaldi::{
+    vector::{Dimension3, DimensionN, Vector},
+    Model,
+};
+
+fn bench_vector<V: Vector + Copy>(c: &mut Criterion, name: &str, a: V, b: V) {
+    let mut group = c.benchmark_group(name);
+    group.bench_function("magnitude", |bench| bench.iter(|| black_box(a).magnitude()));
+    group.bench_function("distance", |bench| {
+        bench.iter(|| black_box(a).distance(&black_box(b)))
+    });
+    group.bench_function("add", |bench| bench.iter(|| black_box(a) + black_box(b)));
+    group.bench_function("sub", |bench| bench.iter(|| black_box(a) - black_box(b)));
+    group.bench_function("mul", |bench| bench.iter(|| black_box(a) * black_box(1.5)));
+    group.bench_function("div", |bench| bench.iter(|| black_box(a) / black_box(1.5)));
+    group.finish();
+}
+
+fn bench_observe<V: Vector + Copy + std::fmt::Debug>(c: &mut Criterion, name: &str) {
+    let mut a = Model::<V>::new();
+    let mut b = Model::<V>::new();
+    let rtt = Duration::from_millis(10);
+
+    c.bench_function(&format!("{}/observe", name), |bench| {
+        bench.iter(|| {
+            a.observe(b.get_coordinate(), black_box(rtt));
+            b.observe(a.get_coordinate(), black_box(rtt));
+        })
+    });
+}
+
+fn vectors(c: &mut Criterion) {
+    bench_vector(
+        c,
+        "Dimension3",
+        Dimension3([1.0, 2.0, 3.0]),
+        Dimension3([0.5, -1.0, 0.25]),
+    );
+    bench_vector(
+        c,
+        "DimensionN<8>",
+        DimensionN([1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0]),
+        DimensionN([0.5, -1.0, 0.25, 2.0, -0.5, 1.0, 0.75, -2.0]),
+    );
+
+    bench_observe::<Dimension3>(c, "Dimension3");
+    bench_observe::<DimensionN<8>>(c, "DimensionN<8>");
+}
+
+criterion_group!(benches, vectors);
+criterion_main!(benches);
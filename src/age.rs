@@ -0,0 +1,124 @@
+use crate::clock::Clock;
+use crate::coordinate::Coordinate;
+use crate::model::{estimate_rtt, saturating_duration};
+use crate::peer_table::PeerTable;
+use crate::vector::Vector;
+use std::hash::Hash;
+use std::time::Duration;
+
+/// An RTT estimate with bounds widened by the age of the remote coordinate,
+/// returned by [`PeerTable::estimate_aged`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AgedEstimate {
+    /// The estimated round-trip time.
+    pub rtt: Duration,
+
+    /// The relative error of the estimate - the sum of the error estimates of
+    /// both coordinates, inflated by the age of the remote coordinate.
+    pub error: f64,
+
+    /// The lower bound of the estimate, `rtt * (1 - error)` (saturating at
+    /// zero).
+    pub low: Duration,
+
+    /// The upper bound of the estimate, `rtt * (1 + error)`.
+    pub high: Duration,
+
+    /// How long ago the remote coordinate was inserted into the table.
+    pub age: Duration,
+}
+
+impl<K, V, C> PeerTable<K, V, C>
+where
+    K: Hash + Eq + Clone,
+    V: Vector,
+    C: Clock,
+{
+    /// Returns the estimated RTT between `local` and `peer`, with the error of
+    /// the remote coordinate inflated by `drift` for each second since it was
+    /// last [updated](PeerTable::updated).
+    ///
+    /// The coordinate of a peer that has not been heard from in an hour is
+    /// likely to have moved, but [`estimate_rtt`] treats it as confidently as
+    /// one received a second ago. This estimate widens its bounds as the
+    /// coordinate ages instead:
+    ///
+    /// ```
+    /// use vivaldi::{Model, PeerTable, vector::Dimension3};
+    ///
+    /// let local = Model::<Dimension3>::new();
+    /// # let remote = Model::<Dimension3>::new();
+    ///
+    /// let mut peers = PeerTable::new();
+    /// peers.insert("remote", remote.get_coordinate());
+    ///
+    /// // Add 1% relative error per minute of age.
+    /// let estimate = peers
+    ///     .estimate_aged(local.get_coordinate(), &"remote", 0.01 / 60.0)
+    ///     .unwrap();
+    /// println!("{:?} to {:?}", estimate.low, estimate.high);
+    /// ```
+    ///
+    /// A suitable `drift` depends on how quickly coordinates of converged
+    /// nodes move in a given deployment.
+    ///
+    /// Returns `None` if `peer` is not in the table.
+    pub fn estimate_aged(
+        &self,
+        local: &Coordinate<V>,
+        peer: &K,
+        drift: f64,
+    ) -> Option<AgedEstimate> {
+        let remote = self.get(peer)?;
+        let age = self
+            .clock()
+            .now()
+            .saturating_sub(self.updated(peer).expect("peer is in the table"));
+
+        let rtt = estimate_rtt(local, &remote);
+        let error = local.error() + remote.error() + drift.max(0.0) * age.as_secs_f64();
+
+        Some(AgedEstimate {
+            rtt,
+            error,
+            low: saturating_duration(rtt.as_secs_f64() * (1.0 - error)),
+            high: saturating_duration(rtt.as_secs_f64() * (1.0 + error)),
+            age,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use crate::vector::Dimension3;
+
+    #[test]
+    fn inflates_with_age() {
+        let clock = MockClock::new(Duration::from_secs(100));
+        let mut t = PeerTable::with_clock(clock.clone());
+        let local = Coordinate::new(Dimension3([0.0, 0.0, 0.0]), 0.1, 0.0);
+        t.insert("a", &Coordinate::new(Dimension3([0.1, 0.0, 0.0]), 0.1, 0.0));
+
+        let fresh = t.estimate_aged(&local, &"a", 0.01).unwrap();
+        assert_eq!(fresh.age, Duration::ZERO);
+        assert_eq!(fresh.rtt, estimate_rtt(&local, &t.get(&"a").unwrap()));
+        assert!((fresh.error - 0.2).abs() < 1e-9);
+        assert!(fresh.low < fresh.rtt && fresh.rtt < fresh.high);
+
+        clock.advance(Duration::from_secs(30));
+        let stale = t.estimate_aged(&local, &"a", 0.01).unwrap();
+        assert_eq!(stale.age, Duration::from_secs(30));
+        assert_eq!(stale.rtt, fresh.rtt);
+        assert!((stale.error - 0.5).abs() < 1e-9);
+
+        // The lower bound saturates at zero.
+        clock.advance(Duration::from_secs(100));
+        let old = t.estimate_aged(&local, &"a", 0.01).unwrap();
+        assert_eq!(old.low, Duration::ZERO);
+        assert!(old.high > stale.high);
+
+        assert!(t.estimate_aged(&local, &"b", 0.01).is_none());
+    }
+}
@@ -0,0 +1,376 @@
+use crate::coordinate::Coordinate;
+use crate::vector::Vector;
+
+/// The maximum number of one-sided Jacobi sweeps used to decompose the
+/// cross-covariance matrix.
+const SVD_SWEEPS: usize = 100;
+
+/// The rigid transform (rotation and translation) best mapping one set of
+/// coordinates onto another, returned by [`align`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alignment {
+    dims: usize,
+    /// The row-major rotation matrix.
+    rotation: Vec<f64>,
+    translation: Vec<f64>,
+    residuals: Vec<f64>,
+}
+
+impl Alignment {
+    /// Returns row `i` of the rotation matrix, which has one row and column
+    /// per dimension.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `i` is not less than the number of dimensions.
+    pub fn rotation_row(&self, i: usize) -> &[f64] {
+        &self.rotation[i * self.dims..(i + 1) * self.dims]
+    }
+
+    /// Returns the translation applied after the rotation.
+    pub fn translation(&self) -> &[f64] {
+        &self.translation
+    }
+
+    /// Returns the distance (in seconds) between each aligned coordinate and
+    /// its counterpart, in the order given to [`align`].
+    pub fn residuals(&self) -> &[f64] {
+        &self.residuals
+    }
+
+    /// Returns the root-mean-square of the [`residuals`](Alignment::residuals).
+    pub fn rmsd(&self) -> f64 {
+        let n = self.residuals.len() as f64;
+        (self.residuals.iter().map(|r| r * r).sum::<f64>() / n).sqrt()
+    }
+
+    /// Applies the transform to `coord`, keeping its error estimate and
+    /// height.
+    pub fn apply<V: Vector>(&self, coord: &Coordinate<V>) -> Coordinate<V> {
+        let x = coord.vector().components();
+        let out = (0..self.dims)
+            .map(|i| {
+                self.rotation_row(i)
+                    .iter()
+                    .zip(x)
+                    .map(|(r, v)| r * v)
+                    .sum::<f64>()
+                    + self.translation[i]
+            })
+            .collect::<Vec<_>>();
+
+        let vector = V::from_components(&out).expect("alignment dimensionality matches");
+        Coordinate::new(vector, coord.error(), coord.height())
+    }
+}
+
+/// Computes the rotation and translation that best maps the coordinates in
+/// `from` onto their counterparts (at the same index) in `to`, minimising the
+/// sum of squared distances between them (the orthogonal Procrustes problem,
+/// solved with the Kabsch algorithm).
+///
+/// Vivaldi coordinates are only meaningful relative to each other - two runs
+/// over the same network converge on layouts differing by an arbitrary
+/// rotation and translation, so the coordinates cannot be compared directly.
+/// Aligning one set onto the other removes this difference, leaving
+/// [`residuals`](Alignment::residuals) that reflect genuine disagreement
+/// between the two embeddings:
+///
+/// ```
+/// use vivaldi::{align, Model, vector::Dimension3};
+///
+/// # let run_a = (0..4).map(|_| Model::<Dimension3>::new()).collect::<Vec<_>>();
+/// # let run_b = run_a.clone();
+/// let a = run_a.iter().map(|m| *m.get_coordinate()).collect::<Vec<_>>();
+/// let b = run_b.iter().map(|m| *m.get_coordinate()).collect::<Vec<_>>();
+///
+/// if let Some(alignment) = align(&a, &b) {
+///     println!("rms disagreement: {:.3}s", alignment.rmsd());
+///     let aligned = alignment.apply(&a[0]);
+/// }
+/// ```
+///
+/// The rotation never includes a reflection, and heights are not aligned as
+/// they do not form part of the Euclidean space. Returns `None` if the two
+/// sets differ in length or are empty.
+pub fn align<V: Vector>(from: &[Coordinate<V>], to: &[Coordinate<V>]) -> Option<Alignment> {
+    if from.len() != to.len() || from.is_empty() {
+        return None;
+    }
+
+    let dims = V::default().components().len();
+    let centroid = |coords: &[Coordinate<V>]| {
+        let n = coords.len() as f64;
+        let mut c = vec![0.0; dims];
+        for coord in coords {
+            for (c, v) in c.iter_mut().zip(coord.vector().components()) {
+                *c += v / n;
+            }
+        }
+        c
+    };
+    let (ca, cb) = (centroid(from), centroid(to));
+
+    // The cross-covariance matrix H = Σ (a - ca)(b - cb)ᵀ, stored as columns.
+    let mut h = vec![vec![0.0; dims]; dims];
+    for (a, b) in from.iter().zip(to) {
+        let (a, b) = (a.vector().components(), b.vector().components());
+        for (j, col) in h.iter_mut().enumerate() {
+            for (i, v) in col.iter_mut().enumerate() {
+                *v += (a[i] - ca[i]) * (b[j] - cb[j]);
+            }
+        }
+    }
+
+    // H = U Σ Vᵀ, and the optimal rotation is R = V D Uᵀ where D flips the
+    // axis of the smallest singular value if required to avoid a reflection.
+    let (mut u, sigma, v) = svd(h);
+    if determinant(&u) * determinant(&v) < 0.0 {
+        let smallest = (0..dims)
+            .min_by(|a, b| sigma[*a].total_cmp(&sigma[*b]))
+            .expect("at least one dimension");
+        for x in u[smallest].iter_mut() {
+            *x = -*x;
+        }
+    }
+
+    let mut rotation = vec![0.0; dims * dims];
+    for i in 0..dims {
+        for j in 0..dims {
+            rotation[i * dims + j] = (0..dims).map(|k| v[k][i] * u[k][j]).sum();
+        }
+    }
+
+    let translation = (0..dims)
+        .map(|i| {
+            cb[i]
+                - (0..dims)
+                    .map(|j| rotation[i * dims + j] * ca[j])
+                    .sum::<f64>()
+        })
+        .collect();
+
+    let mut alignment = Alignment {
+        dims,
+        rotation,
+        translation,
+        residuals: Vec::new(),
+    };
+    alignment.residuals = from
+        .iter()
+        .zip(to)
+        .map(|(a, b)| alignment.apply(a).vector().distance(b.vector()).0)
+        .collect();
+
+    Some(alignment)
+}
+
+/// Computes the singular value decomposition `A = U Σ Vᵀ` of the square matrix
+/// `a` (given as columns) using one-sided Jacobi rotations, returning the
+/// columns of `U`, the singular values and the columns of `V`.
+///
+/// Columns of `U` for zero singular values are completed to an orthonormal
+/// basis.
+fn svd(mut a: Vec<Vec<f64>>) -> (Vec<Vec<f64>>, Vec<f64>, Vec<Vec<f64>>) {
+    let n = a.len();
+    let mut v = (0..n)
+        .map(|j| (0..n).map(|i| if i == j { 1.0 } else { 0.0 }).collect())
+        .collect::<Vec<Vec<f64>>>();
+
+    for _ in 0..SVD_SWEEPS {
+        let mut rotated = false;
+        for p in 0..n {
+            for q in p + 1..n {
+                let alpha = dot(&a[p], &a[p]);
+                let beta = dot(&a[q], &a[q]);
+                let gamma = dot(&a[p], &a[q]);
+                if gamma.abs() <= f64::EPSILON * (alpha * beta).sqrt() || gamma == 0.0 {
+                    continue;
+                }
+                rotated = true;
+
+                // Rotate columns p and q to make them orthogonal.
+                let zeta = (beta - alpha) / (2.0 * gamma);
+                let t = zeta.signum() / (zeta.abs() + (1.0 + zeta * zeta).sqrt());
+                let c = 1.0 / (1.0 + t * t).sqrt();
+                let s = c * t;
+                for m in [&mut a, &mut v] {
+                    let (lo, hi) = m.split_at_mut(q);
+                    for (xp, xq) in lo[p].iter_mut().zip(hi[0].iter_mut()) {
+                        let (vp, vq) = (*xp, *xq);
+                        *xp = c * vp - s * vq;
+                        *xq = s * vp + c * vq;
+                    }
+                }
+            }
+        }
+        if !rotated {
+            break;
+        }
+    }
+
+    let sigma = a.iter().map(|c| dot(c, c).sqrt()).collect::<Vec<_>>();
+    let max = sigma.iter().cloned().fold(0.0, f64::max);
+    let tolerance = max * n as f64 * f64::EPSILON;
+
+    // Normalise the columns with non-zero singular values, then complete the
+    // remainder with basis vectors orthogonalised against them.
+    let mut u = vec![None; n];
+    for (j, col) in a.into_iter().enumerate() {
+        if max > 0.0 && sigma[j] > tolerance {
+            u[j] = Some(col.iter().map(|x| x / sigma[j]).collect::<Vec<_>>());
+        }
+    }
+    for j in 0..n {
+        if u[j].is_some() {
+            continue;
+        }
+        let completed = (0..n)
+            .map(|k| {
+                let mut e = (0..n)
+                    .map(|i| if i == k { 1.0 } else { 0.0 })
+                    .collect::<Vec<_>>();
+                for other in u.iter().flatten() {
+                    let d = dot(&e, other);
+                    for (x, o) in e.iter_mut().zip(other) {
+                        *x -= d * o;
+                    }
+                }
+                e
+            })
+            .max_by(|a, b| dot(a, a).total_cmp(&dot(b, b)))
+            .expect("at least one dimension");
+        let norm = dot(&completed, &completed).sqrt();
+        u[j] = Some(completed.iter().map(|x| x / norm).collect());
+    }
+
+    (u.into_iter().flatten().collect(), sigma, v)
+}
+
+/// Returns the determinant of the square matrix `m` (given as columns) by
+/// Gaussian elimination with partial pivoting.
+fn determinant(m: &[Vec<f64>]) -> f64 {
+    let mut m = m.to_vec();
+    let n = m.len();
+    let mut det = 1.0;
+    for col in 0..n {
+        let pivot = (col..n)
+            .max_by(|a, b| m[*a][col].abs().total_cmp(&m[*b][col].abs()))
+            .expect("non-empty range");
+        if m[pivot][col] == 0.0 {
+            return 0.0;
+        }
+        if pivot != col {
+            m.swap(pivot, col);
+            det = -det;
+        }
+        det *= m[col][col];
+        for row in col + 1..n {
+            let f = m[row][col] / m[col][col];
+            let (lo, hi) = m.split_at_mut(row);
+            for (x, p) in hi[0].iter_mut().zip(&lo[col]).skip(col) {
+                *x -= f * p;
+            }
+        }
+    }
+    det
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::{Dimension2, Dimension3};
+
+    fn coord(v: [f64; 3]) -> Coordinate<Dimension3> {
+        Coordinate::new(Dimension3(v), 0.5, 0.1)
+    }
+
+    /// Rotates `v` by `theta` about the z axis and then by `phi` about the x
+    /// axis, then translates it.
+    fn transform(v: [f64; 3], theta: f64, phi: f64) -> [f64; 3] {
+        let (s, c) = theta.sin_cos();
+        let v = [c * v[0] - s * v[1], s * v[0] + c * v[1], v[2]];
+        let (s, c) = phi.sin_cos();
+        let v = [v[0], c * v[1] - s * v[2], s * v[1] + c * v[2]];
+        [v[0] + 1.0, v[1] - 2.0, v[2] + 0.5]
+    }
+
+    #[test]
+    fn recovers_rigid_transform() {
+        let points = [
+            [0.0, 0.0, 0.0],
+            [1.0, 0.2, -0.3],
+            [-0.5, 2.0, 0.1],
+            [0.3, -1.0, 1.5],
+            [2.0, 1.0, 1.0],
+        ];
+        let from = points.iter().map(|p| coord(*p)).collect::<Vec<_>>();
+        let to = points
+            .iter()
+            .map(|p| coord(transform(*p, 0.7, -1.2)))
+            .collect::<Vec<_>>();
+
+        let got = align(&from, &to).unwrap();
+        assert!(got.rmsd() < 1e-9, "{:?}", got);
+        for (a, b) in from.iter().zip(&to) {
+            let aligned = got.apply(a);
+            assert!(aligned.vector().distance(b.vector()).0 < 1e-9);
+            assert_eq!(aligned.error(), a.error());
+            assert_eq!(aligned.height(), a.height());
+        }
+    }
+
+    #[test]
+    fn coplanar_points() {
+        // Points in the z = 0 plane leave one singular value at zero.
+        let points = [
+            [0.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0],
+            [0.0, 3.0, 0.0],
+            [2.0, 1.0, 0.0],
+        ];
+        let from = points.iter().map(|p| coord(*p)).collect::<Vec<_>>();
+        let to = points
+            .iter()
+            .map(|p| coord(transform(*p, 2.0, 0.4)))
+            .collect::<Vec<_>>();
+
+        let got = align(&from, &to).unwrap();
+        assert!(got.rmsd() < 1e-9, "{:?}", got);
+        let rows = (0..3)
+            .map(|i| got.rotation_row(i).to_vec())
+            .collect::<Vec<_>>();
+        assert!((determinant(&rows) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn never_reflects() {
+        let points = [[1.0, 0.0], [0.0, 2.0], [-1.0, -1.0], [3.0, 1.0]];
+        let from = points
+            .iter()
+            .map(|p| Coordinate::new(Dimension2(*p), 0.5, 0.1))
+            .collect::<Vec<_>>();
+        let mirrored = points
+            .iter()
+            .map(|p| Coordinate::new(Dimension2([-p[0], p[1]]), 0.5, 0.1))
+            .collect::<Vec<_>>();
+
+        let got = align(&from, &mirrored).unwrap();
+        let rows = (0..2)
+            .map(|i| got.rotation_row(i).to_vec())
+            .collect::<Vec<_>>();
+        assert!((determinant(&rows) - 1.0).abs() < 1e-9);
+        assert!(got.rmsd() > 0.1);
+        assert_eq!(got.residuals().len(), 4);
+    }
+
+    #[test]
+    fn invalid_input() {
+        assert!(align::<Dimension3>(&[], &[]).is_none());
+        assert!(align(&[coord([0.0; 3])], &[]).is_none());
+    }
+}
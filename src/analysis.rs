@@ -0,0 +1,199 @@
+use crate::coordinate::Coordinate;
+use crate::vector::Vector;
+
+/// The maximum number of Jacobi rotation sweeps used to diagonalise the
+/// covariance matrix.
+const JACOBI_SWEEPS: usize = 100;
+
+/// The result of a principal component analysis of a set of coordinates,
+/// returned by [`analyse_dimensions`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct DimensionAnalysis {
+    variance: Vec<f64>,
+}
+
+impl DimensionAnalysis {
+    /// Returns the variance captured by each principal component, in
+    /// descending order.
+    pub fn variance(&self) -> &[f64] {
+        &self.variance
+    }
+
+    /// Returns the fraction of the total variance captured by each principal
+    /// component, in descending order.
+    pub fn explained(&self) -> Vec<f64> {
+        let total = self.variance.iter().sum::<f64>();
+        if total <= 0.0 {
+            return vec![0.0; self.variance.len()];
+        }
+        self.variance.iter().map(|v| v / total).collect()
+    }
+
+    /// Returns the smallest number of dimensions that together capture at
+    /// least `fraction` (0 to 1) of the total variance.
+    pub fn dimensions_for(&self, fraction: f64) -> usize {
+        let mut sum = 0.0;
+        for (i, v) in self.explained().iter().enumerate() {
+            sum += v;
+            if sum >= fraction {
+                return i + 1;
+            }
+        }
+        self.variance.len()
+    }
+}
+
+/// Runs a principal component analysis over `coords`, reporting the variance
+/// of the coordinates along each principal axis.
+///
+/// The Vivaldi paper uses this analysis to show most real-world latency
+/// matrices embed in 2 or 3 dimensions. Running it over the coordinates of a
+/// converged cluster shows whether the configured dimensions are all used - a
+/// component capturing a negligible fraction of the variance is a dimension
+/// paying for itself in bandwidth and CPU but contributing nothing, while a
+/// significant smallest component suggests more dimensions may improve
+/// accuracy.
+///
+/// ```
+/// use vivaldi::{analyse_dimensions, Model, vector::Dimension3};
+///
+/// # let models = (0..3).map(|_| Model::<Dimension3>::new()).collect::<Vec<_>>();
+/// let coords = models.iter().map(|m| *m.get_coordinate()).collect::<Vec<_>>();
+///
+/// if let Some(analysis) = analyse_dimensions(&coords) {
+///     println!("captured variance: {:?}", analysis.explained());
+///     println!("dimensions for 95%: {}", analysis.dimensions_for(0.95));
+/// }
+/// ```
+///
+/// Heights are not included, as they do not form part of the Euclidean space.
+/// Returns `None` if fewer than two coordinates are given.
+pub fn analyse_dimensions<V: Vector>(coords: &[Coordinate<V>]) -> Option<DimensionAnalysis> {
+    if coords.len() < 2 {
+        return None;
+    }
+
+    let dims = V::default().components().len();
+    let n = coords.len() as f64;
+
+    let mut mean = vec![0.0; dims];
+    for c in coords {
+        for (m, v) in mean.iter_mut().zip(c.vector().components()) {
+            *m += v / n;
+        }
+    }
+
+    // Sample covariance matrix.
+    let mut cov = vec![vec![0.0; dims]; dims];
+    for c in coords {
+        let v = c.vector().components();
+        for i in 0..dims {
+            for j in 0..dims {
+                cov[i][j] += (v[i] - mean[i]) * (v[j] - mean[j]) / (n - 1.0);
+            }
+        }
+    }
+
+    let mut variance = eigenvalues(cov);
+    variance.sort_by(|a, b| b.total_cmp(a));
+    Some(DimensionAnalysis { variance })
+}
+
+/// Returns the eigenvalues of the symmetric matrix `a` using the cyclic Jacobi
+/// eigenvalue algorithm.
+fn eigenvalues(mut a: Vec<Vec<f64>>) -> Vec<f64> {
+    let n = a.len();
+    for _ in 0..JACOBI_SWEEPS {
+        let off = (0..n)
+            .flat_map(|i| (0..n).filter(move |j| *j != i).map(move |j| (i, j)))
+            .map(|(i, j)| a[i][j] * a[i][j])
+            .sum::<f64>();
+        if off < f64::EPSILON * f64::EPSILON {
+            break;
+        }
+
+        for p in 0..n {
+            for q in p + 1..n {
+                if a[p][q] == 0.0 {
+                    continue;
+                }
+
+                // Rotate rows/columns p and q to zero a[p][q].
+                let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let t = if theta == 0.0 { 1.0 } else { t };
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+
+                for row in a.iter_mut() {
+                    let (kp, kq) = (row[p], row[q]);
+                    row[p] = c * kp - s * kq;
+                    row[q] = s * kp + c * kq;
+                }
+                let (lo, hi) = a.split_at_mut(q);
+                for (pk, qk) in lo[p].iter_mut().zip(hi[0].iter_mut()) {
+                    let (vp, vq) = (*pk, *qk);
+                    *pk = c * vp - s * vq;
+                    *qk = s * vp + c * vq;
+                }
+            }
+        }
+    }
+
+    (0..n).map(|i| a[i][i]).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::Dimension3;
+
+    fn coord(x: f64, y: f64, z: f64) -> Coordinate<Dimension3> {
+        Coordinate::new(Dimension3([x, y, z]), 1.0, 0.0)
+    }
+
+    #[test]
+    fn planar_coordinates() {
+        // Points on the plane x = y, z = 0 with unequal spread along the
+        // diagonal and perpendicular to it.
+        let coords = (0..50)
+            .map(|i| {
+                let t = i as f64 - 25.0;
+                let u = if i % 2 == 0 { 1.0 } else { -1.0 };
+                coord(t + u, t - u, 0.0)
+            })
+            .collect::<Vec<_>>();
+
+        let got = analyse_dimensions(&coords).unwrap();
+        assert_eq!(got.variance().len(), 3);
+        assert!(got.variance()[2].abs() < 1e-9, "{:?}", got);
+        assert!(got.variance()[0] > got.variance()[1]);
+
+        let total = got.explained().iter().sum::<f64>();
+        assert!((total - 1.0).abs() < 1e-9);
+        assert_eq!(got.dimensions_for(0.999), 2);
+        assert_eq!(got.dimensions_for(0.99), 1);
+    }
+
+    #[test]
+    fn matches_axis_variance() {
+        let coords = vec![
+            coord(-2.0, -1.0, 0.0),
+            coord(2.0, 1.0, 0.0),
+            coord(-2.0, 1.0, 0.0),
+            coord(2.0, -1.0, 0.0),
+        ];
+        let got = analyse_dimensions(&coords).unwrap();
+
+        // Sample variance along each axis: 16/3, 4/3 and 0.
+        let want = [16.0 / 3.0, 4.0 / 3.0, 0.0];
+        for (g, w) in got.variance().iter().zip(&want) {
+            assert!((g - w).abs() < 1e-9, "{:?}", got);
+        }
+    }
+
+    #[test]
+    fn too_few_coordinates() {
+        assert!(analyse_dimensions(&[coord(0.0, 0.0, 0.0)]).is_none());
+    }
+}
@@ -0,0 +1,302 @@
+use crate::coordinate::Coordinate;
+use crate::model::{estimate_rtt, saturating_duration};
+use crate::telemetry;
+use crate::vector::Vector;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::Duration;
+
+/// The smoothing factor of the per-peer baselines.
+const BASELINE_ALPHA: f64 = 0.05;
+
+/// The default distance (in seconds) a peer's coordinate must move from its
+/// baseline to be considered shifted.
+const DEFAULT_SHIFT: f64 = 0.010;
+
+/// The default increase in the relative estimation error of a peer considered
+/// a shift.
+const DEFAULT_ERROR_SHIFT: f64 = 0.2;
+
+/// The default number of consecutive shifted observations required to report
+/// an anomaly.
+const DEFAULT_SUSTAIN: usize = 5;
+
+/// An abrupt, sustained change in the network path to a peer reported by
+/// [`AnomalyDetector`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Anomaly<K> {
+    /// The affected peer.
+    pub peer: K,
+
+    /// The change observed.
+    pub kind: AnomalyKind,
+}
+
+/// The type of change described by an [`Anomaly`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AnomalyKind {
+    /// The coordinate of the peer moved away from its previous position,
+    /// typically due to a route change affecting the peer.
+    CoordinateShift {
+        /// The distance between the previous and current position.
+        distance: Duration,
+    },
+
+    /// The relative error of the local estimate of the RTT to the peer
+    /// increased, typically due to congestion or a route change on the path
+    /// between the two nodes.
+    ErrorShift {
+        /// The relative error before the shift.
+        baseline: f64,
+
+        /// The relative error after the shift.
+        current: f64,
+    },
+}
+
+/// Detects abrupt, sustained shifts in the coordinates of peers, or in the
+/// accuracy of the local estimates to them, for alerting on network changes.
+///
+/// Each peer has a slowly moving baseline of its coordinate and of the
+/// relative error of RTT estimates to it. An observation deviating from the
+/// baseline by more than a threshold is considered shifted, and once the
+/// [configured number](AnomalyDetector::set_sustain) of consecutive
+/// observations are shifted an [`Anomaly`] is reported and the baseline reset
+/// to the new state. Isolated outliers reset the count, so are not reported:
+///
+/// ```
+/// use std::time::Duration;
+/// use vivaldi::{AnomalyDetector, Model, vector::Dimension3};
+///
+/// let local = Model::<Dimension3>::new();
+/// # let remote = Model::<Dimension3>::new();
+/// let mut detector = AnomalyDetector::new();
+///
+/// // On each response from a peer:
+/// let rtt = Duration::from_millis(20);
+/// for anomaly in detector.record("remote", local.get_coordinate(), remote.get_coordinate(), rtt) {
+///     println!("network change to {}: {:?}", anomaly.peer, anomaly.kind);
+/// }
+/// ```
+///
+/// With the `metrics` feature enabled, each anomaly also increments the
+/// `vivaldi_anomalies_total` counter for alerting.
+#[derive(Debug, Clone)]
+pub struct AnomalyDetector<K> {
+    shift: f64,
+    error_shift: f64,
+    sustain: usize,
+    peers: HashMap<K, PeerBaseline>,
+}
+
+/// The baseline state of a single peer.
+#[derive(Debug, Clone)]
+struct PeerBaseline {
+    /// The components and height of the peer coordinate.
+    position: Vec<f64>,
+    position_count: usize,
+
+    error: f64,
+    error_count: usize,
+}
+
+impl<K> Default for AnomalyDetector<K>
+where
+    K: Hash + Eq + Clone,
+{
+    fn default() -> Self {
+        AnomalyDetector {
+            shift: DEFAULT_SHIFT,
+            error_shift: DEFAULT_ERROR_SHIFT,
+            sustain: DEFAULT_SUSTAIN,
+            peers: HashMap::new(),
+        }
+    }
+}
+
+impl<K> AnomalyDetector<K>
+where
+    K: Hash + Eq + Clone,
+{
+    /// Initialises a detector with the default thresholds.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the distance a peer's coordinate must move from its baseline to
+    /// be considered shifted. Defaults to 10ms.
+    pub fn set_shift_threshold(&mut self, shift: Duration) {
+        self.shift = shift.as_secs_f64();
+    }
+
+    /// Sets the increase in relative estimation error (for example, 0.2 for
+    /// 20 percentage points) over the baseline considered shifted. Defaults
+    /// to 0.2.
+    pub fn set_error_threshold(&mut self, error_shift: f64) {
+        self.error_shift = error_shift;
+    }
+
+    /// Sets the number of consecutive shifted observations required before an
+    /// anomaly is reported. Defaults to 5.
+    pub fn set_sustain(&mut self, sustain: usize) {
+        self.sustain = sustain.max(1);
+    }
+
+    /// Records an observation of `peer`, returning any anomalies detected.
+    pub fn record<V: Vector>(
+        &mut self,
+        peer: K,
+        local: &Coordinate<V>,
+        remote: &Coordinate<V>,
+        rtt: Duration,
+    ) -> Vec<Anomaly<K>> {
+        let measured = rtt.as_secs_f64();
+        let error = (estimate_rtt(local, remote).as_secs_f64() - measured).abs() / measured;
+        let mut position = remote.vector().components().to_vec();
+        position.push(remote.height());
+        if !error.is_finite() || position.iter().any(|v| !v.is_finite()) {
+            return Vec::new();
+        }
+
+        let state = match self.peers.get_mut(&peer) {
+            Some(v) if v.position.len() == position.len() => v,
+            _ => {
+                self.peers.insert(
+                    peer,
+                    PeerBaseline {
+                        position,
+                        position_count: 0,
+                        error,
+                        error_count: 0,
+                    },
+                );
+                return Vec::new();
+            }
+        };
+
+        let mut anomalies = Vec::new();
+
+        let distance = state
+            .position
+            .iter()
+            .zip(&position)
+            .map(|(a, b)| (a - b) * (a - b))
+            .sum::<f64>()
+            .sqrt();
+        if distance > self.shift {
+            state.position_count += 1;
+            if state.position_count >= self.sustain {
+                anomalies.push(Anomaly {
+                    peer: peer.clone(),
+                    kind: AnomalyKind::CoordinateShift {
+                        distance: saturating_duration(distance),
+                    },
+                });
+                state.position = position;
+                state.position_count = 0;
+            }
+        } else {
+            state.position_count = 0;
+            for (b, v) in state.position.iter_mut().zip(&position) {
+                *b += BASELINE_ALPHA * (v - *b);
+            }
+        }
+
+        if error - state.error > self.error_shift {
+            state.error_count += 1;
+            if state.error_count >= self.sustain {
+                anomalies.push(Anomaly {
+                    peer,
+                    kind: AnomalyKind::ErrorShift {
+                        baseline: state.error,
+                        current: error,
+                    },
+                });
+                state.error = error;
+                state.error_count = 0;
+            }
+        } else {
+            state.error_count = 0;
+            state.error += BASELINE_ALPHA * (error - state.error);
+        }
+
+        anomalies.iter().for_each(|a| telemetry::anomaly(&a.kind));
+        anomalies
+    }
+
+    /// Forgets the baseline of `peer`.
+    pub fn remove(&mut self, peer: &K) {
+        self.peers.remove(peer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::Dimension2;
+
+    fn coord(x: f64) -> Coordinate<Dimension2> {
+        Coordinate::new(Dimension2([x, 0.0]), 0.1, 0.0)
+    }
+
+    #[test]
+    fn coordinate_shift() {
+        let mut d = AnomalyDetector::new();
+        let local = coord(0.0);
+        let rtt = |x: f64| estimate_rtt(&local, &coord(x));
+
+        for _ in 0..20 {
+            assert!(d.record("a", &local, &coord(0.05), rtt(0.05)).is_empty());
+        }
+
+        // An isolated outlier is ignored.
+        assert!(d.record("a", &local, &coord(0.1), rtt(0.1)).is_empty());
+        assert!(d.record("a", &local, &coord(0.05), rtt(0.05)).is_empty());
+
+        // A sustained move is reported once.
+        let reports = (0..10)
+            .flat_map(|_| d.record("a", &local, &coord(0.1), rtt(0.1)))
+            .collect::<Vec<_>>();
+        assert_eq!(reports.len(), 1);
+        match reports[0].kind {
+            AnomalyKind::CoordinateShift { distance } => {
+                assert!(
+                    (distance.as_secs_f64() - 0.05).abs() < 1e-6,
+                    "{:?}",
+                    distance
+                )
+            }
+            k => panic!("unexpected {:?}", k),
+        }
+    }
+
+    #[test]
+    fn error_shift() {
+        let mut d = AnomalyDetector::new();
+        d.set_sustain(3);
+        let (local, remote) = (coord(0.0), coord(0.05));
+        let accurate = estimate_rtt(&local, &remote);
+
+        for _ in 0..20 {
+            assert!(d.record("a", &local, &remote, accurate).is_empty());
+        }
+
+        // Congestion doubles the measured RTT without moving the peer.
+        let reports = (0..5)
+            .flat_map(|_| d.record("a", &local, &remote, accurate * 2))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            reports,
+            vec![Anomaly {
+                peer: "a",
+                kind: AnomalyKind::ErrorShift {
+                    baseline: 0.0,
+                    current: 0.5,
+                },
+            }]
+        );
+
+        d.remove(&"a");
+        assert!(d.record("a", &local, &remote, accurate).is_empty());
+    }
+}
@@ -0,0 +1,198 @@
+use crate::clock::Clock;
+use crate::coordinate::Coordinate;
+use crate::error::Error;
+use crate::force::ForceFunction;
+use crate::model::{estimate_metric, Model};
+use crate::vector::Vector;
+use rand::Rng;
+use std::time::Duration;
+
+/// The number of relaxation rounds used to solve a bootstrap position.
+const BOOTSTRAP_ITERATIONS: usize = 1000;
+
+/// The initial fraction of the mean residual force applied each round, decayed
+/// over the iterations.
+const BOOTSTRAP_STEP: f64 = 0.5;
+
+/// The lowest error estimate assigned to a bootstrapped coordinate.
+///
+/// A handful of measurements can be fitted exactly without the position being
+/// correct relative to the rest of the network, so the bootstrapped
+/// coordinate is never treated as more confident than this.
+const BOOTSTRAP_MIN_ERROR: f64 = 0.5;
+
+impl<V, C, F, R> Model<V, C, F, f64, R>
+where
+    V: Vector + std::fmt::Debug,
+    C: Clock,
+    F: ForceFunction,
+    R: Rng,
+{
+    /// Solves an initial position from a one-shot batch of measurements to
+    /// peers with known coordinates, replacing the current coordinate.
+    ///
+    /// A new model starts at the origin and random-walks towards its position
+    /// one observation at a time, giving poor estimates until enough
+    /// observations have been made. Bootstrapping instead fits the position
+    /// and height that best explain all the measurements at once (minimising
+    /// the error-weighted squared residuals), typically from the coordinates
+    /// and RTTs gathered while joining the cluster:
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use vivaldi::{Model, vector::Dimension3};
+    ///
+    /// # let a = Model::<Dimension3>::new();
+    /// # let b = Model::<Dimension3>::new();
+    /// let mut model = Model::<Dimension3>::new();
+    /// model.bootstrap_from(&[
+    ///     (*a.get_coordinate(), Duration::from_millis(20)),
+    ///     (*b.get_coordinate(), Duration::from_millis(45)),
+    /// ])?;
+    /// # Ok::<(), vivaldi::Error>(())
+    /// ```
+    ///
+    /// The error estimate of the model is set to the mean relative error of
+    /// the fit, but no lower than 0.5 so that subsequent observations can
+    /// still correct the position. Peers with a lower error estimate have more
+    /// influence over the solved position.
+    ///
+    /// Returns [`Error::InvalidRtt`] if any RTT is zero, or
+    /// [`Error::NonFiniteCoordinate`] if any coordinate contains a NaN or
+    /// infinite value, leaving the model unchanged. An empty `peers` slice
+    /// leaves the model unchanged.
+    pub fn bootstrap_from(&mut self, peers: &[(Coordinate<V>, Duration)]) -> Result<(), Error> {
+        if peers.iter().any(|(_, rtt)| *rtt == Duration::from_secs(0)) {
+            return Err(Error::InvalidRtt);
+        }
+        if peers.iter().any(|(c, _)| !c.is_finite()) {
+            return Err(Error::NonFiniteCoordinate);
+        }
+        if peers.is_empty() {
+            return Ok(());
+        }
+
+        let weights = peers
+            .iter()
+            .map(|(c, _)| 1.0 / (c.error() + f64::EPSILON))
+            .collect::<Vec<_>>();
+        let total_weight = weights.iter().sum::<f64>();
+        let mean_rtt =
+            peers.iter().map(|(_, rtt)| rtt.as_secs_f64()).sum::<f64>() / peers.len() as f64;
+
+        // Start from the weighted centroid of the peers, offset in a random
+        // direction so a position off the line/plane of the peers can be
+        // found.
+        let mut position = peers
+            .iter()
+            .zip(&weights)
+            .fold(V::default(), |acc, ((c, _), w)| {
+                acc + c.vector().clone() * *w
+            })
+            / total_weight;
+        let offset = V::random(self.rng());
+        let mag = offset.magnitude().0;
+        if mag > f64::EPSILON {
+            position = position + offset * (mean_rtt / 2.0 / mag);
+        }
+        let mut height = self.get_coordinate().height().min(mean_rtt / 4.0);
+
+        for iter in 0..BOOTSTRAP_ITERATIONS {
+            let step = BOOTSTRAP_STEP / (1.0 + iter as f64 / 100.0);
+
+            // Each peer pulls (or pushes) the position along the direction
+            // between them, and the height, in proportion to their share of
+            // the estimated distance.
+            let mut force = V::default();
+            let mut height_force = 0.0;
+            for ((c, rtt), w) in peers.iter().zip(&weights) {
+                let diff = position.displacement(c.vector());
+                let dist = diff.magnitude().0;
+                let est = dist + height + c.height();
+                let residual = rtt.as_secs_f64() - est;
+
+                if dist > f64::EPSILON {
+                    force = force + diff * (w * residual / est);
+                }
+                height_force += w * residual * (height + c.height()) / est;
+            }
+
+            position = position + force * (step / total_weight);
+            height = (height + height_force * step / total_weight).max(0.0);
+        }
+
+        let solved = Coordinate::new(position, 0.0, height);
+        let residual = peers
+            .iter()
+            .map(|(c, rtt)| {
+                let rtt = rtt.as_secs_f64();
+                (estimate_metric(&solved, c) - rtt).abs() / rtt
+            })
+            .sum::<f64>()
+            / peers.len() as f64;
+
+        self.set_coordinate(Coordinate::new(
+            solved.into_vector(),
+            residual.max(BOOTSTRAP_MIN_ERROR),
+            height,
+        ));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::estimate_rtt;
+    use crate::vector::Dimension2;
+
+    fn coord(x: f64, y: f64, height: f64) -> Coordinate<Dimension2> {
+        Coordinate::new(Dimension2([x, y]), 0.1, height)
+    }
+
+    #[test]
+    fn solves_position() {
+        let truth = coord(0.03, 0.02, 0.005);
+        let peers = [
+            coord(0.0, 0.0, 0.001),
+            coord(0.1, 0.0, 0.002),
+            coord(0.0, 0.1, 0.001),
+            coord(0.1, 0.1, 0.003),
+            coord(-0.05, 0.05, 0.002),
+        ];
+        let measured = peers
+            .iter()
+            .map(|p| (*p, estimate_rtt(&truth, p)))
+            .collect::<Vec<_>>();
+
+        let mut model = Model::<Dimension2>::new();
+        model.bootstrap_from(&measured).unwrap();
+
+        for (p, rtt) in &measured {
+            let got = estimate_rtt(model.get_coordinate(), p).as_secs_f64();
+            let want = rtt.as_secs_f64();
+            assert!(
+                (got - want).abs() / want < 0.05,
+                "got {} want {}",
+                got,
+                want
+            );
+        }
+        assert!((model.get_coordinate().error() - BOOTSTRAP_MIN_ERROR).abs() < 1e-9);
+    }
+
+    #[test]
+    fn rejects_invalid_input() {
+        let mut model = Model::<Dimension2>::new();
+        let before = model.clone();
+
+        let zero = [(coord(0.0, 0.0, 0.0), Duration::from_secs(0))];
+        assert_eq!(model.bootstrap_from(&zero), Err(Error::InvalidRtt));
+
+        let nan = [(coord(f64::NAN, 0.0, 0.0), Duration::from_millis(1))];
+        assert_eq!(model.bootstrap_from(&nan), Err(Error::NonFiniteCoordinate));
+
+        assert_eq!(model.bootstrap_from(&[]), Ok(()));
+        assert_eq!(model, before);
+    }
+}
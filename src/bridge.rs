@@ -0,0 +1,214 @@
+use crate::alignment::{align, Alignment};
+use crate::coordinate::Coordinate;
+use crate::model::{estimate_rtt, saturating_duration};
+use crate::vector::Vector;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::Duration;
+
+/// Translates coordinates between two independent Vivaldi networks bridged by
+/// gateway nodes participating in both, keyed by a caller defined gateway
+/// identifier `K`.
+///
+/// The two networks converge on unrelated coordinate spaces, so coordinates
+/// from one cannot be compared with coordinates from the other. Each gateway
+/// holds a coordinate in both spaces, and the bridge fits the rigid transform
+/// (see [`align`]) best mapping the gateways' coordinates in network `A` onto
+/// their coordinates in network `B`. Any coordinate from `A` can then be
+/// [translated](NetworkBridge::translate) into `B` to estimate the RTT to
+/// nodes in `B`:
+///
+/// ```
+/// use vivaldi::{estimate_rtt, Model, NetworkBridge, vector::Dimension3};
+///
+/// # let gateways = (0..4)
+/// #     .map(|_| (Model::<Dimension3>::new(), Model::<Dimension3>::new()))
+/// #     .collect::<Vec<_>>();
+/// # let (node_a, node_b) = (Model::<Dimension3>::new(), Model::<Dimension3>::new());
+/// let mut bridge = NetworkBridge::new();
+/// for (id, (in_a, in_b)) in gateways.iter().enumerate() {
+///     bridge.update_gateway(id, in_a.get_coordinate(), in_b.get_coordinate());
+/// }
+///
+/// // Estimate the RTT from a node in network A to a node in network B.
+/// let rtt = bridge.estimate_rtt(node_a.get_coordinate(), node_b.get_coordinate());
+/// ```
+///
+/// A good fit requires gateways spread across both networks, and at least one
+/// more gateway than the number of dimensions. The
+/// [`rmsd`](Alignment::rmsd) of the fit (in seconds) indicates how well the
+/// two spaces agree, and is added to every translated estimate. When traffic
+/// must physically traverse a gateway,
+/// [`estimate_via_gateway`](NetworkBridge::estimate_via_gateway) estimates the
+/// routed RTT instead.
+#[derive(Debug, Clone)]
+pub struct NetworkBridge<K, V>
+where
+    K: Hash + Eq,
+    V: Vector,
+{
+    gateways: HashMap<K, (Coordinate<V>, Coordinate<V>)>,
+    alignment: Option<Alignment>,
+}
+
+impl<K, V> Default for NetworkBridge<K, V>
+where
+    K: Hash + Eq,
+    V: Vector,
+{
+    fn default() -> Self {
+        NetworkBridge {
+            gateways: HashMap::new(),
+            alignment: None,
+        }
+    }
+}
+
+impl<K, V> NetworkBridge<K, V>
+where
+    K: Hash + Eq,
+    V: Vector,
+{
+    /// Initialises a bridge with no gateways.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the coordinates of `gateway` in network `A` and network `B`,
+    /// and refits the translation.
+    pub fn update_gateway(&mut self, gateway: K, in_a: &Coordinate<V>, in_b: &Coordinate<V>) {
+        self.gateways.insert(gateway, (in_a.clone(), in_b.clone()));
+        self.fit();
+    }
+
+    /// Removes `gateway`, returning false if it was not present.
+    pub fn remove_gateway(&mut self, gateway: &K) -> bool {
+        let removed = self.gateways.remove(gateway).is_some();
+        if removed {
+            self.fit();
+        }
+        removed
+    }
+
+    /// Returns the number of gateways.
+    pub fn len(&self) -> usize {
+        self.gateways.len()
+    }
+
+    /// Returns true if the bridge has no gateways.
+    pub fn is_empty(&self) -> bool {
+        self.gateways.is_empty()
+    }
+
+    /// Returns the fitted transform from network `A` to network `B`, or `None`
+    /// if there are no gateways.
+    pub fn alignment(&self) -> Option<&Alignment> {
+        self.alignment.as_ref()
+    }
+
+    /// Translates `coord` from network `A` into the coordinate space of
+    /// network `B`, or returns `None` if there are no gateways.
+    pub fn translate(&self, coord: &Coordinate<V>) -> Option<Coordinate<V>> {
+        self.alignment.as_ref().map(|a| a.apply(coord))
+    }
+
+    /// Returns the estimated RTT between `a` in network `A` and `b` in network
+    /// `B`, by translating `a` into network `B`.
+    ///
+    /// The [`rmsd`](Alignment::rmsd) of the fit is added to the estimate.
+    /// Returns `None` if there are no gateways.
+    pub fn estimate_rtt(&self, a: &Coordinate<V>, b: &Coordinate<V>) -> Option<Duration> {
+        let alignment = self.alignment.as_ref()?;
+        let rtt = estimate_rtt(&alignment.apply(a), b);
+        Some(rtt.saturating_add(saturating_duration(alignment.rmsd())))
+    }
+
+    /// Returns the gateway minimising the RTT of a path from `a` in network
+    /// `A`, through the gateway, to `b` in network `B`, along with the
+    /// estimated RTT of that path.
+    ///
+    /// Returns `None` if there are no gateways.
+    pub fn estimate_via_gateway(
+        &self,
+        a: &Coordinate<V>,
+        b: &Coordinate<V>,
+    ) -> Option<(&K, Duration)> {
+        self.gateways
+            .iter()
+            .map(|(id, (in_a, in_b))| {
+                (
+                    id,
+                    estimate_rtt(a, in_a).saturating_add(estimate_rtt(in_b, b)),
+                )
+            })
+            .min_by_key(|(_, rtt)| *rtt)
+    }
+
+    fn fit(&mut self) {
+        let (a, b): (Vec<_>, Vec<_>) = self.gateways.values().cloned().unzip();
+        self.alignment = align(&a, &b);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::Dimension2;
+
+    fn coord(x: f64, y: f64) -> Coordinate<Dimension2> {
+        Coordinate::new(Dimension2([x, y]), 0.2, 0.0)
+    }
+
+    /// Network B is network A rotated by 90 degrees and shifted.
+    fn to_b(c: &Coordinate<Dimension2>) -> Coordinate<Dimension2> {
+        let [x, y] = c.vector().0;
+        coord(-y + 1.0, x + 2.0)
+    }
+
+    #[test]
+    fn translates_between_networks() {
+        let mut bridge = NetworkBridge::new();
+        assert!(bridge.translate(&coord(0.0, 0.0)).is_none());
+        assert!(bridge
+            .estimate_rtt(&coord(0.0, 0.0), &coord(0.0, 0.0))
+            .is_none());
+
+        for (id, g) in [coord(0.0, 0.0), coord(0.1, 0.0), coord(0.0, 0.1)]
+            .iter()
+            .enumerate()
+        {
+            bridge.update_gateway(id, g, &to_b(g));
+        }
+        assert_eq!(bridge.len(), 3);
+        assert!(bridge.alignment().unwrap().rmsd() < 1e-9);
+
+        // A node in A, 50ms from a node in B once translated.
+        let a = coord(0.05, 0.05);
+        let b = to_b(&coord(0.05, 0.10));
+        let got = bridge.estimate_rtt(&a, &b).unwrap().as_secs_f64();
+        assert!((got - 0.05).abs() < 1e-4, "{}", got);
+
+        let translated = bridge.translate(&a).unwrap();
+        assert!(translated.vector().distance(to_b(&a).vector()).0 < 1e-9);
+    }
+
+    #[test]
+    fn routes_via_nearest_gateway() {
+        let mut bridge = NetworkBridge::new();
+        bridge.update_gateway("near", &coord(0.01, 0.0), &coord(5.0, 5.0));
+        bridge.update_gateway("far", &coord(1.0, 0.0), &coord(5.0, 5.0));
+
+        let (id, rtt) = bridge
+            .estimate_via_gateway(&coord(0.0, 0.0), &coord(5.0, 5.02))
+            .unwrap();
+        assert_eq!(*id, "near");
+        assert!((rtt.as_secs_f64() - 0.03).abs() < 1e-4);
+
+        assert!(bridge.remove_gateway(&"near"));
+        assert!(!bridge.remove_gateway(&"near"));
+        let (id, _) = bridge
+            .estimate_via_gateway(&coord(0.0, 0.0), &coord(5.0, 5.02))
+            .unwrap();
+        assert_eq!(*id, "far");
+    }
+}
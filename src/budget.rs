@@ -0,0 +1,125 @@
+use crate::clock::Clock;
+use crate::coordinate::Coordinate;
+use crate::force::ForceFunction;
+use crate::model::{estimate_rtt, Model};
+use crate::vector::Vector;
+use rand::Rng;
+use std::time::Duration;
+
+/// How confident a [`Model`] is that a peer is within a latency budget,
+/// returned by [`Model::within_budget`].
+///
+/// Variants are ordered from most to least confident.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Confidence {
+    /// The estimated RTT is within the budget even allowing for the error
+    /// estimates of both coordinates.
+    Within,
+
+    /// The estimated RTT is within the budget, but may exceed it within the
+    /// error estimates of the coordinates.
+    ProbablyWithin,
+
+    /// The estimated RTT exceeds the budget, but may be within it within the
+    /// error estimates of the coordinates.
+    ProbablyBeyond,
+
+    /// The estimated RTT exceeds the budget even allowing for the error
+    /// estimates of both coordinates.
+    Beyond,
+}
+
+impl Confidence {
+    /// Returns true for [`Within`](Confidence::Within) and
+    /// [`ProbablyWithin`](Confidence::ProbablyWithin).
+    pub fn is_within(&self) -> bool {
+        *self <= Confidence::ProbablyWithin
+    }
+}
+
+impl<V, C, F, R> Model<V, C, F, f64, R>
+where
+    V: Vector + std::fmt::Debug,
+    C: Clock,
+    F: ForceFunction,
+    R: Rng,
+{
+    /// Returns how confident the model is that the RTT to the node with
+    /// coordinate `peer` is within `budget`.
+    ///
+    /// The estimated RTT is bounded by the relative error estimates of both
+    /// coordinates - `rtt * (1 ± (e_local + e_peer))` - and the result grades
+    /// where `budget` falls against those bounds:
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use vivaldi::{Confidence, Model, vector::Dimension3};
+    ///
+    /// let model = Model::<Dimension3>::new();
+    /// # let remote = Model::<Dimension3>::new();
+    ///
+    /// match model.within_budget(remote.get_coordinate(), Duration::from_millis(50)) {
+    ///     Confidence::Within => println!("place it"),
+    ///     Confidence::ProbablyWithin => println!("place it, but measure"),
+    ///     Confidence::ProbablyBeyond | Confidence::Beyond => println!("look elsewhere"),
+    /// }
+    /// ```
+    ///
+    /// A freshly initialised model has a large error estimate, so few peers
+    /// are reported as [`Within`](Confidence::Within) or
+    /// [`Beyond`](Confidence::Beyond) until the model converges.
+    pub fn within_budget(&self, peer: &Coordinate<V>, budget: Duration) -> Confidence {
+        let local = self.get_coordinate();
+        let rtt = estimate_rtt(local, peer);
+        if rtt == Duration::MAX {
+            return Confidence::Beyond;
+        }
+
+        let error = local.error() + peer.error();
+        let (rtt, budget) = (rtt.as_secs_f64(), budget.as_secs_f64());
+
+        if rtt * (1.0 + error) <= budget {
+            Confidence::Within
+        } else if rtt <= budget {
+            Confidence::ProbablyWithin
+        } else if rtt * (1.0 - error) <= budget {
+            Confidence::ProbablyBeyond
+        } else {
+            Confidence::Beyond
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::Dimension3;
+
+    #[test]
+    fn grades() {
+        let mut model = Model::<Dimension3>::new();
+        model.set_coordinate(Coordinate::new(Dimension3([0.0, 0.0, 0.0]), 0.1, 0.0));
+
+        // An estimated RTT of 100ms (plus the minimum heights), with a
+        // combined error of 20%.
+        let peer = Coordinate::new(Dimension3([0.1, 0.0, 0.0]), 0.1, 0.0);
+        let ms = Duration::from_millis;
+
+        assert_eq!(model.within_budget(&peer, ms(130)), Confidence::Within);
+        assert_eq!(
+            model.within_budget(&peer, ms(110)),
+            Confidence::ProbablyWithin
+        );
+        assert_eq!(
+            model.within_budget(&peer, ms(90)),
+            Confidence::ProbablyBeyond
+        );
+        assert_eq!(model.within_budget(&peer, ms(70)), Confidence::Beyond);
+
+        assert!(Confidence::ProbablyWithin.is_within());
+        assert!(!Confidence::ProbablyBeyond.is_within());
+
+        let poisoned = Coordinate::new(Dimension3([f64::NAN, 0.0, 0.0]), 0.1, 0.0);
+        assert_eq!(model.within_budget(&poisoned, ms(100)), Confidence::Beyond);
+    }
+}
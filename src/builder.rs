@@ -0,0 +1,540 @@
+use crate::clock::{Clock, SystemClock};
+use crate::model::{Model, ModelConfig};
+use crate::outlier::OutlierPolicy;
+use crate::vector::Vector;
+use std::time::Duration;
+
+/// Constructs a [`Model`] with tuned algorithm parameters.
+///
+/// The defaults match the Vivaldi paper and [`Model::new`], which suit most
+/// wide-area deployments. Networks with very different latency
+/// characteristics may converge faster or more stably with other values - a
+/// LAN, where RTTs are small and stable, tolerates less damping than a WAN:
+///
+/// ```
+/// use std::time::Duration;
+/// use vivaldi::{ModelBuilder, vector::Dimension3};
+///
+/// let model = ModelBuilder::new()
+///     .ce(0.5)
+///     .cc(0.5)
+///     .initial_height(Duration::from_micros(100))
+///     .build::<Dimension3>();
+/// ```
+///
+/// Coordinates from models built with different parameters remain in the same
+/// space, and may be observed by each other.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ModelBuilder {
+    config: ModelConfig,
+    /// The interval of the coordinate history, if enabled.
+    history: Option<Duration>,
+}
+
+impl ModelBuilder {
+    /// Initialises a builder with the default parameters.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Initialises a builder with the default parameters of HashiCorp's Serf
+    /// (and so Consul), for models participating in a Serf cluster.
+    ///
+    /// Serf embeds coordinates in 8 dimensions, so the model should be built
+    /// over a [`DimensionN<8>`](crate::vector::DimensionN):
+    ///
+    /// ```
+    /// use vivaldi::{ModelBuilder, vector::DimensionN};
+    ///
+    /// let model = ModelBuilder::serf().build::<DimensionN<8>>();
+    /// assert_eq!(model.get_coordinate().error(), 1.5);
+    /// ```
+    ///
+    /// The initial and maximum error estimate are 1.5, the initial and
+    /// minimum height 10µs, the gravity constant 150 seconds, the
+    /// [latency filter](ModelBuilder::latency_filter) holds 3 samples and the
+    /// [adjustment window](ModelBuilder::adjustment_window) 20.
+    pub fn serf() -> Self {
+        let height = Duration::from_micros(10);
+        Self::new()
+            .initial_error(1.5)
+            .max_error(1.5)
+            .initial_height(height)
+            .min_height(height)
+            .gravity(Duration::from_secs(150))
+            .latency_filter(3)
+            .adjustment_window(20)
+    }
+
+    /// Sets the `ce` algorithm value, the fraction of each sample's relative
+    /// error moved into the local error estimate. Defaults to 0.25.
+    ///
+    /// Higher values track changes in accuracy more quickly, at the cost of a
+    /// noisier error estimate.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `ce` is not within `0.0..=1.0`.
+    pub fn ce(mut self, ce: f64) -> Self {
+        assert!((0.0..=1.0).contains(&ce), "invalid ce {}", ce);
+        self.config.ce = ce;
+        self
+    }
+
+    /// Sets the `cc` algorithm value, the fraction of the observed error the
+    /// coordinate moves per observation (before weighting). Defaults to 0.25.
+    ///
+    /// Higher values converge more quickly, at the cost of oscillating more
+    /// in response to noisy measurements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `cc` is not within `0.0..=1.0`.
+    pub fn cc(mut self, cc: f64) -> Self {
+        assert!((0.0..=1.0).contains(&cc), "invalid cc {}", cc);
+        self.config.cc = cc;
+        self
+    }
+
+    /// Sets the error estimate of the new coordinate. Defaults to 2.0,
+    /// reflecting no confidence in its position.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `error` is not positive and finite.
+    pub fn initial_error(mut self, error: f64) -> Self {
+        assert!(
+            error > 0.0 && error.is_finite(),
+            "invalid initial error {}",
+            error
+        );
+        self.config.initial_error = error;
+        self
+    }
+
+    /// Sets the height of the new coordinate. Defaults to 100ms.
+    pub fn initial_height(mut self, height: Duration) -> Self {
+        self.config.initial_height = height.as_secs_f64();
+        self
+    }
+
+    /// Enables gravity, pulling the coordinate towards the origin by
+    /// `(||x|| / rho)²` on each observation, as described in "Network
+    /// Coordinates in the Wild" (Ledlie et al.). Disabled by default.
+    ///
+    /// Vivaldi only constrains the distances between coordinates, so the
+    /// coordinates of a long-running cluster can drift together arbitrarily
+    /// far from the origin, losing floating point precision. Gravity is
+    /// negligible for coordinates well within `rho` of the origin, and grows
+    /// quadratically beyond it - the paper uses a `rho` of 2048ms.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rho` is zero.
+    pub fn gravity(mut self, rho: Duration) -> Self {
+        assert!(!rho.is_zero(), "gravity constant must be non-zero");
+        self.config.gravity = Some(rho.as_secs_f64());
+        self
+    }
+
+    /// Smooths the samples passed to
+    /// [`observe_peer`](Model::observe_peer) with a median filter over the
+    /// most recent `window` samples from each peer, as HashiCorp's Serf does.
+    /// Disabled by default.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `window` is zero.
+    pub fn latency_filter(mut self, window: usize) -> Self {
+        assert!(window > 0, "latency filter window must be non-zero");
+        self.config.latency_filter = Some(window);
+        self
+    }
+
+    /// Publishes an [adjustment](crate::Coordinate::adjustment) with the
+    /// coordinate, learned from the difference between the measured and
+    /// estimated RTT of the most recent `window` observations, as HashiCorp's
+    /// Serf does (with a window of 20). Disabled by default.
+    ///
+    /// The embedding systematically under or overestimates some RTTs, which
+    /// no placement of the coordinate can correct. The adjustment is added to
+    /// every estimate involving the coordinate, so a node whose RTTs are
+    /// consistently underestimated reports larger estimates:
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use vivaldi::{estimate_rtt, ModelBuilder, vector::Dimension3};
+    ///
+    /// let mut model = ModelBuilder::new().adjustment_window(20).build::<Dimension3>();
+    /// # let remote = *ModelBuilder::new().build::<Dimension3>().get_coordinate();
+    /// model.observe(&remote, Duration::from_millis(40));
+    ///
+    /// // Estimates between adjusted coordinates include both adjustments.
+    /// let rtt = estimate_rtt(model.get_coordinate(), &remote);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `window` is zero.
+    pub fn adjustment_window(mut self, window: usize) -> Self {
+        assert!(window > 0, "adjustment window must be non-zero");
+        self.config.adjustment_window = Some(window);
+        self
+    }
+
+    /// Treats observations with a relative error above `threshold` times the
+    /// local error estimate as outliers, handled according to `policy`.
+    /// Disabled by default.
+    ///
+    /// A single wildly wrong sample, such as the RTT of a retransmitted
+    /// probe, otherwise moves a converged coordinate a long way:
+    ///
+    /// ```
+    /// use vivaldi::{ModelBuilder, OutlierPolicy, vector::Dimension3};
+    ///
+    /// let model = ModelBuilder::new()
+    ///     .outlier_rejection(5.0, OutlierPolicy::Discard)
+    ///     .build::<Dimension3>();
+    ///
+    /// let dropped = model.outlier_stats().discarded;
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `threshold` is not positive, or the factor of
+    /// [`OutlierPolicy::DownWeight`] is not within `0.0..=1.0`.
+    pub fn outlier_rejection(mut self, threshold: f64, policy: OutlierPolicy) -> Self {
+        assert!(threshold > 0.0, "invalid outlier threshold {}", threshold);
+        if let OutlierPolicy::DownWeight(factor) = policy {
+            assert!(
+                (0.0..=1.0).contains(&factor),
+                "invalid outlier weight {}",
+                factor
+            );
+        }
+        self.config.outliers = Some((threshold, policy));
+        self
+    }
+
+    /// Limits the distance the coordinate moves per observation to `max`,
+    /// and separately the change in its height. Unlimited by default.
+    ///
+    /// Vivaldi moves the coordinate in proportion to the error of each sample,
+    /// so a single bogus RTT (such as one measured across a suspended
+    /// process) can fling a converged coordinate an arbitrary distance. This
+    /// is similar to the [`CappedSpring`](crate::CappedSpring) force
+    /// function, but also bounds the height and applies to any force
+    /// function.
+    pub fn max_displacement(mut self, max: Duration) -> Self {
+        self.config.max_displacement = Some(max.as_secs_f64());
+        self
+    }
+
+    /// Bounds the error estimate of the coordinate to at most `max`, as
+    /// HashiCorp's Serf does (with a maximum of 1.5). Defaults to 1000, the
+    /// largest error estimate of a valid [`Coordinate`](crate::Coordinate).
+    ///
+    /// Pathological samples can otherwise grow the error estimate without
+    /// limit, after which the sample weight of every observation approaches
+    /// one and the node moves the full distance towards any remote it
+    /// observes. The bound also applies to the
+    /// [initial error](ModelBuilder::initial_error).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max` is not positive.
+    pub fn max_error(mut self, max: f64) -> Self {
+        assert!(max > 0.0, "invalid maximum error {}", max);
+        self.config.max_error = Some(max);
+        self
+    }
+
+    /// Sets the lowest height of the coordinate. Defaults to 10µs.
+    ///
+    /// Heights model the cost of the access link of a node, and must remain
+    /// positive so they can be scaled up or down. The default suits models
+    /// observing RTTs across networks - models embedding another metric
+    /// should use a floor in the units of that metric, and models of very
+    /// low latency fabrics a lower one.
+    pub fn min_height(mut self, min: Duration) -> Self {
+        self.config.min_height = min.as_secs_f64();
+        self
+    }
+
+    /// Embeds the coordinate in a purely Euclidean space, without a height.
+    /// Heights are enabled by default.
+    ///
+    /// The height models the cost of each node's access link, which
+    /// deployments within a single datacentre or low latency fabric may not
+    /// benefit from. The model ignores heights when observing, and publishes
+    /// coordinates with a height of zero, so estimates between the
+    /// coordinates of Euclidean models exclude heights:
+    ///
+    /// ```
+    /// use vivaldi::{ModelBuilder, vector::Dimension3};
+    ///
+    /// let model = ModelBuilder::new().euclidean().build::<Dimension3>();
+    /// assert_eq!(model.get_coordinate().height(), 0.0);
+    /// ```
+    ///
+    /// Every node should use the same mode - to ignore the heights of
+    /// coordinates published by other models, use
+    /// [`estimate_rtt_planar`](crate::estimate_rtt_planar).
+    pub fn euclidean(mut self) -> Self {
+        self.config.heights = false;
+        self
+    }
+
+    /// Records the coordinate at most once per `interval` into a history of
+    /// the 32 most recent coordinates, so the model can
+    /// [roll back](Model::rollback_to) the damage of a burst of bad
+    /// measurements. The history is disabled by default, as it occupies
+    /// several kilobytes per model.
+    ///
+    /// See [`Model::set_history_interval`].
+    pub fn history_interval(mut self, interval: Duration) -> Self {
+        self.history = Some(interval);
+        self
+    }
+
+    /// Builds a model reading the current time from the system clock.
+    pub fn build<V>(self) -> Model<V>
+    where
+        V: Vector + std::fmt::Debug,
+    {
+        self.build_with_clock(SystemClock)
+    }
+
+    /// Builds a model reading the current time from `clock`, as
+    /// [`Model::with_clock`].
+    pub fn build_with_clock<V, C>(self, clock: C) -> Model<V, C>
+    where
+        V: Vector + std::fmt::Debug,
+        C: Clock,
+    {
+        let mut model = Model::with_config(clock, self.config);
+        model.set_history_interval(self.history);
+        model
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coordinate::Coordinate;
+    use crate::model::estimate_rtt;
+    use crate::vector::Dimension2;
+
+    #[test]
+    fn defaults_match_new() {
+        let built = ModelBuilder::new().build::<Dimension2>();
+        let new = Model::<Dimension2>::new();
+        assert_eq!(built.get_coordinate(), new.get_coordinate());
+    }
+
+    #[test]
+    fn initial_coordinate() {
+        let m = ModelBuilder::new()
+            .initial_error(0.5)
+            .initial_height(Duration::from_millis(5))
+            .build::<Dimension2>();
+        assert_eq!(m.get_coordinate().error(), 0.5);
+        assert_eq!(m.get_coordinate().height(), 0.005);
+    }
+
+    #[test]
+    fn damping() {
+        let remote = Coordinate::new(Dimension2([0.05, 0.0]), 0.5, 0.0);
+        let moved = |builder: ModelBuilder| {
+            let mut m = builder.initial_height(Duration::ZERO).build::<Dimension2>();
+            m.observe(&remote, Duration::from_millis(100));
+            let c = m.get_coordinate();
+            (c.vector().distance(&Dimension2::default()).0, c.error())
+        };
+
+        let (default_dist, default_error) = moved(ModelBuilder::new());
+        let (fast_dist, fast_error) = moved(ModelBuilder::new().cc(0.5));
+        assert!(fast_dist > default_dist);
+        assert_eq!(fast_error, default_error);
+
+        let (dist, error) = moved(ModelBuilder::new().ce(0.5));
+        assert_eq!(dist, default_dist);
+        assert_ne!(error, default_error);
+    }
+
+    #[test]
+    fn gravity() {
+        let rho = Duration::from_millis(2048);
+        let far = Coordinate::new(Dimension2([1.0, 0.0]), 0.5, 0.0);
+        // Observing the exact estimated RTT applies no spring force.
+        let remote = Coordinate::new(Dimension2([1.05, 0.0]), 0.5, 0.0);
+        let rtt = estimate_rtt(&far, &remote);
+
+        let mut m = ModelBuilder::new().build::<Dimension2>();
+        m.set_coordinate(far);
+        m.observe(&remote, rtt);
+        assert_eq!(m.get_coordinate().vector(), far.vector());
+
+        let mut m = ModelBuilder::new().gravity(rho).build::<Dimension2>();
+        m.set_coordinate(far);
+        m.observe(&remote, rtt);
+        let want = 1.0 - (1.0 / rho.as_secs_f64()).powi(2);
+        assert!((m.get_coordinate().vector().0[0] - want).abs() < 1e-12);
+        assert_eq!(m.get_coordinate().vector().0[1], 0.0);
+
+        // Gravity never pulls a coordinate through the origin.
+        let mut m = ModelBuilder::new()
+            .gravity(Duration::from_millis(100))
+            .build::<Dimension2>();
+        m.set_coordinate(far);
+        m.observe(&remote, rtt);
+        assert_eq!(m.get_coordinate().vector(), &Dimension2([0.0, 0.0]));
+    }
+
+    #[test]
+    fn adjustment() {
+        let remote = Coordinate::new(Dimension2([0.05, 0.0]), 0.01, 0.0);
+        let origin = Coordinate::new(Dimension2([0.0, 0.0]), 0.0, 0.0);
+        let rtt = Duration::from_millis(70);
+
+        let mut m = ModelBuilder::new()
+            .adjustment_window(20)
+            .build::<Dimension2>();
+        let mut plain = ModelBuilder::new().build::<Dimension2>();
+        for _ in 0..200 {
+            m.observe(&remote, rtt);
+            plain.observe(&remote, rtt);
+        }
+        assert_eq!(plain.get_coordinate().adjustment(), 0.0);
+
+        // The adjustment converges to half the mean residual of the
+        // (converged) coordinate.
+        let c = m.get_coordinate();
+        let raw = c.vector().distance(remote.vector()).0 + c.height() + remote.height();
+        assert!((c.adjustment() - (rtt.as_secs_f64() - raw) / 2.0).abs() < 1e-6);
+
+        // The adjusted estimate differs from the unadjusted one by exactly
+        // the adjustment.
+        let got = estimate_rtt(c, &remote).as_secs_f64();
+        assert!((got - raw - c.adjustment()).abs() < 1e-9);
+
+        // Adjustments never make an estimate negative.
+        let negative = remote.with_adjustment(-10.0);
+        assert_eq!(
+            estimate_rtt(&origin, &negative),
+            estimate_rtt(&origin, &remote)
+        );
+    }
+
+    #[test]
+    fn max_displacement() {
+        let remote = Coordinate::new(Dimension2([0.05, 0.0]), 0.01, 0.02);
+        let max = Duration::from_millis(5);
+        let mut m = ModelBuilder::new()
+            .max_displacement(max)
+            .build::<Dimension2>();
+        let mut unlimited = ModelBuilder::new().build::<Dimension2>();
+        unlimited.set_coordinate(*m.get_coordinate());
+
+        let before = *m.get_coordinate();
+        m.observe(&remote, Duration::from_secs(10));
+        unlimited.observe(&remote, Duration::from_secs(10));
+
+        let moved = |c: &Coordinate<Dimension2>| {
+            (
+                c.vector().distance(before.vector()).0,
+                (c.height() - before.height()).abs(),
+            )
+        };
+        let (dist, height) = moved(m.get_coordinate());
+        assert!((dist - max.as_secs_f64()).abs() < 1e-12);
+        assert!((height - max.as_secs_f64()).abs() < 1e-12);
+
+        let (dist, height) = moved(unlimited.get_coordinate());
+        assert!(dist > 1.0);
+        assert!(height > 0.1);
+        assert_eq!(
+            m.get_coordinate().error(),
+            unlimited.get_coordinate().error()
+        );
+    }
+
+    #[test]
+    fn max_error() {
+        let mut m = ModelBuilder::new().max_error(1.5).build::<Dimension2>();
+        assert_eq!(m.get_coordinate().error(), 1.5);
+
+        // A confident remote and wildly wrong RTTs drive the error upwards.
+        let remote = Coordinate::new(Dimension2([0.05, 0.0]), 0.01, 0.0);
+        let start = Coordinate::new(Dimension2([0.0, 0.0]), 1.0, 0.0);
+        let mut unbounded = ModelBuilder::new().build::<Dimension2>();
+        unbounded.set_coordinate(start);
+        m.set_coordinate(start);
+        let (mut peak, mut unbounded_peak) = (0.0_f64, 0.0_f64);
+        for rtt in [1e-6, 100.0].iter().cycle().take(50) {
+            m.observe(&remote, Duration::from_secs_f64(*rtt));
+            unbounded.observe(&remote, Duration::from_secs_f64(*rtt));
+            peak = peak.max(m.get_coordinate().error());
+            unbounded_peak = unbounded_peak.max(unbounded.get_coordinate().error());
+        }
+        assert_eq!(peak, 1.5);
+        assert!(unbounded_peak > 1.5);
+    }
+
+    #[test]
+    fn min_height() {
+        // Observing RTTs shorter than the heights pushes the height down.
+        let remote = Coordinate::new(Dimension2([0.001, 0.0]), 0.01, 0.0);
+        let lowest = |builder: ModelBuilder| {
+            let mut m = builder.build::<Dimension2>();
+            for _ in 0..200 {
+                m.observe(&remote, Duration::from_micros(1000));
+            }
+            m.get_coordinate().height()
+        };
+
+        assert_eq!(lowest(ModelBuilder::new()), 1.0e-5);
+        assert_eq!(
+            lowest(ModelBuilder::new().min_height(Duration::from_micros(200))),
+            2.0e-4
+        );
+        assert!(lowest(ModelBuilder::new().min_height(Duration::ZERO)) < 1.0e-5);
+    }
+
+    #[test]
+    fn euclidean() {
+        let truth = [[0.0, 0.0], [0.03, 0.0], [0.0, 0.04], [0.03, 0.04]];
+        let mut models = truth
+            .iter()
+            .map(|_| ModelBuilder::new().euclidean().build::<Dimension2>())
+            .collect::<Vec<_>>();
+
+        for _ in 0..200 {
+            for i in 0..models.len() {
+                for j in 0..models.len() {
+                    if i == j {
+                        continue;
+                    }
+                    let d = Dimension2(truth[i]).distance(&Dimension2(truth[j])).0;
+                    let remote = *models[j].get_coordinate();
+                    models[i].observe(&remote, Duration::from_secs_f64(d));
+                }
+            }
+        }
+
+        for (i, m) in models.iter().enumerate() {
+            assert_eq!(m.get_coordinate().height(), 0.0);
+            for (j, other) in models.iter().enumerate() {
+                let want = Dimension2(truth[i]).distance(&Dimension2(truth[j])).0;
+                let got = estimate_rtt(m.get_coordinate(), other.get_coordinate());
+                assert!((got.as_secs_f64() - want).abs() < 1e-3);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid cc")]
+    fn invalid_cc() {
+        ModelBuilder::new().cc(1.5);
+    }
+}
@@ -0,0 +1,97 @@
+use crate::coordinate::Coordinate;
+use crate::model::{adjusted, estimate_rtt};
+use crate::vector::Vector;
+use std::time::Duration;
+
+/// Returns the estimated round-trip time between `local` and each coordinate
+/// in `remotes`, in the same order.
+///
+/// This is equivalent to calling [`estimate_rtt`] for each remote coordinate.
+/// Callers ranking thousands of peers repeatedly should keep them in a
+/// [`PeerTable`](crate::PeerTable), which stores the coordinates in a
+/// struct-of-arrays layout (one contiguous column per dimension) so
+/// [`estimate_all`](crate::PeerTable::estimate_all) runs as a handful of
+/// tight loops the compiler can vectorise:
+///
+/// ```
+/// use vivaldi::{estimate_rtt_many, Model, vector::Dimension3};
+///
+/// let local = Model::<Dimension3>::new();
+/// let peers = (0..3)
+///     .map(|_| *Model::<Dimension3>::new().get_coordinate())
+///     .collect::<Vec<_>>();
+///
+/// let rtts = estimate_rtt_many(local.get_coordinate(), &peers);
+/// assert_eq!(rtts.len(), 3);
+/// ```
+pub fn estimate_rtt_many<V: Vector>(
+    local: &Coordinate<V>,
+    remotes: &[Coordinate<V>],
+) -> Vec<Duration> {
+    // Transposing the remotes into columns costs more than the vectorised
+    // distance computation saves, so each is estimated in turn.
+    remotes.iter().map(|r| estimate_rtt(local, r)).collect()
+}
+
+/// Writes the estimated RTT (in seconds) between `local` and each remote
+/// stored in a struct-of-arrays layout into `out`.
+///
+/// `columns` holds one column of `out.len()` values for each dimension, and
+/// `heights` and `adjustments` hold the height and adjustment of each remote.
+pub(crate) fn estimate_columns<V: Vector>(
+    local: &Coordinate<V>,
+    columns: &[Vec<f64>],
+    heights: &[f64],
+    adjustments: &[f64],
+    out: &mut [f64],
+) {
+    debug_assert_eq!(heights.len(), out.len());
+    debug_assert_eq!(adjustments.len(), out.len());
+
+    // Accumulate the squared distance one dimension at a time.
+    for v in out.iter_mut() {
+        *v = 0.0;
+    }
+    for (l, column) in local.vector().components().iter().zip(columns) {
+        for (acc, r) in out.iter_mut().zip(column) {
+            let diff = l - r;
+            *acc += diff * diff;
+        }
+    }
+
+    // Apply the fixed cost heights
+    let local_height = local.height();
+    for ((acc, h), a) in out.iter_mut().zip(heights).zip(adjustments) {
+        *acc = adjusted(acc.sqrt() + local_height + h, local.adjustment() + a);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::Dimension3;
+
+    #[test]
+    fn matches_estimate_rtt() {
+        let local = Coordinate::new(Dimension3([1.0, 2.0, 3.0]), 1.0, 0.5);
+        let remotes = vec![
+            Coordinate::new(Dimension3([0.0, 0.0, 0.0]), 1.0, 0.1),
+            Coordinate::new(Dimension3([-1.0, 4.0, 2.5]), 1.0, 0.2),
+            Coordinate::new(Dimension3([1.0, 2.0, 3.0]), 1.0, 0.3).with_adjustment(0.01),
+            Coordinate::new(Dimension3([1.0, 2.0, 3.0]), 1.0, 0.3).with_adjustment(-1.0),
+        ];
+
+        let got = estimate_rtt_many(&local, &remotes);
+
+        assert_eq!(got.len(), remotes.len());
+        for (rtt, remote) in got.iter().zip(&remotes) {
+            assert_eq!(*rtt, estimate_rtt(&local, remote));
+        }
+    }
+
+    #[test]
+    fn empty() {
+        let local = Coordinate::new(Dimension3::default(), 1.0, 0.5);
+        assert!(estimate_rtt_many(&local, &[]).is_empty());
+    }
+}
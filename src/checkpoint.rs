@@ -0,0 +1,574 @@
+use crate::clock::{Clock, SystemClock};
+use crate::coordinate::Coordinate;
+use crate::error::Error;
+use crate::force::ForceFunction;
+use crate::model::Model;
+use crate::vector::Vector;
+use rand::Rng;
+use std::fs;
+use std::hash::Hasher;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Identifies an encoded checkpoint.
+const CHECKPOINT_MAGIC: &[u8; 4] = b"VIVC";
+
+/// The version of the checkpoint encoding.
+///
+/// Version 1 checkpoints (written before the coordinate adjustment was
+/// persisted) are still accepted, restoring a zero adjustment.
+const CHECKPOINT_VERSION: u8 = 2;
+
+/// A storage backend for model checkpoints written by a [`Checkpointer`].
+///
+/// Implementations must replace the stored checkpoint atomically - a reader
+/// must observe either the previous or the new checkpoint in full, even if the
+/// process crashes mid-write. Checkpoints are small (tens of bytes).
+pub trait CheckpointStore {
+    /// Replaces the stored checkpoint with `data`.
+    fn save(&mut self, data: &[u8]) -> io::Result<()>;
+
+    /// Returns the stored checkpoint, or `None` if no checkpoint has been
+    /// stored.
+    fn load(&mut self) -> io::Result<Option<Vec<u8>>>;
+}
+
+/// A [`CheckpointStore`] writing to a file.
+///
+/// Each checkpoint is written to a temporary file alongside `path`, synced to
+/// disk and then renamed over `path`, so a crash never leaves a partially
+/// written checkpoint in place.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileStore {
+    path: PathBuf,
+}
+
+impl FileStore {
+    /// Initialises a store writing checkpoints to `path`.
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        FileStore {
+            path: path.as_ref().to_path_buf(),
+        }
+    }
+}
+
+impl CheckpointStore for FileStore {
+    fn save(&mut self, data: &[u8]) -> io::Result<()> {
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+
+        let mut f = fs::File::create(&tmp)?;
+        f.write_all(data)?;
+        f.sync_all()?;
+        fs::rename(&tmp, &self.path)
+    }
+
+    fn load(&mut self) -> io::Result<Option<Vec<u8>>> {
+        match fs::read(&self.path) {
+            Ok(v) => Ok(Some(v)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// An in-memory [`CheckpointStore`], useful for tests or for embedding the
+/// checkpoint in application-managed storage.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoryStore {
+    data: Option<Vec<u8>>,
+}
+
+impl MemoryStore {
+    /// Returns the stored checkpoint, if any.
+    pub fn data(&self) -> Option<&[u8]> {
+        self.data.as_deref()
+    }
+}
+
+impl CheckpointStore for MemoryStore {
+    fn save(&mut self, data: &[u8]) -> io::Result<()> {
+        self.data = Some(data.to_vec());
+        Ok(())
+    }
+
+    fn load(&mut self) -> io::Result<Option<Vec<u8>>> {
+        Ok(self.data.clone())
+    }
+}
+
+/// When a [`Checkpointer`] writes a checkpoint - a checkpoint is written when
+/// any of the configured conditions is met.
+///
+/// The default policy never writes a checkpoint.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CheckpointPolicy {
+    /// Write a checkpoint after this many observations.
+    pub every_observations: Option<u64>,
+
+    /// Write a checkpoint when at least this long has passed since the last.
+    pub every: Option<Duration>,
+
+    /// Write a checkpoint when the coordinate has moved at least this far
+    /// (in seconds of RTT) from the last checkpoint.
+    pub displacement: Option<f64>,
+}
+
+/// Periodically snapshots the coordinate of a [`Model`] to a
+/// [`CheckpointStore`] according to a [`CheckpointPolicy`], so a restarted
+/// node can resume from its last position rather than the origin.
+///
+/// ```
+/// use std::time::Duration;
+/// use vivaldi::{CheckpointPolicy, Checkpointer, MemoryStore, Model, vector::Dimension3};
+///
+/// let mut model = Model::<Dimension3>::new();
+/// let mut checkpointer = Checkpointer::new(
+///     MemoryStore::default(),
+///     CheckpointPolicy {
+///         every_observations: Some(100),
+///         every: Some(Duration::from_secs(60)),
+///         displacement: Some(0.010),
+///     },
+/// );
+///
+/// // On startup:
+/// checkpointer.restore(&mut model)?;
+///
+/// // After each observation:
+/// # let remote = Model::<Dimension3>::new();
+/// model.observe(remote.get_coordinate(), Duration::from_millis(10));
+/// checkpointer.observed(&model)?;
+/// # Ok::<(), vivaldi::Error>(())
+/// ```
+///
+/// Only the [`Coordinate`] (vector, error, height and adjustment) is saved -
+/// the remaining model state, such as the latency filters, observation
+/// history, health window and configuration, is not persisted and starts
+/// afresh in the restored model. Use the `serde` feature to persist the
+/// complete model instead.
+///
+/// Checkpoints carry a checksum, and a corrupt checkpoint is reported by
+/// [`restore`](Checkpointer::restore) rather than loaded.
+#[derive(Debug)]
+pub struct Checkpointer<S, C = SystemClock> {
+    store: S,
+    policy: CheckpointPolicy,
+    clock: C,
+
+    observations: u64,
+    last_time: Duration,
+    last_vector: Option<Vec<f64>>,
+}
+
+impl<S> Checkpointer<S>
+where
+    S: CheckpointStore,
+{
+    /// Initialises a checkpointer writing to `store` according to `policy`.
+    pub fn new(store: S, policy: CheckpointPolicy) -> Self {
+        Checkpointer::with_clock(store, policy, SystemClock)
+    }
+}
+
+impl<S, C> Checkpointer<S, C>
+where
+    S: CheckpointStore,
+    C: Clock,
+{
+    /// Initialises a checkpointer reading the current time from `clock`.
+    pub fn with_clock(store: S, policy: CheckpointPolicy, clock: C) -> Self {
+        let last_time = clock.now();
+        Checkpointer {
+            store,
+            policy,
+            clock,
+            observations: 0,
+            last_time,
+            last_vector: None,
+        }
+    }
+
+    /// Returns the underlying store.
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Records an observation made by `model`, writing a checkpoint if the
+    /// policy conditions are met.
+    ///
+    /// Returns true if a checkpoint was written.
+    pub fn observed<V, MC, F, R>(&mut self, model: &Model<V, MC, F, f64, R>) -> Result<bool, Error>
+    where
+        V: Vector + std::fmt::Debug,
+        MC: Clock,
+        F: ForceFunction,
+        R: Rng,
+    {
+        self.observations += 1;
+        let coord = model.get_coordinate();
+
+        let p = &self.policy;
+        let due = p.every_observations.is_some_and(|n| self.observations >= n)
+            || p.every
+                .is_some_and(|t| self.clock.now().saturating_sub(self.last_time) >= t)
+            || p.displacement.is_some_and(|d| {
+                let last = match &self.last_vector {
+                    Some(v) => v,
+                    None => return true,
+                };
+                let moved = last
+                    .iter()
+                    .zip(coord.vector().components())
+                    .map(|(a, b)| (a - b) * (a - b))
+                    .sum::<f64>()
+                    .sqrt();
+                moved >= d
+            });
+
+        if !due {
+            return Ok(false);
+        }
+        self.checkpoint(model)?;
+        Ok(true)
+    }
+
+    /// Writes a checkpoint of `model` immediately, such as on shutdown.
+    pub fn checkpoint<V, MC, F, R>(&mut self, model: &Model<V, MC, F, f64, R>) -> Result<(), Error>
+    where
+        V: Vector + std::fmt::Debug,
+        MC: Clock,
+        F: ForceFunction,
+        R: Rng,
+    {
+        let coord = model.get_coordinate();
+        self.store
+            .save(&encode(coord))
+            .map_err(|e| Error::Storage(e.to_string()))?;
+
+        self.observations = 0;
+        self.last_time = self.clock.now();
+        self.last_vector = Some(coord.vector().components().to_vec());
+        Ok(())
+    }
+
+    /// Loads the stored checkpoint into `model`, returning false (and leaving
+    /// the model unchanged) if no checkpoint has been stored. The coordinate
+    /// is restored with [`Model::restore_coordinate`], bounding it by the
+    /// configuration of `model`.
+    ///
+    /// Returns [`Error::Serialization`] if the checkpoint is corrupt,
+    /// [`Error::DimensionMismatch`] if it is of a different dimensionality,
+    /// an error from [`Coordinate::validate`] if the stored coordinate is
+    /// invalid, and [`Error::Storage`] if reading it fails.
+    pub fn restore<V, MC, F, R>(
+        &mut self,
+        model: &mut Model<V, MC, F, f64, R>,
+    ) -> Result<bool, Error>
+    where
+        V: Vector + std::fmt::Debug,
+        MC: Clock,
+        F: ForceFunction,
+        R: Rng,
+    {
+        let data = match self
+            .store
+            .load()
+            .map_err(|e| Error::Storage(e.to_string()))?
+        {
+            Some(v) => v,
+            None => return Ok(false),
+        };
+
+        model.restore_coordinate(decode::<V>(&data)?)?;
+        self.last_vector = Some(model.get_coordinate().vector().components().to_vec());
+        Ok(true)
+    }
+}
+
+/// Encodes `coord` as:
+///
+/// ```text
+///     magic (4) | version (1) | dimensions (1) | components (8 each) |
+///     error (8) | height (8) | adjustment (8) |
+///     FNV-1a checksum of the preceding bytes (8)
+/// ```
+///
+/// with all values little-endian.
+fn encode<V: Vector>(coord: &Coordinate<V>) -> Vec<u8> {
+    let components = coord.vector().components();
+
+    let mut buf = Vec::with_capacity(6 + 8 * (components.len() + 4));
+    buf.extend_from_slice(CHECKPOINT_MAGIC);
+    buf.push(CHECKPOINT_VERSION);
+    buf.push(components.len() as u8);
+    for v in components {
+        buf.extend_from_slice(&v.to_le_bytes());
+    }
+    buf.extend_from_slice(&coord.error().to_le_bytes());
+    buf.extend_from_slice(&coord.height().to_le_bytes());
+    buf.extend_from_slice(&coord.adjustment().to_le_bytes());
+
+    let sum = fnv1a(&buf);
+    buf.extend_from_slice(&sum.to_le_bytes());
+    buf
+}
+
+fn decode<V: Vector>(data: &[u8]) -> Result<Coordinate<V>, Error> {
+    let corrupt = |msg: &str| Error::Serialization(format!("invalid checkpoint: {}", msg));
+
+    if data.len() < 6 + 8 * 3 {
+        return Err(corrupt("truncated"));
+    }
+    let (body, sum) = data.split_at(data.len() - 8);
+    if fnv1a(body).to_le_bytes() != sum {
+        return Err(corrupt("checksum mismatch"));
+    }
+    if &body[..4] != CHECKPOINT_MAGIC {
+        return Err(corrupt("bad magic"));
+    }
+    // Version 1 checkpoints carry no adjustment.
+    let trailing = match body[4] {
+        1 => 2,
+        CHECKPOINT_VERSION => 3,
+        _ => return Err(corrupt("unsupported version")),
+    };
+
+    let dims = body[5] as usize;
+    let values = body[6..]
+        .chunks_exact(8)
+        .map(|b| {
+            let mut v = [0; 8];
+            v.copy_from_slice(b);
+            f64::from_le_bytes(v)
+        })
+        .collect::<Vec<_>>();
+    if values.len() != dims + trailing || body[6..].len() % 8 != 0 {
+        return Err(corrupt("length mismatch"));
+    }
+
+    let vector = V::from_components(&values[..dims]).ok_or(Error::DimensionMismatch {
+        expected: V::default().components().len(),
+        got: dims,
+    })?;
+    let adjustment = values.get(dims + 2).copied().unwrap_or(0.0);
+    let coord = Coordinate::new(vector, values[dims], values[dims + 1]).with_adjustment(adjustment);
+    coord.validate()?;
+    Ok(coord)
+}
+
+/// Returns the 64-bit FNV-1a hash of `data`.
+pub(crate) fn fnv1a(data: &[u8]) -> u64 {
+    let mut h = Fnv1a::default();
+    h.write(data);
+    h.finish()
+}
+
+/// A [`Hasher`] computing the 64-bit FNV-1a hash of the written bytes.
+///
+/// Unlike [`DefaultHasher`](std::collections::hash_map::DefaultHasher), the
+/// hash is fixed across Rust releases and platforms (integers are hashed as
+/// little-endian bytes, and `usize` as a `u64`), so may be persisted.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.0 = bytes.iter().fold(self.0, |h, b| {
+            (h ^ *b as u64).wrapping_mul(0x0100_0000_01b3)
+        });
+    }
+
+    fn write_u16(&mut self, v: u16) {
+        self.write(&v.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, v: u32) {
+        self.write(&v.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, v: u64) {
+        self.write(&v.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, v: u128) {
+        self.write(&v.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, v: usize) {
+        self.write_u64(v as u64);
+    }
+
+    fn write_i16(&mut self, v: i16) {
+        self.write_u16(v as u16);
+    }
+
+    fn write_i32(&mut self, v: i32) {
+        self.write_u32(v as u32);
+    }
+
+    fn write_i64(&mut self, v: i64) {
+        self.write_u64(v as u64);
+    }
+
+    fn write_i128(&mut self, v: i128) {
+        self.write_u128(v as u128);
+    }
+
+    fn write_isize(&mut self, v: isize) {
+        self.write_u64(v as u64);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use crate::force::LogarithmicSpring;
+    use crate::vector::{Dimension2, Dimension3};
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn coord(x: f64) -> Coordinate<Dimension3> {
+        Coordinate::new(Dimension3([x, 2.0, 3.0]), 0.5, 0.1)
+    }
+
+    fn model_at(x: f64) -> Model<Dimension3> {
+        let mut m = Model::new();
+        m.set_coordinate(coord(x));
+        m
+    }
+
+    #[test]
+    fn encode_decode() {
+        let c = coord(1.0);
+        assert_eq!(decode::<Dimension3>(&encode(&c)), Ok(c));
+
+        assert_eq!(
+            decode::<Dimension2>(&encode(&c)),
+            Err(Error::DimensionMismatch {
+                expected: 2,
+                got: 3
+            })
+        );
+    }
+
+    #[test]
+    fn encode_decode_adjustment() {
+        let c = coord(1.0).with_adjustment(0.004);
+        assert_eq!(decode::<Dimension3>(&encode(&c)), Ok(c));
+    }
+
+    #[test]
+    fn decode_version_1() {
+        // A version 1 checkpoint has no adjustment.
+        let c = coord(1.0);
+        let mut data = encode(&c.with_adjustment(0.004));
+        data.truncate(data.len() - 16);
+        data[4] = 1;
+        let sum = fnv1a(&data);
+        data.extend_from_slice(&sum.to_le_bytes());
+
+        assert_eq!(decode::<Dimension3>(&data), Ok(c));
+    }
+
+    #[test]
+    fn detects_corruption() {
+        let mut data = encode(&coord(1.0));
+        data[10] ^= 0x01;
+        assert!(matches!(
+            decode::<Dimension3>(&data),
+            Err(Error::Serialization(_))
+        ));
+
+        assert!(matches!(
+            decode::<Dimension3>(&data[..12]),
+            Err(Error::Serialization(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_coordinate() {
+        let data = encode(&Coordinate::new(Dimension3([1.0, 2.0, 3.0]), -1.0, 0.1));
+        assert_eq!(
+            decode::<Dimension3>(&data),
+            Err(Error::OutOfRange { field: "error" })
+        );
+    }
+
+    #[test]
+    fn generic_model() {
+        let mut c = Checkpointer::new(MemoryStore::default(), CheckpointPolicy::default());
+        let mut m = Model::<Dimension3>::new()
+            .with_force_function(LogarithmicSpring)
+            .with_rng(StdRng::seed_from_u64(42));
+        m.set_coordinate(coord(2.0));
+        c.checkpoint(&m).unwrap();
+
+        let mut restored = Model::<Dimension3>::new()
+            .with_force_function(LogarithmicSpring)
+            .with_rng(StdRng::seed_from_u64(1));
+        assert_eq!(c.restore(&mut restored), Ok(true));
+        assert_eq!(restored.get_coordinate(), &coord(2.0));
+    }
+
+    #[test]
+    fn policy() {
+        let clock = MockClock::default();
+        let mut c = Checkpointer::with_clock(
+            MemoryStore::default(),
+            CheckpointPolicy {
+                every_observations: Some(3),
+                every: Some(Duration::from_secs(60)),
+                displacement: Some(0.5),
+            },
+            clock.clone(),
+        );
+        let m = model_at(1.0);
+
+        // The first observation checkpoints, as there is no prior position.
+        assert_eq!(c.observed(&m), Ok(true));
+        assert_eq!(c.observed(&m), Ok(false));
+        assert_eq!(c.observed(&m), Ok(false));
+        assert_eq!(c.observed(&m), Ok(true));
+
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(c.observed(&m), Ok(true));
+
+        assert_eq!(c.observed(&model_at(1.4)), Ok(false));
+        assert_eq!(c.observed(&model_at(1.6)), Ok(true));
+    }
+
+    #[test]
+    fn file_store_round_trip() {
+        let dir = std::env::temp_dir().join(format!("vivaldi-checkpoint-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("model");
+
+        let mut c = Checkpointer::new(FileStore::new(&path), CheckpointPolicy::default());
+        let mut restored = Model::<Dimension3>::new();
+        assert_eq!(c.restore(&mut restored), Ok(false));
+
+        c.checkpoint(&model_at(4.0)).unwrap();
+        assert_eq!(c.restore(&mut restored), Ok(true));
+        assert_eq!(restored.get_coordinate(), &coord(4.0));
+
+        // Corrupt the stored checkpoint.
+        let mut data = fs::read(&path).unwrap();
+        data[8] ^= 0xff;
+        fs::write(&path, data).unwrap();
+        assert!(c.restore(&mut restored).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,111 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A source of the current time for time-dependent features, such as
+/// staleness tracking and coordinate smoothing.
+///
+/// Time is represented as the [`Duration`] elapsed since a fixed epoch chosen
+/// by the implementation, rather than an [`Instant`](std::time::Instant), so
+/// timestamps can be persisted and exchanged between nodes, and targets without
+/// `std::time` can provide their own source.
+pub trait Clock {
+    /// Returns the current time as the duration elapsed since the clock's
+    /// epoch.
+    fn now(&self) -> Duration;
+}
+
+/// A [`Clock`] reading the system wall-clock time, measured from the UNIX
+/// epoch.
+///
+/// Wall-clock time can step backwards - users of a clock treat time moving
+/// backwards as no time passing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Duration {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+    }
+}
+
+/// A manually advanced [`Clock`] for deterministic tests and simulations.
+///
+/// Clones share the same time, so a handle can be kept to advance the time
+/// seen by a component given another clone.
+///
+/// ```
+/// use std::time::Duration;
+/// use vivaldi::{Clock, MockClock};
+///
+/// let clock = MockClock::default();
+/// let handle = clock.clone();
+///
+/// handle.advance(Duration::from_secs(60));
+/// assert_eq!(clock.now(), Duration::from_secs(60));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct MockClock {
+    nanos: Arc<AtomicU64>,
+}
+
+impl MockClock {
+    /// Initialises a clock reading `now`.
+    pub fn new(now: Duration) -> Self {
+        let clock = MockClock::default();
+        clock.set(now);
+        clock
+    }
+
+    /// Sets the current time to `now`.
+    pub fn set(&self, now: Duration) {
+        self.nanos.store(now.as_nanos() as u64, Ordering::SeqCst);
+    }
+
+    /// Moves the current time forwards by `by`.
+    pub fn advance(&self, by: Duration) {
+        self.nanos.fetch_add(by.as_nanos() as u64, Ordering::SeqCst);
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Duration {
+        Duration::from_nanos(self.nanos.load(Ordering::SeqCst))
+    }
+}
+
+impl<C> Clock for &C
+where
+    C: Clock + ?Sized,
+{
+    fn now(&self) -> Duration {
+        (**self).now()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mock_clock() {
+        let clock = MockClock::new(Duration::from_secs(10));
+        let handle = clock.clone();
+        assert_eq!(clock.now(), Duration::from_secs(10));
+
+        handle.advance(Duration::from_millis(1500));
+        assert_eq!(clock.now(), Duration::from_millis(11_500));
+
+        handle.set(Duration::from_secs(1));
+        assert_eq!(clock.now(), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn system_clock() {
+        let a = SystemClock.now();
+        assert!(a > Duration::from_secs(0));
+        assert!(SystemClock.now() >= a);
+    }
+}
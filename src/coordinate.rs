@@ -1,6 +1,11 @@
-use crate::vector::Vector;
+use crate::error::Error;
+use crate::vector::{Magnitude, Scalar, Vector};
+#[cfg(feature = "serde")]
+use std::convert::TryFrom;
 
-/// The minimum "height" a coordinate can have.
+/// The default minimum "height" of the coordinate of a
+/// [`Model`](crate::Model), configurable with
+/// [`ModelBuilder::min_height`](crate::ModelBuilder::min_height).
 ///
 /// The paper states:
 ///
@@ -10,29 +15,170 @@ use crate::vector::Vector;
 /// ```
 ///
 /// So any +ve value can act as the base.
-const MIN_HEIGHT: f64 = 1.0e-5;
+pub(crate) const MIN_HEIGHT: f64 = 1.0e-5;
+
+/// The largest absolute vector component, height or adjustment, in seconds,
+/// of a valid coordinate.
+const MAX_VALID_SECONDS: f64 = 1.0e3;
+
+/// The largest error estimate of a valid coordinate.
+pub(crate) const MAX_VALID_ERROR: f64 = 1.0e3;
+
+/// The largest number of dimensions accepted by
+/// [`Coordinate::parse_untrusted`], bounding the input size.
+const MAX_UNTRUSTED_DIMENSIONS: usize = 64;
+
+/// The largest input accepted by [`Coordinate::parse_untrusted`], leaving room
+/// for the fields appended by future versions of the layout.
+const MAX_ENCODED_LEN: usize = 1024;
+
+/// The version of the layout written by [`Coordinate::to_bytes`].
+pub const WIRE_VERSION: u8 = 1;
+
+/// Set in the version byte starting every encoding.
+const VERSIONED: u8 = 0x80;
 
 /// Coordinate represents a point in the Vivaldi model.
 ///
 /// A Coordinate contains the Euclidean coordinate, estimated position error and
-/// current height above the Euclidean plane.
-#[derive(Debug, Default, Copy, Clone)]
+/// current height above the Euclidean plane. Coordinates are published by a
+/// [`Model`](crate::Model), and coordinates received over a custom transport
+/// or restored from storage are constructed with
+/// [`try_new`](Coordinate::try_new).
+///
+/// With the `serde` feature enabled, deserialising a coordinate validates it
+/// as [`try_new`](Coordinate::try_new) does (additionally bounding the
+/// adjustment to 1000 seconds), so a malformed or malicious peer cannot
+/// inject NaN or absurd values into a [`Model`](crate::Model).
+#[derive(Debug, Copy, Clone, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
-pub struct Coordinate<V>
+#[cfg_attr(
+    feature = "serde",
+    serde(
+        try_from = "RawCoordinate<V, T>",
+        bound(deserialize = "V: serde::Deserialize<'de>, T: serde::Deserialize<'de>")
+    )
+)]
+#[cfg_attr(feature = "rkyv", derive(rkyv::Archive, rkyv::Serialize))]
+pub struct Coordinate<V, T = f64>
 where
-    V: Vector,
+    V: Vector<T>,
+    T: Scalar,
 {
     vector: V,
-    error: f64,
-    height: f64,
+    error: T,
+    height: T,
+    /// The offset added to RTT estimates, learned by a [`Model`] with an
+    /// [adjustment window](crate::ModelBuilder::adjustment_window).
+    ///
+    /// [`Model`]: crate::Model
+    adjustment: T,
+
+    /// The cached magnitude of `vector`, derived on construction.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    #[cfg_attr(feature = "rkyv", rkyv(with = rkyv::with::Skip))]
+    magnitude: T,
 }
 
-impl<V> Coordinate<V>
+/// The serialised form of a [`Coordinate`], used to derive the cached
+/// magnitude when deserialising.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct RawCoordinate<V, T> {
+    vector: V,
+    error: T,
+    height: T,
+    #[serde(default)]
+    adjustment: T,
+}
+
+#[cfg(feature = "serde")]
+impl<V, T> TryFrom<RawCoordinate<V, T>> for Coordinate<V, T>
 where
-    V: Vector,
+    V: Vector<T>,
+    T: Scalar,
+{
+    type Error = Error;
+
+    fn try_from(raw: RawCoordinate<V, T>) -> Result<Self, Self::Error> {
+        let c = Coordinate::new(raw.vector, raw.error, raw.height).with_adjustment(raw.adjustment);
+        c.validate()?;
+        Ok(c)
+    }
+}
+
+impl<V, T> Default for Coordinate<V, T>
+where
+    V: Vector<T>,
+    T: Scalar,
+{
+    fn default() -> Self {
+        Coordinate::new(V::default(), T::zero(), T::zero())
+    }
+}
+
+impl<V, T> Coordinate<V, T>
+where
+    V: Vector<T>,
+    T: Scalar,
 {
+    /// Constructs a coordinate from a received or persisted `vector`, `error`
+    /// estimate and `height`, validating every field:
+    ///
+    /// * [`Error::NonFiniteCoordinate`] if any value is NaN or infinite.
+    /// * [`Error::OutOfRange`] if a component or the height exceeds 1000
+    ///   seconds, the height is negative, or the error estimate is negative
+    ///   or above 1000.
+    ///
+    /// ```
+    /// use vivaldi::{Coordinate, Error, vector::Dimension2};
+    ///
+    /// let coord = Coordinate::try_new(Dimension2([0.01, 0.02]), 0.5, 0.001)?;
+    ///
+    /// assert_eq!(
+    ///     Coordinate::try_new(Dimension2([f64::NAN, 0.02]), 0.5, 0.001),
+    ///     Err(Error::NonFiniteCoordinate),
+    /// );
+    /// # Ok::<(), vivaldi::Error>(())
+    /// ```
+    pub fn try_new(vector: V, error: T, height: T) -> Result<Self, Error> {
+        let c = Coordinate::new(vector, error, height);
+        c.validate()?;
+        Ok(c)
+    }
+
+    /// Returns an error if the coordinate contains a non-finite value, or a
+    /// value outside of the range a real network could produce, as described
+    /// by [`try_new`](Coordinate::try_new) (additionally bounding the
+    /// adjustment to 1000 seconds).
+    pub fn validate(&self) -> Result<(), Error> {
+        if !self.is_finite() {
+            return Err(Error::NonFiniteCoordinate);
+        }
+
+        let components = self.vector.components();
+        if components
+            .iter()
+            .any(|v| v.into_f64().abs() > MAX_VALID_SECONDS)
+        {
+            return Err(Error::OutOfRange { field: "component" });
+        }
+        if !(0.0..=MAX_VALID_ERROR).contains(&self.error.into_f64()) {
+            return Err(Error::OutOfRange { field: "error" });
+        }
+        if !(0.0..=MAX_VALID_SECONDS).contains(&self.height.into_f64()) {
+            return Err(Error::OutOfRange { field: "height" });
+        }
+        if self.adjustment.into_f64().abs() > MAX_VALID_SECONDS {
+            return Err(Error::OutOfRange {
+                field: "adjustment",
+            });
+        }
+        Ok(())
+    }
+
     /// Returns the current estimated position error.
-    pub fn error(&self) -> f64 {
+    pub fn error(&self) -> T {
         self.error
     }
 
@@ -41,32 +187,519 @@ where
         &self.vector
     }
 
+    /// Returns the magnitude of the Euclidean coordinate.
+    ///
+    /// The magnitude is computed once when the Coordinate is constructed, so
+    /// this is cheaper than calling [`Vector::magnitude`] on
+    /// [`vector`](Coordinate::vector).
+    pub fn magnitude(&self) -> Magnitude<T> {
+        Magnitude(self.magnitude)
+    }
+
     /// Returns the height of the Coordinate above the Euclidean plane.
-    pub fn height(&self) -> f64 {
-        if self.height < MIN_HEIGHT {
-            return MIN_HEIGHT;
-        }
+    pub fn height(&self) -> T {
         self.height
     }
 
-    pub(crate) fn new(vector: V, error: f64, height: f64) -> Self {
+    /// Returns the offset added to RTT estimates involving this coordinate,
+    /// compensating for the systematic error of the embedding as HashiCorp's
+    /// Serf does.
+    ///
+    /// This is zero unless the coordinate is published by a model with an
+    /// [adjustment window](crate::ModelBuilder::adjustment_window).
+    pub fn adjustment(&self) -> T {
+        self.adjustment
+    }
+
+    /// Returns true if every vector component, the error, the height and the
+    /// adjustment are finite.
+    pub(crate) fn is_finite(&self) -> bool {
+        self.error.is_finite()
+            && self.height.is_finite()
+            && self.adjustment.is_finite()
+            && self.vector.components().iter().all(|v| v.is_finite())
+    }
+
+    /// Consumes the Coordinate, returning the Euclidean coordinate.
+    pub(crate) fn into_vector(self) -> V {
+        self.vector
+    }
+
+    pub(crate) fn new(vector: V, error: T, height: T) -> Self {
+        let magnitude = vector.magnitude().0;
         Coordinate {
             vector,
             error,
             height,
+            adjustment: T::zero(),
+            magnitude,
         }
     }
+
+    /// Returns the coordinate with its [adjustment](Coordinate::adjustment) set
+    /// to `adjustment`, for restoring the coordinate of a model with an
+    /// [adjustment window](crate::ModelBuilder::adjustment_window) or
+    /// constructing test fixtures:
+    ///
+    /// ```
+    /// use vivaldi::{Coordinate, vector::Dimension2};
+    ///
+    /// let coord = Coordinate::try_new(Dimension2([0.01, 0.02]), 0.5, 0.001)?
+    ///     .with_adjustment(-0.0002);
+    /// coord.validate()?;
+    /// # Ok::<(), vivaldi::Error>(())
+    /// ```
+    ///
+    /// The adjustment is not validated - call
+    /// [`validate`](Coordinate::validate) if it was received from an untrusted
+    /// source.
+    pub fn with_adjustment(mut self, adjustment: T) -> Self {
+        self.adjustment = adjustment;
+        self
+    }
+}
+
+impl<V> Coordinate<V>
+where
+    V: Vector,
+{
+    /// Encodes the coordinate into the fixed-size little-endian layout parsed
+    /// by [`from_bytes`](Coordinate::from_bytes), suitable for embedding in a
+    /// UDP packet or heartbeat:
+    ///
+    /// ```text
+    ///     version (1) | dimensions (1) | components (8 each) | error (8) | height (8) | adjustment (8)
+    /// ```
+    ///
+    /// The encoding of a coordinate of `N` dimensions is always
+    /// `2 + 8 * (N + 3)` bytes long. The version byte holds the
+    /// [`WIRE_VERSION`] with the high bit set - see
+    /// [`parse_untrusted`](Coordinate::parse_untrusted) for the versions
+    /// accepted when decoding.
+    ///
+    /// ```
+    /// use vivaldi::{Coordinate, Model, vector::Dimension2};
+    ///
+    /// let model = Model::<Dimension2>::new();
+    /// let packet = model.get_coordinate().to_bytes();
+    /// assert_eq!(packet.len(), 42);
+    ///
+    /// let coord = Coordinate::<Dimension2>::from_bytes(&packet)?;
+    /// assert_eq!(&coord, model.get_coordinate());
+    /// # Ok::<(), vivaldi::Error>(())
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if `V` has more than 64 dimensions, the most that can be
+    /// decoded.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let components = self.vector.components();
+        assert!(
+            components.len() <= MAX_UNTRUSTED_DIMENSIONS,
+            "coordinate has too many dimensions to encode"
+        );
+
+        let mut buf = Vec::with_capacity(2 + 8 * (components.len() + 3));
+        buf.push(VERSIONED | WIRE_VERSION);
+        buf.push(components.len() as u8);
+        let fields = [&self.error, &self.height, &self.adjustment];
+        for v in components.iter().chain(fields) {
+            buf.extend_from_slice(&v.to_le_bytes());
+        }
+        buf
+    }
+
+    /// Decodes a coordinate encoded by [`to_bytes`](Coordinate::to_bytes),
+    /// validating it exactly as [`parse_untrusted`](Coordinate::parse_untrusted)
+    /// does.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, Error> {
+        Self::parse_untrusted(data)
+    }
+
+    /// Parses a coordinate received from an untrusted source, such as a peer
+    /// on the network.
+    ///
+    /// `data` must be exactly the layout produced by
+    /// [`to_bytes`](Coordinate::to_bytes):
+    ///
+    /// ```text
+    ///     version 1:   0x81 | dimensions (1) | components (8 each) | error (8) | height (8) | adjustment (8)
+    /// ```
+    ///
+    /// with all values little-endian IEEE 754 doubles. Later versions only
+    /// append fields, so nodes running different versions of this crate
+    /// interoperate - an encoding of a version newer than [`WIRE_VERSION`] is
+    /// decoded from the fields known to this version, ignoring the rest.
+    ///
+    /// The length, version and dimensions are checked before any value is
+    /// decoded, and the values are decoded into a fixed-size buffer, so
+    /// parsing a coordinate does not allocate (only the message of a
+    /// [`Error::Serialization`] does). Every field is validated:
+    ///
+    /// * [`Error::Serialization`] if `data` is truncated, too long, or
+    ///   declares an unsupported version or number of dimensions.
+    /// * [`Error::DimensionMismatch`] if the coordinate has a different
+    ///   dimensionality than `V`.
+    /// * [`Error::NonFiniteCoordinate`] if any value is NaN or infinite.
+    /// * [`Error::OutOfRange`] if a component, the height or the adjustment
+    ///   exceeds 1000 seconds, the height is negative, or the error estimate
+    ///   is negative or above 1000.
+    ///
+    /// This function never panics, and is intended as the single entry point
+    /// for coordinates received from the network (and as a fuzz target):
+    ///
+    /// ```
+    /// use vivaldi::{Coordinate, vector::Dimension2};
+    ///
+    /// # let mut packet = vec![0x81, 2];
+    /// # for v in &[0.01_f64, 0.02, 0.5, 0.001, 0.0] {
+    /// #     packet.extend_from_slice(&v.to_le_bytes());
+    /// # }
+    /// let coord = Coordinate::<Dimension2>::parse_untrusted(&packet)?;
+    /// # Ok::<(), vivaldi::Error>(())
+    /// ```
+    pub fn parse_untrusted(data: &[u8]) -> Result<Self, Error> {
+        let invalid = |msg: &str| Error::Serialization(format!("invalid coordinate: {}", msg));
+
+        if data.len() > MAX_ENCODED_LEN {
+            return Err(invalid("too long"));
+        }
+        let (version, data) = match data.split_first() {
+            // The high bit is always set, and version 0 is unused.
+            Some((v, rest)) if *v > VERSIONED => (v & !VERSIONED, rest),
+            Some(_) => return Err(invalid("unsupported version")),
+            None => return Err(invalid("truncated")),
+        };
+        let (dims, body) = match data.split_first() {
+            Some((d, body)) => (*d as usize, body),
+            None => return Err(invalid("truncated")),
+        };
+        if dims == 0 || dims > MAX_UNTRUSTED_DIMENSIONS {
+            return Err(invalid("unsupported dimensions"));
+        }
+
+        // The error, height and adjustment follow the components.
+        let fields = dims + 3;
+        let len_ok = if version > WIRE_VERSION {
+            body.len() >= 8 * fields
+        } else {
+            body.len() == 8 * fields
+        };
+        if !len_ok {
+            return Err(invalid("length mismatch"));
+        }
+
+        let expected = V::default().components().len();
+        if dims != expected {
+            return Err(Error::DimensionMismatch {
+                expected,
+                got: dims,
+            });
+        }
+
+        // The length of the body is checked above, so each of the fields is
+        // in bounds.
+        let value = |i: usize| {
+            let mut v = [0; 8];
+            v.copy_from_slice(&body[8 * i..8 * (i + 1)]);
+            f64::from_le_bytes(v)
+        };
+        let mut components = [0.0; MAX_UNTRUSTED_DIMENSIONS];
+        for (i, c) in components[..dims].iter_mut().enumerate() {
+            *c = value(i);
+        }
+        let (error, height, adjustment) = (value(dims), value(dims + 1), value(dims + 2));
+
+        let vector = V::from_components(&components[..dims]).ok_or(Error::DimensionMismatch {
+            expected,
+            got: dims,
+        })?;
+        let coord = Coordinate::new(vector, error, height).with_adjustment(adjustment);
+        coord.validate()?;
+        Ok(coord)
+    }
+}
+
+/// With the `rkyv` feature enabled, a [`Coordinate`] can be read straight
+/// from a received buffer without deserialising (or allocating):
+///
+/// ```
+/// use vivaldi::{ArchivedCoordinate, Coordinate, Model, vector::Dimension3};
+///
+/// # let model = Model::<Dimension3>::new();
+/// let buf = rkyv::to_bytes::<rkyv::rancor::Error>(model.get_coordinate())?;
+///
+/// let archived = rkyv::access::<ArchivedCoordinate<Dimension3>, rkyv::rancor::Error>(&buf)?;
+/// assert_eq!(archived.error(), model.get_coordinate().error());
+/// # Ok::<(), rkyv::rancor::Error>(())
+/// ```
+///
+/// [`rkyv::access`] validates the buffer before returning a reference into
+/// it, so is safe to use on untrusted input - though unlike
+/// [`Coordinate::parse_untrusted`] it does not check the values are finite
+/// and within range, so [`validate`](Coordinate::validate) any coordinate
+/// deserialised from it.
+#[cfg(feature = "rkyv")]
+impl<V> ArchivedCoordinate<V>
+where
+    V: Vector + rkyv::Archive,
+{
+    /// Returns the archived vector of the coordinate.
+    pub fn vector(&self) -> &V::Archived {
+        &self.vector
+    }
+
+    /// Returns the error estimate of the coordinate.
+    pub fn error(&self) -> f64 {
+        self.error.to_native()
+    }
+
+    /// Returns the height of the coordinate.
+    pub fn height(&self) -> f64 {
+        self.height.to_native()
+    }
+
+    /// Returns the adjustment of the coordinate.
+    pub fn adjustment(&self) -> f64 {
+        self.adjustment.to_native()
+    }
+}
+
+/// Deserialises an archived coordinate, deriving the cached magnitude from
+/// the vector rather than trusting a value carried in the buffer.
+#[cfg(feature = "rkyv")]
+impl<V, T, D> rkyv::Deserialize<Coordinate<V, T>, D> for ArchivedCoordinate<V, T>
+where
+    V: Vector<T> + rkyv::Archive,
+    V::Archived: rkyv::Deserialize<V, D>,
+    T: Scalar + rkyv::Archive,
+    T::Archived: rkyv::Deserialize<T, D>,
+    D: rkyv::rancor::Fallible + ?Sized,
+{
+    fn deserialize(&self, deserializer: &mut D) -> Result<Coordinate<V, T>, D::Error> {
+        let c = Coordinate::new(
+            self.vector.deserialize(deserializer)?,
+            self.error.deserialize(deserializer)?,
+            self.height.deserialize(deserializer)?,
+        );
+        Ok(c.with_adjustment(self.adjustment.deserialize(deserializer)?))
+    }
 }
 
-#[cfg(feature = "serde")]
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::vector::Dimension3;
 
+    #[test]
+    fn cached_magnitude() {
+        let c = Coordinate::new(Dimension3([1.0, 2.0, 3.0]), 1.0, 2.0);
+        assert_eq!(c.magnitude(), c.vector().magnitude());
+
+        let c = Coordinate::<Dimension3>::default();
+        assert_eq!(c.magnitude(), Magnitude(0.0));
+    }
+
+    #[test]
+    fn is_finite() {
+        assert!(Coordinate::new(Dimension3([1.0, 2.0, 3.0]), 1.0, 2.0).is_finite());
+        assert!(!Coordinate::new(Dimension3([1.0, f64::NAN, 3.0]), 1.0, 2.0).is_finite());
+        assert!(!Coordinate::new(Dimension3::default(), f64::INFINITY, 2.0).is_finite());
+        assert!(!Coordinate::new(Dimension3::default(), 1.0, f64::NAN).is_finite());
+    }
+
+    /// Encodes `values` (the components, error and height) as a version 1
+    /// coordinate with no adjustment.
+    fn packet(values: &[f64]) -> Vec<u8> {
+        let mut buf = vec![0x81, (values.len() - 2) as u8];
+        for v in values.iter().chain(&[0.0]) {
+            buf.extend_from_slice(&v.to_le_bytes());
+        }
+        buf
+    }
+
+    #[test]
+    fn try_new() {
+        let c = Coordinate::try_new(Dimension3([1.0, 2.0, 3.0]), 0.5, 0.1).unwrap();
+        assert_eq!(c, Coordinate::new(Dimension3([1.0, 2.0, 3.0]), 0.5, 0.1));
+
+        let new = |v: [f64; 3], e: f64, h: f64| Coordinate::try_new(Dimension3(v), e, h);
+        assert_eq!(
+            new([1.0, f64::NAN, 3.0], 0.5, 0.1),
+            Err(Error::NonFiniteCoordinate)
+        );
+        assert_eq!(
+            new([1.0, 2.0, 3.0], f64::INFINITY, 0.1),
+            Err(Error::NonFiniteCoordinate)
+        );
+        assert_eq!(
+            new([1.0, 2e3, 3.0], 0.5, 0.1),
+            Err(Error::OutOfRange { field: "component" })
+        );
+        assert_eq!(
+            new([1.0, 2.0, 3.0], -0.5, 0.1),
+            Err(Error::OutOfRange { field: "error" })
+        );
+        assert_eq!(
+            new([1.0, 2.0, 3.0], 0.5, 2e3),
+            Err(Error::OutOfRange { field: "height" })
+        );
+        assert_eq!(
+            Coordinate::new(Dimension3([1.0, 2.0, 3.0]), 0.5, 0.1)
+                .with_adjustment(-2e3)
+                .validate(),
+            Err(Error::OutOfRange {
+                field: "adjustment"
+            })
+        );
+    }
+
+    #[test]
+    fn parse_untrusted() {
+        let c =
+            Coordinate::<Dimension3>::parse_untrusted(&packet(&[1.0, 2.0, 3.0, 0.5, 0.1])).unwrap();
+        assert_eq!(c, Coordinate::new(Dimension3([1.0, 2.0, 3.0]), 0.5, 0.1));
+
+        let parse = |v: &[f64]| Coordinate::<Dimension3>::parse_untrusted(&packet(v));
+        assert!(matches!(
+            parse(&[1.0, 2.0, 0.5, 0.1]),
+            Err(Error::DimensionMismatch {
+                expected: 3,
+                got: 2
+            })
+        ));
+        assert_eq!(
+            parse(&[1.0, f64::NAN, 3.0, 0.5, 0.1]),
+            Err(Error::NonFiniteCoordinate)
+        );
+        assert_eq!(
+            parse(&[1.0, 2.0, 3.0, 0.5, f64::INFINITY]),
+            Err(Error::NonFiniteCoordinate)
+        );
+        assert_eq!(
+            parse(&[1.0, -2e3, 3.0, 0.5, 0.1]),
+            Err(Error::OutOfRange { field: "component" })
+        );
+        assert_eq!(
+            parse(&[1.0, 2.0, 3.0, -0.5, 0.1]),
+            Err(Error::OutOfRange { field: "error" })
+        );
+        assert_eq!(
+            parse(&[1.0, 2.0, 3.0, 0.5, -0.1]),
+            Err(Error::OutOfRange { field: "height" })
+        );
+
+        let mut long = packet(&[1.0, 2.0, 3.0, 0.5, 0.1]);
+        long.push(0);
+        assert!(matches!(
+            Coordinate::<Dimension3>::parse_untrusted(&long),
+            Err(Error::Serialization(_))
+        ));
+        assert!(matches!(
+            Coordinate::<Dimension3>::parse_untrusted(&vec![0; 1 << 20]),
+            Err(Error::Serialization(_))
+        ));
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let c = Coordinate::new(Dimension3([1.0, -2.0, 3.0]), 0.5, 0.1).with_adjustment(-0.2);
+        let encoded = c.to_bytes();
+        let mut want = vec![0x81, 3];
+        for v in [1.0, -2.0, 3.0, 0.5, 0.1, -0.2_f64] {
+            want.extend_from_slice(&v.to_le_bytes());
+        }
+        assert_eq!(encoded, want);
+        assert_eq!(Coordinate::<Dimension3>::from_bytes(&encoded), Ok(c));
+
+        assert!(matches!(
+            Coordinate::<Dimension3>::from_bytes(&encoded[..encoded.len() - 1]),
+            Err(Error::Serialization(_))
+        ));
+    }
+
+    #[test]
+    fn wire_versions() {
+        let c = Coordinate::new(Dimension3([1.0, -2.0, 3.0]), 0.5, 0.1);
+
+        // Newer versions append fields, which are ignored.
+        let mut v2 = c.with_adjustment(0.01).to_bytes();
+        v2[0] = 0x82;
+        v2.extend_from_slice(&[0xff; 12]);
+        assert_eq!(
+            Coordinate::<Dimension3>::from_bytes(&v2),
+            Ok(c.with_adjustment(0.01))
+        );
+
+        // Every encoding starts with a version byte.
+        for version in [0x80, 0x01, 0x03] {
+            let mut invalid = c.to_bytes();
+            invalid[0] = version;
+            assert!(matches!(
+                Coordinate::<Dimension3>::from_bytes(&invalid),
+                Err(Error::Serialization(_))
+            ));
+        }
+
+        // Appended fields of known versions are rejected.
+        let mut long = c.to_bytes();
+        long.extend_from_slice(&[0; 8]);
+        assert!(matches!(
+            Coordinate::<Dimension3>::from_bytes(&long),
+            Err(Error::Serialization(_))
+        ));
+    }
+
+    #[test]
+    fn parse_untrusted_never_panics() {
+        use rand::Rng;
+
+        let valid = packet(&[1.0, 2.0, 3.0, 0.5, 0.1]);
+        let mut rng = rand::thread_rng();
+        for len in 0..valid.len() + 16 {
+            let _ = Coordinate::<Dimension3>::parse_untrusted(&valid[..len.min(valid.len())]);
+            for _ in 0..64 {
+                let data = (0..len).map(|_| rng.gen::<u8>()).collect::<Vec<_>>();
+                let _ = Coordinate::<Dimension3>::parse_untrusted(&data);
+
+                let mut flipped = valid.clone();
+                let i = rng.gen_range(0..flipped.len());
+                flipped[i] ^= rng.gen::<u8>();
+                let _ = Coordinate::<Dimension3>::parse_untrusted(&flipped);
+            }
+        }
+    }
+
+    #[cfg(feature = "rkyv")]
+    #[test]
+    fn rkyv() {
+        use rkyv::rancor::Error;
+
+        let c = Coordinate::new(Dimension3([1.0, 2.0, 3.0]), 0.5, 0.1).with_adjustment(-0.01);
+        let buf = rkyv::to_bytes::<Error>(&c).unwrap();
+
+        let archived = rkyv::access::<ArchivedCoordinate<Dimension3>, Error>(&buf).unwrap();
+        assert_eq!(archived.vector().0.map(|v| v.to_native()), [1.0, 2.0, 3.0]);
+        assert_eq!(archived.error(), 0.5);
+        assert_eq!(archived.height(), 0.1);
+        assert_eq!(archived.adjustment(), -0.01);
+
+        let decoded = rkyv::deserialize::<Coordinate<Dimension3>, Error>(archived).unwrap();
+        assert_eq!(decoded, c);
+        assert_eq!(decoded.magnitude(), c.magnitude());
+
+        // The cached magnitude is derived when deserialising, so a buffer
+        // cannot carry one that disagrees with the vector.
+        assert_eq!(std::mem::size_of::<ArchivedCoordinate<Dimension3>>(), 6 * 8);
+
+        assert!(rkyv::access::<ArchivedCoordinate<Dimension3>, Error>(&buf[1..]).is_err());
+    }
+
+    #[cfg(feature = "serde")]
     #[test]
     fn serde() {
-        let c = Coordinate::new(Dimension3::default(), 1.0, 2.0);
+        let c = Coordinate::new(Dimension3([1.0, 2.0, 3.0]), 1.0, 2.0);
 
         let encoded = serde_json::to_string(&c).unwrap();
         let decoded: Coordinate<Dimension3> = serde_json::from_str(&encoded).unwrap();
@@ -74,5 +707,23 @@ mod tests {
         assert_eq!(decoded.vector(), c.vector());
         assert_eq!(decoded.error(), c.error());
         assert_eq!(decoded.height(), c.height());
+        assert_eq!(decoded.magnitude(), c.magnitude());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_validates() {
+        let parse = |json: &str| serde_json::from_str::<Coordinate<Dimension3>>(json);
+        assert!(parse(r#"{"vector":[1.0,2.0,3.0],"error":0.5,"height":0.1}"#).is_ok());
+
+        for json in [
+            r#"{"vector":[1.0,2.0,3.0],"error":-0.5,"height":0.1}"#,
+            r#"{"vector":[1.0,2.0,3.0],"error":0.5,"height":1e9}"#,
+            r#"{"vector":[1.0,2e9,3.0],"error":0.5,"height":0.1}"#,
+            r#"{"vector":[1.0,2.0,3.0],"error":0.5,"height":0.1,"adjustment":-1e9}"#,
+        ] {
+            let err = parse(json).unwrap_err().to_string();
+            assert!(err.starts_with("coordinate"), "{}: {}", json, err);
+        }
     }
 }
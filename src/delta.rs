@@ -0,0 +1,568 @@
+use crate::checkpoint::fnv1a;
+use crate::clock::Clock;
+use crate::coordinate::Coordinate;
+use crate::error::Error;
+use crate::peer_table::{PeerKey, PeerTable, Reader};
+use crate::vector::Vector;
+use std::collections::{HashMap, VecDeque};
+use std::convert::TryFrom;
+use std::hash::Hash;
+
+/// Identifies an encoded delta.
+const DELTA_MAGIC: &[u8; 4] = b"VIVD";
+
+/// The version of the delta encoding.
+const DELTA_VERSION: u8 = 2;
+
+/// The quantised vector components, error, height and adjustment of a peer.
+type Quantised = Vec<i64>;
+
+/// Identifies the quantised state of a peer table, as the FNV-1a hash of its
+/// entries in encoded key order.
+pub type Digest = u64;
+
+/// Produces delta-compressed updates of a [`PeerTable`] for a
+/// [`DeltaReceiver`].
+///
+/// Periodically sending the whole peer table to every peer quickly dominates
+/// gossip bandwidth, while most coordinates change little between rounds. The
+/// sender quantises every value to a fixed `resolution`, remembers the
+/// quantised state it sent under a [`Digest`], and once a receiver
+/// acknowledges a digest, sends only the entries that changed since, as
+/// variable-length integer deltas of the quantised values.
+///
+/// ```
+/// use vivaldi::{DeltaReceiver, DeltaSender, Model, PeerTable, vector::Dimension3};
+///
+/// # let remote = Model::<Dimension3>::new();
+/// let mut local = PeerTable::new();
+/// local.insert(1_u64, remote.get_coordinate());
+///
+/// // 10 microsecond resolution, remembering the last 8 sent states.
+/// let mut sender = DeltaSender::new(1e-5, 8);
+/// let mut receiver = DeltaReceiver::new(1e-5);
+/// let mut mirror = PeerTable::<u64, Dimension3>::new();
+///
+/// // The receiver has nothing yet, so acknowledges nothing.
+/// let (_, full) = sender.encode(&local, receiver.digest());
+/// receiver.apply(&full, &mut mirror)?;
+///
+/// // Subsequent updates only carry the changes since the acknowledged state.
+/// let (_, delta) = sender.encode(&local, receiver.digest());
+/// receiver.apply(&delta, &mut mirror)?;
+/// # Ok::<(), vivaldi::Error>(())
+/// ```
+///
+/// The `resolution` bounds the error introduced by quantisation to half of it
+/// per value (in seconds for the vector components and height), and must
+/// match the receiver. The quarantine state and insertion times of the table
+/// are not sent - the receiver records its own insertion times.
+#[derive(Debug, Clone)]
+pub struct DeltaSender<K> {
+    resolution: f64,
+    history: usize,
+    sent: VecDeque<(Digest, HashMap<K, Quantised>)>,
+}
+
+impl<K> DeltaSender<K>
+where
+    K: Hash + Eq + Clone + PeerKey,
+{
+    /// Initialises a sender quantising values to `resolution`, and able to
+    /// produce deltas against the last `history` encoded states.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `resolution` is not positive and finite.
+    pub fn new(resolution: f64, history: usize) -> Self {
+        assert_resolution(resolution);
+        DeltaSender {
+            resolution,
+            history: history.max(1),
+            sent: VecDeque::new(),
+        }
+    }
+
+    /// Encodes the changes to `table` since the state identified by `acked`,
+    /// returning the digest of the encoded state and the encoded update.
+    ///
+    /// If `acked` is `None` or no longer remembered, the whole table is
+    /// encoded.
+    pub fn encode<V, C>(
+        &mut self,
+        table: &PeerTable<K, V, C>,
+        acked: Option<Digest>,
+    ) -> (Digest, Vec<u8>)
+    where
+        V: Vector,
+        C: Clock,
+    {
+        let state = table
+            .iter()
+            .map(|(k, c)| (k.clone(), quantise(&c, self.resolution)))
+            .collect::<HashMap<_, _>>();
+        let digest = digest(&state);
+
+        let empty = HashMap::new();
+        let base = acked.and_then(|d| self.sent.iter().find(|(sd, _)| *sd == d));
+        let (base_digest, base_state) = match base {
+            Some((d, s)) => (Some(*d), s),
+            None => (None, &empty),
+        };
+
+        let dims = V::default().components().len();
+        let mut buf = Vec::new();
+        buf.extend_from_slice(DELTA_MAGIC);
+        buf.push(DELTA_VERSION);
+        buf.push(dims as u8);
+        match base_digest {
+            Some(d) => {
+                buf.push(1);
+                buf.extend_from_slice(&d.to_le_bytes());
+            }
+            None => buf.push(0),
+        }
+        buf.extend_from_slice(&digest.to_le_bytes());
+
+        let changed = state
+            .iter()
+            .filter(|(k, v)| base_state.get(*k) != Some(*v))
+            .collect::<Vec<_>>();
+        write_varint(&mut buf, changed.len() as u64);
+        let mut key = Vec::new();
+        for (k, values) in changed {
+            key.clear();
+            k.encode(&mut key);
+            write_varint(&mut buf, key.len() as u64);
+            buf.extend_from_slice(&key);
+
+            let zeros = vec![0; values.len()];
+            let old = base_state.get(k).unwrap_or(&zeros);
+            for (v, o) in values.iter().zip(old) {
+                write_varint(&mut buf, zigzag(v.wrapping_sub(*o)));
+            }
+        }
+
+        let removed = base_state
+            .keys()
+            .filter(|k| !state.contains_key(*k))
+            .collect::<Vec<_>>();
+        write_varint(&mut buf, removed.len() as u64);
+        for k in removed {
+            key.clear();
+            k.encode(&mut key);
+            write_varint(&mut buf, key.len() as u64);
+            buf.extend_from_slice(&key);
+        }
+
+        let sum = fnv1a(&buf);
+        buf.extend_from_slice(&sum.to_le_bytes());
+
+        if self.sent.front().map(|(d, _)| *d) != Some(digest) {
+            self.sent.push_front((digest, state));
+            self.sent.truncate(self.history);
+        }
+        (digest, buf)
+    }
+}
+
+/// Applies updates produced by a [`DeltaSender`] to a [`PeerTable`].
+#[derive(Debug, Clone)]
+pub struct DeltaReceiver<K> {
+    resolution: f64,
+    state: HashMap<K, Quantised>,
+    digest: Option<Digest>,
+}
+
+impl<K> DeltaReceiver<K>
+where
+    K: Hash + Eq + Clone + PeerKey,
+{
+    /// Initialises a receiver for a sender quantising values to
+    /// `resolution`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `resolution` is not positive and finite.
+    pub fn new(resolution: f64) -> Self {
+        assert_resolution(resolution);
+        DeltaReceiver {
+            resolution,
+            state: HashMap::new(),
+            digest: None,
+        }
+    }
+
+    /// Returns the digest of the last applied state, to be acknowledged to
+    /// the sender.
+    pub fn digest(&self) -> Option<Digest> {
+        self.digest
+    }
+
+    /// Decodes `data` and applies the changes to `table`, returning the digest
+    /// of the new state.
+    ///
+    /// Returns [`Error::DigestMismatch`] if `data` is a delta against a state
+    /// other than the last applied, in which case the receiver should
+    /// acknowledge `None` to request the whole table. Returns
+    /// [`Error::Serialization`] if `data` is corrupt, or an error if any
+    /// updated coordinate is invalid as described by
+    /// [`Coordinate::try_new`]. `table` is unchanged if an error is returned.
+    pub fn apply<V, C>(
+        &mut self,
+        data: &[u8],
+        table: &mut PeerTable<K, V, C>,
+    ) -> Result<Digest, Error>
+    where
+        V: Vector,
+        C: Clock,
+    {
+        let corrupt = |msg: &str| Error::Serialization(format!("invalid delta: {}", msg));
+        let truncated = || corrupt("truncated");
+
+        if data.len() < 4 + 3 + 8 + 8 {
+            return Err(truncated());
+        }
+        let (body, sum) = data.split_at(data.len() - 8);
+        if fnv1a(body).to_le_bytes() != sum {
+            return Err(corrupt("checksum mismatch"));
+        }
+        if &body[..4] != DELTA_MAGIC {
+            return Err(corrupt("bad magic"));
+        }
+        if body[4] != DELTA_VERSION {
+            return Err(corrupt("unsupported version"));
+        }
+
+        let dims = body[5] as usize;
+        let expected = V::default().components().len();
+        if dims != expected {
+            return Err(Error::DimensionMismatch {
+                expected,
+                got: dims,
+            });
+        }
+
+        let mut r = Reader(&body[6..]);
+        let is_delta = r.take(1).ok_or_else(truncated)?[0] != 0;
+        let mut state = if is_delta {
+            let base = r.u64().ok_or_else(truncated)?;
+            if Some(base) != self.digest {
+                return Err(Error::DigestMismatch);
+            }
+            self.state.clone()
+        } else {
+            HashMap::new()
+        };
+        let digest = r.u64().ok_or_else(truncated)?;
+
+        let mut changed = Vec::new();
+        for _ in 0..r.varint().ok_or_else(truncated)? {
+            let k = read_key(&mut r).ok_or_else(|| corrupt("invalid key"))?;
+            let old = state.get(&k).cloned().unwrap_or_else(|| vec![0; dims + 3]);
+            let values = old
+                .iter()
+                .map(|o| r.varint().map(|d| o.wrapping_add(unzigzag(d))))
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(truncated)?;
+            state.insert(k.clone(), values);
+            changed.push(k);
+        }
+
+        let mut removed = Vec::new();
+        for _ in 0..r.varint().ok_or_else(truncated)? {
+            let k = read_key(&mut r).ok_or_else(|| corrupt("invalid key"))?;
+            state.remove(&k);
+            removed.push(k);
+        }
+        if !r.0.is_empty() {
+            return Err(corrupt("trailing data"));
+        }
+        if self::digest(&state) != digest {
+            return Err(corrupt("state does not match digest"));
+        }
+
+        // A full update replaces the table contents.
+        if !is_delta {
+            removed.extend(
+                table
+                    .iter()
+                    .map(|(k, _)| k.clone())
+                    .filter(|k| !state.contains_key(k)),
+            );
+        }
+
+        // Every coordinate is decoded and validated before the table is
+        // modified, rejecting the whole update if any is invalid.
+        let coords = changed
+            .into_iter()
+            .map(|k| {
+                let c = dequantise::<V>(&state[&k], self.resolution)
+                    .ok_or_else(|| corrupt("invalid coordinate"))?;
+                c.validate()?;
+                Ok((k, c))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        for (k, c) in coords {
+            table.try_insert(k, &c)?;
+        }
+        for k in removed {
+            table.remove(&k);
+        }
+
+        self.state = state;
+        self.digest = Some(digest);
+        Ok(digest)
+    }
+}
+
+fn assert_resolution(resolution: f64) {
+    assert!(
+        resolution > 0.0 && resolution.is_finite(),
+        "invalid delta resolution {}",
+        resolution
+    );
+}
+
+fn quantise<V: Vector>(c: &Coordinate<V>, resolution: f64) -> Quantised {
+    c.vector()
+        .components()
+        .iter()
+        .chain(&[c.error(), c.height(), c.adjustment()])
+        .map(|v| (v / resolution).round() as i64)
+        .collect()
+}
+
+fn dequantise<V: Vector>(values: &[i64], resolution: f64) -> Option<Coordinate<V>> {
+    let (vector, rest) = values.split_at(values.len().checked_sub(3)?);
+    let vector = vector
+        .iter()
+        .map(|v| *v as f64 * resolution)
+        .collect::<Vec<_>>();
+    Some(
+        Coordinate::new(
+            V::from_components(&vector)?,
+            rest[0] as f64 * resolution,
+            rest[1] as f64 * resolution,
+        )
+        .with_adjustment(rest[2] as f64 * resolution),
+    )
+}
+
+/// Hashes the entries of `state`, ordered by their encoded keys.
+fn digest<K: PeerKey>(state: &HashMap<K, Quantised>) -> Digest {
+    let mut entries = state
+        .iter()
+        .map(|(k, v)| {
+            let mut key = Vec::new();
+            k.encode(&mut key);
+            (key, v)
+        })
+        .collect::<Vec<_>>();
+    entries.sort();
+
+    let mut buf = Vec::new();
+    for (key, values) in entries {
+        write_varint(&mut buf, key.len() as u64);
+        buf.extend_from_slice(&key);
+        for v in values {
+            buf.extend_from_slice(&v.to_le_bytes());
+        }
+    }
+    fnv1a(&buf)
+}
+
+fn read_key<K: PeerKey>(r: &mut Reader<'_>) -> Option<K> {
+    let len = r.varint()?;
+    K::decode(r.take(usize::try_from(len).ok()?)?)
+}
+
+/// Appends `v` as an unsigned LEB128 varint.
+fn write_varint(buf: &mut Vec<u8>, mut v: u64) {
+    while v >= 0x80 {
+        buf.push((v as u8) | 0x80);
+        v >>= 7;
+    }
+    buf.push(v as u8);
+}
+
+/// Maps signed integers to unsigned so small magnitudes encode compactly.
+fn zigzag(v: i64) -> u64 {
+    ((v << 1) ^ (v >> 63)) as u64
+}
+
+fn unzigzag(v: u64) -> i64 {
+    ((v >> 1) as i64) ^ -((v & 1) as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::{Dimension2, Dimension3};
+
+    const RES: f64 = 1e-5;
+
+    fn coord(x: f64) -> Coordinate<Dimension3> {
+        Coordinate::new(Dimension3([x, 0.02, -0.01]), 0.5, 0.001)
+    }
+
+    fn table(n: u64) -> PeerTable<u64, Dimension3> {
+        let mut t = PeerTable::new();
+        for i in 0..n {
+            t.insert(i, &coord(i as f64 / 1000.0));
+        }
+        t
+    }
+
+    fn assert_synced(a: &PeerTable<u64, Dimension3>, b: &PeerTable<u64, Dimension3>) {
+        assert_eq!(a.len(), b.len());
+        for (k, c) in a.iter() {
+            let got = b.get(k).unwrap();
+            for (x, y) in c.vector().0.iter().zip(got.vector().0.iter()) {
+                assert!((x - y).abs() <= RES / 2.0);
+            }
+            assert!((c.error() - got.error()).abs() <= RES / 2.0);
+            assert!((c.height() - got.height()).abs() <= RES / 2.0);
+            assert!((c.adjustment() - got.adjustment()).abs() <= RES / 2.0);
+        }
+    }
+
+    #[test]
+    fn zigzag_round_trip() {
+        for v in [0, 1, -1, 63, -64, i64::MAX, i64::MIN] {
+            assert_eq!(unzigzag(zigzag(v)), v);
+        }
+    }
+
+    #[test]
+    fn full_then_delta() {
+        let mut local = table(100);
+        let mut sender = DeltaSender::new(RES, 4);
+        let mut receiver = DeltaReceiver::new(RES);
+        let mut mirror = PeerTable::new();
+
+        let (d1, full) = sender.encode(&local, receiver.digest());
+        assert_eq!(receiver.apply(&full, &mut mirror), Ok(d1));
+        assert_synced(&local, &mirror);
+
+        // Move one peer slightly, add one and remove another.
+        local.insert(5, &coord(0.0051));
+        local.insert(1000, &coord(0.5));
+        local.remove(&7);
+
+        let (d2, delta) = sender.encode(&local, receiver.digest());
+        assert!(
+            delta.len() * 10 < full.len(),
+            "{} vs {}",
+            delta.len(),
+            full.len()
+        );
+        assert_eq!(receiver.apply(&delta, &mut mirror), Ok(d2));
+        assert_synced(&local, &mirror);
+
+        // Nothing changed.
+        let (d3, empty) = sender.encode(&local, receiver.digest());
+        assert_eq!(d3, d2);
+        assert_eq!(receiver.apply(&empty, &mut mirror), Ok(d2));
+    }
+
+    #[test]
+    fn adjustment_change() {
+        let mut local = table(10);
+        let mut sender = DeltaSender::new(RES, 4);
+        let mut receiver = DeltaReceiver::new(RES);
+        let mut mirror = PeerTable::new();
+
+        let (d1, full) = sender.encode(&local, receiver.digest());
+        assert_eq!(receiver.apply(&full, &mut mirror), Ok(d1));
+
+        // Only the adjustment of one peer changes.
+        local.insert(3, &coord(0.003).with_adjustment(0.002));
+
+        let (d2, delta) = sender.encode(&local, receiver.digest());
+        assert_ne!(d1, d2);
+        assert_eq!(receiver.apply(&delta, &mut mirror), Ok(d2));
+        assert_synced(&local, &mirror);
+        assert!((mirror.get(&3).unwrap().adjustment() - 0.002).abs() <= RES / 2.0);
+    }
+
+    #[test]
+    fn unknown_ack_sends_full_table() {
+        let local = table(10);
+        let mut sender = DeltaSender::new(RES, 1);
+        let mut receiver = DeltaReceiver::new(RES);
+        let mut mirror = PeerTable::new();
+        mirror.insert(99, &coord(1.0));
+
+        let (_, data) = sender.encode(&local, Some(42));
+        receiver.apply(&data, &mut mirror).unwrap();
+        assert_synced(&local, &mirror);
+        assert!(!mirror.contains(&99));
+    }
+
+    #[test]
+    fn digest_mismatch() {
+        let local = table(10);
+        let mut sender = DeltaSender::new(RES, 4);
+        let mut a = DeltaReceiver::<u64>::new(RES);
+        let mut b = DeltaReceiver::<u64>::new(RES);
+        let mut mirror = PeerTable::<u64, Dimension3>::new();
+
+        let (_, full) = sender.encode(&local, a.digest());
+        a.apply(&full, &mut mirror).unwrap();
+        let (_, delta) = sender.encode(&local, a.digest());
+
+        // b has not applied the base state.
+        let mut other = PeerTable::<u64, Dimension3>::new();
+        assert_eq!(b.apply(&delta, &mut other), Err(Error::DigestMismatch));
+        assert!(other.is_empty());
+    }
+
+    #[test]
+    fn corrupt() {
+        let mut sender = DeltaSender::new(RES, 4);
+        let (_, mut data) = sender.encode(&table(3), None);
+
+        let mut r2 = DeltaReceiver::<u64>::new(RES);
+        let mut t2 = PeerTable::<u64, Dimension2>::new();
+        assert!(matches!(
+            r2.apply(&data, &mut t2),
+            Err(Error::DimensionMismatch { .. })
+        ));
+
+        data[20] ^= 0xff;
+        let mut r = DeltaReceiver::new(RES);
+        let mut t = PeerTable::<u64, Dimension3>::new();
+        assert!(matches!(
+            r.apply(&data, &mut t),
+            Err(Error::Serialization(_))
+        ));
+        assert_eq!(r.digest(), None);
+    }
+
+    #[test]
+    fn invalid_coordinate() {
+        let mut local = table(3);
+        local.insert(
+            9,
+            &Coordinate::new(Dimension3([0.01, 0.0, 0.0]), -1.0, 0.001),
+        );
+        let mut sender = DeltaSender::new(RES, 4);
+        let (_, data) = sender.encode(&local, None);
+
+        let mut r = DeltaReceiver::new(RES);
+        let mut t = table(1);
+        assert_eq!(
+            r.apply(&data, &mut t),
+            Err(Error::OutOfRange { field: "error" })
+        );
+        assert_eq!(t.len(), 1);
+        assert_eq!(r.digest(), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid delta resolution")]
+    fn zero_resolution() {
+        DeltaReceiver::<u64>::new(0.0);
+    }
+}
@@ -0,0 +1,214 @@
+use crate::clock::{Clock, SystemClock};
+use crate::source::{Observation, RttSource};
+use crate::vector::Vector;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::Duration;
+
+/// The default relative RTT change above which a sample is considered
+/// divergent.
+const DEFAULT_DIVERGENCE: f64 = 0.1;
+
+/// An [`RttSource`] adapter limiting the rate of observations applied to a
+/// model, preferring novel and divergent samples over redundant ones.
+///
+/// During a probe storm a node may receive thousands of near-identical
+/// measurements per second, each costing an update to the model while adding
+/// little information. The downsampler admits at most `max_per_second`
+/// observations from the wrapped source (with bursts of up to one second's
+/// worth), discarding the rest:
+///
+/// ```
+/// use vivaldi::{Downsampler, ManualSource, Model, vector::Dimension3};
+///
+/// let mut model = Model::<Dimension3>::new();
+/// let mut source = Downsampler::new(ManualSource::<&str, Dimension3>::default(), 100.0);
+///
+/// // Applies at most 100 observations per second.
+/// model.drain(&mut source);
+/// ```
+///
+/// Half of the rate budget is reserved for important samples - those from a
+/// peer not previously admitted, or with an RTT differing from the last
+/// admitted RTT of the peer by more than the
+/// [divergence threshold](Downsampler::set_divergence). Redundant samples are
+/// only admitted while more than half of the budget remains, so under load the
+/// model keeps learning about new peers and changing paths.
+#[derive(Debug)]
+pub struct Downsampler<S, K, C = SystemClock> {
+    inner: S,
+    max_per_second: f64,
+    divergence: f64,
+    clock: C,
+
+    tokens: f64,
+    last_refill: Duration,
+    last_rtt: HashMap<K, Duration>,
+    dropped: u64,
+}
+
+impl<S, K> Downsampler<S, K>
+where
+    K: Hash + Eq + Clone,
+{
+    /// Wraps `inner`, admitting at most `max_per_second` observations per
+    /// second.
+    pub fn new(inner: S, max_per_second: f64) -> Self {
+        Downsampler::with_clock(inner, max_per_second, SystemClock)
+    }
+}
+
+impl<S, K, C> Downsampler<S, K, C>
+where
+    K: Hash + Eq + Clone,
+    C: Clock,
+{
+    /// Wraps `inner`, reading the current time from `clock`.
+    pub fn with_clock(inner: S, max_per_second: f64, clock: C) -> Self {
+        let last_refill = clock.now();
+        Downsampler {
+            inner,
+            max_per_second,
+            divergence: DEFAULT_DIVERGENCE,
+            clock,
+            tokens: max_per_second,
+            last_refill,
+            last_rtt: HashMap::new(),
+            dropped: 0,
+        }
+    }
+
+    /// Sets the relative change in a peer's RTT (for example, 0.1 for 10%)
+    /// above which a sample is considered divergent and prioritised.
+    ///
+    /// Defaults to 0.1.
+    pub fn set_divergence(&mut self, divergence: f64) {
+        self.divergence = divergence;
+    }
+
+    /// Returns the number of observations discarded.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Returns the wrapped source.
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    /// Returns true if `obs` should be admitted, consuming budget if so.
+    fn admit<V: Vector>(&mut self, obs: &Observation<K, V>) -> bool {
+        let now = self.clock.now();
+        let elapsed = now.saturating_sub(self.last_refill).as_secs_f64();
+        self.last_refill = now;
+        self.tokens = (self.tokens + elapsed * self.max_per_second).min(self.max_per_second);
+
+        let important = match self.last_rtt.get(&obs.peer) {
+            Some(last) => {
+                let last = last.as_secs_f64();
+                (obs.rtt.as_secs_f64() - last).abs() > last * self.divergence
+            }
+            None => true,
+        };
+        let required = if important {
+            1.0
+        } else {
+            1.0 + self.max_per_second / 2.0
+        };
+        if self.tokens < required {
+            return false;
+        }
+
+        self.tokens -= 1.0;
+        self.last_rtt.insert(obs.peer.clone(), obs.rtt);
+        true
+    }
+}
+
+impl<S, K, V, C> RttSource<K, V> for Downsampler<S, K, C>
+where
+    S: RttSource<K, V>,
+    K: Hash + Eq + Clone,
+    V: Vector,
+    C: Clock,
+{
+    fn next_observation(&mut self) -> Option<Observation<K, V>> {
+        loop {
+            let obs = self.inner.next_observation()?;
+            if self.admit(&obs) {
+                return Some(obs);
+            }
+            self.dropped += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use crate::coordinate::Coordinate;
+    use crate::source::ManualSource;
+    use crate::vector::Dimension3;
+
+    fn obs(peer: u32, rtt_ms: u64) -> Observation<u32, Dimension3> {
+        Observation {
+            peer,
+            coordinate: Coordinate::new(Dimension3([1.0, 0.0, 0.0]), 1.0, 0.1),
+            rtt: Duration::from_millis(rtt_ms),
+        }
+    }
+
+    fn drain(s: &mut Downsampler<ManualSource<u32, Dimension3>, u32, MockClock>) -> Vec<u32> {
+        std::iter::from_fn(|| s.next_observation())
+            .map(|o| o.peer)
+            .collect()
+    }
+
+    #[test]
+    fn limits_rate() {
+        let clock = MockClock::default();
+        let mut s = Downsampler::with_clock(ManualSource::default(), 10.0, clock.clone());
+
+        // Redundant samples from one peer only use half the budget.
+        for _ in 0..100 {
+            s.inner.push(obs(1, 10));
+        }
+        assert_eq!(drain(&mut s).len(), 5);
+        assert_eq!(s.dropped(), 95);
+
+        // Novel peers use the remainder.
+        for peer in 2..20 {
+            s.inner.push(obs(peer, 10));
+        }
+        assert_eq!(drain(&mut s).len(), 5);
+
+        // The budget refills over time.
+        clock.advance(Duration::from_secs(1));
+        for peer in 20..40 {
+            s.inner.push(obs(peer, 10));
+        }
+        assert_eq!(drain(&mut s).len(), 10);
+    }
+
+    #[test]
+    fn prefers_divergent_samples() {
+        let clock = MockClock::default();
+        let mut s = Downsampler::with_clock(ManualSource::default(), 4.0, clock);
+
+        // Exhaust the budget available to redundant samples.
+        for _ in 0..10 {
+            s.inner.push(obs(1, 10));
+        }
+        assert_eq!(drain(&mut s).len(), 2);
+
+        s.inner.push(obs(1, 10));
+        s.inner.push(obs(1, 20));
+        assert_eq!(drain(&mut s), vec![1]);
+
+        // Within the divergence threshold of the last admitted sample.
+        s.set_divergence(0.5);
+        s.inner.push(obs(1, 25));
+        assert!(drain(&mut s).is_empty());
+    }
+}
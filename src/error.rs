@@ -0,0 +1,110 @@
+use std::fmt;
+use std::time::Duration;
+
+/// Errors returned by the fallible APIs of this crate.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The measured round-trip time is zero, and cannot be used to update the
+    /// model.
+    InvalidRtt,
+
+    /// A coordinate contains a NaN or infinite vector component, error or
+    /// height.
+    NonFiniteCoordinate,
+
+    /// A vector has a different number of dimensions than expected.
+    DimensionMismatch {
+        /// The expected number of dimensions.
+        expected: usize,
+        /// The number of dimensions actually found.
+        got: usize,
+    },
+
+    /// Encoding or decoding a coordinate failed.
+    Serialization(String),
+
+    /// Reading or writing a storage backend failed.
+    Storage(String),
+
+    /// A delta update was encoded against a different state than the one held
+    /// by the receiver.
+    DigestMismatch,
+
+    /// A coordinate field holds a value outside of the range a real network
+    /// could produce.
+    OutOfRange {
+        /// The name of the offending field.
+        field: &'static str,
+    },
+
+    /// The measured round-trip time is below the physically plausible
+    /// minimum configured for the peer, typically due to a clock bug or a
+    /// proxy answering on behalf of the peer.
+    BelowFloor {
+        /// The minimum plausible RTT to the peer.
+        floor: Duration,
+    },
+
+    /// An observation triggered a condition the model would otherwise absorb
+    /// silently, reported only in [strict mode](crate::Model::set_strict).
+    Strict(Violation),
+}
+
+/// A condition silently corrected by the [`Model`](crate::Model), reported as
+/// an [`Error::Strict`] in [strict mode](crate::Model::set_strict).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Violation {
+    /// The error estimates of both coordinates were zero (or one was
+    /// infinite), leaving the sample weight undefined.
+    DegenerateWeight,
+
+    /// The local and remote coordinates were at the same position, so the
+    /// local node would be pushed in a random direction.
+    CoincidentCoordinates,
+
+    /// The updated height fell below the minimum height, and would be
+    /// clamped.
+    HeightClamped,
+
+    /// The updated coordinate contained a non-finite value.
+    NonFiniteUpdate,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Violation::DegenerateWeight => write!(f, "sample weight is undefined"),
+            Violation::CoincidentCoordinates => write!(f, "coordinates are coincident"),
+            Violation::HeightClamped => write!(f, "height clamped to the minimum"),
+            Violation::NonFiniteUpdate => write!(f, "update produced a non-finite coordinate"),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidRtt => write!(f, "round-trip time must be non-zero"),
+            Error::NonFiniteCoordinate => write!(f, "coordinate contains a non-finite value"),
+            Error::DimensionMismatch { expected, got } => write!(
+                f,
+                "dimension mismatch: expected {} dimensions, got {}",
+                expected, got
+            ),
+            Error::Serialization(msg) => write!(f, "serialization failed: {}", msg),
+            Error::Storage(msg) => write!(f, "storage failed: {}", msg),
+            Error::DigestMismatch => write!(f, "delta base does not match the receiver state"),
+            Error::OutOfRange { field } => write!(f, "coordinate {} is out of range", field),
+            Error::BelowFloor { floor } => write!(
+                f,
+                "round-trip time is below the plausible minimum of {:?}",
+                floor
+            ),
+            Error::Strict(v) => write!(f, "strict mode: {}", v),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,235 @@
+use crate::clock::Clock;
+use crate::coordinate::Coordinate;
+use crate::force::ForceFunction;
+use crate::model::Model;
+use crate::privacy::random_offset;
+use crate::vector::Vector;
+use rand::Rng;
+use std::time::Duration;
+
+/// The default upper bound of the backoff multiplier applied to the patience
+/// of an [`EscapeSchedule`].
+const DEFAULT_MAX_BACKOFF: u32 = 64;
+
+/// Perturbs a [`Model`] whose error estimate stays high for too long, to
+/// escape configurations the model would otherwise never leave.
+///
+/// Vivaldi converges to a local minimum of the prediction error, which is
+/// occasionally far from the global one - a node may settle on the wrong side
+/// of a group of peers, with every observation pulling it against the others.
+/// Once the error estimate has been above the threshold for the configured
+/// patience, the schedule displaces the coordinate by a random offset and
+/// resets the error estimate so the model re-converges from the new position:
+///
+/// ```
+/// use std::time::Duration;
+/// use vivaldi::{EscapeSchedule, Model, vector::Dimension3};
+///
+/// let mut model = Model::<Dimension3>::new();
+/// let mut escape = EscapeSchedule::new(0.5, Duration::from_secs(300), Duration::from_millis(50));
+///
+/// // After each observation:
+/// # let (remote, rtt) = (*Model::<Dimension3>::new().get_coordinate(), Duration::from_millis(20));
+/// model.observe(&remote, rtt);
+/// if escape.check(&mut model) {
+///     println!("perturbed a stuck coordinate");
+/// }
+/// ```
+///
+/// Each perturbation is judged one patience period later: if the error
+/// estimate fell, the perturbation counts as an
+/// [improvement](EscapeSchedule::improvements). Otherwise the patience before
+/// the next perturbation doubles, up to the [maximum
+/// backoff](EscapeSchedule::set_max_backoff), so a model that is inaccurate
+/// for reasons a perturbation cannot fix is not disrupted continually. The
+/// backoff resets once the error estimate falls below the threshold.
+#[derive(Debug, Clone)]
+pub struct EscapeSchedule<V>
+where
+    V: Vector,
+{
+    threshold: f64,
+    patience: Duration,
+    radius: f64,
+    max_backoff: u32,
+    backoff: u32,
+
+    /// The time the error estimate was first seen above the threshold.
+    above_since: Option<Duration>,
+    /// The time of, and error estimate before, the most recent perturbation
+    /// yet to be judged.
+    pending: Option<(Duration, f64)>,
+
+    attempts: usize,
+    improvements: usize,
+
+    _vector: std::marker::PhantomData<V>,
+}
+
+impl<V> EscapeSchedule<V>
+where
+    V: Vector + std::fmt::Debug,
+{
+    /// Initialises a schedule perturbing a model by at most `radius` once its
+    /// error estimate has been above `threshold` for `patience`.
+    pub fn new(threshold: f64, patience: Duration, radius: Duration) -> Self {
+        EscapeSchedule {
+            threshold,
+            patience,
+            radius: radius.as_secs_f64(),
+            max_backoff: DEFAULT_MAX_BACKOFF,
+            backoff: 1,
+            above_since: None,
+            pending: None,
+            attempts: 0,
+            improvements: 0,
+            _vector: std::marker::PhantomData,
+        }
+    }
+
+    /// Sets the largest multiple of the patience waited between unsuccessful
+    /// perturbations. Defaults to 64.
+    pub fn set_max_backoff(&mut self, max_backoff: u32) {
+        self.max_backoff = max_backoff.max(1);
+        self.backoff = self.backoff.min(self.max_backoff);
+    }
+
+    /// Returns the number of perturbations applied.
+    pub fn attempts(&self) -> usize {
+        self.attempts
+    }
+
+    /// Returns the number of perturbations followed by a lower error estimate.
+    pub fn improvements(&self) -> usize {
+        self.improvements
+    }
+
+    /// Returns the patience currently required before the next perturbation,
+    /// including any backoff.
+    pub fn current_patience(&self) -> Duration {
+        self.patience * self.backoff
+    }
+
+    /// Inspects the error estimate of `model`, perturbing the coordinate if it
+    /// has been above the threshold for too long.
+    ///
+    /// Returns true if the coordinate was perturbed.
+    pub fn check<C, F, R>(&mut self, model: &mut Model<V, C, F, f64, R>) -> bool
+    where
+        C: Clock,
+        F: ForceFunction,
+        R: Rng,
+    {
+        let now = model.clock().now();
+        let error = model.get_coordinate().error();
+
+        if let Some((at, before)) = self.pending {
+            if now.saturating_sub(at) < self.patience {
+                return false;
+            }
+            self.pending = None;
+            if error < before {
+                self.improvements += 1;
+            } else {
+                self.backoff = self.backoff.saturating_mul(2).min(self.max_backoff);
+            }
+            self.above_since = Some(now);
+        }
+
+        if error <= self.threshold {
+            self.above_since = None;
+            self.backoff = 1;
+            return false;
+        }
+
+        let since = *self.above_since.get_or_insert(now);
+        if now.saturating_sub(since) < self.current_patience() {
+            return false;
+        }
+
+        let offset = random_offset::<V, _>(self.radius, model.rng());
+        let current = model.get_coordinate();
+        let perturbed = Coordinate::new(
+            current.vector().clone() + offset,
+            model.config().reset_error(),
+            current.height(),
+        );
+        model.set_coordinate(perturbed);
+
+        self.pending = Some((now, error));
+        self.above_since = None;
+        self.attempts += 1;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use crate::model::INITIAL_ERROR;
+    use crate::vector::Dimension2;
+
+    fn stuck_model(clock: &MockClock, error: f64) -> Model<Dimension2, MockClock> {
+        let mut m = Model::with_clock(clock.clone());
+        m.set_coordinate(Coordinate::new(Dimension2([0.1, 0.1]), error, 0.01));
+        m
+    }
+
+    #[test]
+    fn perturbs_after_patience() {
+        let clock = MockClock::default();
+        let mut m = stuck_model(&clock, 0.9);
+        let mut e = EscapeSchedule::new(0.5, Duration::from_secs(10), Duration::from_millis(50));
+
+        assert!(!e.check(&mut m));
+        clock.advance(Duration::from_secs(9));
+        assert!(!e.check(&mut m));
+        clock.advance(Duration::from_secs(1));
+        assert!(e.check(&mut m));
+        assert_eq!(e.attempts(), 1);
+
+        let c = m.get_coordinate();
+        assert_eq!(c.error(), INITIAL_ERROR);
+        assert_eq!(c.height(), 0.01);
+        assert!(c.vector().distance(&Dimension2([0.1, 0.1])).0 <= 0.050);
+
+        // The model re-converges to a lower error than before.
+        m.set_coordinate(Coordinate::new(*c.vector(), 0.1, 0.01));
+        clock.advance(Duration::from_secs(10));
+        assert!(!e.check(&mut m));
+        assert_eq!(e.improvements(), 1);
+        assert_eq!(e.current_patience(), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn backs_off_without_improvement() {
+        let clock = MockClock::default();
+        let mut m = stuck_model(&clock, 0.9);
+        let mut e = EscapeSchedule::new(0.5, Duration::from_secs(10), Duration::from_millis(50));
+        e.set_max_backoff(4);
+
+        let mut perturbed_at = Vec::new();
+        for t in 0..200 {
+            if e.check(&mut m) {
+                perturbed_at.push(t);
+            }
+            // Observations never reduce the error below its stuck value.
+            let c = *m.get_coordinate();
+            m.set_coordinate(Coordinate::new(*c.vector(), c.error().max(0.9), c.height()));
+            clock.advance(Duration::from_secs(1));
+        }
+
+        // Each failed perturbation doubles the wait, up to 4x the patience.
+        assert_eq!(perturbed_at, vec![10, 40, 90, 140, 190]);
+        assert_eq!(e.improvements(), 0);
+        assert_eq!(e.current_patience(), Duration::from_secs(40));
+
+        // Recovering resets the backoff.
+        m.set_coordinate(Coordinate::new(Dimension2([0.1, 0.1]), 0.1, 0.01));
+        clock.advance(Duration::from_secs(10));
+        e.check(&mut m);
+        e.check(&mut m);
+        assert_eq!(e.current_patience(), Duration::from_secs(10));
+    }
+}
@@ -0,0 +1,128 @@
+use crate::clock::Clock;
+use crate::coordinate::Coordinate;
+use crate::force::ForceFunction;
+use crate::model::{estimate_rtt, Model};
+use crate::vector::Vector;
+use rand::Rng;
+use std::time::Duration;
+
+/// An algorithm estimating the round-trip time between the local node and
+/// remote nodes from exchanged coordinates.
+///
+/// [`Model`] implements the Vivaldi algorithm, but applications coding against
+/// this trait can swap in alternative algorithms (or a mock in tests) without
+/// changing their integration layer:
+///
+/// ```
+/// use std::time::Duration;
+/// use vivaldi::{LatencyEstimator, Model, vector::Dimension3};
+///
+/// fn on_response<E: LatencyEstimator>(local: &mut E, remote: &E::Coordinate, rtt: Duration) {
+///     local.observe(remote, rtt);
+/// }
+///
+/// let mut local = Model::<Dimension3>::new();
+/// let remote = Model::<Dimension3>::new();
+///
+/// on_response(&mut local, remote.get_coordinate(), Duration::from_millis(10));
+/// ```
+pub trait LatencyEstimator {
+    /// The coordinate type exchanged between nodes.
+    type Coordinate;
+
+    /// Updates the local node with the coordinate of a remote node and the
+    /// measured round-trip time to it.
+    fn observe(&mut self, remote: &Self::Coordinate, rtt: Duration);
+
+    /// Returns the estimated round-trip time between the local node and the
+    /// remote node with coordinate `remote`.
+    fn estimate(&self, remote: &Self::Coordinate) -> Duration;
+
+    /// Returns the current coordinate of the local node, to be sent to remote
+    /// nodes.
+    fn coordinate(&self) -> &Self::Coordinate;
+}
+
+impl<V, C, F, R> LatencyEstimator for Model<V, C, F, f64, R>
+where
+    V: Vector + std::fmt::Debug,
+    C: Clock,
+    F: ForceFunction,
+    R: Rng,
+{
+    type Coordinate = Coordinate<V>;
+
+    fn observe(&mut self, remote: &Self::Coordinate, rtt: Duration) {
+        Model::observe(self, remote, rtt)
+    }
+
+    fn estimate(&self, remote: &Self::Coordinate) -> Duration {
+        estimate_rtt(self.get_coordinate(), remote)
+    }
+
+    fn coordinate(&self) -> &Self::Coordinate {
+        self.get_coordinate()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::Dimension3;
+
+    /// A fixed-latency estimator, as an application might use in tests.
+    struct Fixed(Duration);
+
+    impl LatencyEstimator for Fixed {
+        type Coordinate = ();
+
+        fn observe(&mut self, _remote: &(), rtt: Duration) {
+            self.0 = rtt;
+        }
+
+        fn estimate(&self, _remote: &()) -> Duration {
+            self.0
+        }
+
+        fn coordinate(&self) -> &() {
+            &()
+        }
+    }
+
+    fn converge<E: LatencyEstimator>(a: &mut E, b: &mut E, rtt: Duration) -> Duration
+    where
+        E::Coordinate: Clone,
+    {
+        for _ in 0..20 {
+            let bc = b.coordinate().clone();
+            a.observe(&bc, rtt);
+            let ac = a.coordinate().clone();
+            b.observe(&ac, rtt);
+        }
+        a.estimate(b.coordinate())
+    }
+
+    #[test]
+    fn model_estimator() {
+        let mut a = Model::<Dimension3>::new();
+        let mut b = Model::<Dimension3>::new();
+        let rtt = Duration::new(1, 0);
+
+        let got = converge(&mut a, &mut b, rtt);
+        assert_eq!(got, estimate_rtt(a.get_coordinate(), b.get_coordinate()));
+        assert!((got.as_secs_f64() - 1.0).abs() < 0.115);
+    }
+
+    #[test]
+    fn mock_estimator() {
+        let rtt = Duration::from_millis(42);
+        assert_eq!(
+            converge(
+                &mut Fixed(Duration::default()),
+                &mut Fixed(Duration::default()),
+                rtt
+            ),
+            rtt
+        );
+    }
+}
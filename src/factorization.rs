@@ -0,0 +1,355 @@
+use crate::error::Error;
+use crate::estimator::LatencyEstimator;
+use crate::model::saturating_duration;
+use crate::vector::Vector;
+#[cfg(feature = "serde")]
+use std::convert::TryFrom;
+use std::time::Duration;
+
+/// The default SGD learning rate.
+const LEARNING_RATE: f64 = 0.05;
+
+/// The default L2 regularisation applied to each update.
+const REGULARISATION: f64 = 0.001;
+
+/// The largest factor component of a valid coordinate, bounding the
+/// estimated RTT well within the range of a [`Duration`].
+const MAX_VALID_COMPONENT: f64 = 1.0e3;
+
+/// A coordinate in a matrix factorisation embedding.
+///
+/// Each node has an outgoing and an incoming vector, with the estimated RTT
+/// from node `i` to node `j` being the dot product of `i`'s outgoing vector and
+/// `j`'s incoming vector. Because the estimate is not a metric distance it need
+/// not satisfy the triangle inequality, nor be symmetric.
+///
+/// With the `serde` feature enabled, deserialising a coordinate validates it
+/// as [`try_new`](FactorCoordinate::try_new) does.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(
+        try_from = "RawFactorCoordinate<V>",
+        bound(deserialize = "V: serde::Deserialize<'de>")
+    )
+)]
+pub struct FactorCoordinate<V>
+where
+    V: Vector,
+{
+    outgoing: V,
+    incoming: V,
+}
+
+/// The serialised form of a [`FactorCoordinate`], validated when
+/// deserialising.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct RawFactorCoordinate<V> {
+    outgoing: V,
+    incoming: V,
+}
+
+#[cfg(feature = "serde")]
+impl<V> TryFrom<RawFactorCoordinate<V>> for FactorCoordinate<V>
+where
+    V: Vector,
+{
+    type Error = Error;
+
+    fn try_from(raw: RawFactorCoordinate<V>) -> Result<Self, Self::Error> {
+        FactorCoordinate::try_new(raw.outgoing, raw.incoming)
+    }
+}
+
+impl<V> FactorCoordinate<V>
+where
+    V: Vector,
+{
+    /// Constructs a coordinate from a received or persisted `outgoing` and
+    /// `incoming` vector, validating every component:
+    ///
+    /// * [`Error::NonFiniteCoordinate`] if any component is NaN or infinite.
+    /// * [`Error::OutOfRange`] if any component is negative or exceeds 1000.
+    pub fn try_new(outgoing: V, incoming: V) -> Result<Self, Error> {
+        let c = FactorCoordinate { outgoing, incoming };
+        c.validate()?;
+        Ok(c)
+    }
+
+    /// Checks every component of the coordinate as described by
+    /// [`try_new`](FactorCoordinate::try_new).
+    pub fn validate(&self) -> Result<(), Error> {
+        let components = || {
+            self.outgoing
+                .components()
+                .iter()
+                .chain(self.incoming.components())
+        };
+        if !components().all(|v| v.is_finite()) {
+            return Err(Error::NonFiniteCoordinate);
+        }
+        if !components().all(|v| (0.0..=MAX_VALID_COMPONENT).contains(v)) {
+            return Err(Error::OutOfRange { field: "component" });
+        }
+        Ok(())
+    }
+
+    /// Returns the outgoing vector of the node.
+    pub fn outgoing(&self) -> &V {
+        &self.outgoing
+    }
+
+    /// Returns the incoming vector of the node.
+    pub fn incoming(&self) -> &V {
+        &self.incoming
+    }
+}
+
+/// A [Phoenix]-style estimator, embedding RTTs by decentralised low-rank
+/// non-negative matrix factorisation.
+///
+/// Internet RTTs routinely violate the triangle inequality (a detour via a
+/// third node can be faster than the direct path), which a metric embedding
+/// such as the Vivaldi [`Model`](crate::model::Model) cannot represent. This
+/// estimator instead models the RTT matrix as the product of two low-rank
+/// non-negative matrices, updated by stochastic gradient descent for each
+/// observation.
+///
+/// The dimensionality of `V` is the rank of the factorisation.
+///
+/// ```
+/// use std::time::Duration;
+/// use vivaldi::{FactorizationEstimator, LatencyEstimator, vector::Dimension3};
+///
+/// let mut a = FactorizationEstimator::<Dimension3>::new();
+/// let b = FactorizationEstimator::<Dimension3>::new();
+///
+/// a.observe(b.coordinate(), Duration::from_millis(100));
+/// let rtt = a.estimate(b.coordinate());
+/// ```
+///
+/// [Phoenix]: https://ieeexplore.ieee.org/document/5934994
+#[derive(Debug, Clone, PartialEq)]
+pub struct FactorizationEstimator<V>
+where
+    V: Vector,
+{
+    coordinate: FactorCoordinate<V>,
+    learning_rate: f64,
+    regularisation: f64,
+}
+
+impl<V> Default for FactorizationEstimator<V>
+where
+    V: Vector,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> FactorizationEstimator<V>
+where
+    V: Vector,
+{
+    /// Initialises an estimator with random non-negative factors.
+    pub fn new() -> Self {
+        Self::with_rates(LEARNING_RATE, REGULARISATION)
+    }
+
+    /// Initialises an estimator using the given SGD learning rate and L2
+    /// regularisation constant.
+    pub fn with_rates(learning_rate: f64, regularisation: f64) -> Self {
+        let mut rng = rand::thread_rng();
+        FactorizationEstimator {
+            coordinate: FactorCoordinate {
+                outgoing: V::random(&mut rng),
+                incoming: V::random(&mut rng),
+            },
+            learning_rate,
+            regularisation,
+        }
+    }
+}
+
+impl<V> LatencyEstimator for FactorizationEstimator<V>
+where
+    V: Vector,
+{
+    type Coordinate = FactorCoordinate<V>;
+
+    fn observe(&mut self, remote: &FactorCoordinate<V>, rtt: Duration) {
+        let rtt = rtt.as_secs_f64();
+        let lr = self.learning_rate;
+        let reg = self.regularisation;
+
+        // Local to remote: rtt ≈ u_i · v_j
+        let out_err = rtt - dot(&self.coordinate.outgoing, &remote.incoming);
+
+        // Remote to local: rtt ≈ u_j · v_i
+        let in_err = rtt - dot(&remote.outgoing, &self.coordinate.incoming);
+
+        let c = &mut self.coordinate;
+        c.outgoing = non_negative(
+            c.outgoing.clone() * (1.0 - lr * reg) + remote.incoming.clone() * (lr * out_err),
+        );
+        c.incoming = non_negative(
+            c.incoming.clone() * (1.0 - lr * reg) + remote.outgoing.clone() * (lr * in_err),
+        );
+    }
+
+    fn estimate(&self, remote: &FactorCoordinate<V>) -> Duration {
+        saturating_duration(dot(&self.coordinate.outgoing, &remote.incoming))
+    }
+
+    fn coordinate(&self) -> &FactorCoordinate<V> {
+        &self.coordinate
+    }
+}
+
+/// Returns the dot product of `a` and `b`.
+fn dot<V: Vector>(a: &V, b: &V) -> f64 {
+    a.components()
+        .iter()
+        .zip(b.components())
+        .map(|(a, b)| a * b)
+        .sum()
+}
+
+/// Projects `v` onto the non-negative orthant.
+fn non_negative<V: Vector>(v: V) -> V {
+    if v.components().iter().all(|c| *c >= 0.0) {
+        return v;
+    }
+    let clamped = v
+        .components()
+        .iter()
+        .map(|c| c.max(0.0))
+        .collect::<Vec<_>>();
+    V::from_components(&clamped).unwrap_or(v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::Model;
+    use crate::vector::{Dimension2, Dimension3};
+
+    /// Trains a set of estimators against the `rtts` matrix.
+    fn train(rtts: &[[f64; 3]; 3], rounds: usize) -> Vec<FactorizationEstimator<Dimension3>> {
+        let mut nodes = (0..3)
+            .map(|_| FactorizationEstimator::new())
+            .collect::<Vec<_>>();
+        for _ in 0..rounds {
+            for i in 0..3 {
+                for j in 0..3 {
+                    if i == j {
+                        continue;
+                    }
+                    let remote = *nodes[j].coordinate();
+                    nodes[i].observe(&remote, Duration::from_secs_f64(rtts[i][j]));
+                }
+            }
+        }
+        nodes
+    }
+
+    #[test]
+    fn converges() {
+        let rtts = [[0.0, 1.0, 2.0], [1.0, 0.0, 1.5], [2.0, 1.5, 0.0]];
+        let nodes = train(&rtts, 2000);
+
+        for i in 0..3 {
+            for j in 0..3 {
+                if i == j {
+                    continue;
+                }
+                let got = nodes[i].estimate(nodes[j].coordinate()).as_secs_f64();
+                let want = rtts[i][j];
+                assert!((got - want).abs() / want < 0.1, "{} vs {}", got, want);
+            }
+        }
+    }
+
+    #[test]
+    fn triangle_inequality_violation() {
+        // a <-> c is far slower than the detour via b, which no metric
+        // embedding can represent.
+        let rtts = [[0.0, 1.0, 5.0], [1.0, 0.0, 1.0], [5.0, 1.0, 0.0]];
+        let nodes = train(&rtts, 2000);
+
+        let got = nodes[0].estimate(nodes[2].coordinate()).as_secs_f64();
+        assert!((got - 5.0).abs() / 5.0 < 0.15, "estimated {}", got);
+
+        // Compare with the metric model, which must respect the triangle
+        // inequality and therefore estimate a <-> c at no more than the
+        // detour.
+        let mut models = (0..3)
+            .map(|_| Model::<Dimension3>::new())
+            .collect::<Vec<_>>();
+        for _ in 0..200 {
+            for i in 0..3 {
+                for j in 0..3 {
+                    if i != j {
+                        let remote = *models[j].get_coordinate();
+                        models[i].observe(&remote, Duration::from_secs_f64(rtts[i][j]));
+                    }
+                }
+            }
+        }
+        let metric =
+            crate::model::estimate_rtt(models[0].get_coordinate(), models[2].get_coordinate())
+                .as_secs_f64();
+        assert!((got - 5.0).abs() < (metric - 5.0).abs());
+    }
+
+    #[test]
+    fn factors_stay_non_negative() {
+        let mut a = FactorizationEstimator::<Dimension3>::new();
+        let b = FactorizationEstimator::<Dimension3>::new();
+        for _ in 0..100 {
+            a.observe(b.coordinate(), Duration::from_nanos(1));
+        }
+        let c = a.coordinate();
+        assert!(c.outgoing().components().iter().all(|v| *v >= 0.0));
+        assert!(c.incoming().components().iter().all(|v| *v >= 0.0));
+    }
+
+    #[test]
+    fn validate() {
+        let ok = Dimension2([0.5, 1.0]);
+        assert!(FactorCoordinate::try_new(ok, ok).is_ok());
+
+        for bad in [
+            Dimension2([f64::NAN, 1.0]),
+            Dimension2([f64::INFINITY, 1.0]),
+        ] {
+            assert_eq!(
+                FactorCoordinate::try_new(ok, bad),
+                Err(Error::NonFiniteCoordinate)
+            );
+        }
+        for bad in [Dimension2([-0.5, 1.0]), Dimension2([1e300, 1.0])] {
+            assert_eq!(
+                FactorCoordinate::try_new(bad, ok),
+                Err(Error::OutOfRange { field: "component" })
+            );
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_validates() {
+        let parse = |json: &str| serde_json::from_str::<FactorCoordinate<Dimension2>>(json);
+        assert!(parse(r#"{"outgoing":[0.5,1.0],"incoming":[1.0,0.5]}"#).is_ok());
+
+        for json in [
+            r#"{"outgoing":[-0.5,1.0],"incoming":[1.0,0.5]}"#,
+            r#"{"outgoing":[0.5,1.0],"incoming":[1e300,0.5]}"#,
+        ] {
+            assert!(parse(json).is_err(), "{}", json);
+        }
+    }
+}
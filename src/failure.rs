@@ -0,0 +1,277 @@
+use crate::coordinate::Coordinate;
+use crate::model::{estimate_rtt, saturating_duration};
+use crate::vector::Vector;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::Duration;
+
+/// The default phi threshold above which a peer is suspected to have failed,
+/// corresponding to a false positive rate of roughly 1 in 10^8.
+const DEFAULT_THRESHOLD: f64 = 8.0;
+
+/// The default minimum standard deviation of a response time, in seconds.
+const DEFAULT_MIN_DEVIATION: f64 = 0.005;
+
+/// The smoothing factor of the per-peer residual averages.
+const RESIDUAL_ALPHA: f64 = 0.1;
+
+/// A phi-accrual failure detector using the RTT estimated from network
+/// coordinates as the expected response time of each peer.
+///
+/// Rather than a fixed timeout for every peer, the detector reports a
+/// suspicion level `phi` that grows the longer a response is outstanding
+/// relative to the response time expected of that peer. The expected response
+/// time is the coordinate RTT estimate, corrected by the error observed in
+/// previous responses from the peer, and its deviation is derived from the
+/// error estimates of both coordinates. Nearby peers are therefore suspected
+/// quickly, while distant or poorly positioned peers are given longer:
+///
+/// ```
+/// use std::time::Duration;
+/// use vivaldi::{FailureDetector, Model, vector::Dimension3};
+///
+/// let local = Model::<Dimension3>::new();
+/// # let remote = Model::<Dimension3>::new();
+/// let (l, r) = (local.get_coordinate(), remote.get_coordinate());
+/// let mut detector = FailureDetector::new();
+///
+/// // On each response, record the measured RTT.
+/// detector.record("remote", l, r, Duration::from_millis(20));
+///
+/// // While a probe is outstanding:
+/// if !detector.is_available(&"remote", l, r, Duration::from_millis(500)) {
+///     println!("suspect");
+/// }
+/// ```
+///
+/// A `phi` of `1` implies a 10% chance the response is merely slow, `2` a 1%
+/// chance, and so on. Peers are suspected once `phi` exceeds the
+/// [threshold](FailureDetector::set_threshold), which defaults to 8.
+#[derive(Debug, Clone)]
+pub struct FailureDetector<K> {
+    threshold: f64,
+    min_deviation: f64,
+    residuals: HashMap<K, Residual>,
+}
+
+/// The moving average and variance of the difference between the measured
+/// and estimated RTT of a peer, in seconds.
+#[derive(Debug, Clone, Copy)]
+struct Residual {
+    mean: f64,
+    variance: f64,
+}
+
+impl<K> Default for FailureDetector<K>
+where
+    K: Hash + Eq,
+{
+    fn default() -> Self {
+        FailureDetector {
+            threshold: DEFAULT_THRESHOLD,
+            min_deviation: DEFAULT_MIN_DEVIATION,
+            residuals: HashMap::new(),
+        }
+    }
+}
+
+impl<K> FailureDetector<K>
+where
+    K: Hash + Eq,
+{
+    /// Initialises a failure detector with the default threshold.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the `phi` above which a peer is suspected to have failed.
+    pub fn set_threshold(&mut self, threshold: f64) {
+        self.threshold = threshold;
+    }
+
+    /// Sets the minimum standard deviation of the response time of any peer,
+    /// preventing confident estimates of nearby peers from producing
+    /// unrealistically tight timeouts. Defaults to 5ms.
+    pub fn set_min_deviation(&mut self, min: Duration) {
+        self.min_deviation = min.as_secs_f64();
+    }
+
+    /// Records a response from `peer` received after `rtt`, given the
+    /// coordinates of the local node and the peer at the time.
+    pub fn record<V: Vector>(
+        &mut self,
+        peer: K,
+        local: &Coordinate<V>,
+        remote: &Coordinate<V>,
+        rtt: Duration,
+    ) {
+        let residual = rtt.as_secs_f64() - estimate_rtt(local, remote).as_secs_f64();
+        if !residual.is_finite() {
+            return;
+        }
+
+        self.residuals
+            .entry(peer)
+            .and_modify(|r| {
+                let diff = residual - r.mean;
+                r.mean += RESIDUAL_ALPHA * diff;
+                r.variance = (1.0 - RESIDUAL_ALPHA) * (r.variance + RESIDUAL_ALPHA * diff * diff);
+            })
+            .or_insert(Residual {
+                mean: residual,
+                variance: 0.0,
+            });
+    }
+
+    /// Forgets the response history of `peer`.
+    pub fn remove(&mut self, peer: &K) {
+        self.residuals.remove(peer);
+    }
+
+    /// Returns the suspicion level of `peer` after waiting `elapsed` for a
+    /// response.
+    pub fn phi<V: Vector>(
+        &self,
+        peer: &K,
+        local: &Coordinate<V>,
+        remote: &Coordinate<V>,
+        elapsed: Duration,
+    ) -> f64 {
+        let (mean, deviation) = self.expected(peer, local, remote);
+        phi((elapsed.as_secs_f64() - mean) / deviation)
+    }
+
+    /// Returns true if the suspicion level of `peer` after waiting `elapsed`
+    /// is below the threshold.
+    pub fn is_available<V: Vector>(
+        &self,
+        peer: &K,
+        local: &Coordinate<V>,
+        remote: &Coordinate<V>,
+        elapsed: Duration,
+    ) -> bool {
+        self.phi(peer, local, remote, elapsed) < self.threshold
+    }
+
+    /// Returns how long to wait for a response from `peer` before it is
+    /// suspected, for use as a per-peer timeout.
+    pub fn timeout<V: Vector>(
+        &self,
+        peer: &K,
+        local: &Coordinate<V>,
+        remote: &Coordinate<V>,
+    ) -> Duration {
+        let (mean, deviation) = self.expected(peer, local, remote);
+
+        // phi is monotonic in y, so bisect for the threshold.
+        let (mut lo, mut hi) = (-10.0, 40.0);
+        for _ in 0..64 {
+            let mid = (lo + hi) / 2.0;
+            if phi(mid) < self.threshold {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+
+        saturating_duration(mean + hi * deviation)
+    }
+
+    /// Returns the expected response time of `peer` and its standard
+    /// deviation, in seconds.
+    fn expected<V: Vector>(
+        &self,
+        peer: &K,
+        local: &Coordinate<V>,
+        remote: &Coordinate<V>,
+    ) -> (f64, f64) {
+        let estimate = estimate_rtt(local, remote).as_secs_f64();
+        let coordinate_deviation = estimate * (local.error() + remote.error());
+
+        let (mean, variance) = match self.residuals.get(peer) {
+            Some(r) => (estimate + r.mean, r.variance),
+            None => (estimate, 0.0),
+        };
+        let deviation = (variance + coordinate_deviation * coordinate_deviation).sqrt();
+
+        (mean, deviation.max(self.min_deviation))
+    }
+}
+
+/// Returns `-log10` of the probability of a normally distributed value
+/// exceeding the mean by `y` standard deviations, using the logistic
+/// approximation of the normal CDF.
+fn phi(y: f64) -> f64 {
+    let e = (-y * (1.5976 + 0.070566 * y * y)).exp();
+    if y > 0.0 {
+        -(e / (1.0 + e)).log10()
+    } else {
+        -(1.0 - 1.0 / (1.0 + e)).log10()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::Dimension2;
+
+    fn coord(x: f64, error: f64) -> Coordinate<Dimension2> {
+        Coordinate::new(Dimension2([x, 0.0]), error, 0.0)
+    }
+
+    #[test]
+    fn phi_increases_with_elapsed() {
+        let d = FailureDetector::new();
+        let (local, remote) = (coord(0.0, 0.1), coord(0.1, 0.1));
+        let ms = Duration::from_millis;
+
+        let phis = [50, 100, 150, 200]
+            .iter()
+            .map(|t| d.phi(&"a", &local, &remote, ms(*t)))
+            .collect::<Vec<_>>();
+        assert!(phis.windows(2).all(|w| w[0] < w[1]), "{:?}", phis);
+        assert!((phis[1] - 2_f64.log10()).abs() < 0.01);
+
+        assert!(d.is_available(&"a", &local, &remote, ms(100)));
+        assert!(!d.is_available(&"a", &local, &remote, ms(500)));
+
+        let timeout = d.timeout(&"a", &local, &remote);
+        assert!(d.is_available(&"a", &local, &remote, timeout - ms(1)));
+        assert!(!d.is_available(&"a", &local, &remote, timeout + ms(1)));
+    }
+
+    #[test]
+    fn proximity_and_confidence_shorten_timeouts() {
+        let d = FailureDetector::<&str>::new();
+        let local = coord(0.0, 0.1);
+
+        let near = d.timeout(&"a", &local, &coord(0.01, 0.1));
+        let far = d.timeout(&"a", &local, &coord(0.2, 0.1));
+        let uncertain = d.timeout(&"a", &local, &coord(0.2, 1.0));
+        assert!(
+            near < far && far < uncertain,
+            "{:?} {:?} {:?}",
+            near,
+            far,
+            uncertain
+        );
+    }
+
+    #[test]
+    fn learns_residuals() {
+        let mut d = FailureDetector::new();
+        let (local, remote) = (coord(0.0, 0.01), coord(0.1, 0.01));
+        let before = d.timeout(&"a", &local, &remote);
+
+        // The peer consistently responds slower than its coordinate suggests.
+        for _ in 0..100 {
+            d.record("a", &local, &remote, Duration::from_millis(300));
+        }
+        let after = d.timeout(&"a", &local, &remote);
+        assert!(after > before + Duration::from_millis(150), "{:?}", after);
+        assert_eq!(d.timeout(&"b", &local, &remote), before);
+
+        d.remove(&"a");
+        assert_eq!(d.timeout(&"a", &local, &remote), before);
+    }
+}
@@ -0,0 +1,480 @@
+//! Filters smoothing raw RTT samples before they are applied to a
+//! [`Model`](crate::Model).
+//!
+//! Raw RTT measurements are noisy - a single delayed packet can move the
+//! local coordinate a long way. Keep one filter per peer, and observe only the
+//! filtered value:
+//!
+//! ```
+//! use std::collections::HashMap;
+//! use std::time::Duration;
+//! use vivaldi::{Model, vector::Dimension3};
+//! use vivaldi::filters::{LatencyFilter, MovingMedian};
+//!
+//! let mut model = Model::<Dimension3>::new();
+//! let mut filters = HashMap::new();
+//! # let remote = Model::<Dimension3>::new();
+//!
+//! // On each response:
+//! let rtt = Duration::from_millis(20);
+//! let filter = filters.entry("remote").or_insert_with(|| MovingMedian::new(5));
+//! if let Some(rtt) = filter.push(rtt) {
+//!     model.observe(remote.get_coordinate(), rtt);
+//! }
+//! ```
+//!
+//! Alternatively, a [`Model`](crate::Model) built with
+//! [`ModelBuilder::latency_filter`](crate::ModelBuilder::latency_filter) keeps
+//! a [`MovingMedian`] per peer itself, applied by
+//! [`observe_peer`](crate::Model::observe_peer).
+
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// A filter over a sequence of RTT samples from a single peer.
+pub trait LatencyFilter {
+    /// Adds `rtt` to the filter, returning the filtered RTT to observe, or
+    /// `None` if the filter has no output for this sample.
+    fn push(&mut self, rtt: Duration) -> Option<Duration>;
+
+    /// Discards all samples, such as after a known route change.
+    fn reset(&mut self);
+}
+
+/// Outputs the median of the most recent samples, discarding isolated
+/// spikes.
+///
+/// This is the filter used by HashiCorp's Serf.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MovingMedian {
+    window: Window,
+}
+
+impl MovingMedian {
+    /// Initialises a filter over the most recent `window` samples.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `window` is zero.
+    pub fn new(window: usize) -> Self {
+        MovingMedian {
+            window: Window::new(window),
+        }
+    }
+
+    /// Returns true if the window size is non-zero and it holds no more
+    /// samples than its size, as it always does unless deserialised from
+    /// corrupt state.
+    #[cfg(feature = "serde")]
+    pub(crate) fn is_valid(&self) -> bool {
+        self.window.size > 0 && self.window.samples.len() <= self.window.size
+    }
+}
+
+impl LatencyFilter for MovingMedian {
+    fn push(&mut self, rtt: Duration) -> Option<Duration> {
+        self.window.push(rtt);
+        Some(self.window.percentile(0.5))
+    }
+
+    fn reset(&mut self) {
+        self.window.clear();
+    }
+}
+
+/// Outputs the lowest sample of each burst of `n` probes, once the burst
+/// completes.
+///
+/// Queueing delays only ever add to the RTT, so the minimum of a short burst
+/// of probes is the best estimate of the underlying path latency.
+#[derive(Debug, Clone)]
+pub struct MinOfN {
+    n: usize,
+    count: usize,
+    min: Duration,
+}
+
+impl MinOfN {
+    /// Initialises a filter over bursts of `n` samples.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is zero.
+    pub fn new(n: usize) -> Self {
+        assert!(n > 0, "burst size must be non-zero");
+        MinOfN {
+            n,
+            count: 0,
+            min: Duration::MAX,
+        }
+    }
+}
+
+impl LatencyFilter for MinOfN {
+    fn push(&mut self, rtt: Duration) -> Option<Duration> {
+        self.min = self.min.min(rtt);
+        self.count += 1;
+        if self.count < self.n {
+            return None;
+        }
+
+        let min = self.min;
+        self.reset();
+        Some(min)
+    }
+
+    fn reset(&mut self) {
+        self.count = 0;
+        self.min = Duration::MAX;
+    }
+}
+
+/// Outputs an exponentially weighted moving average of the samples.
+///
+/// Each sample contributes `alpha` of the output, so smaller values smooth
+/// more heavily but track changes more slowly.
+#[derive(Debug, Clone)]
+pub struct Ewma {
+    alpha: f64,
+    value: Option<f64>,
+}
+
+impl Ewma {
+    /// Initialises a filter weighting each new sample by `alpha`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `alpha` is not in `(0.0, 1.0]`.
+    pub fn new(alpha: f64) -> Self {
+        assert!(alpha > 0.0 && alpha <= 1.0, "invalid alpha {}", alpha);
+        Ewma { alpha, value: None }
+    }
+}
+
+impl LatencyFilter for Ewma {
+    fn push(&mut self, rtt: Duration) -> Option<Duration> {
+        let rtt = rtt.as_secs_f64();
+        let value = match self.value {
+            Some(v) => v + self.alpha * (rtt - v),
+            None => rtt,
+        };
+        self.value = Some(value);
+        Some(Duration::from_secs_f64(value))
+    }
+
+    fn reset(&mut self) {
+        self.value = None;
+    }
+}
+
+/// Outputs a percentile of the most recent samples.
+///
+/// Low percentiles (such as the 10th) track the uncongested path latency,
+/// ignoring most queueing delay.
+#[derive(Debug, Clone)]
+pub struct PercentileWindow {
+    window: Window,
+    percentile: f64,
+}
+
+impl PercentileWindow {
+    /// Initialises a filter outputting the `percentile` (0 to 1) of the most
+    /// recent `window` samples.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `window` is zero or `percentile` is outside of `0.0..=1.0`.
+    pub fn new(window: usize, percentile: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&percentile),
+            "invalid percentile {}",
+            percentile
+        );
+        PercentileWindow {
+            window: Window::new(window),
+            percentile,
+        }
+    }
+}
+
+impl LatencyFilter for PercentileWindow {
+    fn push(&mut self, rtt: Duration) -> Option<Duration> {
+        self.window.push(rtt);
+        Some(self.window.percentile(self.percentile))
+    }
+
+    fn reset(&mut self) {
+        self.window.clear();
+    }
+}
+
+/// A [`MovingMedian`] over a window of `N` samples held inline, for targets
+/// without a heap.
+///
+/// Requires the `heapless` feature.
+#[cfg(feature = "heapless")]
+#[derive(Debug, Clone)]
+pub struct FixedMovingMedian<const N: usize> {
+    window: FixedWindow<N>,
+}
+
+#[cfg(feature = "heapless")]
+impl<const N: usize> FixedMovingMedian<N> {
+    /// Initialises a filter over the most recent `N` samples.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `N` is zero.
+    pub fn new() -> Self {
+        FixedMovingMedian {
+            window: FixedWindow::new(),
+        }
+    }
+}
+
+#[cfg(feature = "heapless")]
+impl<const N: usize> Default for FixedMovingMedian<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(feature = "heapless")]
+impl<const N: usize> LatencyFilter for FixedMovingMedian<N> {
+    fn push(&mut self, rtt: Duration) -> Option<Duration> {
+        self.window.push(rtt);
+        Some(self.window.percentile(0.5))
+    }
+
+    fn reset(&mut self) {
+        self.window.clear();
+    }
+}
+
+/// A [`PercentileWindow`] over a window of `N` samples held inline, for
+/// targets without a heap.
+///
+/// Requires the `heapless` feature.
+#[cfg(feature = "heapless")]
+#[derive(Debug, Clone)]
+pub struct FixedPercentileWindow<const N: usize> {
+    window: FixedWindow<N>,
+    percentile: f64,
+}
+
+#[cfg(feature = "heapless")]
+impl<const N: usize> FixedPercentileWindow<N> {
+    /// Initialises a filter outputting the `percentile` (0 to 1) of the most
+    /// recent `N` samples.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `N` is zero or `percentile` is outside of `0.0..=1.0`.
+    pub fn new(percentile: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&percentile),
+            "invalid percentile {}",
+            percentile
+        );
+        FixedPercentileWindow {
+            window: FixedWindow::new(),
+            percentile,
+        }
+    }
+}
+
+#[cfg(feature = "heapless")]
+impl<const N: usize> LatencyFilter for FixedPercentileWindow<N> {
+    fn push(&mut self, rtt: Duration) -> Option<Duration> {
+        self.window.push(rtt);
+        Some(self.window.percentile(self.percentile))
+    }
+
+    fn reset(&mut self) {
+        self.window.clear();
+    }
+}
+
+/// A fixed-size window of the most recent samples.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Window {
+    size: usize,
+    samples: VecDeque<Duration>,
+    /// Scratch space for computing percentiles.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    sorted: Vec<Duration>,
+}
+
+impl Window {
+    fn new(size: usize) -> Self {
+        assert!(size > 0, "window size must be non-zero");
+        Window {
+            size,
+            samples: VecDeque::with_capacity(size),
+            sorted: Vec::with_capacity(size),
+        }
+    }
+
+    fn push(&mut self, rtt: Duration) {
+        if self.samples.len() == self.size {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(rtt);
+    }
+
+    fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    /// Returns the nearest-rank `p` percentile of the samples.
+    fn percentile(&mut self, p: f64) -> Duration {
+        self.sorted.clear();
+        self.sorted.extend(&self.samples);
+        self.sorted.sort_unstable();
+
+        let rank = (p * self.sorted.len() as f64).ceil() as usize;
+        self.sorted[rank.clamp(1, self.sorted.len()) - 1]
+    }
+}
+
+/// A [`Window`] of `N` samples held inline.
+#[cfg(feature = "heapless")]
+#[derive(Debug, Clone)]
+struct FixedWindow<const N: usize> {
+    samples: heapless::Deque<Duration, N>,
+}
+
+#[cfg(feature = "heapless")]
+impl<const N: usize> FixedWindow<N> {
+    fn new() -> Self {
+        assert!(N > 0, "window size must be non-zero");
+        FixedWindow {
+            samples: heapless::Deque::new(),
+        }
+    }
+
+    fn push(&mut self, rtt: Duration) {
+        if self.samples.is_full() {
+            self.samples.pop_front();
+        }
+        // The window cannot be full after popping.
+        let _ = self.samples.push_back(rtt);
+    }
+
+    fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    /// Returns the nearest-rank `p` percentile of the samples.
+    fn percentile(&self, p: f64) -> Duration {
+        let mut sorted = self
+            .samples
+            .iter()
+            .copied()
+            .collect::<heapless::Vec<_, N>>();
+        sorted.sort_unstable();
+
+        let rank = (p * sorted.len() as f64).ceil() as usize;
+        sorted[rank.clamp(1, sorted.len()) - 1]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(v: u64) -> Duration {
+        Duration::from_millis(v)
+    }
+
+    fn run<F: LatencyFilter>(f: &mut F, samples: &[u64]) -> Vec<Option<Duration>> {
+        samples.iter().map(|v| f.push(ms(*v))).collect()
+    }
+
+    #[test]
+    fn moving_median() {
+        let mut f = MovingMedian::new(3);
+        assert_eq!(
+            run(&mut f, &[10, 500, 12, 11, 13]),
+            vec![
+                Some(ms(10)),
+                Some(ms(10)),
+                Some(ms(12)),
+                Some(ms(12)),
+                Some(ms(12))
+            ]
+        );
+
+        f.reset();
+        assert_eq!(f.push(ms(100)), Some(ms(100)));
+    }
+
+    #[test]
+    fn min_of_n() {
+        let mut f = MinOfN::new(3);
+        assert_eq!(
+            run(&mut f, &[15, 10, 20, 30, 25, 40]),
+            vec![None, None, Some(ms(10)), None, None, Some(ms(25))]
+        );
+
+        f.push(ms(1));
+        f.reset();
+        assert_eq!(run(&mut f, &[5, 6, 7]), vec![None, None, Some(ms(5))]);
+    }
+
+    #[test]
+    fn ewma() {
+        let mut f = Ewma::new(0.5);
+        assert_eq!(
+            run(&mut f, &[10, 20, 20]),
+            vec![
+                Some(ms(10)),
+                Some(ms(15)),
+                Some(Duration::from_micros(17_500))
+            ]
+        );
+
+        f.reset();
+        assert_eq!(f.push(ms(100)), Some(ms(100)));
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid alpha")]
+    fn ewma_invalid_alpha() {
+        Ewma::new(0.0);
+    }
+
+    #[test]
+    fn percentile_window() {
+        let mut f = PercentileWindow::new(10, 0.1);
+        let out = run(&mut f, &[50, 40, 30, 20, 10, 60, 70, 80, 90, 100, 110]);
+        assert_eq!(out[4], Some(ms(10)));
+        // The 10ms sample remains in the window of 10.
+        assert_eq!(out[10], Some(ms(10)));
+
+        let mut f = PercentileWindow::new(4, 1.0);
+        assert_eq!(run(&mut f, &[1, 4, 2, 3]).last(), Some(&Some(ms(4))));
+
+        let mut f = PercentileWindow::new(4, 0.0);
+        assert_eq!(run(&mut f, &[3, 4, 2, 3]).last(), Some(&Some(ms(2))));
+    }
+
+    #[cfg(feature = "heapless")]
+    #[test]
+    fn fixed_windows_match() {
+        let samples = [50, 40, 30, 500, 10, 60, 70, 5, 90, 100, 110, 20];
+
+        let mut heap = MovingMedian::new(5);
+        let mut fixed = FixedMovingMedian::<5>::new();
+        assert_eq!(run(&mut fixed, &samples), run(&mut heap, &samples));
+
+        let mut heap = PercentileWindow::new(4, 0.25);
+        let mut fixed = FixedPercentileWindow::<4>::new(0.25);
+        assert_eq!(run(&mut fixed, &samples), run(&mut heap, &samples));
+
+        fixed.reset();
+        assert_eq!(fixed.push(ms(100)), Some(ms(100)));
+    }
+}
@@ -0,0 +1,188 @@
+use crate::clock::Clock;
+use crate::coordinate::Coordinate;
+use crate::error::Error;
+use crate::force::ForceFunction;
+use crate::geo::GeoPoint;
+use crate::model::{estimate_rtt, Model};
+use crate::telemetry;
+use crate::vector::Vector;
+use rand::Rng;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::Duration;
+
+/// The lowest plausible RTT to each peer, rejecting observations below it and
+/// clamping estimates to it.
+///
+/// When peers advertise their approximate location, the speed of light bounds
+/// the RTT to them from below. Measurements under the floor cannot be real -
+/// they are typically caused by clock bugs, or a proxy answering on behalf of
+/// the peer - and would drag the local coordinate towards the peer:
+///
+/// ```
+/// use std::time::Duration;
+/// use vivaldi::{Error, GeoPoint, Model, RttFloors, vector::Dimension3};
+///
+/// let mut model = Model::<Dimension3>::new();
+/// # let remote = Model::<Dimension3>::new();
+/// let mut floors = RttFloors::new();
+///
+/// let local = GeoPoint { lat: 51.5, lon: -0.13 };
+/// let sydney = GeoPoint { lat: -33.9, lon: 151.2 };
+/// floors.set_from_geo("sydney", &local, &sydney);
+///
+/// // A 1ms response cannot have come from Sydney.
+/// let result = floors.observe(&mut model, &"sydney", remote.get_coordinate(), Duration::from_millis(1));
+/// assert!(matches!(result, Err(Error::BelowFloor { .. })));
+/// ```
+#[derive(Debug, Clone)]
+pub struct RttFloors<K> {
+    floors: HashMap<K, Duration>,
+}
+
+impl<K> Default for RttFloors<K>
+where
+    K: Hash + Eq,
+{
+    fn default() -> Self {
+        RttFloors {
+            floors: HashMap::new(),
+        }
+    }
+}
+
+impl<K> RttFloors<K>
+where
+    K: Hash + Eq,
+{
+    /// Initialises an empty set of floors.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the lowest plausible RTT to `peer`.
+    pub fn set(&mut self, peer: K, floor: Duration) {
+        self.floors.insert(peer, floor);
+    }
+
+    /// Sets the floor of `peer` to the [minimum RTT](GeoPoint::min_rtt)
+    /// between the `local` location and the `remote` location advertised by
+    /// the peer.
+    pub fn set_from_geo(&mut self, peer: K, local: &GeoPoint, remote: &GeoPoint) {
+        self.set(peer, local.min_rtt(remote));
+    }
+
+    /// Returns the floor of `peer`, if set.
+    pub fn get(&self, peer: &K) -> Option<Duration> {
+        self.floors.get(peer).copied()
+    }
+
+    /// Removes the floor of `peer`, returning it if set.
+    pub fn remove(&mut self, peer: &K) -> Option<Duration> {
+        self.floors.remove(peer)
+    }
+
+    /// Returns [`Error::BelowFloor`] if `rtt` is below the floor of `peer`.
+    ///
+    /// Peers without a floor accept any RTT.
+    pub fn check(&self, peer: &K, rtt: Duration) -> Result<(), Error> {
+        match self.get(peer) {
+            Some(floor) if rtt < floor => Err(Error::BelowFloor { floor }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Observes `rtt` to `peer` at `coord` with [`Model::try_observe`], unless
+    /// it is below the floor of `peer`.
+    ///
+    /// The model is left unchanged if an error is returned.
+    pub fn observe<V, C, F, R>(
+        &self,
+        model: &mut Model<V, C, F, f64, R>,
+        peer: &K,
+        coord: &Coordinate<V>,
+        rtt: Duration,
+    ) -> Result<(), Error>
+    where
+        V: Vector + std::fmt::Debug,
+        C: Clock,
+        F: ForceFunction,
+        R: Rng,
+    {
+        if let Err(e) = self.check(peer, rtt) {
+            telemetry::rejected(&e);
+            return Err(e);
+        }
+        model.try_observe(coord, rtt)
+    }
+
+    /// Estimates the RTT between `local` and `peer` at `remote`, clamped to at
+    /// least the floor of `peer`.
+    pub fn estimate_rtt<V: Vector>(
+        &self,
+        peer: &K,
+        local: &Coordinate<V>,
+        remote: &Coordinate<V>,
+    ) -> Duration {
+        let estimate = estimate_rtt(local, remote);
+        self.get(peer).map_or(estimate, |floor| estimate.max(floor))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::Dimension2;
+
+    #[test]
+    fn rejects_below_floor() {
+        let mut model = Model::<Dimension2>::new();
+        let remote = Coordinate::new(Dimension2([0.05, 0.0]), 0.5, 0.0);
+
+        let mut floors = RttFloors::new();
+        floors.set("a", Duration::from_millis(30));
+
+        let before = *model.get_coordinate();
+        assert_eq!(
+            floors.observe(&mut model, &"a", &remote, Duration::from_millis(29)),
+            Err(Error::BelowFloor {
+                floor: Duration::from_millis(30)
+            })
+        );
+        assert_eq!(*model.get_coordinate(), before);
+
+        floors
+            .observe(&mut model, &"a", &remote, Duration::from_millis(30))
+            .unwrap();
+        floors
+            .observe(&mut model, &"b", &remote, Duration::from_millis(1))
+            .unwrap();
+        assert_ne!(*model.get_coordinate(), before);
+
+        assert_eq!(floors.remove(&"a"), Some(Duration::from_millis(30)));
+        assert_eq!(floors.check(&"a", Duration::from_millis(1)), Ok(()));
+    }
+
+    #[test]
+    fn clamps_estimates() {
+        let local = Coordinate::new(Dimension2([0.0, 0.0]), 0.5, 0.0);
+        let remote = Coordinate::new(Dimension2([0.01, 0.0]), 0.5, 0.0);
+        let estimate = estimate_rtt(&local, &remote);
+
+        let mut floors = RttFloors::new();
+        assert_eq!(floors.estimate_rtt(&"a", &local, &remote), estimate);
+
+        let london = GeoPoint {
+            lat: 51.5,
+            lon: -0.13,
+        };
+        let new_york = GeoPoint {
+            lat: 40.7,
+            lon: -74.0,
+        };
+        floors.set_from_geo("a", &london, &new_york);
+        let floor = floors.get(&"a").unwrap();
+        assert!(floor > estimate);
+        assert_eq!(floors.estimate_rtt(&"a", &local, &remote), floor);
+    }
+}
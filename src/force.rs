@@ -0,0 +1,130 @@
+/// Computes the force applied to the local coordinate by an observation,
+/// allowing alternative force curves to the linear spring of the Vivaldi
+/// paper.
+///
+/// A [`Model`](crate::Model) uses the [`LinearSpring`] by default - use
+/// [`with_force_function`](crate::Model::with_force_function) to experiment
+/// with another curve:
+///
+/// ```
+/// use vivaldi::{CappedSpring, Model, vector::Dimension3};
+///
+/// let model = Model::<Dimension3>::new()
+///     .with_force_function(CappedSpring::new(std::time::Duration::from_millis(20)));
+/// ```
+pub trait ForceFunction {
+    /// Returns the signed distance (in the units of the observed metric) the
+    /// local coordinate moves away from the remote coordinate, given the
+    /// `measured` value, the value `estimated` from the current coordinates,
+    /// and the adaptive timestep (`δ` in the paper, between 0 and 1).
+    ///
+    /// A positive force pushes the nodes apart, a negative force pulls them
+    /// together.
+    fn force(&self, measured: f64, estimated: f64, timestep: f64) -> f64;
+}
+
+/// The linear spring force of the Vivaldi paper, proportional to the
+/// difference between the measured and estimated values:
+///
+/// ```text
+///     δ × (rtt − estimate)
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LinearSpring;
+
+impl ForceFunction for LinearSpring {
+    fn force(&self, measured: f64, estimated: f64, timestep: f64) -> f64 {
+        timestep * (measured - estimated)
+    }
+}
+
+/// A [`LinearSpring`] whose force is limited to a maximum magnitude, bounding
+/// the distance moved by a single (possibly wildly wrong) observation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CappedSpring {
+    max: f64,
+}
+
+impl CappedSpring {
+    /// Initialises a spring moving the local coordinate by at most `max` per
+    /// observation.
+    pub fn new(max: std::time::Duration) -> Self {
+        CappedSpring {
+            max: max.as_secs_f64(),
+        }
+    }
+}
+
+impl ForceFunction for CappedSpring {
+    fn force(&self, measured: f64, estimated: f64, timestep: f64) -> f64 {
+        LinearSpring
+            .force(measured, estimated, timestep)
+            .clamp(-self.max, self.max)
+    }
+}
+
+/// A spring whose force grows with the logarithm of the ratio between the
+/// measured and estimated values:
+///
+/// ```text
+///     δ × estimate × ln(rtt / estimate)
+/// ```
+///
+/// Small errors produce (almost) the same force as the [`LinearSpring`].
+/// Measurements far above the estimate (such as congestion spikes) are
+/// damped, while measurements far below it are amplified - relative, rather
+/// than absolute, errors drive the movement.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LogarithmicSpring;
+
+impl ForceFunction for LogarithmicSpring {
+    fn force(&self, measured: f64, estimated: f64, timestep: f64) -> f64 {
+        if !(measured > 0.0 && estimated > 0.0) {
+            return LinearSpring.force(measured, estimated, timestep);
+        }
+        timestep * estimated * (measured / estimated).ln()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn linear() {
+        assert_eq!(LinearSpring.force(0.75, 0.25, 0.5), 0.25);
+        assert_eq!(LinearSpring.force(0.25, 0.75, 0.5), -0.25);
+    }
+
+    #[test]
+    fn capped() {
+        let f = CappedSpring::new(Duration::from_millis(50));
+        assert_eq!(f.force(0.75, 0.25, 0.5), 0.05);
+        assert_eq!(f.force(0.25, 0.75, 0.5), -0.05);
+        assert_eq!(f.force(0.1, 0.12, 0.5), LinearSpring.force(0.1, 0.12, 0.5));
+    }
+
+    #[test]
+    fn logarithmic() {
+        // Close to linear for small errors.
+        let (log, lin) = (
+            LogarithmicSpring.force(0.101, 0.1, 0.5),
+            LinearSpring.force(0.101, 0.1, 0.5),
+        );
+        assert!((log - lin).abs() < lin * 0.01, "{} vs {}", log, lin);
+
+        // Spikes are damped, and short measurements amplified.
+        assert!(LogarithmicSpring.force(1.0, 0.1, 0.5) < LinearSpring.force(1.0, 0.1, 0.5));
+        assert!(LogarithmicSpring.force(0.01, 0.1, 0.5) < LinearSpring.force(0.01, 0.1, 0.5));
+
+        // Degenerate estimates fall back to the linear force.
+        assert_eq!(
+            LogarithmicSpring.force(0.1, 0.0, 0.5),
+            LinearSpring.force(0.1, 0.0, 0.5)
+        );
+    }
+}
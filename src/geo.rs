@@ -0,0 +1,356 @@
+use crate::coordinate::Coordinate;
+use crate::model::saturating_duration;
+use crate::vector::Vector;
+use std::time::Duration;
+
+/// The mean radius of the Earth in kilometres.
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+/// The speed of light in optical fibre in kilometres per second, roughly two
+/// thirds of the speed of light in a vacuum.
+const FIBRE_KM_PER_SEC: f64 = 200_000.0;
+
+/// A geographic location in degrees.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GeoPoint {
+    /// Latitude in degrees, positive north of the equator.
+    pub lat: f64,
+    /// Longitude in degrees, positive east of the prime meridian.
+    pub lon: f64,
+}
+
+impl GeoPoint {
+    /// Returns the great-circle distance between `self` and `other` in
+    /// kilometres.
+    pub fn distance_km(&self, other: &GeoPoint) -> f64 {
+        let (lat1, lat2) = (self.lat.to_radians(), other.lat.to_radians());
+        let dlat = lat2 - lat1;
+        let dlon = (other.lon - self.lon).to_radians();
+
+        let h = (dlat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (dlon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS_KM * h.sqrt().asin()
+    }
+
+    /// Returns the lowest physically plausible RTT between `self` and
+    /// `other` - the time taken for light in fibre to travel the great-circle
+    /// distance between them and back.
+    ///
+    /// Real paths are longer than the great circle, so measured RTTs are
+    /// typically well above this floor.
+    ///
+    /// Returns zero, imposing no floor, if either location is NaN or
+    /// infinite.
+    pub fn min_rtt(&self, other: &GeoPoint) -> Duration {
+        let km = self.distance_km(other);
+        if !km.is_finite() {
+            return Duration::ZERO;
+        }
+        saturating_duration(2.0 * km / FIBRE_KM_PER_SEC)
+    }
+}
+
+/// A fitted mapping between coordinate space and geography, computed from a
+/// handful of nodes with known locations.
+///
+/// Coordinate distances are in seconds of RTT, and network paths only loosely
+/// follow geography, so the mapping is a coarse approximation suited to
+/// visualisation and geo-fencing heuristics rather than precise location.
+///
+/// Two mappings are fitted:
+///
+/// * A scale factor between the Euclidean distance separating two coordinates
+///   (excluding their heights, which model access link costs rather than
+///   geography) and the great-circle distance between them, used by
+///   [`distance_km`](GeoCalibration::distance_km).
+///
+/// * A 2D similarity transform (rotation, reflection, scale and translation)
+///   from the first two coordinate components to a local map projection, used
+///   by [`locate`](GeoCalibration::locate).
+///
+/// ```
+/// use vivaldi::{GeoCalibration, GeoPoint, Model, vector::Dimension2};
+///
+/// # let a = Model::<Dimension2>::new();
+/// # let b = Model::<Dimension2>::new();
+/// let london = GeoPoint { lat: 51.5, lon: -0.13 };
+/// let new_york = GeoPoint { lat: 40.7, lon: -74.0 };
+///
+/// let calibration = GeoCalibration::fit(&[
+///     (london, *a.get_coordinate()),
+///     (new_york, *b.get_coordinate()),
+/// ]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GeoCalibration {
+    km_per_unit: f64,
+
+    /// The projection origin.
+    origin: GeoPoint,
+
+    /// The complex similarity transform `w = a × z + b` (or `a × conj(z) + b`
+    /// when `reflect` is set) from coordinate plane `z` to local km plane `w`.
+    a: (f64, f64),
+    b: (f64, f64),
+    reflect: bool,
+}
+
+impl GeoCalibration {
+    /// Fits a calibration from nodes with known locations and coordinates.
+    ///
+    /// Returns `None` if fewer than two nodes are given, or the nodes are all
+    /// at the same location or coordinate.
+    pub fn fit<V: Vector>(known: &[(GeoPoint, Coordinate<V>)]) -> Option<Self> {
+        if known.len() < 2 {
+            return None;
+        }
+
+        // Least squares scale through the origin over all pairs:
+        //
+        //      s = Σ(g × c) / Σ(c²)
+        //
+        let mut gc = 0.0;
+        let mut cc = 0.0;
+        for (i, (gi, ci)) in known.iter().enumerate() {
+            for (gj, cj) in &known[i + 1..] {
+                let g = gi.distance_km(gj);
+                let c = ci.vector().distance(cj.vector()).0;
+                gc += g * c;
+                cc += c * c;
+            }
+        }
+        if cc <= 0.0 || gc <= 0.0 {
+            return None;
+        }
+        let km_per_unit = gc / cc;
+
+        // Project the known locations onto a local plane centred on their
+        // mean, and fit a similarity transform from the coordinate plane.
+        let n = known.len() as f64;
+        let origin = GeoPoint {
+            lat: known.iter().map(|(g, _)| g.lat).sum::<f64>() / n,
+            lon: known.iter().map(|(g, _)| g.lon).sum::<f64>() / n,
+        };
+        let w = known
+            .iter()
+            .map(|(g, _)| project(&origin, g))
+            .collect::<Vec<_>>();
+        let z = known
+            .iter()
+            .map(|(_, c)| plane(c.vector()))
+            .collect::<Vec<_>>();
+
+        let z_conj = z.iter().map(|v| (v.0, -v.1)).collect::<Vec<_>>();
+        let (a, b, residual) = fit_similarity(&z, &w)?;
+        let (ar, br, residual_r) = fit_similarity(&z_conj, &w)?;
+
+        let (a, b, reflect) = if residual_r < residual {
+            (ar, br, true)
+        } else {
+            (a, b, false)
+        };
+
+        Some(GeoCalibration {
+            km_per_unit,
+            origin,
+            a,
+            b,
+            reflect,
+        })
+    }
+
+    /// Returns the fitted number of kilometres per unit of coordinate
+    /// distance.
+    pub fn km_per_unit(&self) -> f64 {
+        self.km_per_unit
+    }
+
+    /// Returns the approximate geographic distance in kilometres between the
+    /// nodes at coordinates `a` and `b`.
+    pub fn distance_km<V: Vector>(&self, a: &Coordinate<V>, b: &Coordinate<V>) -> f64 {
+        a.vector().distance(b.vector()).0 * self.km_per_unit
+    }
+
+    /// Returns the approximate geographic location of the node at `coord`.
+    pub fn locate<V: Vector>(&self, coord: &Coordinate<V>) -> GeoPoint {
+        let mut z = plane(coord.vector());
+        if self.reflect {
+            z.1 = -z.1;
+        }
+        let w = add(mul(self.a, z), self.b);
+        unproject(&self.origin, w)
+    }
+}
+
+/// Returns the first two components of `v` as a point on a plane.
+fn plane<V: Vector>(v: &V) -> (f64, f64) {
+    let c = v.components();
+    (
+        c.first().copied().unwrap_or_default(),
+        c.get(1).copied().unwrap_or_default(),
+    )
+}
+
+/// Projects `p` onto a plane in km centred on `origin` (equirectangular).
+fn project(origin: &GeoPoint, p: &GeoPoint) -> (f64, f64) {
+    let x = (p.lon - origin.lon).to_radians() * origin.lat.to_radians().cos() * EARTH_RADIUS_KM;
+    let y = (p.lat - origin.lat).to_radians() * EARTH_RADIUS_KM;
+    (x, y)
+}
+
+/// The inverse of [`project`].
+fn unproject(origin: &GeoPoint, (x, y): (f64, f64)) -> GeoPoint {
+    let lat = origin.lat + (y / EARTH_RADIUS_KM).to_degrees();
+    let lon = origin.lon + (x / (EARTH_RADIUS_KM * origin.lat.to_radians().cos())).to_degrees();
+    GeoPoint { lat, lon }
+}
+
+/// Least squares fit of the complex similarity `w = a × z + b`, returning `a`,
+/// `b` and the sum of squared residuals.
+#[allow(clippy::type_complexity)]
+fn fit_similarity(z: &[(f64, f64)], w: &[(f64, f64)]) -> Option<((f64, f64), (f64, f64), f64)> {
+    let n = z.len() as f64;
+    let zm = z.iter().fold((0.0, 0.0), |acc, v| add(acc, *v));
+    let zm = (zm.0 / n, zm.1 / n);
+    let wm = w.iter().fold((0.0, 0.0), |acc, v| add(acc, *v));
+    let wm = (wm.0 / n, wm.1 / n);
+
+    // a = Σ (w - w̄) × conj(z - z̄) / Σ |z - z̄|²
+    let mut num = (0.0, 0.0);
+    let mut den = 0.0;
+    for (zi, wi) in z.iter().zip(w) {
+        let dz = (zi.0 - zm.0, zi.1 - zm.1);
+        let dw = (wi.0 - wm.0, wi.1 - wm.1);
+        num = add(num, mul(dw, (dz.0, -dz.1)));
+        den += dz.0 * dz.0 + dz.1 * dz.1;
+    }
+    if den <= 0.0 {
+        return None;
+    }
+    let a = (num.0 / den, num.1 / den);
+    let b = sub(wm, mul(a, zm));
+
+    let residual = z
+        .iter()
+        .zip(w)
+        .map(|(zi, wi)| {
+            let d = sub(add(mul(a, *zi), b), *wi);
+            d.0 * d.0 + d.1 * d.1
+        })
+        .sum();
+
+    Some((a, b, residual))
+}
+
+fn add(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    (a.0 + b.0, a.1 + b.1)
+}
+
+fn sub(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    (a.0 - b.0, a.1 - b.1)
+}
+
+fn mul(a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    (a.0 * b.0 - a.1 * b.1, a.0 * b.1 + a.1 * b.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::Dimension2;
+
+    fn coord(x: f64, y: f64) -> Coordinate<Dimension2> {
+        Coordinate::new(Dimension2([x, y]), 1.0, 0.0)
+    }
+
+    #[test]
+    fn haversine() {
+        let london = GeoPoint {
+            lat: 51.5074,
+            lon: -0.1278,
+        };
+        let paris = GeoPoint {
+            lat: 48.8566,
+            lon: 2.3522,
+        };
+
+        let d = london.distance_km(&paris);
+        assert!((d - 343.5).abs() < 1.0, "got {}", d);
+        assert_eq!(london.distance_km(&london), 0.0);
+
+        // 343.5km there and back at 200,000km/s.
+        let floor = london.min_rtt(&paris).as_secs_f64();
+        assert!((floor - 0.003435).abs() < 1e-5, "got {}", floor);
+    }
+
+    #[test]
+    fn min_rtt_invalid_location() {
+        let london = GeoPoint {
+            lat: 51.5074,
+            lon: -0.1278,
+        };
+        for bad in [f64::NAN, f64::INFINITY] {
+            let p = GeoPoint { lat: bad, lon: 0.0 };
+            assert_eq!(london.min_rtt(&p), Duration::ZERO);
+            assert_eq!(p.min_rtt(&london), Duration::ZERO);
+        }
+    }
+
+    #[test]
+    fn fit_and_locate() {
+        // Build coordinates from a synthetic, rotated and reflected
+        // projection of the known locations at 1 unit = 1000km.
+        let origin = GeoPoint {
+            lat: 45.0,
+            lon: 5.0,
+        };
+        let points = [
+            GeoPoint {
+                lat: 44.0,
+                lon: 4.0,
+            },
+            GeoPoint {
+                lat: 46.0,
+                lon: 4.5,
+            },
+            GeoPoint {
+                lat: 45.5,
+                lon: 6.5,
+            },
+            GeoPoint {
+                lat: 44.5,
+                lon: 5.5,
+            },
+        ];
+        let known = points
+            .iter()
+            .map(|p| {
+                let (x, y) = project(&origin, p);
+                (*p, coord(y / 1000.0, x / 1000.0))
+            })
+            .collect::<Vec<_>>();
+
+        let cal = GeoCalibration::fit(&known).unwrap();
+        assert!(
+            (cal.km_per_unit() - 1000.0).abs() < 10.0,
+            "{}",
+            cal.km_per_unit()
+        );
+
+        for (p, c) in &known {
+            let got = cal.locate(c);
+            assert!(got.distance_km(p) < 1.0, "{:?} vs {:?}", got, p);
+        }
+
+        let d = cal.distance_km(&known[0].1, &known[1].1);
+        let want = points[0].distance_km(&points[1]);
+        assert!((d - want).abs() / want < 0.05);
+    }
+
+    #[test]
+    fn fit_requires_two_distinct_nodes() {
+        let p = GeoPoint { lat: 0.0, lon: 0.0 };
+        assert!(GeoCalibration::fit(&[(p, coord(0.0, 0.0))]).is_none());
+        assert!(GeoCalibration::fit(&[(p, coord(0.0, 0.0)), (p, coord(1.0, 0.0))]).is_none());
+    }
+}
@@ -0,0 +1,334 @@
+use crate::estimator::LatencyEstimator;
+use crate::model::saturating_duration;
+use crate::vector::Vector;
+use rand::Rng;
+use std::time::Duration;
+
+/// The number of gradient descent iterations used to solve a position.
+const SOLVE_ITERATIONS: usize = 500;
+
+/// The initial gradient descent step size, as a fraction of the mean distance
+/// error, decayed over the iterations.
+const SOLVE_STEP: f64 = 0.5;
+
+/// The number of randomly initialised attempts made when solving landmark
+/// coordinates, keeping the one with the lowest residual error.
+const LANDMARK_RESTARTS: usize = 16;
+
+/// A [Global Network Positioning] (GNP) estimator.
+///
+/// Unlike the decentralised [`Model`](crate::model::Model), GNP positions each
+/// node relative to a small fixed set of landmark nodes with known
+/// coordinates. The landmark coordinates are computed once (see
+/// [`solve_landmarks`](GnpEstimator::solve_landmarks)) from the measured RTTs
+/// between every pair of landmarks, and each host solves its own coordinate by
+/// minimising the relative error of its measured RTT to each landmark.
+///
+/// This suits deployments with stable infrastructure nodes that can act as
+/// landmarks, trading decentralisation for accuracy.
+///
+/// Observations of nodes that are not landmarks are ignored - only landmark
+/// coordinates (as returned by [`landmarks`](GnpEstimator::landmarks)) are used
+/// to position the local node.
+///
+/// ```
+/// use std::time::Duration;
+/// use vivaldi::{GnpEstimator, LatencyEstimator, vector::Dimension2};
+///
+/// let landmarks = vec![
+///     Dimension2([0.0, 0.0]),
+///     Dimension2([1.0, 0.0]),
+///     Dimension2([0.0, 1.0]),
+/// ];
+/// let mut gnp = GnpEstimator::new(landmarks.clone());
+///
+/// for (landmark, rtt) in landmarks.iter().zip(&[0.5, 0.5, 0.5]) {
+///     gnp.observe(landmark, Duration::from_secs_f64(*rtt));
+/// }
+///
+/// let rtt = gnp.estimate(&Dimension2([1.0, 1.0]));
+/// ```
+///
+/// [Global Network Positioning]: https://www.cs.rice.edu/~eugeneng/papers/INFOCOM02.pdf
+#[derive(Debug, Clone, PartialEq)]
+pub struct GnpEstimator<V>
+where
+    V: Vector + PartialEq,
+{
+    landmarks: Vec<V>,
+    /// The most recent RTT (in seconds) measured to each landmark, if any.
+    rtts: Vec<Option<f64>>,
+    position: V,
+}
+
+impl<V> GnpEstimator<V>
+where
+    V: Vector + PartialEq,
+{
+    /// Initialises a GNP estimator positioned relative to the given landmark
+    /// coordinates.
+    pub fn new(landmarks: Vec<V>) -> Self {
+        let rtts = vec![None; landmarks.len()];
+        let position = centroid(&landmarks);
+        GnpEstimator {
+            landmarks,
+            rtts,
+            position,
+        }
+    }
+
+    /// Computes landmark coordinates from the measured RTTs between every
+    /// pair of landmarks.
+    ///
+    /// `rtts[i][j]` is the RTT between landmark `i` and landmark `j`; the
+    /// matrix must be square. The returned coordinates are in the same order
+    /// as the rows of `rtts`.
+    ///
+    /// The embedding is solved several times from starting positions drawn
+    /// from `rng` to avoid local minima, returning the solution with the
+    /// lowest error.
+    pub fn solve_landmarks<R>(rtts: &[Vec<Duration>], rng: &mut R) -> Vec<V>
+    where
+        R: Rng + ?Sized,
+    {
+        let n = rtts.len();
+        let residual = |positions: &[V]| -> f64 {
+            let mut sum = 0.0;
+            for i in 0..n {
+                for j in (i + 1)..n {
+                    let want = rtts[i][j].as_secs_f64();
+                    if want > 0.0 {
+                        let got = positions[i].distance(&positions[j]).0;
+                        sum += ((got - want) / want).powi(2);
+                    }
+                }
+            }
+            sum
+        };
+
+        let mut best: Option<(f64, Vec<V>)> = None;
+        for _ in 0..LANDMARK_RESTARTS {
+            let mut positions = (0..n).map(|_| V::random(rng)).collect::<Vec<_>>();
+
+            'solve: for iter in 0..SOLVE_ITERATIONS {
+                let step = step_size(iter);
+                for i in 0..n {
+                    let targets = (0..n)
+                        .filter(|&j| j != i)
+                        .map(|j| (&positions[j], rtts[i][j].as_secs_f64()));
+                    let next = positions[i].clone() - &(gradient(&positions[i], targets) * step);
+                    if !is_finite(&next) {
+                        break 'solve;
+                    }
+                    positions[i] = next;
+                }
+            }
+
+            let err = residual(&positions);
+            if err.is_finite() && best.as_ref().is_none_or(|(e, _)| err < *e) {
+                best = Some((err, positions));
+            }
+        }
+
+        best.map(|(_, p)| p).unwrap_or_default()
+    }
+
+    /// Returns the landmark coordinates.
+    pub fn landmarks(&self) -> &[V] {
+        &self.landmarks
+    }
+
+    /// Re-solves the local position from the latest landmark measurements,
+    /// starting from the current position.
+    fn solve(&mut self) {
+        let mut position = self.position.clone();
+        for iter in 0..SOLVE_ITERATIONS {
+            let targets = self
+                .landmarks
+                .iter()
+                .zip(&self.rtts)
+                .filter_map(|(l, rtt)| rtt.map(|rtt| (l, rtt)));
+            let next = position.clone() - &(gradient(&position, targets) * step_size(iter));
+            if !is_finite(&next) {
+                break;
+            }
+            position = next;
+        }
+        self.position = position;
+    }
+}
+
+impl<V> LatencyEstimator for GnpEstimator<V>
+where
+    V: Vector + PartialEq,
+{
+    type Coordinate = V;
+
+    fn observe(&mut self, remote: &V, rtt: Duration) {
+        let idx = match self.landmarks.iter().position(|l| l == remote) {
+            Some(v) => v,
+            None => return,
+        };
+        if rtt == Duration::from_secs(0) {
+            return;
+        }
+
+        self.rtts[idx] = Some(rtt.as_secs_f64());
+        self.solve();
+    }
+
+    fn estimate(&self, remote: &V) -> Duration {
+        saturating_duration(self.position.distance(remote).0)
+    }
+
+    fn coordinate(&self) -> &V {
+        &self.position
+    }
+}
+
+/// Returns the mean gradient of the squared relative errors between the
+/// distance from `from` to each target position and the target distance.
+///
+/// Each term is scaled by half the square of its target distance, so the
+/// result is the mean displacement (in seconds) from `from` to the nearest
+/// point at the target distance - the step is independent of the scale of the
+/// RTTs, where the raw gradient grows with the inverse square of the RTT and
+/// overshoots at millisecond scale.
+fn gradient<'a, V, I>(from: &V, targets: I) -> V
+where
+    V: Vector + 'a,
+    I: Iterator<Item = (&'a V, f64)>,
+{
+    let mut grad = V::default();
+    let mut n = 0;
+    for (to, want) in targets {
+        let dist = from.distance(to).0;
+        if dist < f64::EPSILON || want <= 0.0 {
+            continue;
+        }
+
+        // d/dx ((||x - l|| - d) / d)^2 = 2 (||x - l|| - d) / d^2 × (x - l) / ||x - l||
+        //
+        // scaled by d^2 / 2.
+        let scale = (dist - want) / dist;
+        grad = grad + (from.clone() - to) * scale;
+        n += 1;
+    }
+
+    if n == 0 {
+        return grad;
+    }
+    grad / n as f64
+}
+
+/// Returns true if every component of `v` is finite.
+fn is_finite<V: Vector>(v: &V) -> bool {
+    v.components().iter().all(|c| c.is_finite())
+}
+
+/// Returns the step size to use for gradient descent iteration `iter`.
+fn step_size(iter: usize) -> f64 {
+    SOLVE_STEP / (1.0 + iter as f64 / 100.0)
+}
+
+/// Returns the mean of `vectors`, or the default vector if empty.
+fn centroid<V: Vector>(vectors: &[V]) -> V {
+    if vectors.is_empty() {
+        return V::default();
+    }
+    let sum = vectors.iter().fold(V::default(), |acc, v| acc + v.clone());
+    sum / vectors.len() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::Dimension2;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    fn landmarks() -> Vec<Dimension2> {
+        vec![
+            Dimension2([0.0, 0.0]),
+            Dimension2([1.0, 0.0]),
+            Dimension2([0.0, 1.0]),
+            Dimension2([1.0, 1.0]),
+        ]
+    }
+
+    #[test]
+    fn solves_host_position() {
+        let landmarks = landmarks();
+        let host = Dimension2([0.3, 0.6]);
+
+        let mut gnp = GnpEstimator::new(landmarks.clone());
+        for l in &landmarks {
+            gnp.observe(l, Duration::from_secs_f64(host.distance(l).0));
+        }
+
+        let got = gnp.coordinate().distance(&host).0;
+        assert!(got < 0.01, "solved position {} from truth", got);
+
+        let remote = Dimension2([2.0, 2.0]);
+        let want = host.distance(&remote).0;
+        let est = gnp.estimate(&remote).as_secs_f64();
+        assert!((est - want).abs() / want < 0.05);
+    }
+
+    #[test]
+    fn solves_millisecond_landmarks() {
+        let landmarks = vec![
+            Dimension2([0.0, 0.0]),
+            Dimension2([0.03, 0.0]),
+            Dimension2([0.0, 0.04]),
+        ];
+        let host = Dimension2([0.01, 0.01]);
+
+        let mut gnp = GnpEstimator::new(landmarks.clone());
+        for l in &landmarks {
+            gnp.observe(l, Duration::from_secs_f64(host.distance(l).0));
+        }
+
+        assert!(is_finite(gnp.coordinate()), "{:?}", gnp.coordinate());
+        for l in &landmarks {
+            let want = host.distance(l).0;
+            let est = gnp.estimate(l).as_secs_f64();
+            assert!((est - want).abs() / want < 0.01, "{} vs {}", est, want);
+        }
+    }
+
+    #[test]
+    fn ignores_non_landmarks() {
+        let mut gnp = GnpEstimator::new(landmarks());
+        let before = gnp.clone();
+
+        gnp.observe(&Dimension2([5.0, 5.0]), Duration::new(1, 0));
+        assert_eq!(gnp, before);
+    }
+
+    #[test]
+    fn solve_landmarks_preserves_distances() {
+        let truth = landmarks();
+        let rtts = truth
+            .iter()
+            .map(|a| {
+                truth
+                    .iter()
+                    .map(|b| Duration::from_secs_f64(a.distance(b).0))
+                    .collect()
+            })
+            .collect::<Vec<Vec<_>>>();
+
+        let mut rng = StdRng::seed_from_u64(42);
+        let solved = GnpEstimator::<Dimension2>::solve_landmarks(&rtts, &mut rng);
+        for i in 0..truth.len() {
+            for j in 0..truth.len() {
+                if i == j {
+                    continue;
+                }
+                let want = rtts[i][j].as_secs_f64();
+                let got = solved[i].distance(&solved[j]).0;
+                assert!((got - want).abs() / want < 0.05, "{} vs {}", got, want);
+            }
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use crate::coordinate::Coordinate;
+use crate::model::estimate_rtt;
+use crate::vector::Vector;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::Duration;
+
+/// The smallest member error estimate used when weighting members, preventing
+/// a single member with a zero error from dominating its group.
+const MIN_MEMBER_ERROR: f64 = 1.0e-3;
+
+/// Maintains a virtual coordinate for each group of nodes (such as a zone or
+/// datacenter), computed from the coordinates of its members.
+///
+/// Traffic steering typically operates on groups of nodes rather than
+/// individual nodes. Each group is represented by the error-weighted centroid
+/// of its members, allowing group-to-group RTT estimates:
+///
+/// ```
+/// use vivaldi::{GroupCoordinates, Model, vector::Dimension3};
+///
+/// # let nodes = (0..4).map(|_| Model::<Dimension3>::new()).collect::<Vec<_>>();
+/// let mut groups = GroupCoordinates::new();
+/// groups.update("us-east", "node-1", nodes[0].get_coordinate());
+/// groups.update("us-east", "node-2", nodes[1].get_coordinate());
+/// groups.update("eu-west", "node-3", nodes[2].get_coordinate());
+/// groups.update("eu-west", "node-4", nodes[3].get_coordinate());
+///
+/// let rtt = groups.estimate_rtt(&"us-east", &"eu-west");
+/// ```
+///
+/// Members are weighted by the inverse of their error estimate, so confident
+/// members pull the virtual coordinate harder than uncertain ones. The error
+/// estimate of the virtual coordinate is propagated from its members as for a
+/// weighted mean of independent estimates - it is lower than that of any one
+/// member, reflecting the additional information in the aggregate. How far
+/// members sit from the virtual coordinate is reported separately by
+/// [`spread`](GroupCoordinates::spread), as a widely spread group is poorly
+/// described by a single point regardless of how accurate that point is.
+#[derive(Debug, Clone)]
+pub struct GroupCoordinates<G, K, V>
+where
+    G: Hash + Eq,
+    K: Hash + Eq,
+    V: Vector,
+{
+    groups: HashMap<G, HashMap<K, Coordinate<V>>>,
+}
+
+impl<G, K, V> Default for GroupCoordinates<G, K, V>
+where
+    G: Hash + Eq,
+    K: Hash + Eq,
+    V: Vector,
+{
+    fn default() -> Self {
+        GroupCoordinates {
+            groups: HashMap::new(),
+        }
+    }
+}
+
+impl<G, K, V> GroupCoordinates<G, K, V>
+where
+    G: Hash + Eq,
+    K: Hash + Eq,
+    V: Vector,
+{
+    /// Initialises an empty set of groups.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inserts or replaces the coordinate of `member` of `group`.
+    pub fn update(&mut self, group: G, member: K, coord: &Coordinate<V>) {
+        self.groups
+            .entry(group)
+            .or_default()
+            .insert(member, coord.clone());
+    }
+
+    /// Removes `member` from `group`, returning false if it was not present.
+    ///
+    /// A group is removed along with its last member.
+    pub fn remove(&mut self, group: &G, member: &K) -> bool {
+        let members = match self.groups.get_mut(group) {
+            Some(v) => v,
+            None => return false,
+        };
+        let removed = members.remove(member).is_some();
+        if members.is_empty() {
+            self.groups.remove(group);
+        }
+        removed
+    }
+
+    /// Returns an iterator over the groups with at least one member.
+    pub fn groups(&self) -> impl Iterator<Item = &G> {
+        self.groups.keys()
+    }
+
+    /// Returns the number of members of `group`.
+    pub fn members(&self, group: &G) -> usize {
+        self.groups.get(group).map_or(0, |m| m.len())
+    }
+
+    /// Returns the virtual coordinate of `group`, or `None` if it has no
+    /// members.
+    pub fn coordinate(&self, group: &G) -> Option<Coordinate<V>> {
+        let members = self.groups.get(group)?;
+        let weight = |c: &Coordinate<V>| 1.0 / c.error().max(MIN_MEMBER_ERROR);
+
+        let total = members.values().map(weight).sum::<f64>();
+        let mut vector = V::default().components().to_vec();
+        let (mut height, mut variance) = (0.0, 0.0);
+        for c in members.values() {
+            let w = weight(c) / total;
+            for (acc, v) in vector.iter_mut().zip(c.vector().components()) {
+                *acc += w * v;
+            }
+            height += w * c.height();
+            variance += w * w * c.error() * c.error();
+        }
+
+        let vector =
+            V::from_components(&vector).expect("default vector has the group dimensionality");
+        Some(Coordinate::new(vector, variance.sqrt(), height))
+    }
+
+    /// Returns the root-mean-square distance (in seconds) of the members of
+    /// `group` from its virtual coordinate, or `None` if it has no members.
+    pub fn spread(&self, group: &G) -> Option<f64> {
+        let centre = self.coordinate(group)?;
+        let members = &self.groups[group];
+
+        let sum = members
+            .values()
+            .map(|c| c.vector().distance(centre.vector()).0.powi(2))
+            .sum::<f64>();
+        Some((sum / members.len() as f64).sqrt())
+    }
+
+    /// Returns the estimated RTT between the virtual coordinates of groups `a`
+    /// and `b`, or `None` if either has no members.
+    pub fn estimate_rtt(&self, a: &G, b: &G) -> Option<Duration> {
+        Some(estimate_rtt(&self.coordinate(a)?, &self.coordinate(b)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::Dimension2;
+
+    fn coord(x: f64, y: f64, error: f64) -> Coordinate<Dimension2> {
+        Coordinate::new(Dimension2([x, y]), error, 0.001)
+    }
+
+    #[test]
+    fn virtual_coordinates() {
+        let mut g = GroupCoordinates::new();
+        assert!(g.coordinate(&"a").is_none());
+
+        g.update("a", 1, &coord(0.0, 0.0, 0.2));
+        g.update("a", 2, &coord(0.02, 0.0, 0.2));
+        g.update("b", 3, &coord(0.1, 0.0, 0.1));
+        g.update("b", 4, &coord(0.1, 0.03, 0.2));
+
+        let a = g.coordinate(&"a").unwrap();
+        assert!((a.vector().0[0] - 0.01).abs() < 1e-12);
+        assert!((a.error() - 0.2 / 2_f64.sqrt()).abs() < 1e-12);
+        assert!((a.height() - 0.001).abs() < 1e-12);
+        assert!((g.spread(&"a").unwrap() - 0.01).abs() < 1e-12);
+
+        // The more confident member pulls harder.
+        let b = g.coordinate(&"b").unwrap();
+        assert!((b.vector().0[1] - 0.01).abs() < 1e-12);
+
+        let rtt = g.estimate_rtt(&"a", &"b").unwrap();
+        assert_eq!(rtt, estimate_rtt(&a, &b));
+        assert!(g.estimate_rtt(&"a", &"c").is_none());
+    }
+
+    #[test]
+    fn membership() {
+        let mut g = GroupCoordinates::new();
+        g.update("a", 1, &coord(0.0, 0.0, 0.2));
+        g.update("a", 2, &coord(1.0, 0.0, 0.2));
+        g.update("a", 1, &coord(1.0, 0.0, 0.2));
+        assert_eq!(g.members(&"a"), 2);
+        assert_eq!(g.spread(&"a"), Some(0.0));
+
+        assert!(g.remove(&"a", &1));
+        assert!(!g.remove(&"a", &1));
+        assert!(g.remove(&"a", &2));
+        assert_eq!(g.groups().count(), 0);
+        assert!(!g.remove(&"b", &1));
+    }
+}
@@ -0,0 +1,256 @@
+use crate::clock::Clock;
+use crate::force::ForceFunction;
+use crate::model::Model;
+use crate::vector::Vector;
+use rand::Rng;
+use std::time::Duration;
+
+/// The number of recent observations considered when assessing health.
+const WINDOW: usize = 16;
+
+/// The fractional change in mean error between the older and newer half of the
+/// window considered a trend, rather than noise.
+const TREND: f64 = 0.1;
+
+/// The verdict returned by [`Model::health`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Health {
+    /// The error estimate is low and stable.
+    Healthy,
+
+    /// The error estimate is falling, or too few observations have been made
+    /// to judge the trend.
+    Converging,
+
+    /// The error estimate is neither falling nor low - the coordinate keeps
+    /// moving without the estimates improving, typically due to inconsistent
+    /// or contradictory measurements.
+    Oscillating,
+
+    /// The error estimate or the movement of the coordinate is growing, or the
+    /// coordinate contains non-finite values.
+    Diverging,
+
+    /// The model has not been updated within
+    /// [`stale_after`](HealthThresholds::stale_after).
+    Stale,
+}
+
+/// The thresholds used by [`Model::health_with`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HealthThresholds {
+    /// The time since the last observation after which a model is considered
+    /// [`Stale`](Health::Stale).
+    ///
+    /// Defaults to 5 minutes.
+    pub stale_after: Duration,
+
+    /// The highest error estimate considered [`Healthy`](Health::Healthy).
+    ///
+    /// Defaults to 0.2.
+    pub healthy_error: f64,
+}
+
+impl Default for HealthThresholds {
+    fn default() -> Self {
+        HealthThresholds {
+            stale_after: Duration::from_secs(5 * 60),
+            healthy_error: 0.2,
+        }
+    }
+}
+
+impl<V, C, F, R> Model<V, C, F, f64, R>
+where
+    V: Vector + std::fmt::Debug,
+    C: Clock,
+    F: ForceFunction,
+    R: Rng,
+{
+    /// Returns a verdict on the state of the model using the default
+    /// [`HealthThresholds`].
+    ///
+    /// The verdict is derived from the trend of the error estimate and the
+    /// variance of the coordinate displacement over the last few
+    /// observations, and the time since the last observation - allowing
+    /// orchestration to restart or re-bootstrap unhealthy nodes:
+    ///
+    /// ```
+    /// use vivaldi::{Health, Model, vector::Dimension3};
+    ///
+    /// let model = Model::<Dimension3>::new();
+    /// assert_eq!(model.health(), Health::Converging);
+    /// ```
+    pub fn health(&self) -> Health {
+        self.health_with(&HealthThresholds::default())
+    }
+
+    /// Returns a verdict on the state of the model using `thresholds`.
+    pub fn health_with(&self, thresholds: &HealthThresholds) -> Health {
+        let coord = self.get_coordinate();
+        if !coord.is_finite() {
+            return Health::Diverging;
+        }
+
+        self.health_history()
+            .assess(coord.error(), self.clock().now(), thresholds)
+    }
+}
+
+/// A fixed-size ring of recent error estimates and displacements, recorded by
+/// [`Model::observe_metric`] without allocating.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) struct HealthHistory {
+    errors: [f64; WINDOW],
+    displacements: [f64; WINDOW],
+    next: usize,
+    len: usize,
+    last_update: Duration,
+}
+
+impl HealthHistory {
+    /// Initialises an empty history, treating `now` as the last update.
+    pub(crate) fn new(now: Duration) -> Self {
+        HealthHistory {
+            errors: [0.0; WINDOW],
+            displacements: [0.0; WINDOW],
+            next: 0,
+            len: 0,
+            last_update: now,
+        }
+    }
+
+    /// Returns true if the ring indices are in bounds, as they always are
+    /// unless deserialised from corrupt state.
+    #[cfg(feature = "serde")]
+    pub(crate) fn is_valid(&self) -> bool {
+        self.next < WINDOW && self.len <= WINDOW
+    }
+
+    /// Records the error estimate and the distance the coordinate moved for
+    /// an observation made at `now`.
+    pub(crate) fn record(&mut self, error: f64, displacement: f64, now: Duration) {
+        self.errors[self.next] = error;
+        self.displacements[self.next] = displacement;
+        self.next = (self.next + 1) % WINDOW;
+        self.len = (self.len + 1).min(WINDOW);
+        self.last_update = now;
+    }
+
+    pub(crate) fn assess(&self, error: f64, now: Duration, t: &HealthThresholds) -> Health {
+        if now.saturating_sub(self.last_update) > t.stale_after {
+            return Health::Stale;
+        }
+
+        if self.len < WINDOW {
+            if error <= t.healthy_error {
+                return Health::Healthy;
+            }
+            return Health::Converging;
+        }
+
+        // Split the (full) window into the older and newer half, in
+        // chronological order.
+        let (old_err, new_err) = halves(&self.errors, self.next);
+        let (old_disp, new_disp) = halves(&self.displacements, self.next);
+
+        let (old_err, new_err) = (mean(&old_err), mean(&new_err));
+        let error_rising = new_err > old_err * (1.0 + TREND);
+        let error_falling = new_err < old_err * (1.0 - TREND);
+
+        // Growing movement without an improving error is the model being
+        // thrown further each observation.
+        let movement_growing =
+            variance(&new_disp) > variance(&old_disp) * 4.0 && mean(&new_disp) > mean(&old_disp);
+
+        if error_rising || (movement_growing && !error_falling && error > t.healthy_error) {
+            return Health::Diverging;
+        }
+        if error <= t.healthy_error {
+            return Health::Healthy;
+        }
+        if error_falling {
+            return Health::Converging;
+        }
+        Health::Oscillating
+    }
+}
+
+/// Returns the older and newer halves of the full ring `v`, where `next` is
+/// the index of the oldest value.
+fn halves(v: &[f64; WINDOW], next: usize) -> ([f64; WINDOW / 2], [f64; WINDOW / 2]) {
+    let mut old = [0.0; WINDOW / 2];
+    let mut new = [0.0; WINDOW / 2];
+    for i in 0..WINDOW / 2 {
+        old[i] = v[(next + i) % WINDOW];
+        new[i] = v[(next + WINDOW / 2 + i) % WINDOW];
+    }
+    (old, new)
+}
+
+fn mean(v: &[f64]) -> f64 {
+    v.iter().sum::<f64>() / v.len() as f64
+}
+
+fn variance(v: &[f64]) -> f64 {
+    let m = mean(v);
+    v.iter().map(|x| (x - m) * (x - m)).sum::<f64>() / v.len() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use crate::vector::Dimension3;
+
+    fn history(samples: impl Iterator<Item = (f64, f64)>) -> HealthHistory {
+        let mut h = HealthHistory::new(Duration::from_secs(0));
+        for (e, d) in samples {
+            h.record(e, d, Duration::from_secs(0));
+        }
+        h
+    }
+
+    fn assess(h: &HealthHistory) -> Health {
+        let error = h.errors[(h.next + WINDOW - 1) % WINDOW];
+        h.assess(error, Duration::from_secs(0), &HealthThresholds::default())
+    }
+
+    #[test]
+    fn verdicts() {
+        let falling = history((0..20).map(|i| (2.0 - i as f64 * 0.05, 0.1)));
+        assert_eq!(assess(&falling), Health::Converging);
+
+        let low = history((0..20).map(|_| (0.1, 0.001)));
+        assert_eq!(assess(&low), Health::Healthy);
+
+        let rising = history((0..20).map(|i| (0.5 + i as f64 * 0.05, 0.1)));
+        assert_eq!(assess(&rising), Health::Diverging);
+
+        let flat = history((0..20).map(|i| (0.8, 0.1 + (i % 2) as f64 * 0.01)));
+        assert_eq!(assess(&flat), Health::Oscillating);
+
+        let thrown = history((0..20).map(|i| (0.8, i as f64 * i as f64)));
+        assert_eq!(assess(&thrown), Health::Diverging);
+    }
+
+    #[test]
+    fn model_health() {
+        let clock = MockClock::default();
+        let mut a = Model::<Dimension3, _>::with_clock(clock.clone());
+        let mut b = Model::<Dimension3, _>::with_clock(clock.clone());
+        assert_eq!(a.health(), Health::Converging);
+
+        let rtt = Duration::from_millis(100);
+        for _ in 0..100 {
+            clock.advance(Duration::from_secs(1));
+            a.observe(&b.get_coordinate().clone(), rtt);
+            b.observe(&a.get_coordinate().clone(), rtt);
+        }
+        assert_eq!(a.health(), Health::Healthy);
+
+        clock.advance(Duration::from_secs(10 * 60));
+        assert_eq!(a.health(), Health::Stale);
+    }
+}
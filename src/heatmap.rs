@@ -0,0 +1,348 @@
+use crate::coordinate::Coordinate;
+use crate::model::estimate_metric;
+use crate::vector::Vector;
+use std::fmt::Write as _;
+use std::io::{self, Write};
+
+/// The largest payload of a single stored (uncompressed) deflate block.
+const MAX_STORED_BLOCK: usize = 65535;
+
+/// A rectangular region of the plane formed by the first two dimensions of a
+/// coordinate space, in seconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bounds {
+    /// The smallest `(x, y)` of the region.
+    pub min: (f64, f64),
+
+    /// The largest `(x, y)` of the region.
+    pub max: (f64, f64),
+}
+
+impl Bounds {
+    /// Returns the smallest region containing the first two components of
+    /// every coordinate in `coords`, expanded by `margin` seconds on each
+    /// side, or `None` if `coords` is empty.
+    pub fn covering<V: Vector>(coords: &[Coordinate<V>], margin: f64) -> Option<Bounds> {
+        let mut points = coords.iter().map(|c| plane(c.vector()));
+        let first = points.next()?;
+        let (min, max) = points.fold((first, first), |(min, max), (x, y)| {
+            ((min.0.min(x), min.1.min(y)), (max.0.max(x), max.1.max(y)))
+        });
+
+        Some(Bounds {
+            min: (min.0 - margin, min.1 - margin),
+            max: (max.0 + margin, max.1 + margin),
+        })
+    }
+}
+
+/// A grid of estimated RTTs from a reference coordinate, produced by
+/// [`rasterize`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Heatmap {
+    width: usize,
+    height: usize,
+    /// Estimated RTTs in seconds, row-major from the top (largest `y`) row.
+    values: Vec<f64>,
+}
+
+impl Heatmap {
+    /// Returns the number of columns.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Returns the number of rows.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the estimated RTT in seconds of the cell in `column` and `row`,
+    /// where row 0 is the top (largest `y`) row.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the cell is outside of the grid.
+    pub fn get(&self, column: usize, row: usize) -> f64 {
+        assert!(
+            column < self.width && row < self.height,
+            "cell out of range"
+        );
+        self.values[row * self.width + column]
+    }
+
+    /// Returns the estimated RTTs in seconds, row-major from the top row.
+    pub fn values(&self) -> &[f64] {
+        &self.values
+    }
+
+    /// Renders the grid as CSV, one line per row from the top row, with each
+    /// value the estimated RTT in milliseconds.
+    pub fn to_csv(&self) -> String {
+        let mut out = String::new();
+        for row in self.values.chunks(self.width.max(1)) {
+            for (i, v) in row.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write!(out, "{:.3}", v * 1000.0).expect("writing to a string");
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Encodes the grid as an 8-bit greyscale PNG image with one pixel per
+    /// cell, scaled so the lowest RTT in the grid is white and the highest is
+    /// black.
+    pub fn to_png(&self) -> Vec<u8> {
+        let finite = self.values.iter().filter(|v| v.is_finite());
+        let lo = finite.clone().cloned().fold(f64::INFINITY, f64::min);
+        let hi = finite.cloned().fold(f64::NEG_INFINITY, f64::max);
+        let range = if hi > lo { hi - lo } else { 1.0 };
+
+        // Each scanline is prefixed with filter type 0 (none).
+        let mut raw = Vec::with_capacity((self.width + 1) * self.height);
+        for row in self.values.chunks(self.width.max(1)) {
+            raw.push(0);
+            raw.extend(row.iter().map(|v| {
+                if v.is_finite() {
+                    (255.0 * (1.0 - (v - lo) / range)).round() as u8
+                } else {
+                    0
+                }
+            }));
+        }
+
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&(self.width as u32).to_be_bytes());
+        header.extend_from_slice(&(self.height as u32).to_be_bytes());
+        // 8-bit depth, greyscale, deflate, adaptive filtering, no interlace.
+        header.extend_from_slice(&[8, 0, 0, 0, 0]);
+        png_chunk(&mut png, b"IHDR", &header);
+        png_chunk(&mut png, b"IDAT", &zlib_stored(&raw));
+        png_chunk(&mut png, b"IEND", &[]);
+        png
+    }
+
+    /// Writes the [PNG encoding](Heatmap::to_png) of the grid to `w`.
+    pub fn write_png<W: Write>(&self, mut w: W) -> io::Result<()> {
+        w.write_all(&self.to_png())
+    }
+}
+
+/// Estimates the RTT from `reference` to the centre of each cell of a
+/// `width` by `height` grid spanning `bounds`, for rendering heatmaps of the
+/// latency space.
+///
+/// The grid is a slice through the plane of the first two dimensions - the
+/// remaining components of each point are taken from `reference`, and each
+/// point has a height of zero, so the estimates include only the height of
+/// `reference`.
+///
+/// ```
+/// use vivaldi::{rasterize, Bounds, Model, vector::Dimension3};
+///
+/// # let nodes = (0..4).map(|_| *Model::<Dimension3>::new().get_coordinate()).collect::<Vec<_>>();
+/// let local = Model::<Dimension3>::new();
+///
+/// let bounds = Bounds::covering(&nodes, 0.010).unwrap();
+/// let heatmap = rasterize(local.get_coordinate(), bounds, 64, 64);
+///
+/// let csv = heatmap.to_csv();
+/// let png = heatmap.to_png();
+/// ```
+///
+/// # Panics
+///
+/// Panics if `width` or `height` is zero, as an image must contain at least
+/// one pixel.
+pub fn rasterize<V: Vector>(
+    reference: &Coordinate<V>,
+    bounds: Bounds,
+    width: usize,
+    height: usize,
+) -> Heatmap {
+    assert!(
+        width > 0 && height > 0,
+        "invalid heatmap size {}x{}",
+        width,
+        height
+    );
+    let mut components = reference.vector().components().to_vec();
+    let step_x = (bounds.max.0 - bounds.min.0) / width as f64;
+    let step_y = (bounds.max.1 - bounds.min.1) / height as f64;
+
+    let mut values = Vec::with_capacity(width * height);
+    for row in 0..height {
+        let y = bounds.max.1 - (row as f64 + 0.5) * step_y;
+        for column in 0..width {
+            let x = bounds.min.0 + (column as f64 + 0.5) * step_x;
+            if let Some(c) = components.get_mut(0) {
+                *c = x;
+            }
+            if let Some(c) = components.get_mut(1) {
+                *c = y;
+            }
+
+            let point = V::from_components(&components).expect("reference dimensionality");
+            values.push(estimate_metric(
+                reference,
+                &Coordinate::new(point, 0.0, 0.0),
+            ));
+        }
+    }
+
+    Heatmap {
+        width,
+        height,
+        values,
+    }
+}
+
+/// Returns the first two components of `v`, treating missing components as
+/// zero.
+fn plane<V: Vector>(v: &V) -> (f64, f64) {
+    let c = v.components();
+    (
+        c.first().cloned().unwrap_or_default(),
+        c.get(1).cloned().unwrap_or_default(),
+    )
+}
+
+/// Appends a PNG chunk of type `kind` containing `data` to `out`.
+fn png_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(&(data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend_from_slice(&crc.to_be_bytes());
+}
+
+/// Wraps `data` in a zlib stream of uncompressed deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let mut blocks = data.chunks(MAX_STORED_BLOCK).peekable();
+    if blocks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        out.push(blocks.peek().is_none() as u8);
+        let len = block.len() as u16;
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0_u32, |mut crc, b| {
+        crc ^= *b as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xedb8_8320
+            } else {
+                crc >> 1
+            };
+        }
+        crc
+    })
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let (a, b) = data.iter().fold((1_u32, 0_u32), |(a, b), v| {
+        let a = (a + *v as u32) % 65521;
+        (a, (b + a) % 65521)
+    });
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::{Dimension2, Dimension3};
+
+    #[test]
+    fn rasterizes_distance() {
+        let reference = Coordinate::new(Dimension2([0.0, 0.0]), 1.0, 0.0);
+        let bounds = Bounds {
+            min: (-2.0, -2.0),
+            max: (2.0, 2.0),
+        };
+        let h = rasterize(&reference, bounds, 4, 2);
+        assert_eq!((h.width(), h.height()), (4, 2));
+        assert_eq!(h.values().len(), 8);
+
+        // Cell centres at x = -1.5, -0.5, 0.5, 1.5 and y = 1, -1.
+        let d = |x: f64, y: f64| (x * x + y * y).sqrt() + reference.height();
+        assert!((h.get(0, 0) - d(-1.5, 1.0)).abs() < 1e-4);
+        assert!((h.get(2, 1) - d(0.5, -1.0)).abs() < 1e-4);
+
+        let csv = h.to_csv();
+        assert_eq!(csv.lines().count(), 2);
+        assert_eq!(csv.lines().next().unwrap().split(',').count(), 4);
+    }
+
+    #[test]
+    fn slices_through_reference() {
+        let reference = Coordinate::new(Dimension3([0.0, 0.0, 5.0]), 1.0, 0.002);
+        let bounds = Bounds {
+            min: (-1.0, -1.0),
+            max: (1.0, 1.0),
+        };
+        let h = rasterize(&reference, bounds, 1, 1);
+        assert_eq!(h.get(0, 0), reference.height());
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid heatmap size 0x4")]
+    fn zero_width() {
+        let reference = Coordinate::new(Dimension2([0.0, 0.0]), 1.0, 0.0);
+        let bounds = Bounds {
+            min: (-1.0, -1.0),
+            max: (1.0, 1.0),
+        };
+        rasterize(&reference, bounds, 0, 4);
+    }
+
+    #[test]
+    fn covering() {
+        let coords = vec![
+            Coordinate::new(Dimension2([1.0, -2.0]), 1.0, 0.0),
+            Coordinate::new(Dimension2([-3.0, 4.0]), 1.0, 0.0),
+        ];
+        let b = Bounds::covering(&coords, 0.5).unwrap();
+        assert_eq!(b.min, (-3.5, -2.5));
+        assert_eq!(b.max, (1.5, 4.5));
+        assert!(Bounds::covering::<Dimension2>(&[], 0.5).is_none());
+    }
+
+    #[test]
+    fn png_encoding() {
+        assert_eq!(crc32(b"IEND"), 0xae42_6082);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e6_0398);
+
+        let reference = Coordinate::new(Dimension2([0.0, 0.0]), 1.0, 0.0);
+        let bounds = Bounds {
+            min: (-1.0, -1.0),
+            max: (1.0, 1.0),
+        };
+        let png = rasterize(&reference, bounds, 3, 3).to_png();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[png.len() - 8..png.len() - 4], b"IEND");
+
+        // The IDAT payload holds 3 scanlines of a filter byte and 3 pixels,
+        // with the centre (nearest) pixel white.
+        let idat = &png[33 + 8..];
+        let raw = &idat[2 + 5..2 + 5 + 12];
+        assert_eq!(raw[0], 0);
+        assert_eq!(raw[4 + 2], 255);
+        assert_eq!(raw[1], 0);
+    }
+}
@@ -0,0 +1,258 @@
+use crate::clock::Clock;
+use crate::coordinate::Coordinate;
+use crate::force::ForceFunction;
+use crate::model::Model;
+use crate::vector::{Scalar, Vector};
+use rand::Rng;
+use std::time::Duration;
+
+/// The number of coordinates retained by the history of a [`Model`].
+const HISTORY_LEN: usize = 32;
+
+impl<V, C, F, R> Model<V, C, F, f64, R>
+where
+    V: Vector + std::fmt::Debug,
+    C: Clock,
+    F: ForceFunction,
+    R: Rng,
+{
+    /// Enables the history used by [`rollback_to`](Model::rollback_to),
+    /// recording a coordinate at most once per `interval`, as
+    /// [`ModelBuilder::history_interval`](crate::ModelBuilder::history_interval).
+    /// The history is disabled by default.
+    ///
+    /// The history holds the 32 most recent coordinates, so spans at least 32
+    /// intervals of observations. It is allocated when first enabled, after
+    /// which recording never allocates.
+    ///
+    /// Set to `None` to disable the history, freeing it and discarding any
+    /// recorded coordinates.
+    pub fn set_history_interval(&mut self, interval: Option<Duration>) {
+        let history = self.history_mut();
+        match (history.as_mut(), interval) {
+            (Some(h), Some(interval)) => h.interval = interval,
+            (None, Some(interval)) => *history = Some(Box::new(CoordinateHistory::new(interval))),
+            (_, None) => *history = None,
+        }
+    }
+
+    /// Returns the recorded coordinates and the clock time each was recorded
+    /// at, oldest first.
+    ///
+    /// The iterator is empty if the history is disabled.
+    pub fn history(&self) -> impl Iterator<Item = (Duration, &Coordinate<V>)> + '_ {
+        self.coordinate_history().into_iter().flat_map(|h| h.iter())
+    }
+
+    /// Restores the most recent coordinate recorded at or before `timestamp`,
+    /// discarding any coordinates recorded since, and returns the time it was
+    /// recorded at.
+    ///
+    /// This undoes the damage of a burst of bad measurements (for example,
+    /// during a routing incident) without waiting for the model to
+    /// re-converge:
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use vivaldi::{Clock, MockClock, ModelBuilder, vector::Dimension3};
+    ///
+    /// let clock = MockClock::default();
+    /// let mut model = ModelBuilder::new()
+    ///     .history_interval(Duration::from_secs(60))
+    ///     .build_with_clock::<Dimension3, _>(clock.clone());
+    /// # use vivaldi::Model;
+    /// # let remote = *Model::<Dimension3>::new().get_coordinate();
+    /// # let rtt = Duration::from_millis(10);
+    /// model.observe(&remote, rtt);
+    ///
+    /// let before_incident = clock.now();
+    /// clock.advance(Duration::from_secs(120));
+    /// model.observe(&remote, Duration::from_secs(2));
+    ///
+    /// assert_eq!(model.rollback_to(before_incident), Some(before_incident));
+    /// ```
+    ///
+    /// Returns `None`, leaving the model unchanged, if no coordinate recorded
+    /// at or before `timestamp` remains in the history, or the history is
+    /// disabled.
+    pub fn rollback_to(&mut self, timestamp: Duration) -> Option<Duration> {
+        let (at, coord) = self.history_mut().as_mut()?.truncate_after(timestamp)?;
+        self.set_coordinate(coord);
+        Some(at)
+    }
+}
+
+/// A fixed-size ring of recent coordinates and the times they were recorded,
+/// populated by [`Model::observe_metric`] without allocating.
+///
+/// The ring holds several kilobytes of coordinates, so is boxed by the model
+/// and only allocated once enabled.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "V: serde::Serialize, T: serde::Serialize",
+        deserialize = "V: serde::Deserialize<'de>, T: serde::Deserialize<'de>"
+    ))
+)]
+pub(crate) struct CoordinateHistory<V, T = f64>
+where
+    V: Vector<T>,
+    T: Scalar,
+{
+    entries: [(Duration, Coordinate<V, T>); HISTORY_LEN],
+    /// The index of the oldest entry.
+    start: usize,
+    len: usize,
+    /// The minimum time between recorded entries.
+    interval: Duration,
+}
+
+impl<V, T> CoordinateHistory<V, T>
+where
+    V: Vector<T>,
+    T: Scalar,
+{
+    pub(crate) fn new(interval: Duration) -> Self {
+        CoordinateHistory {
+            entries: std::array::from_fn(|_| (Duration::default(), Coordinate::default())),
+            start: 0,
+            len: 0,
+            interval,
+        }
+    }
+
+    /// Records `coord` at `now`, unless a coordinate was recorded within the
+    /// interval.
+    pub(crate) fn record(&mut self, now: Duration, coord: &Coordinate<V, T>) {
+        if let Some(last) = self.newest() {
+            if now.saturating_sub(last) < self.interval {
+                return;
+            }
+        }
+
+        let slot = if self.len < HISTORY_LEN {
+            self.len += 1;
+            (self.start + self.len - 1) % HISTORY_LEN
+        } else {
+            let slot = self.start;
+            self.start = (self.start + 1) % HISTORY_LEN;
+            slot
+        };
+        self.entries[slot] = (now, coord.clone());
+    }
+
+    /// Returns true if the ring indices are in bounds, as they always are
+    /// unless deserialised from corrupt state.
+    #[cfg(feature = "serde")]
+    pub(crate) fn is_valid(&self) -> bool {
+        self.start < HISTORY_LEN && self.len <= HISTORY_LEN
+    }
+
+    /// Returns the time the newest entry was recorded at, if any.
+    fn newest(&self) -> Option<Duration> {
+        if self.len == 0 {
+            return None;
+        }
+        Some(self.entries[(self.start + self.len - 1) % HISTORY_LEN].0)
+    }
+
+    pub(crate) fn iter(&self) -> impl Iterator<Item = (Duration, &Coordinate<V, T>)> + '_ {
+        (0..self.len).map(move |i| {
+            let (at, coord) = &self.entries[(self.start + i) % HISTORY_LEN];
+            (*at, coord)
+        })
+    }
+
+    /// Discards every entry recorded after `timestamp`, returning the newest
+    /// remaining entry, or `None` (discarding nothing) if there is none.
+    fn truncate_after(&mut self, timestamp: Duration) -> Option<(Duration, Coordinate<V, T>)> {
+        let keep = self.iter().take_while(|(at, _)| *at <= timestamp).count();
+        if keep == 0 {
+            return None;
+        }
+        self.len = keep;
+        self.iter().last().map(|(at, coord)| (at, coord.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use crate::vector::Dimension2;
+
+    #[test]
+    fn records_at_interval() {
+        let mut h = CoordinateHistory::<Dimension2>::new(Duration::from_secs(60));
+        let coord = |x: f64| Coordinate::new(Dimension2([x, 0.0]), 1.0, 0.1);
+
+        // Recorded at most once per interval, overwriting the oldest.
+        for t in 0..(HISTORY_LEN as u64 + 5) * 2 {
+            h.record(Duration::from_secs(t * 30), &coord(t as f64));
+        }
+        let times = h.iter().map(|(at, _)| at.as_secs()).collect::<Vec<_>>();
+        assert_eq!(times.len(), HISTORY_LEN);
+        assert_eq!(times[0], 5 * 60);
+        assert!(times.windows(2).all(|w| w[1] - w[0] == 60));
+        assert_eq!(h.iter().next().unwrap().1, &coord(10.0));
+    }
+
+    #[test]
+    fn rollback() {
+        let clock = MockClock::default();
+        let mut m = Model::<Dimension2, _>::with_clock(clock.clone());
+        m.set_history_interval(Some(Duration::from_secs(10)));
+        let remote = Coordinate::new(Dimension2([0.05, 0.0]), 0.1, 0.0);
+
+        assert_eq!(m.rollback_to(Duration::from_secs(100)), None);
+
+        for _ in 0..10 {
+            clock.advance(Duration::from_secs(10));
+            m.observe(&remote, Duration::from_millis(60));
+        }
+        let good = *m.get_coordinate();
+        let good_at = clock.now();
+        assert_eq!(m.history().last(), Some((good_at, &good)));
+
+        // A routing incident inflates measurements.
+        for _ in 0..10 {
+            clock.advance(Duration::from_secs(10));
+            m.observe(&remote, Duration::from_secs(1));
+        }
+        assert!(m.get_coordinate().vector().distance(good.vector()).0 > 0.1);
+
+        assert_eq!(
+            m.rollback_to(good_at + Duration::from_secs(5)),
+            Some(good_at)
+        );
+        assert_eq!(m.get_coordinate(), &good);
+        assert_eq!(m.history().last().unwrap().0, good_at);
+
+        let first = Duration::from_secs(10);
+        assert_eq!(m.rollback_to(Duration::from_secs(0)), None);
+        assert_eq!(m.rollback_to(first), Some(first));
+        assert_eq!(m.history().count(), 1);
+    }
+
+    #[test]
+    fn disabled() {
+        // The history is disabled by default.
+        let mut m = Model::<Dimension2>::new();
+        let remote = Coordinate::new(Dimension2([0.05, 0.0]), 0.1, 0.0);
+        m.observe(&remote, Duration::from_millis(60));
+        assert_eq!(m.history().count(), 0);
+        assert_eq!(m.rollback_to(Duration::MAX), None);
+
+        m.set_history_interval(Some(Duration::from_secs(60)));
+        m.observe(&remote, Duration::from_millis(60));
+        assert_eq!(m.history().count(), 1);
+
+        m.set_history_interval(None);
+        assert_eq!(m.history().count(), 0);
+        m.observe(&remote, Duration::from_millis(60));
+        assert_eq!(m.history().count(), 0);
+        assert_eq!(m.rollback_to(Duration::MAX), None);
+    }
+}
@@ -26,9 +26,9 @@
 //!
 //! ## Use It
 //!
-//! This implementation is for the algorithm described in the original paper -
-//! there's no update filters (which requires storing more state per node) or
-//! gravity as proposed in the follow-up texts. The caller is responsible for
+//! This implementation is for the algorithm described in the original paper,
+//! with the latency filters and gravity proposed in the follow-up texts
+//! available as opt-in [`ModelBuilder`] settings. The caller is responsible for
 //! storing the last known coordinate of each node to later derive RTT
 //! estimations for any pair of nodes.
 //!
@@ -44,6 +44,77 @@
 //! principle component analysis by the authors shows there is little benefit
 //! beyond 3 dimensions, with 2 dimensions being adequate if overhead is to be
 //! kept to a minimum.
+//! The [`vector::DimensionN`] type embeds coordinates in any number of
+//! dimensions, converting to and from the [`vector::Dimension1`],
+//! [`vector::Dimension2`] and [`vector::Dimension3`] types for the common
+//! cases. With the `nalgebra` feature enabled, `nalgebra`'s `SVector<f64, N>`
+//! types can be used directly, as can `glam`'s `DVec2` and `DVec3` with the
+//! `glam` feature.
+//!
+//! Enable the `simd` feature to vectorise the arithmetic of the
+//! [`vector::Dimension3`] and [`vector::DimensionN`] types on x86_64 - the
+//! `vector` benchmark compares the two:
+//!
+//! ```text
+//! cargo bench --bench vector -- --save-baseline scalar
+//! cargo bench --bench vector --features simd -- --baseline scalar
+//! ```
+//!
+//!
+//! ## Serf and Consul
+//!
+//! Rust nodes can participate in an existing HashiCorp Serf or Consul cluster
+//! by building their model with [`ModelBuilder::serf`] over a
+//! [`vector::DimensionN<8>`], and, with the `serde` feature enabled,
+//! exchanging coordinates in the JSON representation of Serf's
+//! `coordinate.Coordinate` with the `SerfCoordinate` type.
+//!
+//!
+//! ## Wire Formats
+//!
+//! [`Coordinate::to_bytes`] encodes a coordinate in a compact fixed-size
+//! layout independent of any serialisation framework, versioned so nodes
+//! running different versions of this crate interoperate. With the `serde`
+//! feature enabled coordinates and models implement `Serialize` and
+//! `Deserialize`, and with the `prost` feature the `proto` module provides
+//! protobuf messages implementing the schema in `proto/vivaldi.proto`. The
+//! `rkyv` feature archives coordinates for zero-copy access from received
+//! buffers, for high-frequency gossip. The `postcard` feature implements
+//! `postcard`'s `MaxSize` for coordinates, so constrained devices can budget
+//! packet space for the compact `postcard` encoding.
+//!
+//!
+//! ## Embedded Use
+//!
+//! The core [`Model`] with the fixed-size vector types of the [`vector`]
+//! module never allocates - observing, estimating and the health history all
+//! operate on inline, fixed-size state, with the stack usage bounded by the
+//! vector type. The per-peer RTT filters of the [`filters`] module buffer
+//! their samples on the heap by default; enable the `heapless` feature for the
+//! inline `FixedMovingMedian` and `FixedPercentileWindow` alternatives sized
+//! at compile time, for memory-constrained firmware such as mesh radio nodes.
+//! The crate itself still links against `std`.
+//!
+//!
+//! ## Metrics
+//!
+//! With the `metrics` feature enabled, telemetry is emitted through the
+//! [`metrics`](https://docs.rs/metrics) facade to whichever recorder the
+//! application installs:
+//!
+//! | Name                                   | Type      | Description                                         |
+//! |----------------------------------------|-----------|-----------------------------------------------------|
+//! | `vivaldi_observations_total`           | counter   | Observations applied to a [`Model`]                 |
+//! | `vivaldi_observations_rejected_total`  | counter   | Observations rejected, labelled by `reason`         |
+//! | `vivaldi_coordinate_error`             | gauge     | The error estimate of the most recently updated model |
+//! | `vivaldi_coordinate_height_seconds`    | gauge     | The height of the most recently updated model       |
+//! | `vivaldi_displacement_seconds`         | histogram | The distance moved by each observation              |
+//! | `vivaldi_anomalies_total`              | counter   | Anomalies reported by an [`AnomalyDetector`], labelled by `kind` |
+//! | `vivaldi_outliers_total`               | counter   | Outlying observations, labelled by `action`         |
+//!
+//! The `reason` label is one of `invalid_rtt`, `non_finite`, `below_floor`,
+//! `strict` or `other`, the `kind` label one of `coordinate_shift` or `error_shift`,
+//! and the `action` label one of `discarded` or `down_weighted`.
 //!
 //!
 //! [follow-up]: https://www.usenix.org/legacy/events/nsdi07/tech/full_papers/ledlie/ledlie_html/index_save.html
@@ -54,11 +125,119 @@
 #![deny(missing_docs)]
 #![warn(missing_debug_implementations, rust_2018_idioms, unreachable_pub)]
 
+mod age;
+mod alignment;
+mod analysis;
+mod anomaly;
+mod bootstrap;
+mod bridge;
+mod budget;
+mod builder;
+mod bulk;
+mod checkpoint;
+mod clock;
 mod coordinate;
+mod delta;
+mod downsample;
+mod error;
+mod escape;
+mod estimator;
+mod factorization;
+mod failure;
+mod floor;
+mod force;
+mod geo;
+mod gnp;
+mod groups;
+mod health;
+mod heatmap;
+mod history;
 mod model;
+mod multi;
+mod multi_height;
+mod outlier;
+mod peer_heights;
+mod peer_table;
+mod privacy;
+mod probe;
+mod profiles;
+mod projection;
+#[cfg(feature = "queue")]
+mod queue;
+mod reconcile;
+mod rings;
+mod rng;
+mod scoring;
+mod selection;
+#[cfg(feature = "serde")]
+mod serf;
+mod sharded;
+mod simulation;
+mod smoothing;
+mod snapshot;
+mod source;
+mod telemetry;
+mod warm_start;
+mod weights;
 
 /// Vector defines N-dimensional Euclidean vectors and traits to implement them.
 pub mod vector;
 
+pub mod filters;
+
+pub mod prelude;
+
+#[cfg(feature = "prost")]
+pub mod proto;
+
+pub use age::*;
+pub use alignment::*;
+pub use analysis::*;
+pub use anomaly::*;
+pub use bridge::*;
+pub use budget::*;
+pub use builder::*;
+pub use bulk::*;
+pub use checkpoint::*;
+pub use clock::*;
 pub use coordinate::*;
+pub use delta::*;
+pub use downsample::*;
+pub use error::*;
+pub use escape::*;
+pub use estimator::*;
+pub use factorization::*;
+pub use failure::*;
+pub use floor::*;
+pub use force::*;
+pub use geo::*;
+pub use gnp::*;
+pub use groups::*;
+pub use health::*;
+pub use heatmap::*;
 pub use model::*;
+pub use multi::*;
+pub use multi_height::*;
+pub use outlier::*;
+pub use peer_heights::*;
+pub use peer_table::*;
+pub use privacy::*;
+pub use probe::*;
+pub use profiles::*;
+pub use projection::*;
+#[cfg(feature = "queue")]
+pub use queue::*;
+pub use reconcile::*;
+pub use rings::*;
+pub use rng::*;
+pub use scoring::*;
+pub use selection::*;
+#[cfg(feature = "serde")]
+pub use serf::*;
+pub use sharded::*;
+pub use simulation::*;
+pub use smoothing::*;
+pub use snapshot::*;
+pub use source::*;
+pub use warm_start::*;
+pub use weights::*;
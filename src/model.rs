@@ -1,22 +1,49 @@
-use crate::coordinate::Coordinate;
-use crate::vector::Vector;
+use crate::checkpoint::Fnv1a;
+use crate::clock::{Clock, SystemClock};
+use crate::coordinate::{Coordinate, MAX_VALID_ERROR, MIN_HEIGHT};
+use crate::error::{Error, Violation};
+use crate::filters::{LatencyFilter, MovingMedian};
+use crate::force::{ForceFunction, LinearSpring};
+use crate::health::HealthHistory;
+use crate::history::CoordinateHistory;
+use crate::outlier::{OutlierPolicy, OutlierStats};
+use crate::rng::ThreadLocalRng;
+use crate::telemetry;
+use crate::vector::{Magnitude, Scalar, Vector};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
 use std::time::Duration;
 
-const FLOAT_ZERO: f64 = 1.0e-8;
+pub(crate) const FLOAT_ZERO: f64 = 1.0e-8;
 
 /// The Ce algorithm value.
-const ERROR_LIMIT: f64 = 0.25;
+pub(crate) const ERROR_LIMIT: f64 = 0.25;
+
+/// The default Cc algorithm value.
+const TIMESTEP_LIMIT: f64 = 0.25;
+
+/// The error estimate of a new coordinate, reflecting no confidence in its
+/// position.
+pub(crate) const INITIAL_ERROR: f64 = 2.0;
+
+/// The default height of a new coordinate, in seconds.
+const INITIAL_HEIGHT: f64 = 0.1;
+
+const NANOS_PER_SEC: f64 = 1.0e9;
+const NANOS_PER_SEC_INV: f64 = 1.0e-9;
 
 /// UnitVector contains a vector that has a magnitude of 1.
 #[derive(PartialEq, Debug)]
-struct UnitVector<V: Vector>(V);
+pub(crate) struct UnitVector<V>(pub(crate) V);
 
-impl<V> UnitVector<V>
-where
-    V: Vector,
-{
-    fn new(vec: V) -> Self {
-        debug_assert!(1.0 - vec.magnitude().0.abs() < FLOAT_ZERO);
+impl<V> UnitVector<V> {
+    fn new<T: Scalar>(vec: V) -> Self
+    where
+        V: Vector<T>,
+    {
+        debug_assert!(1.0 - vec.magnitude().0.into_f64().abs() < FLOAT_ZERO);
         UnitVector(vec)
     }
 }
@@ -29,17 +56,353 @@ where
 /// Messages exchanged between nodes in the network
 /// should include the current model coordinate, and the model should be updated
 /// with the measured round-trip time by calling [`observe`](crate::model::Model::observe).
-#[derive(Debug)]
-pub struct Model<V>
+///
+/// Two models are equal if their current coordinates are equal.
+///
+/// Time-dependent features such as [`health`](Model::health) read the current
+/// time from a [`Clock`], which defaults to the [`SystemClock`].
+///
+/// The force moving the coordinate in response to each observation is
+/// computed by a [`ForceFunction`], which defaults to the [`LinearSpring`] of
+/// the paper.
+///
+/// Coordinates are computed in the [`Scalar`] type `T` of the vector, which
+/// defaults to `f64`. The core model (observing, estimating and the
+/// coordinate history) runs in any scalar, while the extensions of this crate
+/// operate on `f64` models.
+///
+/// The random unit vectors separating coincident coordinates are drawn from
+/// an [`Rng`], which defaults to the [`ThreadLocalRng`] - a model
+/// [seeded](Model::with_seed) with the same value moves identically given
+/// the same observations, for reproducible tests and simulations.
+///
+/// With the `serde` feature enabled the entire model state, including the
+/// per-peer latency filters, the adjustment window and the coordinate
+/// history, can be persisted and restored across restarts. The clock and RNG
+/// are not serialised and are restored from their [`Default`] values, so a
+/// seeded model should be [re-seeded](Model::with_seed) after deserialising.
+///
+/// Deserialising rejects state the model could not have produced itself,
+/// such as out of range configuration or ring buffer indices, with an
+/// [`Error::Serialization`].
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(
+        try_from = "RawModel<V, F, T>",
+        bound(
+            serialize = "V: serde::Serialize, F: serde::Serialize, T: serde::Serialize",
+            deserialize = "V: serde::Deserialize<'de>, F: serde::Deserialize<'de>, \
+                           T: serde::Deserialize<'de>, C: Default, R: Default"
+        )
+    )
+)]
+pub struct Model<V, C = SystemClock, F = LinearSpring, T = f64, R = ThreadLocalRng>
+where
+    V: Vector<T> + std::fmt::Debug,
+    T: Scalar,
+{
+    coordinate: Coordinate<V, T>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    clock: C,
+    health: HealthHistory,
+    /// The bounds applied to the sample weight in
+    /// [`observe_metric`](Model::observe_metric).
+    weight_limits: (f64, f64),
+    /// Report silently corrected conditions as errors.
+    strict: bool,
+    /// Incremented each time the coordinate changes.
+    epoch: u64,
+    force: F,
+    /// Update only the error estimate, leaving the position unchanged.
+    pinned: bool,
+    /// Recent coordinates, for [`rollback_to`](Model::rollback_to), if
+    /// enabled.
+    history: Option<Box<CoordinateHistory<V, T>>>,
+    config: ModelConfig,
+    /// The latency filter of each peer observed with
+    /// [`observe_peer`](Model::observe_peer), keyed by the hash of the peer.
+    filters: HashMap<u64, MovingMedian>,
+    /// The most recent differences between the measured and estimated RTT,
+    /// averaged into the adjustment of the coordinate.
+    residuals: Residuals,
+    outliers: OutlierStats,
+    /// Generates the random unit vectors separating coincident coordinates.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    rng: R,
+}
+
+/// The serialised form of a [`Model`], validated when deserialising.
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+#[serde(bound(
+    deserialize = "V: serde::Deserialize<'de>, F: serde::Deserialize<'de>, \
+                             T: serde::Deserialize<'de>"
+))]
+struct RawModel<V, F, T>
+where
+    V: Vector<T> + std::fmt::Debug,
+    T: Scalar,
+{
+    coordinate: Coordinate<V, T>,
+    health: HealthHistory,
+    weight_limits: (f64, f64),
+    strict: bool,
+    epoch: u64,
+    force: F,
+    pinned: bool,
+    history: Option<Box<CoordinateHistory<V, T>>>,
+    config: ModelConfig,
+    filters: HashMap<u64, MovingMedian>,
+    residuals: Residuals,
+    outliers: OutlierStats,
+}
+
+#[cfg(feature = "serde")]
+impl<V, C, F, T, R> std::convert::TryFrom<RawModel<V, F, T>> for Model<V, C, F, T, R>
+where
+    V: Vector<T> + std::fmt::Debug,
+    C: Default,
+    T: Scalar,
+    R: Default,
+{
+    type Error = Error;
+
+    fn try_from(raw: RawModel<V, F, T>) -> Result<Self, Self::Error> {
+        let invalid = |field: &str| Error::Serialization(format!("invalid model {}", field));
+
+        raw.config.validate().map_err(invalid)?;
+        let (floor, ceiling) = raw.weight_limits;
+        if !((0.0..=1.0).contains(&floor) && (0.0..=1.0).contains(&ceiling) && floor <= ceiling) {
+            return Err(invalid("weight limits"));
+        }
+        if !raw.health.is_valid() {
+            return Err(invalid("health"));
+        }
+        if !raw.history.as_ref().is_none_or(|h| h.is_valid()) {
+            return Err(invalid("history"));
+        }
+        if !raw.filters.values().all(MovingMedian::is_valid) {
+            return Err(invalid("latency filter"));
+        }
+        if !raw
+            .residuals
+            .is_valid(raw.config.adjustment_window.unwrap_or(0))
+        {
+            return Err(invalid("residuals"));
+        }
+
+        Ok(Model {
+            coordinate: raw.coordinate,
+            clock: C::default(),
+            health: raw.health,
+            weight_limits: raw.weight_limits,
+            strict: raw.strict,
+            epoch: raw.epoch,
+            force: raw.force,
+            pinned: raw.pinned,
+            history: raw.history,
+            config: raw.config,
+            filters: raw.filters,
+            residuals: raw.residuals,
+            outliers: raw.outliers,
+            rng: R::default(),
+        })
+    }
+}
+
+/// The tuning parameters of a [`Model`], set with a
+/// [`ModelBuilder`](crate::ModelBuilder).
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) struct ModelConfig {
+    /// The Ce algorithm value, damping changes to the error estimate.
+    pub(crate) ce: f64,
+    /// The Cc algorithm value, damping the movement of the coordinate.
+    pub(crate) cc: f64,
+    pub(crate) initial_error: f64,
+    pub(crate) initial_height: f64,
+    /// The gravity constant ρ in seconds, or `None` to disable gravity.
+    pub(crate) gravity: Option<f64>,
+    /// The number of samples in the per-peer median filter, or `None` to
+    /// observe samples unfiltered.
+    pub(crate) latency_filter: Option<usize>,
+    /// The number of residuals averaged into the adjustment, or `None` to
+    /// publish coordinates without an adjustment.
+    pub(crate) adjustment_window: Option<usize>,
+    /// The multiple of the local error estimate above which the relative
+    /// error of a sample is an outlier, and the treatment of outliers.
+    pub(crate) outliers: Option<(f64, OutlierPolicy)>,
+    /// The largest distance in seconds the coordinate, and separately the
+    /// height, may move per observation.
+    pub(crate) max_displacement: Option<f64>,
+    /// The upper bound of the error estimate.
+    pub(crate) max_error: Option<f64>,
+    /// The lowest height of the coordinate.
+    pub(crate) min_height: f64,
+    /// Model the access link cost of each node with a height, rather than
+    /// embedding in a purely Euclidean space.
+    pub(crate) heights: bool,
+}
+
+impl Default for ModelConfig {
+    fn default() -> Self {
+        ModelConfig {
+            ce: ERROR_LIMIT,
+            cc: TIMESTEP_LIMIT,
+            initial_error: INITIAL_ERROR,
+            initial_height: INITIAL_HEIGHT,
+            gravity: None,
+            latency_filter: None,
+            adjustment_window: None,
+            outliers: None,
+            max_displacement: None,
+            max_error: None,
+            min_height: MIN_HEIGHT,
+            heights: true,
+        }
+    }
+}
+
+impl ModelConfig {
+    /// Returns the height of a new coordinate, which is zero for a purely
+    /// Euclidean model.
+    pub(crate) fn initial_height(&self) -> f64 {
+        if self.heights {
+            self.initial_height
+        } else {
+            0.0
+        }
+    }
+
+    /// Checks the parameters are within the ranges accepted by the
+    /// [`ModelBuilder`](crate::ModelBuilder), returning the name of the first
+    /// that is not.
+    #[cfg(feature = "serde")]
+    fn validate(&self) -> Result<(), &'static str> {
+        let non_negative = |v: f64| v.is_finite() && v >= 0.0;
+        if !(0.0..=1.0).contains(&self.ce) {
+            return Err("ce");
+        }
+        if !(0.0..=1.0).contains(&self.cc) {
+            return Err("cc");
+        }
+        if !(self.initial_error.is_finite() && self.initial_error > 0.0) {
+            return Err("initial error");
+        }
+        if !non_negative(self.initial_height) {
+            return Err("initial height");
+        }
+        if self
+            .gravity
+            .is_some_and(|rho| !(rho.is_finite() && rho > 0.0))
+        {
+            return Err("gravity");
+        }
+        if self.latency_filter == Some(0) {
+            return Err("latency filter");
+        }
+        if self.adjustment_window == Some(0) {
+            return Err("adjustment window");
+        }
+        if let Some((threshold, policy)) = self.outliers {
+            let factor_valid = match policy {
+                OutlierPolicy::Discard => true,
+                OutlierPolicy::DownWeight(f) => (0.0..=1.0).contains(&f),
+            };
+            if !(threshold > 0.0 && factor_valid) {
+                return Err("outlier rejection");
+            }
+        }
+        if self.max_displacement.is_some_and(|v| !non_negative(v)) {
+            return Err("maximum displacement");
+        }
+        if self.max_error.is_some_and(|v| !(v.is_finite() && v > 0.0)) {
+            return Err("maximum error");
+        }
+        if !non_negative(self.min_height) {
+            return Err("minimum height");
+        }
+        Ok(())
+    }
+
+    /// Returns the lowest height of a coordinate, which is zero for a purely
+    /// Euclidean model.
+    pub(crate) fn height_floor(&self) -> f64 {
+        if self.heights {
+            self.min_height
+        } else {
+            0.0
+        }
+    }
+
+    /// Applies the bounds of the configuration to `coordinate` as an
+    /// observation would - bounding the error, raising the height to the
+    /// minimum (or zeroing it for a purely Euclidean model) and projecting
+    /// the vector onto the space of the model.
+    pub(crate) fn normalise<V, T>(&self, coordinate: Coordinate<V, T>) -> Coordinate<V, T>
+    where
+        V: Vector<T>,
+        T: Scalar,
+    {
+        let error = self.bound_error(coordinate.error().into_f64());
+        let height = if self.heights {
+            // Written as a comparison so a NaN height is still caught as a
+            // non-finite update.
+            let height = coordinate.height().into_f64();
+            if height < self.min_height {
+                self.min_height
+            } else {
+                height
+            }
+        } else {
+            0.0
+        };
+        let adjustment = coordinate.adjustment();
+        Coordinate::new(
+            coordinate.into_vector().project(),
+            T::from_f64(error),
+            T::from_f64(height),
+        )
+        .with_adjustment(adjustment)
+    }
+
+    /// Returns the error estimate of a new (or reset) coordinate, bounded by
+    /// the maximum error.
+    pub(crate) fn reset_error(&self) -> f64 {
+        self.bound_error(self.initial_error)
+    }
+
+    /// Bounds `error` by the configured maximum error, and by the largest
+    /// error of a valid coordinate so the coordinate of the model always
+    /// passes [`Coordinate::validate`] (and round-trips through storage).
+    pub(crate) fn bound_error(&self, error: f64) -> f64 {
+        // Written as a comparison so a NaN error is still caught as a
+        // non-finite update.
+        let error = if error > MAX_VALID_ERROR {
+            MAX_VALID_ERROR
+        } else {
+            error
+        };
+        self.max_error.map_or(error, |max| error.min(max))
+    }
+}
+
+impl<V, C, F, T, R> PartialEq for Model<V, C, F, T, R>
 where
-    V: Vector + std::fmt::Debug,
+    V: Vector<T> + std::fmt::Debug + PartialEq,
+    T: Scalar,
 {
-    coordinate: Coordinate<V>,
+    fn eq(&self, other: &Self) -> bool {
+        self.coordinate == other.coordinate
+    }
 }
 
-impl<V> Model<V>
+impl<V, T> Model<V, SystemClock, LinearSpring, T>
 where
-    V: Vector + std::fmt::Debug,
+    V: Vector<T> + std::fmt::Debug,
+    T: Scalar,
 {
     /// New initialises a new Vivaldi model.
     ///
@@ -52,11 +415,126 @@ where
     ///
     /// let model = Model::<Dimension3>::new();
     /// ```
-    pub fn new() -> Model<V> {
+    pub fn new() -> Self {
+        Model::with_clock(SystemClock)
+    }
+}
+
+impl<V, C, T> Model<V, C, LinearSpring, T>
+where
+    V: Vector<T> + std::fmt::Debug,
+    C: Clock,
+    T: Scalar,
+{
+    /// Initialises a new Vivaldi model reading the current time from `clock`.
+    ///
+    /// ```
+    /// use vivaldi::{MockClock, Model, vector::Dimension3};
+    ///
+    /// let clock = MockClock::default();
+    /// let model = Model::<Dimension3, _>::with_clock(clock.clone());
+    /// ```
+    pub fn with_clock(clock: C) -> Self {
+        Model::with_config(clock, ModelConfig::default())
+    }
+
+    pub(crate) fn with_config(clock: C, config: ModelConfig) -> Self {
+        let health = HealthHistory::new(clock.now());
         Model {
-            coordinate: Coordinate::new(V::default(), 2.0, 0.1),
+            coordinate: Coordinate::new(
+                V::default(),
+                T::from_f64(config.reset_error()),
+                T::from_f64(config.initial_height()),
+            ),
+            clock,
+            health,
+            weight_limits: (0.0, 1.0),
+            strict: false,
+            epoch: 0,
+            force: LinearSpring,
+            pinned: false,
+            history: None,
+            config,
+            filters: HashMap::new(),
+            residuals: Residuals::new(config.adjustment_window.unwrap_or(0)),
+            outliers: OutlierStats::default(),
+            rng: ThreadLocalRng,
         }
     }
+}
+
+impl<V, C, F, T, R> Model<V, C, F, T, R>
+where
+    V: Vector<T> + std::fmt::Debug,
+    C: Clock,
+    F: ForceFunction,
+    T: Scalar,
+    R: Rng,
+{
+    /// Replaces the [`ForceFunction`] computing the movement of the
+    /// coordinate in response to each observation.
+    pub fn with_force_function<G: ForceFunction>(self, force: G) -> Model<V, C, G, T, R> {
+        Model {
+            coordinate: self.coordinate,
+            clock: self.clock,
+            health: self.health,
+            weight_limits: self.weight_limits,
+            strict: self.strict,
+            epoch: self.epoch,
+            force,
+            pinned: self.pinned,
+            history: self.history,
+            config: self.config,
+            filters: self.filters,
+            residuals: self.residuals,
+            outliers: self.outliers,
+            rng: self.rng,
+        }
+    }
+
+    /// Replaces the [`Rng`] generating the random unit vectors that separate
+    /// coincident coordinates.
+    pub fn with_rng<S: Rng>(self, rng: S) -> Model<V, C, F, T, S> {
+        Model {
+            coordinate: self.coordinate,
+            clock: self.clock,
+            health: self.health,
+            weight_limits: self.weight_limits,
+            strict: self.strict,
+            epoch: self.epoch,
+            force: self.force,
+            pinned: self.pinned,
+            history: self.history,
+            config: self.config,
+            filters: self.filters,
+            residuals: self.residuals,
+            outliers: self.outliers,
+            rng,
+        }
+    }
+
+    /// Generates the random unit vectors of the model from a [`StdRng`]
+    /// seeded with `seed`, so the model moves deterministically:
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use vivaldi::{Model, vector::Dimension3};
+    ///
+    /// let run = || {
+    ///     let mut a = Model::<Dimension3>::new().with_seed(42);
+    ///     let b = Model::<Dimension3>::new();
+    ///
+    ///     // Both models are at the origin, so the direction `a` moves in is
+    ///     // random.
+    ///     a.observe(b.get_coordinate(), Duration::from_millis(10));
+    ///     *a.get_coordinate()
+    /// };
+    ///
+    /// assert_eq!(run(), run());
+    /// ```
+    pub fn with_seed(self, seed: u64) -> Model<V, C, F, T, StdRng> {
+        self.with_rng(StdRng::seed_from_u64(seed))
+    }
 
     /// Observe updates the positional coordinate of the local node.
     ///
@@ -79,78 +557,562 @@ where
     /// // And then updates the model with the remote coordinate and rtt
     /// model.observe(&coordinate_from_remote, rtt);
     /// ```
-    pub fn observe(&mut self, coord: &Coordinate<V>, rtt: Duration) {
+    ///
+    /// Observing does not allocate, making it suitable for latency critical
+    /// paths (the default [`ThreadLocalRng`] used to generate random unit
+    /// vectors allocates its state once per thread on first use).
+    ///
+    /// A zero `rtt` or a remote coordinate containing non-finite values will
+    /// corrupt the model - use [`try_observe`](Model::try_observe) when either
+    /// may be received.
+    ///
+    /// # Panics
+    ///
+    /// Panics in [strict mode](Model::set_strict) if the observation would be
+    /// rejected by [`try_observe`](Model::try_observe).
+    pub fn observe(&mut self, coord: &Coordinate<V, T>, rtt: Duration) {
+        self.observe_metric(coord, rtt.as_secs_f64())
+    }
+
+    /// Observe updates the positional coordinate of the local node with an
+    /// RTT in integer nanoseconds, as [`observe`](Model::observe).
+    ///
+    /// This avoids constructing a [`Duration`] on hot paths that already
+    /// measure time as integer nanoseconds:
+    ///
+    /// ```
+    /// # use vivaldi::{Model, vector::Dimension3};
+    /// # let mut model = Model::<Dimension3>::new();
+    /// # let remote = Model::<Dimension3>::new();
+    /// let (sent, received) = (1_000_000_u64, 1_042_000_u64);
+    /// model.observe_nanos(remote.get_coordinate(), received - sent);
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics in [strict mode](Model::set_strict) if the observation would be
+    /// rejected by [`try_observe`](Model::try_observe).
+    pub fn observe_nanos(&mut self, coord: &Coordinate<V, T>, rtt_nanos: u64) {
+        self.observe_metric(coord, rtt_nanos as f64 * NANOS_PER_SEC_INV)
+    }
+
+    /// Updates the positional coordinate of the local node using an arbitrary
+    /// pairwise metric rather than a round-trip time.
+    ///
+    /// The model can embed any symmetric metric that behaves like a distance -
+    /// positive, with smaller values meaning "closer". For example, to embed
+    /// available bandwidth, observe the transfer time per unit of data (such
+    /// as seconds per megabyte) rather than the bandwidth itself:
+    ///
+    /// ```
+    /// # use vivaldi::{estimate_metric, Model, vector::Dimension3};
+    /// let mut local = Model::<Dimension3>::new();
+    /// let remote = Model::<Dimension3>::new();
+    ///
+    /// // 80 megabytes per second measured to the remote.
+    /// local.observe_metric(remote.get_coordinate(), 1.0 / 80.0);
+    ///
+    /// let mbps = 1.0 / estimate_metric(local.get_coordinate(), remote.get_coordinate());
+    /// ```
+    ///
+    /// A model embeds a single metric - use a separate model per metric, and
+    /// only exchange coordinates between models embedding the same metric.
+    /// The value is in the same units as [`estimate_metric`], and must be
+    /// positive and finite.
+    ///
+    /// # Panics
+    ///
+    /// Panics in [strict mode](Model::set_strict) if the value is not positive
+    /// and finite, or the update triggers a [`Violation`].
+    pub fn observe_metric(&mut self, coord: &Coordinate<V, T>, value: f64) {
+        if let Err(e) = self.update(coord, value) {
+            panic!("{}", e);
+        }
+    }
+
+    /// Applies an observation of `value` to `coord`, returning an error (and
+    /// leaving the model unchanged) only in strict mode.
+    fn update(&mut self, coord: &Coordinate<V, T>, value: f64) -> Result<(), Error> {
+        // Scalar arithmetic is in f64 for every scalar type, with only the
+        // vector arithmetic in T.
+        let local_error = self.coordinate.error().into_f64();
+        let local_height = self.coordinate.height().into_f64();
+        let (remote_error, remote_height) = (coord.error().into_f64(), coord.height().into_f64());
+
+        if self.strict {
+            if !(value > 0.0 && value.is_finite()) {
+                return Err(Error::InvalidRtt);
+            }
+            if !coord.is_finite() {
+                return Err(Error::NonFiniteCoordinate);
+            }
+        }
+
         // Sample weight balances local and remote error (1)
         //
         // 		w = ei/(ei + ej)
         //
-        let weight = self.coordinate.error() / (self.coordinate.error() + coord.error());
+        // Degenerate error values (both zero, or one infinite) are clamped to
+        // the configured weight limits, weighting both sides evenly if the
+        // weight is undefined.
+        let weight = local_error / (local_error + remote_error);
+        if self.strict && weight.is_nan() {
+            return Err(Error::Strict(Violation::DegenerateWeight));
+        }
+        let (floor, ceiling) = self.weight_limits;
+        let weight = if weight.is_nan() { 0.5 } else { weight }.clamp(floor, ceiling);
 
         // Compute relative error of this sample (2)
         //
         // 		es = | ||xi -  xj|| - rtt | / rtt
         //
-        let diff_vec = self.coordinate.vector().clone() - coord.vector().clone();
+        // The displacement vector is computed once and reused for both the
+        // distance and the unit vector below.
+        let diff_vec = self.coordinate.vector().displacement(coord.vector());
         let diff_mag = diff_vec.magnitude();
-        let dist = estimate_rtt(&self.coordinate, &coord).as_secs_f64();
-        let relative_error = (dist - rtt.as_secs_f64()).abs() / rtt.as_secs_f64();
+        let heights = if self.config.heights {
+            local_height + remote_height
+        } else {
+            0.0
+        };
+        let dist = diff_mag.0.into_f64() + heights;
+        let relative_error = (dist - value).abs() / value;
+
+        // Discard or down-weight samples whose relative error is far above
+        // the local error estimate. A down-weighted sample is counted once
+        // the update can no longer be rejected in strict mode.
+        let mut weight = weight;
+        let mut down_weighted = false;
+        if let Some((threshold, policy)) = self.config.outliers {
+            if relative_error > threshold * local_error {
+                match policy {
+                    OutlierPolicy::Discard => {
+                        telemetry::outlier(&policy);
+                        self.outliers.observed += 1;
+                        self.outliers.discarded += 1;
+                        return Ok(());
+                    }
+                    OutlierPolicy::DownWeight(factor) => {
+                        down_weighted = true;
+                        weight *= factor;
+                    }
+                }
+            }
+        }
 
         // Update weighted moving average of local error (3)
         //
         // 		ei = es × ce × w + ei × (1 − ce × w)
         //
-        let error = relative_error * ERROR_LIMIT * weight
-            + self.coordinate.error() * (1.0 - ERROR_LIMIT * weight);
+        let ce = self.config.ce;
+        let error = relative_error * ce * weight + local_error * (1.0 - ce * weight);
+        let error = self.config.bound_error(error);
 
         // Calculate the adaptive timestep (part of 4)
         //
         // 		δ = cc × w
         //
-        let weighted_error = ERROR_LIMIT * weight;
+        let weighted_error = self.config.cc * weight;
 
         // Weighted force (part of 4)
         //
         // 		δ × ( rtt − ||xi − xj|| )
         //
-        let weighted_force = weighted_error * (rtt.as_secs_f64() - dist);
+        // for the default linear spring.
+        let weighted_force = self.force.force(value, dist, weighted_error);
+
+        // A pinned coordinate is not moved, refining only the error estimate,
+        // and no observation moves it further than the displacement limit.
+        let weighted_force = match (self.pinned, self.config.max_displacement) {
+            (true, _) => 0.0,
+            (false, Some(max)) => weighted_force.clamp(-max, max),
+            (false, None) => weighted_force,
+        };
 
         // Unit vector (part of 4)
         //
         // 		u(xi − xj)
         //
-        let unit_vec = unit_vector_for(self.coordinate.vector().clone(), coord.vector().clone());
-        let unit_vec = match unit_vec {
+        let unit_vec = match unit_vector_from_diff(diff_vec, &diff_mag) {
             Some(v) => v,
-            None => new_random_unit_vec(),
+            None if self.strict && !self.pinned => {
+                return Err(Error::Strict(Violation::CoincidentCoordinates))
+            }
+            None => new_random_unit_vec(&mut self.rng),
         };
 
         // Calculate the new height of the local node:
         //
         //      (Old height + coord.Height) * weighted_force / diff_mag.0 + old height
         //
-        let mut new_height = self.coordinate.height();
-        if diff_mag.0 > FLOAT_ZERO {
-            new_height = (self.coordinate.height() + coord.height()) * weighted_force / diff_mag.0
-                + self.coordinate.height();
+        let mut new_height = local_height;
+        let diff_mag = diff_mag.0.into_f64();
+        if diff_mag > FLOAT_ZERO && self.config.heights {
+            new_height = (local_height + remote_height) * weighted_force / diff_mag + local_height;
+        }
+        if let Some(max) = self.config.max_displacement {
+            new_height = new_height.clamp(local_height - max, local_height + max);
         }
 
         // Update the local coordinate (4)
         //
         // 		xi = xi + δ × ( rtt − ||xi − xj|| ) × u(xi − xj)
         //
-        self.coordinate = Coordinate::new(
-            self.coordinate.vector().clone() + unit_vec.0 * weighted_force,
+        // The local vector is moved out of the coordinate rather than cloned,
+        // except in strict mode where the model must be left unchanged if the
+        // update is rejected. The height is raised to the minimum (rejected
+        // in strict mode), and the vector projected onto the space of the
+        // model.
+        if self.strict {
+            if new_height < self.config.height_floor() {
+                return Err(Error::Strict(Violation::HeightClamped));
+            }
+            let updated = self.config.normalise(Coordinate::new(
+                self.coordinate.vector().clone() + unit_vec.0 * T::from_f64(weighted_force),
+                T::from_f64(error),
+                T::from_f64(new_height),
+            ));
+            if !updated.is_finite() {
+                return Err(Error::Strict(Violation::NonFiniteUpdate));
+            }
+            self.coordinate = updated;
+        } else {
+            let vector = std::mem::take(&mut self.coordinate).into_vector();
+            self.coordinate = self.config.normalise(Coordinate::new(
+                vector + unit_vec.0 * T::from_f64(weighted_force),
+                T::from_f64(error),
+                T::from_f64(new_height),
+            ));
+        }
+
+        if let Some((_, policy)) = self.config.outliers {
+            self.outliers.observed += 1;
+            if down_weighted {
+                telemetry::outlier(&policy);
+                self.outliers.down_weighted += 1;
+            }
+        }
+
+        // Pull the coordinate towards the origin (Ledlie et al.)
+        //
+        // 		xi = xi − ( ||xi|| / ρ )² × u(xi)
+        //
+        // equivalent to scaling the vector by 1 − ||xi|| / ρ², stopping at
+        // the origin rather than overshooting it.
+        if let (Some(rho), false) = (self.config.gravity, self.pinned) {
+            let scale = (1.0 - self.coordinate.magnitude().0.into_f64() / (rho * rho)).max(0.0);
+            let (error, height) = (self.coordinate.error(), self.coordinate.height());
+            let vector = std::mem::take(&mut self.coordinate).into_vector();
+            self.coordinate = Coordinate::new(vector * T::from_f64(scale), error, height);
+        }
+
+        // Learn the offset correcting the systematic error of the estimates
+        // from the updated coordinate (Serf).
+        if !self.residuals.is_empty() {
+            let mut estimate = self
+                .coordinate
+                .vector()
+                .distance(coord.vector())
+                .0
+                .into_f64();
+            if self.config.heights {
+                estimate += self.coordinate.height().into_f64() + remote_height;
+            }
+            let adjustment = T::from_f64(self.residuals.record(value - estimate));
+            self.coordinate = std::mem::take(&mut self.coordinate).with_adjustment(adjustment);
+        }
+        self.epoch = self.epoch.wrapping_add(1);
+
+        // A single clock read serves both the staleness of the health
+        // verdict and the history, which skips recording in O(1) when
+        // disabled or within its interval.
+        let now = self.clock.now();
+        if let Some(history) = self.history.as_mut() {
+            history.record(now, &self.coordinate);
+        }
+        self.health.record(error, weighted_force.abs(), now);
+        telemetry::observed(
             error,
-            new_height,
+            self.coordinate.height().into_f64(),
+            weighted_force.abs(),
         );
 
-        // TODO: add gravity
+        Ok(())
+    }
+
+    /// Observe updates the positional coordinate of the local node, after
+    /// validating the inputs.
+    ///
+    /// This is the fallible variant of [`observe`](Model::observe), returning
+    /// [`Error::InvalidRtt`] for a zero `rtt`, and
+    /// [`Error::NonFiniteCoordinate`] if `coord` contains a NaN or infinite
+    /// value. In [strict mode](Model::set_strict), [`Error::Strict`] is
+    /// returned for any condition the model would otherwise correct silently.
+    /// The model is left unchanged if an error is returned.
+    pub fn try_observe(&mut self, coord: &Coordinate<V, T>, rtt: Duration) -> Result<(), Error> {
+        let result = if rtt == Duration::from_secs(0) {
+            Err(Error::InvalidRtt)
+        } else if !coord.is_finite() {
+            Err(Error::NonFiniteCoordinate)
+        } else {
+            self.update(coord, rtt.as_secs_f64())
+        };
+
+        if let Err(e) = &result {
+            telemetry::rejected(e);
+        }
+        result
+    }
+
+    /// Observes the `rtt` measured to `peer` at `coord`, smoothed by the
+    /// per-peer latency filter if one was configured with
+    /// [`ModelBuilder::latency_filter`](crate::ModelBuilder::latency_filter).
+    ///
+    /// The filter outputs the median of the most recent samples from `peer`,
+    /// so an isolated spike does not move the coordinate:
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use vivaldi::{ModelBuilder, vector::Dimension3};
+    ///
+    /// let mut model = ModelBuilder::new().latency_filter(5).build::<Dimension3>();
+    /// # let remote = *ModelBuilder::new().build::<Dimension3>().get_coordinate();
+    ///
+    /// // On each response from "node-b":
+    /// model.observe_peer("node-b", &remote, Duration::from_millis(20));
+    /// ```
+    ///
+    /// Peers are identified by the hash of `peer`. Without a filter this is
+    /// equivalent to [`observe`](Model::observe).
+    ///
+    /// # Panics
+    ///
+    /// Panics in [strict mode](Model::set_strict) if the observation would be
+    /// rejected by [`try_observe`](Model::try_observe).
+    pub fn observe_peer<K>(&mut self, peer: &K, coord: &Coordinate<V, T>, rtt: Duration)
+    where
+        K: Hash + ?Sized,
+    {
+        let rtt = match self.config.latency_filter {
+            Some(window) => self
+                .filters
+                .entry(peer_key(peer))
+                .or_insert_with(|| MovingMedian::new(window))
+                .push(rtt)
+                .unwrap_or(rtt),
+            None => rtt,
+        };
+        self.observe(coord, rtt)
+    }
+
+    /// Discards the latency filter samples of `peer`, such as when it leaves
+    /// the cluster or its route is known to have changed.
+    pub fn forget_peer<K>(&mut self, peer: &K)
+    where
+        K: Hash + ?Sized,
+    {
+        self.filters.remove(&peer_key(peer));
+    }
+
+    /// Pins the coordinate in place.
+    ///
+    /// While pinned, observations continue to update the error estimate and
+    /// [health](Model::health) statistics, but do not move the coordinate -
+    /// for designated stable reference nodes, or during maintenance windows
+    /// when measurements are known to be unrepresentative:
+    ///
+    /// ```
+    /// # use std::time::Duration;
+    /// # use vivaldi::{Model, vector::Dimension3};
+    /// # let remote = Model::<Dimension3>::new();
+    /// let mut model = Model::<Dimension3>::new();
+    /// model.pin();
+    ///
+    /// let before = *model.get_coordinate().vector();
+    /// model.observe(remote.get_coordinate(), Duration::from_millis(42));
+    /// assert_eq!(*model.get_coordinate().vector(), before);
+    ///
+    /// model.unpin();
+    /// ```
+    pub fn pin(&mut self) {
+        self.pinned = true;
+    }
+
+    /// Unpins the coordinate, so observations move it again.
+    pub fn unpin(&mut self) {
+        self.pinned = false;
+    }
+
+    /// Returns true if the coordinate is [pinned](Model::pin).
+    pub fn is_pinned(&self) -> bool {
+        self.pinned
+    }
+
+    /// Enables or disables strict mode, in which conditions the model
+    /// otherwise absorbs silently are reported as errors.
+    ///
+    /// By default the model clamps heights below the minimum, pushes the
+    /// local node in a random direction when it coincides with a remote
+    /// coordinate, weights both sides evenly when the sample weight is
+    /// undefined, and accepts invalid inputs. These are all expected in
+    /// rare cases, but frequent occurrences typically indicate an integration
+    /// bug such as observing the local coordinate, or mixing models.
+    ///
+    /// In strict mode, [`try_observe`](Model::try_observe) returns an
+    /// [`Error::Strict`] describing the [`Violation`] (leaving the model
+    /// unchanged), and [`observe`](Model::observe) panics, which is useful
+    /// when testing or in staging environments:
+    ///
+    /// ```
+    /// use std::time::Duration;
+    /// use vivaldi::{Error, Model, Violation, vector::Dimension3};
+    ///
+    /// let mut model = Model::<Dimension3>::new();
+    /// model.set_strict(true);
+    ///
+    /// // Observing a coordinate at the same position as the local node.
+    /// let local = *model.get_coordinate();
+    /// assert_eq!(
+    ///     model.try_observe(&local, Duration::from_millis(10)),
+    ///     Err(Error::Strict(Violation::CoincidentCoordinates)),
+    /// );
+    /// ```
+    ///
+    /// Strict mode is disabled by default.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// Bounds the sample weight `ei/(ei + ej)` computed for each observation
+    /// to `floor..=ceiling`.
+    ///
+    /// The sample weight balances the error estimate of the local node
+    /// against that of the remote node, and scales how far the local node
+    /// moves. A weight near zero (a confident local node observing an
+    /// uncertain remote) effectively freezes the model, while a weight near
+    /// one (an uncertain local node observing a confident remote) moves the
+    /// local node the full distance of the observed error:
+    ///
+    /// ```
+    /// use vivaldi::{Model, vector::Dimension3};
+    ///
+    /// let mut model = Model::<Dimension3>::new();
+    /// model.set_weight_limits(0.05, 0.95);
+    /// ```
+    ///
+    /// By default the weight is unbounded (`0.0..=1.0`).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `floor` is greater than `ceiling`, or either is outside of
+    /// `0.0..=1.0`.
+    pub fn set_weight_limits(&mut self, floor: f64, ceiling: f64) {
+        assert!(
+            (0.0..=1.0).contains(&floor) && (0.0..=1.0).contains(&ceiling) && floor <= ceiling,
+            "invalid weight limits {}..={}",
+            floor,
+            ceiling
+        );
+        self.weight_limits = (floor, ceiling);
     }
 
     /// Returns the current positional coordinate of the local node.
-    pub fn get_coordinate(&self) -> &Coordinate<V> {
+    pub fn get_coordinate(&self) -> &Coordinate<V, T> {
         &self.coordinate
     }
+
+    /// Replaces the coordinate of the model with `coordinate`, such as one
+    /// persisted before a restart, so the node resumes from its previous
+    /// position and error estimate rather than the origin:
+    ///
+    /// ```
+    /// use vivaldi::{Coordinate, Model, vector::Dimension3};
+    ///
+    /// # let before_restart = Model::<Dimension3>::new();
+    /// let saved = before_restart.get_coordinate().to_bytes();
+    ///
+    /// // After restarting:
+    /// let mut model = Model::<Dimension3>::new();
+    /// model.restore_coordinate(Coordinate::from_bytes(&saved)?)?;
+    /// # Ok::<(), vivaldi::Error>(())
+    /// ```
+    ///
+    /// The restored coordinate is bounded by the configuration of the model
+    /// as an observation would be: the error is capped at the
+    /// [maximum error](crate::ModelBuilder::max_error), the height is raised
+    /// to the [minimum height](crate::ModelBuilder::min_height) (or zeroed for
+    /// a [Euclidean](crate::ModelBuilder::euclidean) model) and the vector is
+    /// projected onto the space of the model.
+    ///
+    /// Returns an error, leaving the model unchanged, if `coordinate` fails
+    /// [`Coordinate::validate`].
+    pub fn restore_coordinate(&mut self, coordinate: Coordinate<V, T>) -> Result<(), Error> {
+        coordinate.validate()?;
+        let coordinate = self.config.normalise(coordinate);
+        self.set_coordinate(coordinate);
+        Ok(())
+    }
+
+    /// Returns the model with its coordinate replaced by `coordinate`, as
+    /// [`restore_coordinate`](Model::restore_coordinate).
+    ///
+    /// ```
+    /// # use vivaldi::{Coordinate, Model, vector::Dimension3};
+    /// # let saved = Model::<Dimension3>::new().get_coordinate().to_bytes();
+    /// let model = Model::<Dimension3>::new().with_coordinate(Coordinate::from_bytes(&saved)?)?;
+    /// # Ok::<(), vivaldi::Error>(())
+    /// ```
+    pub fn with_coordinate(mut self, coordinate: Coordinate<V, T>) -> Result<Self, Error> {
+        self.restore_coordinate(coordinate)?;
+        Ok(self)
+    }
+
+    pub(crate) fn set_coordinate(&mut self, coordinate: Coordinate<V, T>) {
+        self.coordinate = coordinate;
+        self.epoch = self.epoch.wrapping_add(1);
+    }
+
+    pub(crate) fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    pub(crate) fn clock(&self) -> &C {
+        &self.clock
+    }
+
+    pub(crate) fn health_history(&self) -> &HealthHistory {
+        &self.health
+    }
+
+    pub(crate) fn config(&self) -> &ModelConfig {
+        &self.config
+    }
+
+    pub(crate) fn outlier_counts(&self) -> &OutlierStats {
+        &self.outliers
+    }
+
+    pub(crate) fn coordinate_history(&self) -> Option<&CoordinateHistory<V, T>> {
+        self.history.as_deref()
+    }
+
+    pub(crate) fn history_mut(&mut self) -> &mut Option<Box<CoordinateHistory<V, T>>> {
+        &mut self.history
+    }
+
+    pub(crate) fn rng(&mut self) -> &mut R {
+        &mut self.rng
+    }
+}
+
+impl<V, T> Default for Model<V, SystemClock, LinearSpring, T>
+where
+    V: Vector<T> + std::fmt::Debug,
+    T: Scalar,
+{
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 /// Returns an estimate round-trip time given two coordinates.
@@ -161,21 +1123,221 @@ where
 /// If the nodes represented by `A` and `B` have never communicated the
 /// estimation will still be fairly accurate given a sufficiently mature, dense
 /// model.
-pub fn estimate_rtt<V: Vector>(a: &Coordinate<V>, b: &Coordinate<V>) -> Duration {
-    let diff = a.vector().clone() - b.vector().clone();
+///
+/// Estimating does not allocate, and never panics - a non-finite coordinate
+/// (for example, one received from a misbehaving peer) results in an estimate
+/// of [`Duration::MAX`]. Use [`try_estimate_rtt`] to detect non-finite
+/// coordinates instead.
+pub fn estimate_rtt<V, T>(a: &Coordinate<V, T>, b: &Coordinate<V, T>) -> Duration
+where
+    V: Vector<T>,
+    T: Scalar,
+{
+    saturating_duration(estimate_metric(a, b))
+}
+
+/// Returns an estimate of the round-trip time between two coordinates
+/// excluding their heights.
+///
+/// Heights model the cost of each node's access link, which nodes sharing the
+/// same access infrastructure (such as two hosts in one datacentre) do not
+/// pay when communicating with each other - adding both heights
+/// systematically overestimates the RTT between them. Only use this variant
+/// for nodes known to share their access links:
+///
+/// ```
+/// # use vivaldi::{estimate_rtt, estimate_rtt_planar, Model, vector::Dimension3};
+/// # let a = Model::<Dimension3>::new();
+/// # let b = Model::<Dimension3>::new();
+/// let same_rack = estimate_rtt_planar(a.get_coordinate(), b.get_coordinate());
+/// assert!(same_rack <= estimate_rtt(a.get_coordinate(), b.get_coordinate()));
+/// ```
+///
+/// Non-finite coordinates saturate as in [`estimate_rtt`].
+pub fn estimate_rtt_planar<V, T>(a: &Coordinate<V, T>, b: &Coordinate<V, T>) -> Duration
+where
+    V: Vector<T>,
+    T: Scalar,
+{
+    saturating_duration(a.vector().distance(b.vector()).0.into_f64())
+}
+
+/// Returns an estimate of the round-trip time in integer nanoseconds given two
+/// coordinates, as [`estimate_rtt`] without constructing a [`Duration`].
+///
+/// Estimates saturate at zero and [`u64::MAX`], which a non-finite coordinate
+/// also produces.
+pub fn estimate_rtt_nanos<V, T>(a: &Coordinate<V, T>, b: &Coordinate<V, T>) -> u64
+where
+    V: Vector<T>,
+    T: Scalar,
+{
+    let nanos = estimate_metric(a, b) * NANOS_PER_SEC;
+    if nanos.is_nan() {
+        return u64::MAX;
+    }
+    // Float to integer casts saturate at the bounds of the integer type.
+    nanos as u64
+}
+
+/// Converts `secs` to a [`Duration`], saturating at zero for negative values
+/// and at [`Duration::MAX`] for values too large to represent, infinity and
+/// NaN.
+pub(crate) fn saturating_duration(secs: f64) -> Duration {
+    if secs.is_nan() {
+        return Duration::MAX;
+    }
+    Duration::try_from_secs_f64(secs.max(0.0)).unwrap_or(Duration::MAX)
+}
+
+/// Returns an estimate of the metric embedded by
+/// [`observe_metric`](Model::observe_metric) given two coordinates.
+///
+/// For models updated with [`observe`](Model::observe) this is the RTT in
+/// seconds.
+pub fn estimate_metric<V, T>(a: &Coordinate<V, T>, b: &Coordinate<V, T>) -> f64
+where
+    V: Vector<T>,
+    T: Scalar,
+{
+    let diff = a.vector().distance(b.vector());
 
     // Apply the fixed cost height
-    let diff = diff.magnitude().0 + a.height() + b.height();
+    adjusted(
+        (diff.0 + a.height() + b.height()).into_f64(),
+        (a.adjustment() + b.adjustment()).into_f64(),
+    )
+}
+
+/// Applies the sum of the [adjustments](Coordinate::adjustment) of two
+/// coordinates to the estimate between them, unless the adjusted estimate
+/// would not be positive (as Serf).
+pub(crate) fn adjusted(estimate: f64, adjustment: f64) -> f64 {
+    let adjusted = estimate + adjustment;
+    if adjusted > 0.0 {
+        adjusted
+    } else {
+        estimate
+    }
+}
+
+/// Returns an estimate round-trip time given two coordinates, or
+/// [`Error::NonFiniteCoordinate`] if either coordinate contains a NaN or
+/// infinite value.
+///
+/// This is the fallible variant of [`estimate_rtt`], which saturates when
+/// given non-finite coordinates.
+pub fn try_estimate_rtt<V, T>(a: &Coordinate<V, T>, b: &Coordinate<V, T>) -> Result<Duration, Error>
+where
+    V: Vector<T>,
+    T: Scalar,
+{
+    if !a.is_finite() || !b.is_finite() {
+        return Err(Error::NonFiniteCoordinate);
+    }
+
+    Duration::try_from_secs_f64(estimate_metric(a, b)).map_err(|_| Error::NonFiniteCoordinate)
+}
+
+/// Applies a single round-trip time measurement between two co-located models
+/// to both of them.
+///
+/// Both models observe the coordinate the other held *before* this call, so
+/// the result does not depend on argument order - swapping `a` and `b`
+/// produces the same pair of coordinates (up to the random direction chosen
+/// when the two coordinates coincide). This differs from calling
+/// [`observe`](Model::observe) on each in turn, where the second model
+/// observes the already updated coordinate of the first.
+///
+/// This is useful for simulations, and for pairs of models maintained by the
+/// same process:
+///
+/// ```
+/// use std::time::Duration;
+/// use vivaldi::{observe_symmetric, Model, vector::Dimension3};
+///
+/// let mut a = Model::<Dimension3>::new();
+/// let mut b = Model::<Dimension3>::new();
+///
+/// observe_symmetric(&mut a, &mut b, Duration::from_millis(10));
+/// ```
+pub fn observe_symmetric<V, CA, CB, FA, FB, T>(
+    a: &mut Model<V, CA, FA, T>,
+    b: &mut Model<V, CB, FB, T>,
+    rtt: Duration,
+) where
+    V: Vector<T> + std::fmt::Debug,
+    CA: Clock,
+    CB: Clock,
+    FA: ForceFunction,
+    FB: ForceFunction,
+    T: Scalar,
+{
+    let before = a.get_coordinate().clone();
+    a.observe(b.get_coordinate(), rtt);
+    b.observe(&before, rtt);
+}
 
-    Duration::from_secs_f64(diff)
+/// A fixed-size ring of the residuals (measured minus estimated RTT) of the
+/// most recent observations.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Residuals {
+    samples: Vec<f64>,
+    next: usize,
 }
 
-/// A returns a random unit vector.
-fn new_random_unit_vec<V: Vector>() -> UnitVector<V> {
+impl Residuals {
+    /// Allocates a zeroed window of `len` residuals.
+    fn new(len: usize) -> Self {
+        Residuals {
+            samples: vec![0.0; len],
+            next: 0,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Returns true if the window holds `len` residuals and the next index is
+    /// in bounds, as it always does unless deserialised from corrupt state.
+    #[cfg(feature = "serde")]
+    fn is_valid(&self, len: usize) -> bool {
+        self.samples.len() == len && (self.next < len || (len == 0 && self.next == 0))
+    }
+
+    /// Records `residual`, overwriting the oldest, and returns the adjustment
+    /// of the coordinate - half the mean residual, as each end of an estimate
+    /// contributes its own adjustment.
+    fn record(&mut self, residual: f64) -> f64 {
+        self.samples[self.next] = residual;
+        self.next = (self.next + 1) % self.samples.len();
+        self.samples.iter().sum::<f64>() / (2.0 * self.samples.len() as f64)
+    }
+}
+
+/// Returns the key identifying `peer` in the latency filters of a [`Model`].
+///
+/// The key is persisted with the filters, so is computed with a hash that is
+/// fixed across Rust releases.
+fn peer_key<K: Hash + ?Sized>(peer: &K) -> u64 {
+    let mut hasher = Fnv1a::default();
+    peer.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// A returns a random unit vector drawn from `rng`.
+pub(crate) fn new_random_unit_vec<V, T, R>(rng: &mut R) -> UnitVector<V>
+where
+    V: Vector<T>,
+    T: Scalar,
+    R: Rng + ?Sized,
+{
     loop {
-        let vec = V::random();
+        let vec = V::random(rng);
         let mag = vec.magnitude().0;
-        if mag > FLOAT_ZERO {
+        if mag.into_f64() > FLOAT_ZERO {
             return UnitVector::new(vec / mag);
         }
     }
@@ -183,21 +1345,32 @@ fn new_random_unit_vec<V: Vector>() -> UnitVector<V> {
 
 /// Returns the unit vector, or None if the division by zero is likely or the
 /// magnitude too small to generate an accurate vector.
+#[cfg(test)]
 fn unit_vector_for<V: Vector>(from: V, to: V) -> Option<UnitVector<V>> {
     let diff = from - to;
+    let mag = diff.magnitude();
+    unit_vector_from_diff(diff, &mag)
+}
 
-    let mag = diff.magnitude().0;
-    if mag < FLOAT_ZERO {
+/// Returns the unit vector of `diff` given its precomputed magnitude, or None
+/// if the magnitude is too small to generate an accurate vector.
+pub(crate) fn unit_vector_from_diff<V, T>(diff: V, mag: &Magnitude<T>) -> Option<UnitVector<V>>
+where
+    V: Vector<T>,
+    T: Scalar,
+{
+    if mag.0.into_f64() < FLOAT_ZERO {
         return None;
     }
 
-    Some(UnitVector::new(diff / mag))
+    Some(UnitVector::new(diff / mag.0))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::vector::Dimension3;
+    use crate::builder::ModelBuilder;
+    use crate::vector::{Dimension3, DimensionN};
 
     macro_rules! reciprocal_measurements {
         ($node_a:ident, $node_b:ident, $n:expr, $rtt:ident) => {
@@ -221,11 +1394,13 @@ mod tests {
 
             assert!(
                 estimation_error < (1.0 + $max_diff),
-                format!("estimation error {} is above spec", estimation_error)
+                "estimation error {} is above spec",
+                estimation_error
             );
             assert!(
                 estimation_error > (1.0 - $max_diff),
-                format!("estimation error {} is below spec", estimation_error)
+                "estimation error {} is below spec",
+                estimation_error
             );
         };
     }
@@ -249,12 +1424,232 @@ mod tests {
             unit_vector_for(from, to),
             Some(UnitVector(Dimension3([
                 0.5773502691896258,
-                0.57735026931896258,
+                0.5773502691896258,
                 0.5773502691896258,
             ])))
         );
     }
 
+    #[test]
+    fn try_observe_rejects_invalid_input() {
+        let mut a = Model::<Dimension3>::new();
+        let before = a.clone();
+
+        let b = Model::<Dimension3>::new();
+        assert_eq!(
+            a.try_observe(b.get_coordinate(), Duration::new(0, 0)),
+            Err(Error::InvalidRtt)
+        );
+
+        let poisoned = Coordinate::new(Dimension3([f64::NAN, 0.0, 0.0]), 1.0, 0.1);
+        assert_eq!(
+            a.try_observe(&poisoned, Duration::new(1, 0)),
+            Err(Error::NonFiniteCoordinate)
+        );
+        assert_eq!(a, before);
+
+        assert_eq!(
+            a.try_observe(b.get_coordinate(), Duration::new(1, 0)),
+            Ok(())
+        );
+        assert_ne!(a, before);
+    }
+
+    #[test]
+    fn strict_mode_reports_violations() {
+        let mut a = Model::<Dimension3>::new();
+        a.set_strict(true);
+        let rtt = Duration::from_millis(1);
+
+        // Degenerate weight.
+        a.set_coordinate(Coordinate::new(Dimension3([0.0, 0.0, 0.0]), 0.0, 0.1));
+        let b = Coordinate::new(Dimension3([1.0, 0.0, 0.0]), 0.0, 0.1);
+        assert_eq!(
+            a.try_observe(&b, rtt),
+            Err(Error::Strict(Violation::DegenerateWeight))
+        );
+
+        // Coincident coordinates.
+        a.set_coordinate(Coordinate::new(Dimension3([1.0, 0.0, 0.0]), 1.0, 0.1));
+        let before = a.clone();
+        let b = Coordinate::new(Dimension3([1.0, 0.0, 0.0]), 1.0, 0.1);
+        assert_eq!(
+            a.try_observe(&b, rtt),
+            Err(Error::Strict(Violation::CoincidentCoordinates))
+        );
+
+        // Height clamped.
+        let b = Coordinate::new(Dimension3([0.0, 0.0, 0.0]), 1.0, 1.0);
+        assert_eq!(
+            a.try_observe(&b, rtt),
+            Err(Error::Strict(Violation::HeightClamped))
+        );
+        assert_eq!(a, before);
+
+        // Outside strict mode the conditions are absorbed.
+        a.set_strict(false);
+        assert_eq!(a.try_observe(&b, rtt), Ok(()));
+        assert_ne!(a, before);
+    }
+
+    #[test]
+    #[should_panic(expected = "strict mode")]
+    fn strict_mode_observe_panics() {
+        let mut a = Model::<Dimension3>::new();
+        a.set_strict(true);
+        let local = *a.get_coordinate();
+        a.observe(&local, Duration::from_millis(1));
+    }
+
+    #[test]
+    #[should_panic(expected = "round-trip time")]
+    fn strict_mode_rejects_invalid_metric() {
+        let mut a = Model::<Dimension3>::new();
+        a.set_strict(true);
+        let b = Model::<Dimension3>::new();
+        a.observe_metric(b.get_coordinate(), -1.0);
+    }
+
+    #[test]
+    fn try_estimate_rtt_rejects_non_finite() {
+        let a = Coordinate::new(Dimension3([1.0, 0.0, 0.0]), 1.0, 0.1);
+        let b = Coordinate::new(Dimension3([0.0, 0.0, 0.0]), 1.0, 0.1);
+        assert_eq!(try_estimate_rtt(&a, &b), Ok(estimate_rtt(&a, &b)));
+
+        let poisoned = Coordinate::new(Dimension3([f64::INFINITY, 0.0, 0.0]), 1.0, 0.1);
+        assert_eq!(
+            try_estimate_rtt(&a, &poisoned),
+            Err(Error::NonFiniteCoordinate)
+        );
+    }
+
+    #[test]
+    fn estimate_rtt_saturates() {
+        let a = Coordinate::new(Dimension3([1.0, 0.0, 0.0]), 1.0, 0.1);
+        for v in [f64::NAN, f64::INFINITY, f64::MAX] {
+            let poisoned = Coordinate::new(Dimension3([v, 0.0, 0.0]), 1.0, 0.1);
+            assert_eq!(estimate_rtt(&a, &poisoned), Duration::MAX);
+        }
+    }
+
+    #[test]
+    fn observe_metric() {
+        // Embed transfer time per megabyte, derived from bandwidth.
+        let mut a = Model::<Dimension3>::new();
+        let mut b = Model::<Dimension3>::new();
+        let mbps = 80.0;
+
+        for _ in 0..100 {
+            a.observe_metric(b.get_coordinate(), 1.0 / mbps);
+            b.observe_metric(a.get_coordinate(), 1.0 / mbps);
+        }
+
+        let got = 1.0 / estimate_metric(a.get_coordinate(), b.get_coordinate());
+        assert!((got - mbps).abs() / mbps < 0.115, "estimated {}", got);
+    }
+
+    #[test]
+    fn weight_limits() {
+        // A local node with zero error would never move with an unbounded
+        // weight.
+        let confident = Coordinate::new(Dimension3([0.0, 0.0, 0.0]), 0.0, 0.1);
+        let remote = Coordinate::new(Dimension3([1.0, 0.0, 0.0]), 1.0, 0.1);
+
+        let mut a = Model::<Dimension3>::new();
+        a.set_coordinate(confident);
+        a.observe(&remote, Duration::new(2, 0));
+        assert_eq!(a.get_coordinate().vector(), confident.vector());
+
+        a.set_weight_limits(0.1, 0.9);
+        a.observe(&remote, Duration::new(2, 0));
+        assert!(a.get_coordinate().vector().0[0] < 0.0);
+
+        // Both errors zero gives an undefined weight.
+        let mut b = Model::<Dimension3>::new();
+        b.set_coordinate(confident);
+        b.observe(
+            &Coordinate::new(Dimension3([1.0, 0.0, 0.0]), 0.0, 0.1),
+            Duration::new(2, 0),
+        );
+        assert!(b.get_coordinate().is_finite());
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid weight limits")]
+    fn weight_limits_invalid() {
+        Model::<Dimension3>::new().set_weight_limits(0.9, 0.1);
+    }
+
+    #[test]
+    fn observe_symmetric_order_independent() {
+        let a = Coordinate::new(Dimension3([1.0, 0.0, 0.0]), 1.0, 0.1);
+        let b = Coordinate::new(Dimension3([0.0, 2.0, 0.0]), 0.5, 0.1);
+        let rtt = Duration::new(1, 0);
+
+        let (mut a1, mut b1) = (Model::<Dimension3>::new(), Model::<Dimension3>::new());
+        a1.set_coordinate(a);
+        b1.set_coordinate(b);
+        observe_symmetric(&mut a1, &mut b1, rtt);
+
+        let (mut a2, mut b2) = (Model::<Dimension3>::new(), Model::<Dimension3>::new());
+        a2.set_coordinate(a);
+        b2.set_coordinate(b);
+        observe_symmetric(&mut b2, &mut a2, rtt);
+
+        assert_eq!(a1, a2);
+        assert_eq!(b1, b2);
+
+        // Each observed the other's original coordinate.
+        let mut want = Model::<Dimension3>::new();
+        want.set_coordinate(b);
+        want.observe(&a, rtt);
+        assert_eq!(b1, want);
+    }
+
+    #[test]
+    fn f32_scalar() {
+        type Model32 = Model<DimensionN<3, f32>, SystemClock, LinearSpring, f32>;
+
+        let start = |x: f32| Coordinate::new(DimensionN([x, 0.0, 0.0]), 1.0, 0.01);
+        let (mut a32, mut b32) = (Model32::new(), Model32::new());
+        a32.set_coordinate(start(0.01));
+        b32.set_coordinate(start(-0.01));
+
+        let start = |x: f64| Coordinate::new(DimensionN([x, 0.0, 0.0]), 1.0, 0.01);
+        let (mut a64, mut b64) = (Model::<DimensionN<3>>::new(), Model::new());
+        a64.set_coordinate(start(0.01));
+        b64.set_coordinate(start(-0.01));
+
+        let rtt = Duration::from_millis(50);
+        for _ in 0..100 {
+            observe_symmetric(&mut a32, &mut b32, rtt);
+            observe_symmetric(&mut a64, &mut b64, rtt);
+        }
+
+        // The f32 model converges as the f64 model does.
+        let got = estimate_rtt(a32.get_coordinate(), b32.get_coordinate()).as_secs_f64();
+        let want = estimate_rtt(a64.get_coordinate(), b64.get_coordinate()).as_secs_f64();
+        assert!((got - 0.05).abs() < 0.001, "f32 estimate {}", got);
+        assert!((got - want).abs() < 1e-5, "{} vs {}", got, want);
+        assert!((a32.get_coordinate().error() as f64 - a64.get_coordinate().error()).abs() < 1e-4);
+    }
+
+    #[test]
+    fn default_clone_eq() {
+        let mut a = Model::<Dimension3>::default();
+        assert_eq!(a, Model::<Dimension3>::new());
+
+        let b = Model::<Dimension3>::new();
+        a.observe(b.get_coordinate(), Duration::new(1, 0));
+        assert_ne!(a, b);
+
+        let snapshot = a.clone();
+        assert_eq!(snapshot, a);
+
+        a.observe(b.get_coordinate(), Duration::new(1, 0));
+        assert_ne!(snapshot, a);
+    }
+
     #[test]
     fn independent_coords() {
         let mut a = Model::<Dimension3>::new();
@@ -377,4 +1772,245 @@ mod tests {
         assert_within!(dc1_B, dc2_C, slow_rtt.as_secs_f64(), 0.25);
         assert_within!(dc2_C, dc1_B, slow_rtt.as_secs_f64(), 0.25);
     }
+
+    #[test]
+    fn nanos_api() {
+        let remote = Coordinate::new(Dimension3([0.01, 0.02, 0.0]), 0.5, 0.001);
+        let mut a = Model::<Dimension3>::new();
+        let mut b = Model::<Dimension3>::new();
+        for _ in 0..10 {
+            a.observe(&remote, Duration::from_nanos(42_000_000));
+            b.observe_nanos(&remote, 42_000_000);
+        }
+        assert_eq!(a, b);
+
+        let nanos = estimate_rtt_nanos(a.get_coordinate(), &remote);
+        let duration = estimate_rtt(a.get_coordinate(), &remote).as_nanos() as u64;
+        assert!(nanos.abs_diff(duration) <= 1, "{} vs {}", nanos, duration);
+
+        let far = Coordinate::new(Dimension3([f64::MAX, 0.0, 0.0]), 0.5, 0.0);
+        assert_eq!(estimate_rtt_nanos(&remote, &far), u64::MAX);
+        let nan = Coordinate::new(Dimension3([f64::NAN, 0.0, 0.0]), 0.5, 0.0);
+        assert_eq!(estimate_rtt_nanos(&remote, &nan), u64::MAX);
+    }
+
+    #[test]
+    fn force_function() {
+        use crate::force::CappedSpring;
+
+        let remote = Coordinate::new(Dimension3([0.01, 0.0, 0.0]), 0.5, 0.001);
+        let mut linear = Model::<Dimension3>::new();
+        let mut capped = Model::<Dimension3>::new()
+            .with_force_function(CappedSpring::new(Duration::from_millis(1)));
+
+        linear.observe(&remote, Duration::from_secs(1));
+        capped.observe(&remote, Duration::from_secs(1));
+
+        let origin = Dimension3::default();
+        let moved = |m: &Coordinate<Dimension3>| m.vector().distance(&origin).0;
+        assert!(moved(linear.get_coordinate()) > 0.1);
+        assert!(moved(capped.get_coordinate()) <= 0.001 + 1e-12);
+    }
+
+    #[test]
+    fn planar_estimate() {
+        let a = Coordinate::new(Dimension3([0.0, 0.03, 0.0]), 0.5, 0.002);
+        let b = Coordinate::new(Dimension3([0.04, 0.0, 0.0]), 0.5, 0.003);
+        assert_eq!(estimate_rtt_planar(&a, &b), Duration::from_millis(50));
+        assert_eq!(estimate_rtt(&a, &b), Duration::from_millis(55));
+
+        let nan = Coordinate::new(Dimension3([f64::NAN, 0.0, 0.0]), 0.5, 0.0);
+        assert_eq!(estimate_rtt_planar(&a, &nan), Duration::MAX);
+    }
+
+    #[test]
+    fn pinned() {
+        let remote = Coordinate::new(Dimension3([0.05, 0.0, 0.0]), 0.1, 0.001);
+        let mut m = Model::<Dimension3>::new();
+        m.set_strict(true);
+        m.pin();
+        assert!(m.is_pinned());
+
+        // Accurate measurements lower the error estimate.
+        let before = *m.get_coordinate();
+        let rtt = estimate_rtt(&before, &remote);
+        for _ in 0..20 {
+            m.try_observe(&remote, rtt).unwrap();
+        }
+        assert_eq!(m.get_coordinate().vector(), before.vector());
+        assert_eq!(m.get_coordinate().height(), before.height());
+        assert!(m.get_coordinate().error() < before.error());
+
+        // Coincident coordinates are not a violation when the coordinate
+        // does not move.
+        m.try_observe(&before, Duration::from_millis(30)).unwrap();
+
+        m.unpin();
+        m.observe(&remote, Duration::from_millis(30));
+        assert_ne!(m.get_coordinate().vector(), before.vector());
+    }
+
+    #[test]
+    fn observe_peer_filters_spikes() {
+        let remote = Coordinate::new(Dimension3([0.05, 0.0, 0.0]), 0.5, 0.001);
+        let ms = Duration::from_millis;
+
+        let mut raw = Model::<Dimension3>::new();
+        let mut filtered = ModelBuilder::new().latency_filter(3).build::<Dimension3>();
+        for rtt in [ms(20), ms(20), ms(20)] {
+            raw.observe(&remote, rtt);
+        }
+        for rtt in [ms(20), ms(20), ms(500)] {
+            filtered.observe_peer("a", &remote, rtt);
+        }
+        assert_eq!(filtered.get_coordinate(), raw.get_coordinate());
+
+        // Samples from other peers are filtered separately.
+        let mut other = filtered.clone();
+        filtered.observe_peer("b", &remote, ms(500));
+        other.observe(&remote, ms(500));
+        assert_eq!(filtered.get_coordinate(), other.get_coordinate());
+
+        // Forgetting a peer discards its samples.
+        let mut other = filtered.clone();
+        filtered.forget_peer("a");
+        filtered.observe_peer("a", &remote, ms(40));
+        other.observe(&remote, ms(40));
+        assert_eq!(filtered.get_coordinate(), other.get_coordinate());
+
+        // Without a filter, samples are observed as-is.
+        let mut a = Model::<Dimension3>::new();
+        let mut b = a.clone();
+        a.observe_peer("a", &remote, ms(500));
+        b.observe(&remote, ms(500));
+        assert_eq!(a.get_coordinate(), b.get_coordinate());
+    }
+
+    #[test]
+    fn peer_key_is_stable() {
+        // The keys are persisted with the filters, so must not change.
+        assert_eq!(peer_key("node-a"), 0x4b91_89dd_2d42_1544);
+        assert_eq!(peer_key(&42_u32), 0x8d9a_adc8_352f_df7f);
+    }
+
+    #[test]
+    fn restore_coordinate() {
+        let coord = Coordinate::new(Dimension3([0.01, 0.02, 0.0]), 0.3, 0.001);
+        let mut m = Model::<Dimension3>::new().with_coordinate(coord).unwrap();
+        assert_eq!(m.get_coordinate(), &coord);
+        let epoch = m.epoch();
+
+        let nan = Coordinate::new(Dimension3([f64::NAN, 0.0, 0.0]), 0.3, 0.001);
+        assert_eq!(m.restore_coordinate(nan), Err(Error::NonFiniteCoordinate));
+        let negative = Coordinate::new(Dimension3([0.01, 0.02, 0.0]), -1.0, 0.001);
+        assert_eq!(
+            m.restore_coordinate(negative),
+            Err(Error::OutOfRange { field: "error" })
+        );
+        assert_eq!(m.get_coordinate(), &coord);
+        assert_eq!(m.epoch(), epoch);
+
+        // The model resumes from the restored coordinate.
+        let remote = Coordinate::new(Dimension3([0.0, 0.02, 0.0]), 0.3, 0.001);
+        let rtt = estimate_rtt(&coord, &remote);
+        m.observe(&remote, rtt);
+        assert!(m.get_coordinate().vector().distance(coord.vector()).0 < 1e-6);
+
+        // The coordinate is bounded by the model configuration.
+        let mut m = ModelBuilder::new()
+            .max_error(0.2)
+            .min_height(Duration::from_millis(2))
+            .build::<Dimension3>();
+        m.restore_coordinate(coord).unwrap();
+        assert_eq!(m.get_coordinate().error(), 0.2);
+        assert_eq!(m.get_coordinate().height(), 0.002);
+
+        let mut m = ModelBuilder::new().euclidean().build::<Dimension3>();
+        m.restore_coordinate(coord.with_adjustment(0.001)).unwrap();
+        assert_eq!(m.get_coordinate().vector(), coord.vector());
+        assert_eq!(m.get_coordinate().height(), 0.0);
+        assert_eq!(m.get_coordinate().adjustment(), 0.001);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        let ms = Duration::from_millis;
+        let mut a = ModelBuilder::new()
+            .latency_filter(5)
+            .adjustment_window(4)
+            .build::<Dimension3>();
+        let mut remote = Model::<Dimension3>::new();
+        remote.set_coordinate(Coordinate::new(Dimension3([0.01, 0.0, 0.0]), 1.0, 0.01));
+        for rtt in [50, 60, 400, 55] {
+            a.observe_peer("a", remote.get_coordinate(), ms(rtt));
+        }
+
+        let json = serde_json::to_string(&a).unwrap();
+        let mut b: Model<Dimension3> = serde_json::from_str(&json).unwrap();
+        assert_eq!(serde_json::to_string(&b).unwrap(), json);
+
+        // The restored model, including the per-peer filter, continues as the
+        // original does.
+        for rtt in [45, 500, 52] {
+            a.observe_peer("a", remote.get_coordinate(), ms(rtt));
+            b.observe_peer("a", remote.get_coordinate(), ms(rtt));
+            assert_eq!(a.get_coordinate(), b.get_coordinate());
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_large_error() {
+        // A sample far shorter than the distance to the remote grows the
+        // error estimate beyond the range of a valid coordinate.
+        let remote = Coordinate::new(Dimension3([1.0, 0.0, 0.0]), 0.01, 0.001);
+        let mut a = Model::<Dimension3>::new();
+        a.observe(&remote, Duration::from_micros(1));
+        assert_eq!(a.get_coordinate().error(), MAX_VALID_ERROR);
+
+        let json = serde_json::to_string(&a).unwrap();
+        let b: Model<Dimension3> = serde_json::from_str(&json).unwrap();
+        assert_eq!(b.get_coordinate(), a.get_coordinate());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde_rejects_corrupt_state() {
+        let mut a = ModelBuilder::new()
+            .latency_filter(3)
+            .adjustment_window(4)
+            .max_displacement(Duration::from_millis(10))
+            .history_interval(Duration::from_secs(60))
+            .build::<Dimension3>();
+        let remote = Coordinate::new(Dimension3([0.01, 0.0, 0.0]), 1.0, 0.01);
+        a.observe_peer("a", &remote, Duration::from_millis(20));
+        let valid = serde_json::to_value(&a).unwrap();
+        assert!(serde_json::from_value::<Model<Dimension3>>(valid.clone()).is_ok());
+
+        let corrupt = |pointer: &str, value: serde_json::Value| {
+            let mut v = valid.clone();
+            *v.pointer_mut(pointer).unwrap() = value;
+            let err = serde_json::from_value::<Model<Dimension3>>(v).unwrap_err();
+            assert!(
+                err.to_string().contains("invalid model"),
+                "{}: {}",
+                pointer,
+                err
+            );
+        };
+        corrupt("/residuals/next", 7.into());
+        corrupt("/residuals/samples", serde_json::json!([0.0]));
+        corrupt("/weight_limits", serde_json::json!([0.9, 0.1]));
+        corrupt("/weight_limits", serde_json::json!([-0.5, 0.5]));
+        corrupt("/config/max_displacement", (-1.0).into());
+        corrupt("/config/latency_filter", 0.into());
+        corrupt("/config/max_error", 0.0.into());
+        corrupt("/health/next", 16.into());
+        corrupt("/history/start", 32.into());
+        corrupt("/history/len", 33.into());
+
+        let key = valid["filters"].as_object().unwrap().keys().next().unwrap();
+        corrupt(&format!("/filters/{}/window/size", key), 0.into());
+    }
 }
@@ -0,0 +1,213 @@
+use crate::coordinate::Coordinate;
+use crate::model::{estimate_metric, estimate_rtt, Model};
+use crate::vector::Vector;
+use std::time::Duration;
+
+/// The set of measurements taken for a single probe of a remote node.
+///
+/// Only the RTT is mandatory - metrics that were not measured for a probe are
+/// left unchanged in the [`MultiModel`].
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Measurements {
+    /// The measured round-trip time.
+    pub rtt: Duration,
+
+    /// The measured jitter (variation in RTT), if any.
+    pub jitter: Option<Duration>,
+
+    /// The measured available bandwidth in bytes per second, if any.
+    pub bandwidth: Option<f64>,
+}
+
+/// The estimated metrics between two nodes, returned by
+/// [`MultiModel::estimate`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Estimates {
+    /// The estimated round-trip time.
+    pub rtt: Duration,
+
+    /// The estimated jitter.
+    pub jitter: Duration,
+
+    /// The estimated available bandwidth in bytes per second.
+    pub bandwidth: f64,
+}
+
+/// The coordinates of a node in each of the embeddings maintained by a
+/// [`MultiModel`], exchanged between nodes in place of a single
+/// [`Coordinate`].
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(bound(
+        serialize = "V: serde::Serialize",
+        deserialize = "V: serde::Deserialize<'de>"
+    ))
+)]
+pub struct MultiCoordinate<V>
+where
+    V: Vector,
+{
+    /// The coordinate in the RTT embedding.
+    pub rtt: Coordinate<V>,
+
+    /// The coordinate in the jitter embedding.
+    pub jitter: Coordinate<V>,
+
+    /// The coordinate in the bandwidth embedding.
+    pub bandwidth: Coordinate<V>,
+}
+
+/// A set of Vivaldi models embedding RTT, jitter and bandwidth side by side,
+/// updated from a single observation.
+///
+/// Each metric is embedded in its own [`Model`] using
+/// [`observe_metric`](Model::observe_metric). Bandwidth is embedded as its
+/// reciprocal (the transfer time per byte) so that, like RTT and jitter,
+/// smaller values mean "closer".
+///
+/// ```
+/// use std::time::Duration;
+/// use vivaldi::{Measurements, MultiModel, vector::Dimension3};
+///
+/// let mut local = MultiModel::<Dimension3>::new();
+/// let remote = MultiModel::<Dimension3>::new();
+///
+/// local.observe(
+///     &remote.get_coordinate(),
+///     &Measurements {
+///         rtt: Duration::from_millis(40),
+///         jitter: Some(Duration::from_millis(2)),
+///         bandwidth: Some(10_000_000.0),
+///     },
+/// );
+///
+/// let estimates = local.estimate(&remote.get_coordinate());
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct MultiModel<V>
+where
+    V: Vector + std::fmt::Debug,
+{
+    rtt: Model<V>,
+    jitter: Model<V>,
+    bandwidth: Model<V>,
+}
+
+impl<V> MultiModel<V>
+where
+    V: Vector + std::fmt::Debug,
+{
+    /// Initialises a new set of models.
+    pub fn new() -> Self {
+        MultiModel {
+            rtt: Model::new(),
+            jitter: Model::new(),
+            bandwidth: Model::new(),
+        }
+    }
+
+    /// Updates each embedding with the corresponding measurement to the remote
+    /// node.
+    ///
+    /// Zero or non-finite jitter and bandwidth measurements are ignored, as
+    /// they cannot be embedded.
+    pub fn observe(&mut self, coord: &MultiCoordinate<V>, m: &Measurements) {
+        self.rtt.observe(&coord.rtt, m.rtt);
+
+        if let Some(jitter) = m.jitter.filter(|v| *v > Duration::from_secs(0)) {
+            self.jitter.observe(&coord.jitter, jitter);
+        }
+
+        if let Some(bw) = m.bandwidth.filter(|v| v.is_finite() && *v > 0.0) {
+            self.bandwidth.observe_metric(&coord.bandwidth, 1.0 / bw);
+        }
+    }
+
+    /// Returns the estimated metrics between the local node and the remote
+    /// node with coordinates `coord`.
+    pub fn estimate(&self, coord: &MultiCoordinate<V>) -> Estimates {
+        Estimates {
+            rtt: estimate_rtt(self.rtt.get_coordinate(), &coord.rtt),
+            jitter: estimate_rtt(self.jitter.get_coordinate(), &coord.jitter),
+            bandwidth: 1.0 / estimate_metric(self.bandwidth.get_coordinate(), &coord.bandwidth),
+        }
+    }
+
+    /// Returns the current coordinates of the local node.
+    pub fn get_coordinate(&self) -> MultiCoordinate<V> {
+        MultiCoordinate {
+            rtt: self.rtt.get_coordinate().clone(),
+            jitter: self.jitter.get_coordinate().clone(),
+            bandwidth: self.bandwidth.get_coordinate().clone(),
+        }
+    }
+
+    /// Returns the model embedding RTT.
+    pub fn rtt_model(&self) -> &Model<V> {
+        &self.rtt
+    }
+
+    /// Returns the model embedding jitter.
+    pub fn jitter_model(&self) -> &Model<V> {
+        &self.jitter
+    }
+
+    /// Returns the model embedding bandwidth, as the reciprocal of bytes per
+    /// second.
+    pub fn bandwidth_model(&self) -> &Model<V> {
+        &self.bandwidth
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::Dimension3;
+
+    fn within(got: f64, want: f64) -> bool {
+        (got - want).abs() / want < 0.115
+    }
+
+    #[test]
+    fn converges_each_metric() {
+        let mut a = MultiModel::<Dimension3>::new();
+        let mut b = MultiModel::<Dimension3>::new();
+        let m = Measurements {
+            rtt: Duration::from_millis(500),
+            jitter: Some(Duration::from_millis(50)),
+            bandwidth: Some(100.0),
+        };
+
+        for _ in 0..100 {
+            a.observe(&b.get_coordinate(), &m);
+            b.observe(&a.get_coordinate(), &m);
+        }
+
+        let got = a.estimate(&b.get_coordinate());
+        assert!(within(got.rtt.as_secs_f64(), 0.5), "{:?}", got);
+        assert!(within(got.jitter.as_secs_f64(), 0.05), "{:?}", got);
+        assert!(within(got.bandwidth, 100.0), "{:?}", got);
+    }
+
+    #[test]
+    fn missing_measurements_leave_models_unchanged() {
+        let mut a = MultiModel::<Dimension3>::new();
+        let b = MultiModel::<Dimension3>::new();
+        let before = a.clone();
+
+        a.observe(
+            &b.get_coordinate(),
+            &Measurements {
+                rtt: Duration::from_millis(10),
+                jitter: Some(Duration::from_secs(0)),
+                bandwidth: None,
+            },
+        );
+
+        assert_ne!(a.rtt_model(), before.rtt_model());
+        assert_eq!(a.jitter_model(), before.jitter_model());
+        assert_eq!(a.bandwidth_model(), before.bandwidth_model());
+    }
+}
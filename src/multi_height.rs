@@ -0,0 +1,276 @@
+use crate::coordinate::{Coordinate, MIN_HEIGHT};
+use crate::model::{
+    new_random_unit_vec, saturating_duration, unit_vector_from_diff, ERROR_LIMIT, FLOAT_ZERO,
+    INITIAL_ERROR,
+};
+use crate::vector::Vector;
+use std::time::Duration;
+
+/// The initial total height of the adaptive components of a
+/// [`MultiHeightModel`], matching the initial height of a [`Model`](crate::Model).
+const INITIAL_HEIGHT: f64 = 0.1;
+
+/// How a single height component of a [`MultiHeightModel`] responds to
+/// observations.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HeightRule {
+    /// Updated by the Vivaldi height rule, as the scalar height of a
+    /// [`Model`](crate::Model).
+    Adaptive,
+
+    /// Updated by the Vivaldi height rule scaled by the given rate (0 to 1),
+    /// for segments whose cost changes slowly.
+    Damped(f64),
+
+    /// Held at the given cost, for segments with a known fixed cost such as a
+    /// satellite transit.
+    Fixed(Duration),
+}
+
+/// A coordinate with `N` height components, produced by a
+/// [`MultiHeightModel`].
+///
+/// The heights are summed when estimating RTTs, so the estimate between two
+/// coordinates is the Euclidean distance plus every height of both.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MultiHeightCoordinate<V, const N: usize>
+where
+    V: Vector,
+{
+    vector: V,
+    error: f64,
+    heights: [f64; N],
+}
+
+impl<V, const N: usize> MultiHeightCoordinate<V, N>
+where
+    V: Vector,
+{
+    /// Returns the Euclidean component of the coordinate.
+    pub fn vector(&self) -> &V {
+        &self.vector
+    }
+
+    /// Returns the error estimate of the coordinate.
+    pub fn error(&self) -> f64 {
+        self.error
+    }
+
+    /// Returns each height component in seconds.
+    pub fn heights(&self) -> &[f64; N] {
+        &self.heights
+    }
+
+    /// Returns the sum of the height components in seconds.
+    pub fn height(&self) -> f64 {
+        self.heights.iter().sum()
+    }
+
+    /// Returns the estimated RTT between `self` and `other`.
+    pub fn estimate_rtt(&self, other: &Self) -> Duration {
+        saturating_duration(self.vector.distance(&other.vector).0 + self.height() + other.height())
+    }
+
+    /// Collapses the height components into the single height of a
+    /// [`Coordinate`], for use with APIs expecting one.
+    pub fn to_coordinate(&self) -> Coordinate<V> {
+        Coordinate::new(self.vector.clone(), self.error, self.height())
+    }
+}
+
+/// An experimental Vivaldi model with `N` height components, each following
+/// its own [`HeightRule`].
+///
+/// The single height of a [`Model`](crate::Model) models the cost of the
+/// access link, which the Euclidean space cannot capture. Nodes behind
+/// several distinct fixed-cost segments (such as an access link followed by a
+/// satellite transit) can model each separately - a known cost can be held
+/// [fixed](HeightRule::Fixed) while the remaining segments adapt:
+///
+/// ```
+/// use std::time::Duration;
+/// use vivaldi::{HeightRule, MultiHeightModel, vector::Dimension3};
+///
+/// let mut model = MultiHeightModel::<Dimension3, 2>::new([
+///     HeightRule::Adaptive,
+///     HeightRule::Fixed(Duration::from_millis(250)),
+/// ]);
+/// # let remote = *MultiHeightModel::<Dimension3, 2>::new([HeightRule::Adaptive; 2]).get_coordinate();
+///
+/// model.observe(&remote, Duration::from_millis(300));
+/// let estimate = model.get_coordinate().estimate_rtt(&remote);
+/// ```
+///
+/// Heights are updated per component: each receives the Vivaldi height
+/// update computed from its own value and the matching component of the
+/// remote coordinate, so peers should use the same component layout.
+#[derive(Debug, Clone)]
+pub struct MultiHeightModel<V, const N: usize>
+where
+    V: Vector,
+{
+    coordinate: MultiHeightCoordinate<V, N>,
+    rules: [HeightRule; N],
+}
+
+impl<V, const N: usize> MultiHeightModel<V, N>
+where
+    V: Vector,
+{
+    /// Initialises a model with one height component per rule.
+    ///
+    /// Fixed components start at their cost, while the remaining components
+    /// share the initial height of a [`Model`](crate::Model).
+    pub fn new(rules: [HeightRule; N]) -> Self {
+        let adaptive = rules
+            .iter()
+            .filter(|r| !matches!(r, HeightRule::Fixed(_)))
+            .count();
+
+        let mut heights = [0.0; N];
+        for (h, rule) in heights.iter_mut().zip(&rules) {
+            *h = match rule {
+                HeightRule::Fixed(cost) => cost.as_secs_f64(),
+                _ => INITIAL_HEIGHT / adaptive as f64,
+            };
+        }
+
+        MultiHeightModel {
+            coordinate: MultiHeightCoordinate {
+                vector: V::default(),
+                error: INITIAL_ERROR,
+                heights,
+            },
+            rules,
+        }
+    }
+
+    /// Returns the rule of each height component.
+    pub fn rules(&self) -> &[HeightRule; N] {
+        &self.rules
+    }
+
+    /// Returns the current coordinate.
+    pub fn get_coordinate(&self) -> &MultiHeightCoordinate<V, N> {
+        &self.coordinate
+    }
+
+    /// Updates the local coordinate with the `rtt` measured to a remote node
+    /// at `coord`, as [`Model::observe`](crate::Model::observe).
+    pub fn observe(&mut self, coord: &MultiHeightCoordinate<V, N>, rtt: Duration) {
+        let value = rtt.as_secs_f64();
+        let local = &self.coordinate;
+
+        let weight = local.error / (local.error + coord.error);
+        let weight = if weight.is_nan() { 0.5 } else { weight }.clamp(0.0, 1.0);
+
+        let diff = local.vector.displacement(&coord.vector);
+        let mag = diff.magnitude();
+        let dist = mag.0 + local.height() + coord.height();
+        let relative_error = (dist - value).abs() / value;
+
+        let error =
+            relative_error * ERROR_LIMIT * weight + local.error * (1.0 - ERROR_LIMIT * weight);
+        let force = ERROR_LIMIT * weight * (value - dist);
+        let unit = unit_vector_from_diff(diff, &mag)
+            .unwrap_or_else(|| new_random_unit_vec(&mut rand::thread_rng()));
+
+        let mut heights = local.heights;
+        if mag.0 > FLOAT_ZERO {
+            for ((h, remote), rule) in heights.iter_mut().zip(&coord.heights).zip(&self.rules) {
+                let step = (*h + remote) * force / mag.0;
+                *h = match rule {
+                    HeightRule::Adaptive => (*h + step).max(MIN_HEIGHT),
+                    HeightRule::Damped(rate) => (*h + rate * step).max(MIN_HEIGHT),
+                    HeightRule::Fixed(_) => *h,
+                };
+            }
+        }
+
+        self.coordinate = MultiHeightCoordinate {
+            vector: local.vector.clone() + unit.0 * force,
+            error,
+            heights,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::Dimension2;
+
+    fn peer(x: f64, y: f64, access: f64) -> MultiHeightCoordinate<Dimension2, 2> {
+        MultiHeightCoordinate {
+            vector: Dimension2([x, y]),
+            error: 0.05,
+            heights: [access, 0.0],
+        }
+    }
+
+    #[test]
+    fn initial_heights() {
+        let m = MultiHeightModel::<Dimension2, 3>::new([
+            HeightRule::Adaptive,
+            HeightRule::Damped(0.5),
+            HeightRule::Fixed(Duration::from_millis(250)),
+        ]);
+        assert_eq!(m.get_coordinate().heights(), &[0.05, 0.05, 0.25]);
+        assert!((m.get_coordinate().height() - 0.35).abs() < 1e-12);
+
+        let c = m.get_coordinate().to_coordinate();
+        assert_eq!(c.height(), m.get_coordinate().height());
+        assert_eq!(c.error(), INITIAL_ERROR);
+    }
+
+    #[test]
+    fn fixed_segment_learns_access_link() {
+        let satellite = Duration::from_millis(250);
+        let mut m = MultiHeightModel::<Dimension2, 2>::new([
+            HeightRule::Adaptive,
+            HeightRule::Fixed(satellite),
+        ]);
+
+        let peers = [
+            peer(0.0, 0.0, 0.005),
+            peer(0.05, 0.0, 0.01),
+            peer(0.0, 0.05, 0.002),
+            peer(0.05, 0.05, 0.008),
+        ];
+        let truth = MultiHeightCoordinate {
+            vector: Dimension2([0.02, 0.03]),
+            error: 0.0,
+            heights: [0.02, 0.25],
+        };
+
+        for _ in 0..500 {
+            for p in &peers {
+                m.observe(p, truth.estimate_rtt(p));
+            }
+        }
+
+        let c = m.get_coordinate();
+        assert_eq!(c.heights()[1], satellite.as_secs_f64());
+        for p in &peers {
+            let got = c.estimate_rtt(p).as_secs_f64();
+            let want = truth.estimate_rtt(p).as_secs_f64();
+            assert!((got - want).abs() / want < 0.02, "{} vs {}", got, want);
+        }
+    }
+
+    #[test]
+    fn damped_components_move_slower() {
+        let mut m =
+            MultiHeightModel::<Dimension2, 2>::new([HeightRule::Adaptive, HeightRule::Damped(0.1)]);
+        let start = *m.get_coordinate().heights();
+
+        // A far higher RTT than estimated grows both heights.
+        let remote = peer(0.05, 0.0, 0.05);
+        m.observe(&remote, Duration::from_secs(1));
+
+        let h = m.get_coordinate().heights();
+        let (adaptive, damped) = (h[0] - start[0], h[1] - start[1]);
+        assert!(adaptive > 0.0 && damped > 0.0);
+        assert!(damped < adaptive / 5.0, "{} vs {}", damped, adaptive);
+    }
+}
@@ -0,0 +1,139 @@
+use crate::clock::Clock;
+use crate::force::ForceFunction;
+use crate::model::Model;
+use crate::vector::Vector;
+use rand::Rng;
+
+/// How a [`Model`] built with
+/// [`outlier_rejection`](crate::ModelBuilder::outlier_rejection) treats an
+/// outlying observation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum OutlierPolicy {
+    /// Ignore the observation, leaving the model unchanged.
+    Discard,
+
+    /// Apply the observation with its sample weight multiplied by the given
+    /// factor, between 0 and 1.
+    DownWeight(f64),
+}
+
+/// Counts of the observations assessed by the outlier rejection of a
+/// [`Model`], returned by [`Model::outlier_stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OutlierStats {
+    /// The number of observations assessed.
+    pub observed: u64,
+
+    /// The number of observations discarded by [`OutlierPolicy::Discard`].
+    pub discarded: u64,
+
+    /// The number of observations down-weighted by
+    /// [`OutlierPolicy::DownWeight`].
+    pub down_weighted: u64,
+}
+
+impl<V, C, F, R> Model<V, C, F, f64, R>
+where
+    V: Vector + std::fmt::Debug,
+    C: Clock,
+    F: ForceFunction,
+    R: Rng,
+{
+    /// Returns the number of observations treated as outliers, out of those
+    /// assessed since the model was built.
+    ///
+    /// All counts are zero unless the model was built with
+    /// [`outlier_rejection`](crate::ModelBuilder::outlier_rejection).
+    pub fn outlier_stats(&self) -> OutlierStats {
+        *self.outlier_counts()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::ModelBuilder;
+    use crate::coordinate::Coordinate;
+    use crate::error::{Error, Violation};
+    use crate::model::estimate_rtt;
+    use crate::vector::Dimension2;
+    use std::time::Duration;
+
+    fn converged(policy: OutlierPolicy) -> (Model<Dimension2>, Coordinate<Dimension2>) {
+        let remote = Coordinate::new(Dimension2([0.05, 0.0]), 0.1, 0.001);
+        let mut m = ModelBuilder::new()
+            .outlier_rejection(5.0, policy)
+            .build::<Dimension2>();
+        for _ in 0..50 {
+            m.observe(&remote, Duration::from_millis(50));
+        }
+        (m, remote)
+    }
+
+    #[test]
+    fn discard() {
+        let (mut m, remote) = converged(OutlierPolicy::Discard);
+        assert_eq!(m.outlier_stats().discarded, 0);
+        let before = m.clone();
+
+        // A retransmitted probe measures several times the real RTT.
+        m.observe(&remote, Duration::from_millis(1000));
+        assert_eq!(m.get_coordinate(), before.get_coordinate());
+        assert_eq!(
+            m.outlier_stats(),
+            OutlierStats {
+                observed: 51,
+                discarded: 1,
+                down_weighted: 0,
+            }
+        );
+
+        // Accurate samples are unaffected.
+        let rtt = estimate_rtt(m.get_coordinate(), &remote);
+        m.observe(&remote, rtt);
+        assert_eq!(m.outlier_stats().discarded, 1);
+        assert_eq!(m.outlier_stats().observed, 52);
+    }
+
+    #[test]
+    fn down_weight() {
+        let (mut m, remote) = converged(OutlierPolicy::DownWeight(0.1));
+        let mut unfiltered = ModelBuilder::new().build::<Dimension2>();
+        unfiltered.set_coordinate(*m.get_coordinate());
+        let start = *m.get_coordinate().vector();
+
+        m.observe(&remote, Duration::from_millis(1000));
+        unfiltered.observe(&remote, Duration::from_millis(1000));
+        assert_eq!(m.outlier_stats().down_weighted, 1);
+
+        let moved = m.get_coordinate().vector().distance(&start).0;
+        let moved_unfiltered = unfiltered.get_coordinate().vector().distance(&start).0;
+        assert!(moved > 0.0);
+        assert!(moved < moved_unfiltered / 5.0);
+    }
+
+    #[test]
+    fn strict_rejection_not_counted() {
+        let (mut m, remote) = converged(OutlierPolicy::DownWeight(0.1));
+        m.set_strict(true);
+        let stats = m.outlier_stats();
+
+        // An outlying sample rejected in strict mode after being assessed.
+        m.set_coordinate(Coordinate::new(*remote.vector(), 0.1, 0.001));
+        assert_eq!(
+            m.try_observe(&remote, Duration::from_millis(1000)),
+            Err(Error::Strict(Violation::CoincidentCoordinates))
+        );
+        assert_eq!(m.outlier_stats(), stats);
+    }
+
+    #[test]
+    fn disabled() {
+        let mut m = Model::<Dimension2>::new();
+        let remote = Coordinate::new(Dimension2([0.05, 0.0]), 0.1, 0.001);
+        m.observe(&remote, Duration::from_millis(1000));
+        assert_eq!(m.outlier_stats(), OutlierStats::default());
+    }
+}
@@ -0,0 +1,183 @@
+use crate::clock::{Clock, SystemClock};
+use crate::coordinate::Coordinate;
+use crate::model::{estimate_metric, saturating_duration, Model, ERROR_LIMIT};
+use crate::vector::Vector;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::Duration;
+
+/// An experimental Vivaldi model learning a separate height to each peer,
+/// for multihomed nodes whose access links differ in cost.
+///
+/// The height of a [`Model`] models the cost of a single access link, paid
+/// on every path to and from the node. A node reaching some peers over a fast
+/// link and others over a slow one (such as a fibre uplink and a backup LTE
+/// link) can only be assigned the average cost, overestimating one set of
+/// peers and underestimating the other.
+///
+/// This model publishes the coordinate of an ordinary [`Model`], so peers
+/// need no changes, and additionally learns a correction to the local height
+/// for each peer from the residual error of its observations:
+///
+/// ```
+/// use std::time::Duration;
+/// use vivaldi::{PeerHeightModel, vector::Dimension3};
+///
+/// let mut model = PeerHeightModel::<&str, Dimension3>::new();
+/// # let remote = *model.get_coordinate();
+///
+/// model.observe("node-b", &remote, Duration::from_millis(40));
+///
+/// // Estimates to observed peers include the learned height.
+/// let rtt = model.estimate_rtt(&"node-b", &remote);
+/// ```
+///
+/// Estimates to peers that have never been observed use the shared height.
+#[derive(Debug, Clone)]
+pub struct PeerHeightModel<K, V, C = SystemClock>
+where
+    V: Vector + std::fmt::Debug,
+{
+    model: Model<V, C>,
+    /// The correction added to the shared height for each peer, in seconds.
+    corrections: HashMap<K, f64>,
+}
+
+impl<K, V> PeerHeightModel<K, V>
+where
+    K: Hash + Eq,
+    V: Vector + std::fmt::Debug,
+{
+    /// Initialises a model with no per-peer heights.
+    pub fn new() -> Self {
+        PeerHeightModel::with_clock(SystemClock)
+    }
+}
+
+impl<K, V> Default for PeerHeightModel<K, V>
+where
+    K: Hash + Eq,
+    V: Vector + std::fmt::Debug,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K, V, C> PeerHeightModel<K, V, C>
+where
+    K: Hash + Eq,
+    V: Vector + std::fmt::Debug,
+    C: Clock,
+{
+    /// Initialises a model reading the current time from `clock`.
+    pub fn with_clock(clock: C) -> Self {
+        PeerHeightModel {
+            model: Model::with_clock(clock),
+            corrections: HashMap::new(),
+        }
+    }
+
+    /// Updates the shared coordinate and the height to `peer` with the `rtt`
+    /// measured to it at `coord`.
+    pub fn observe(&mut self, peer: K, coord: &Coordinate<V>, rtt: Duration) {
+        self.model.observe(coord, rtt);
+
+        // Move the correction towards the residual the shared coordinate
+        // cannot explain.
+        let residual = rtt.as_secs_f64() - estimate_metric(self.model.get_coordinate(), coord);
+        let correction = self.corrections.entry(peer).or_insert(0.0);
+        *correction += ERROR_LIMIT * (residual - *correction);
+    }
+
+    /// Returns the coordinate to be sent to peers, with the shared height.
+    pub fn get_coordinate(&self) -> &Coordinate<V> {
+        self.model.get_coordinate()
+    }
+
+    /// Returns the local height on the path to `peer` - the shared height
+    /// plus the correction learned for `peer`, if observed.
+    pub fn height_to(&self, peer: &K) -> Duration {
+        let shared = self.model.get_coordinate().height();
+        saturating_duration(shared + self.corrections.get(peer).copied().unwrap_or(0.0))
+    }
+
+    /// Estimates the RTT to `peer` at `remote`, using the local height on the
+    /// path to `peer`.
+    pub fn estimate_rtt(&self, peer: &K, remote: &Coordinate<V>) -> Duration {
+        let local = self.model.get_coordinate();
+        let correction = self.corrections.get(peer).copied().unwrap_or(0.0);
+
+        // The corrected height never falls below zero.
+        let correction = correction.max(-local.height());
+        saturating_duration(estimate_metric(local, remote) + correction)
+    }
+
+    /// Discards the height learned for `peer`, such as when it leaves the
+    /// cluster.
+    pub fn forget(&mut self, peer: &K) {
+        self.corrections.remove(peer);
+    }
+
+    /// Returns the underlying model of the shared coordinate.
+    pub fn model(&self) -> &Model<V, C> {
+        &self.model
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::estimate_rtt;
+    use crate::vector::Dimension2;
+
+    #[test]
+    fn learns_asymmetric_access_links() {
+        // Peers reached over a fast link, and others over a slow one.
+        let peers = [
+            ("a", [0.0, 0.0], 0.005),
+            ("b", [0.05, 0.0], 0.005),
+            ("c", [0.0, 0.05], 0.040),
+            ("d", [0.05, 0.05], 0.040),
+        ];
+        let local = Dimension2([0.02, 0.03]);
+        let remotes = peers
+            .iter()
+            .map(|(_, v, _)| Coordinate::new(Dimension2(*v), 0.05, 0.002))
+            .collect::<Vec<_>>();
+        let truth = |i: usize| {
+            let (_, v, link) = peers[i];
+            Duration::from_secs_f64(local.distance(&Dimension2(v)).0 + link + 0.002)
+        };
+
+        let mut m = PeerHeightModel::<&str, Dimension2>::new();
+        for _ in 0..500 {
+            for (i, remote) in remotes.iter().enumerate() {
+                m.observe(peers[i].0, remote, truth(i));
+            }
+        }
+
+        let mut per_peer = 0.0;
+        let mut shared = 0.0;
+        for (i, remote) in remotes.iter().enumerate() {
+            let want = truth(i).as_secs_f64();
+            let got = m.estimate_rtt(&peers[i].0, remote).as_secs_f64();
+            per_peer += (got - want).abs() / want;
+            let got = estimate_rtt(m.get_coordinate(), remote).as_secs_f64();
+            shared += (got - want).abs() / want;
+        }
+        assert!(per_peer < 0.02, "per-peer error {}", per_peer);
+        assert!(per_peer < shared / 5.0, "{} vs {}", per_peer, shared);
+
+        assert_eq!(
+            m.height_to(&"unknown"),
+            Duration::from_secs_f64(m.get_coordinate().height())
+        );
+
+        m.forget(&"c");
+        assert_eq!(
+            m.estimate_rtt(&"c", &remotes[2]),
+            estimate_rtt(m.get_coordinate(), &remotes[2])
+        );
+    }
+}
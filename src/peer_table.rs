@@ -0,0 +1,1083 @@
+use crate::bulk::estimate_columns;
+use crate::checkpoint::fnv1a;
+use crate::clock::{Clock, SystemClock};
+use crate::coordinate::Coordinate;
+use crate::error::Error;
+use crate::model::{estimate_rtt, saturating_duration};
+use crate::vector::Vector;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::time::Duration;
+
+/// Identifies an encoded peer table.
+const TABLE_MAGIC: &[u8; 4] = b"VIVT";
+
+/// The version of the peer table encoding.
+const TABLE_VERSION: u8 = 2;
+
+/// A table of the last known coordinate of each peer, keyed by a caller
+/// defined peer identifier `K`.
+///
+/// Coordinates are stored in a struct-of-arrays layout - one contiguous array
+/// per vector dimension, plus contiguous error and height arrays - rather than
+/// as a collection of [`Coordinate`] values. Bulk operations over the whole
+/// table (such as [`estimate_all`](PeerTable::estimate_all)) walk contiguous
+/// memory, which is considerably more cache friendly than iterating a
+/// `HashMap<K, Coordinate<V>>` when the table holds many thousands of peers.
+///
+/// ```
+/// use vivaldi::{Model, PeerTable, vector::Dimension3};
+///
+/// let local = Model::<Dimension3>::new();
+/// let remote = Model::<Dimension3>::new();
+///
+/// let mut peers = PeerTable::new();
+/// peers.insert("remote", remote.get_coordinate());
+///
+/// for (peer, rtt) in peers.estimate_all(local.get_coordinate()) {
+///     println!("{}: {:?}", peer, rtt);
+/// }
+/// ```
+///
+/// Each row also records when the coordinate was last inserted (read from the
+/// table's [`Clock`]) and whether the peer is
+/// [quarantined](PeerTable::quarantine). The whole table, including this
+/// state, can be persisted with serde (with the `serde` feature) or the
+/// compact binary encoding of [`to_bytes`](PeerTable::to_bytes), so a
+/// restarting node restores its view of the mesh along with its own
+/// coordinate.
+#[derive(Debug, Clone)]
+pub struct PeerTable<K, V, C = SystemClock>
+where
+    K: Hash + Eq + Clone,
+    V: Vector,
+{
+    /// The peer identifier of each row.
+    ids: Vec<K>,
+    /// Maps a peer identifier to its row index.
+    index: HashMap<K, usize>,
+    /// One column per vector dimension, each holding a value for every row.
+    columns: Vec<Vec<f64>>,
+    errors: Vec<f64>,
+    heights: Vec<f64>,
+    adjustments: Vec<f64>,
+    /// The clock time each row was last inserted.
+    updated: Vec<Duration>,
+    quarantined: Vec<bool>,
+    clock: C,
+    _vector: PhantomData<V>,
+}
+
+impl<K, V> Default for PeerTable<K, V>
+where
+    K: Hash + Eq + Clone,
+    V: Vector,
+{
+    fn default() -> Self {
+        PeerTable::with_clock(SystemClock)
+    }
+}
+
+impl<K, V> PeerTable<K, V>
+where
+    K: Hash + Eq + Clone,
+    V: Vector,
+{
+    /// Initialises an empty table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<K, V, C> PeerTable<K, V, C>
+where
+    K: Hash + Eq + Clone,
+    V: Vector,
+    C: Clock,
+{
+    /// Initialises an empty table reading insertion times from `clock`.
+    pub fn with_clock(clock: C) -> Self {
+        PeerTable {
+            ids: Vec::new(),
+            index: HashMap::new(),
+            columns: Vec::new(),
+            errors: Vec::new(),
+            heights: Vec::new(),
+            adjustments: Vec::new(),
+            updated: Vec::new(),
+            quarantined: Vec::new(),
+            clock,
+            _vector: PhantomData,
+        }
+    }
+
+    /// Returns the number of peers in the table.
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    /// Returns true if the table contains no peers.
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    /// Returns true if the table contains a coordinate for `peer`.
+    pub fn contains(&self, peer: &K) -> bool {
+        self.index.contains_key(peer)
+    }
+
+    /// Inserts or replaces the coordinate of `peer`, returning the previous
+    /// coordinate if one was present.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the dimensionality of `coord` differs from the coordinates
+    /// already in the table, which cannot happen for fixed size vector types
+    /// such as [`Dimension3`](crate::vector::Dimension3). Use
+    /// [`try_insert`](PeerTable::try_insert) for variable sized vector types.
+    pub fn insert(&mut self, peer: K, coord: &Coordinate<V>) -> Option<Coordinate<V>> {
+        match self.try_insert(peer, coord) {
+            Ok(v) => v,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Inserts or replaces the coordinate of `peer`, returning the previous
+    /// coordinate if one was present, or [`Error::DimensionMismatch`] if the
+    /// dimensionality of `coord` differs from the coordinates already in the
+    /// table.
+    ///
+    /// Replacing a coordinate does not change the quarantine state of the
+    /// peer.
+    pub fn try_insert(
+        &mut self,
+        peer: K,
+        coord: &Coordinate<V>,
+    ) -> Result<Option<Coordinate<V>>, Error> {
+        let now = self.clock.now();
+        self.insert_row(peer, coord, now, false)
+    }
+
+    fn insert_row(
+        &mut self,
+        peer: K,
+        coord: &Coordinate<V>,
+        updated: Duration,
+        quarantined: bool,
+    ) -> Result<Option<Coordinate<V>>, Error> {
+        let components = coord.vector().components();
+        if self.columns.is_empty() {
+            self.columns = vec![Vec::new(); components.len()];
+        }
+        if components.len() != self.columns.len() {
+            return Err(Error::DimensionMismatch {
+                expected: self.columns.len(),
+                got: components.len(),
+            });
+        }
+
+        if let Some(&row) = self.index.get(&peer) {
+            let old = self.row(row);
+            for (column, v) in self.columns.iter_mut().zip(components) {
+                column[row] = *v;
+            }
+            self.errors[row] = coord.error();
+            self.heights[row] = coord.height();
+            self.adjustments[row] = coord.adjustment();
+            self.updated[row] = updated;
+            return Ok(Some(old));
+        }
+
+        self.index.insert(peer.clone(), self.ids.len());
+        self.ids.push(peer);
+        for (column, v) in self.columns.iter_mut().zip(components) {
+            column.push(*v);
+        }
+        self.errors.push(coord.error());
+        self.heights.push(coord.height());
+        self.adjustments.push(coord.adjustment());
+        self.updated.push(updated);
+        self.quarantined.push(quarantined);
+
+        Ok(None)
+    }
+
+    /// Returns the coordinate of `peer`, if known.
+    pub fn get(&self, peer: &K) -> Option<Coordinate<V>> {
+        self.index.get(peer).map(|&row| self.row(row))
+    }
+
+    /// Returns the clock time at which the coordinate of `peer` was last
+    /// inserted, if known.
+    pub fn updated(&self, peer: &K) -> Option<Duration> {
+        self.index.get(peer).map(|&row| self.updated[row])
+    }
+
+    /// Marks `peer` as quarantined, returning false if it is not in the table.
+    ///
+    /// Quarantined peers (for example, peers reporting implausible
+    /// coordinates) remain in the table and continue to be updated, but are
+    /// excluded from [`nearest`](PeerTable::nearest).
+    pub fn quarantine(&mut self, peer: &K) -> bool {
+        self.set_quarantined(peer, true)
+    }
+
+    /// Clears the quarantine of `peer`, returning false if it is not in the
+    /// table.
+    pub fn release(&mut self, peer: &K) -> bool {
+        self.set_quarantined(peer, false)
+    }
+
+    /// Returns true if `peer` is in the table and quarantined.
+    pub fn is_quarantined(&self, peer: &K) -> bool {
+        self.index
+            .get(peer)
+            .is_some_and(|&row| self.quarantined[row])
+    }
+
+    fn set_quarantined(&mut self, peer: &K, v: bool) -> bool {
+        match self.index.get(peer) {
+            Some(&row) => {
+                self.quarantined[row] = v;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Removes `peer` from the table, returning its coordinate if it was
+    /// present.
+    ///
+    /// The last row is moved into the vacated slot, so removal is `O(d)` for
+    /// `d` dimensions but does not preserve iteration order.
+    pub fn remove(&mut self, peer: &K) -> Option<Coordinate<V>> {
+        let row = self.index.remove(peer)?;
+        let old = self.row(row);
+
+        self.ids.swap_remove(row);
+        for column in self.columns.iter_mut() {
+            column.swap_remove(row);
+        }
+        self.errors.swap_remove(row);
+        self.heights.swap_remove(row);
+        self.adjustments.swap_remove(row);
+        self.updated.swap_remove(row);
+        self.quarantined.swap_remove(row);
+
+        // Fix up the index of the row that was moved into the gap, if any.
+        if let Some(moved) = self.ids.get(row) {
+            self.index.insert(moved.clone(), row);
+        }
+
+        Some(old)
+    }
+
+    /// Merges the coordinates of `other` into this table, returning the
+    /// number of peers inserted or replaced.
+    ///
+    /// For each peer, the most recently [updated](PeerTable::updated)
+    /// coordinate wins, keeping its timestamp. Coordinates updated at the same
+    /// time are ordered by their error estimate (lowest wins), and then by
+    /// their values, so the result never depends on which table is merged
+    /// into which. Nodes exchanging and merging tables in any order therefore
+    /// converge on the same view of the mesh:
+    ///
+    /// ```
+    /// use vivaldi::{Model, PeerTable, vector::Dimension3};
+    ///
+    /// # let remote = Model::<Dimension3>::new();
+    /// let mut local = PeerTable::new();
+    /// let mut received = PeerTable::new();
+    /// received.insert("remote", remote.get_coordinate());
+    ///
+    /// local.merge(&received)?;
+    /// assert!(local.contains(&"remote"));
+    /// # Ok::<(), vivaldi::Error>(())
+    /// ```
+    ///
+    /// Timestamps are compared directly, so the tables should be populated by
+    /// loosely synchronised clocks such as the [`SystemClock`]. Quarantine
+    /// state is a local decision and is not merged - merged peers keep their
+    /// existing quarantine state, and newly merged peers are not quarantined.
+    ///
+    /// Returns [`Error::DimensionMismatch`] (leaving this table unchanged) if
+    /// the dimensionality of the two tables differs.
+    pub fn merge<OC>(&mut self, other: &PeerTable<K, V, OC>) -> Result<usize, Error>
+    where
+        OC: Clock,
+    {
+        if !self.columns.is_empty()
+            && !other.columns.is_empty()
+            && self.columns.len() != other.columns.len()
+        {
+            return Err(Error::DimensionMismatch {
+                expected: self.columns.len(),
+                got: other.columns.len(),
+            });
+        }
+
+        let mut changed = 0;
+        for (row, peer) in other.ids.iter().enumerate() {
+            let theirs = (other.updated[row], other.row(row));
+            let newer = match self.index.get(peer) {
+                Some(&mine) => merge_order(&theirs, &(self.updated[mine], self.row(mine))).is_gt(),
+                None => true,
+            };
+            if newer {
+                self.insert_row(peer.clone(), &theirs.1, theirs.0, false)?;
+                changed += 1;
+            }
+        }
+        Ok(changed)
+    }
+
+    /// Returns an iterator over the peers and their coordinates, including
+    /// quarantined peers.
+    pub fn iter(&self) -> impl Iterator<Item = (&K, Coordinate<V>)> + '_ {
+        self.ids
+            .iter()
+            .enumerate()
+            .map(move |(row, id)| (id, self.row(row)))
+    }
+
+    /// Returns the estimated RTT between `local` and every peer in the table,
+    /// including quarantined peers.
+    pub fn estimate_all(&self, local: &Coordinate<V>) -> impl Iterator<Item = (&K, Duration)> {
+        let mut out = vec![0.0; self.len()];
+        estimate_columns(
+            local,
+            &self.columns,
+            &self.heights,
+            &self.adjustments,
+            &mut out,
+        );
+
+        self.ids
+            .iter()
+            .zip(out.into_iter().map(saturating_duration))
+    }
+
+    /// Returns up to `n` peers with the lowest estimated RTT from `local`,
+    /// ordered nearest first.
+    ///
+    /// Quarantined peers are never returned.
+    pub fn nearest(&self, local: &Coordinate<V>, n: usize) -> Vec<(&K, Duration)> {
+        let mut all = self
+            .estimate_all(local)
+            .zip(&self.quarantined)
+            .filter(|(_, q)| !**q)
+            .map(|(v, _)| v)
+            .collect::<Vec<_>>();
+        all.sort_by_key(|(_, rtt)| *rtt);
+        all.truncate(n);
+        all
+    }
+
+    /// Returns the fraction (0 to 1) of the other peers in the table with a
+    /// higher estimated RTT from `local` than the node with coordinate
+    /// `coord`.
+    ///
+    /// This ranks a peer relative to the rest of the mesh rather than by an
+    /// absolute latency, allowing adaptive thresholds such as "only use peers
+    /// faster than 90% of the others":
+    ///
+    /// ```
+    /// use vivaldi::{Model, PeerTable, vector::Dimension3};
+    ///
+    /// let local = Model::<Dimension3>::new();
+    /// # let remote = Model::<Dimension3>::new();
+    /// let mut peers = PeerTable::new();
+    /// peers.insert("remote", remote.get_coordinate());
+    ///
+    /// let candidate = peers.get(&"remote").unwrap();
+    /// if let Some(rank) = peers.percentile_rank(local.get_coordinate(), &candidate) {
+    ///     println!("faster than {:.0}% of peers", rank * 100.0);
+    /// }
+    /// ```
+    ///
+    /// `coord` does not need to be in the table - if it is, the entry holding
+    /// it is not ranked against itself. Quarantined peers are not ranked
+    /// against, and `None` is returned if there are no other peers to rank
+    /// against.
+    pub fn percentile_rank(&self, local: &Coordinate<V>, coord: &Coordinate<V>) -> Option<f64> {
+        let rtt = estimate_rtt(local, coord);
+
+        let (mut slower, mut total) = (0_usize, 0_usize);
+        let mut ranked = false;
+        for (row, ((_, other), q)) in self.estimate_all(local).zip(&self.quarantined).enumerate() {
+            if *q {
+                continue;
+            }
+            if !ranked && other == rtt && self.row_eq(row, coord) {
+                ranked = true;
+                continue;
+            }
+            total += 1;
+            if other > rtt {
+                slower += 1;
+            }
+        }
+
+        if total == 0 {
+            return None;
+        }
+        Some(slower as f64 / total as f64)
+    }
+
+    pub(crate) fn clock(&self) -> &C {
+        &self.clock
+    }
+
+    /// Returns true if `row` holds exactly `coord`.
+    fn row_eq(&self, row: usize, coord: &Coordinate<V>) -> bool {
+        self.columns
+            .iter()
+            .zip(coord.vector().components())
+            .all(|(c, v)| c[row] == *v)
+            && self.errors[row] == coord.error()
+            && self.heights[row] == coord.height()
+            && self.adjustments[row] == coord.adjustment()
+    }
+
+    /// Reconstructs the coordinate stored in `row`.
+    fn row(&self, row: usize) -> Coordinate<V> {
+        let components = self.columns.iter().map(|c| c[row]).collect::<Vec<_>>();
+        let vector =
+            V::from_components(&components).expect("table dimensionality matches the vector type");
+
+        Coordinate::new(vector, self.errors[row], self.heights[row])
+            .with_adjustment(self.adjustments[row])
+    }
+}
+
+/// Orders two timestamped coordinates of the same peer for
+/// [`PeerTable::merge`], where the greater wins.
+///
+/// This is a total order, so merging is commutative even for coordinates
+/// sharing a timestamp.
+fn merge_order<V: Vector>(
+    a: &(Duration, Coordinate<V>),
+    b: &(Duration, Coordinate<V>),
+) -> std::cmp::Ordering {
+    let values = |c: &Coordinate<V>| {
+        let mut v = c.vector().components().to_vec();
+        v.push(c.height());
+        v.push(c.adjustment());
+        v
+    };
+
+    a.0.cmp(&b.0)
+        .then_with(|| b.1.error().total_cmp(&a.1.error()))
+        .then_with(|| {
+            values(&a.1)
+                .iter()
+                .zip(&values(&b.1))
+                .map(|(x, y)| x.total_cmp(y))
+                .find(|o| o.is_ne())
+                .unwrap_or(std::cmp::Ordering::Equal)
+        })
+}
+
+/// A peer identifier that can be encoded by [`PeerTable::to_bytes`].
+pub trait PeerKey: Sized {
+    /// Appends the encoded key to `out`.
+    fn encode(&self, out: &mut Vec<u8>);
+
+    /// Decodes a key previously encoded by [`encode`](PeerKey::encode),
+    /// returning `None` if `data` is not a valid key.
+    fn decode(data: &[u8]) -> Option<Self>;
+}
+
+impl PeerKey for String {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self.as_bytes())
+    }
+
+    fn decode(data: &[u8]) -> Option<Self> {
+        String::from_utf8(data.to_vec()).ok()
+    }
+}
+
+impl PeerKey for Vec<u8> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self)
+    }
+
+    fn decode(data: &[u8]) -> Option<Self> {
+        Some(data.to_vec())
+    }
+}
+
+macro_rules! peer_key_int {
+    ($($t:ty),*) => {
+        $(
+            impl PeerKey for $t {
+                fn encode(&self, out: &mut Vec<u8>) {
+                    out.extend_from_slice(&self.to_le_bytes())
+                }
+
+                fn decode(data: &[u8]) -> Option<Self> {
+                    <[u8; std::mem::size_of::<$t>()]>::try_from(data)
+                        .ok()
+                        .map(<$t>::from_le_bytes)
+                }
+            }
+        )*
+    };
+}
+
+peer_key_int!(u16, u32, u64, u128);
+
+impl<K, V, C> PeerTable<K, V, C>
+where
+    K: Hash + Eq + Clone + PeerKey,
+    V: Vector,
+    C: Clock,
+{
+    /// Encodes the table, including insertion times and quarantine state, in
+    /// a compact binary format:
+    ///
+    /// ```text
+    ///     magic (4) | version (1) | dimensions (1) | rows (4) |
+    ///     rows × [ key length (2) | key | components (8 each) | error (8) |
+    ///              height (8) | adjustment (8) | updated nanoseconds (8) |
+    ///              quarantined (1) ] |
+    ///     FNV-1a checksum of the preceding bytes (8)
+    /// ```
+    ///
+    /// with all values little-endian.
+    ///
+    /// ```
+    /// use vivaldi::{Model, PeerTable, vector::Dimension3};
+    ///
+    /// let remote = Model::<Dimension3>::new();
+    ///
+    /// let mut peers = PeerTable::new();
+    /// peers.insert("10.0.0.1:4242".to_string(), remote.get_coordinate());
+    ///
+    /// let data = peers.to_bytes();
+    /// let restored = PeerTable::<String, Dimension3>::from_bytes(&data)?;
+    /// assert_eq!(restored.len(), 1);
+    /// # Ok::<(), vivaldi::Error>(())
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if an encoded key is longer than 65535 bytes.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(TABLE_MAGIC);
+        buf.push(TABLE_VERSION);
+        buf.push(self.columns.len() as u8);
+        buf.extend_from_slice(&(self.len() as u32).to_le_bytes());
+
+        let mut key = Vec::new();
+        for (row, id) in self.ids.iter().enumerate() {
+            key.clear();
+            id.encode(&mut key);
+            let len = u16::try_from(key.len()).expect("peer key too long to encode");
+            buf.extend_from_slice(&len.to_le_bytes());
+            buf.extend_from_slice(&key);
+
+            for column in &self.columns {
+                buf.extend_from_slice(&column[row].to_le_bytes());
+            }
+            buf.extend_from_slice(&self.errors[row].to_le_bytes());
+            buf.extend_from_slice(&self.heights[row].to_le_bytes());
+            buf.extend_from_slice(&self.adjustments[row].to_le_bytes());
+            buf.extend_from_slice(&(self.updated[row].as_nanos() as u64).to_le_bytes());
+            buf.push(self.quarantined[row] as u8);
+        }
+
+        let sum = fnv1a(&buf);
+        buf.extend_from_slice(&sum.to_le_bytes());
+        buf
+    }
+}
+
+impl<K, V, C> PeerTable<K, V, C>
+where
+    K: Hash + Eq + Clone + PeerKey,
+    V: Vector,
+    C: Clock + Default,
+{
+    /// Decodes a table encoded by [`to_bytes`](PeerTable::to_bytes).
+    ///
+    /// Tables encoded before coordinates carried an
+    /// [adjustment](Coordinate::adjustment) (version 1) are also accepted,
+    /// with every adjustment zero.
+    ///
+    /// Returns [`Error::Serialization`] if `data` is corrupt,
+    /// [`Error::DimensionMismatch`] if it holds coordinates of a different
+    /// dimensionality than `V`, or an error if any coordinate is invalid as
+    /// described by [`Coordinate::try_new`] - a single invalid row rejects the
+    /// whole table.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, Error> {
+        let corrupt = |msg: &str| Error::Serialization(format!("invalid peer table: {}", msg));
+
+        if data.len() < 10 + 8 {
+            return Err(corrupt("truncated"));
+        }
+        let (body, sum) = data.split_at(data.len() - 8);
+        if fnv1a(body).to_le_bytes() != sum {
+            return Err(corrupt("checksum mismatch"));
+        }
+        if &body[..4] != TABLE_MAGIC {
+            return Err(corrupt("bad magic"));
+        }
+        let version = body[4];
+        if !(1..=TABLE_VERSION).contains(&version) {
+            return Err(corrupt("unsupported version"));
+        }
+
+        let dims = body[5] as usize;
+        let mut r = Reader(&body[6..]);
+        let rows = r.u32().ok_or_else(|| corrupt("truncated"))?;
+
+        let mut table = PeerTable::with_clock(C::default());
+        let mut components = vec![0.0; dims];
+        for _ in 0..rows {
+            let len = r.u16().ok_or_else(|| corrupt("truncated"))?;
+            let key = r.take(len as usize).ok_or_else(|| corrupt("truncated"))?;
+            let key = K::decode(key).ok_or_else(|| corrupt("invalid key"))?;
+
+            for v in components.iter_mut() {
+                *v = r.f64().ok_or_else(|| corrupt("truncated"))?;
+            }
+            let error = r.f64().ok_or_else(|| corrupt("truncated"))?;
+            let height = r.f64().ok_or_else(|| corrupt("truncated"))?;
+            let adjustment = match version {
+                1 => 0.0,
+                _ => r.f64().ok_or_else(|| corrupt("truncated"))?,
+            };
+            let updated = r.u64().ok_or_else(|| corrupt("truncated"))?;
+            let quarantined = r.take(1).ok_or_else(|| corrupt("truncated"))?[0] != 0;
+
+            let vector = V::from_components(&components).ok_or(Error::DimensionMismatch {
+                expected: V::default().components().len(),
+                got: dims,
+            })?;
+            let coord = Coordinate::new(vector, error, height).with_adjustment(adjustment);
+            coord.validate()?;
+            table.insert_row(key, &coord, Duration::from_nanos(updated), quarantined)?;
+        }
+
+        if !r.0.is_empty() {
+            return Err(corrupt("trailing data"));
+        }
+        Ok(table)
+    }
+}
+
+/// A cursor over little-endian encoded values.
+pub(crate) struct Reader<'a>(pub(crate) &'a [u8]);
+
+impl<'a> Reader<'a> {
+    pub(crate) fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        if self.0.len() < n {
+            return None;
+        }
+        let (v, rest) = self.0.split_at(n);
+        self.0 = rest;
+        Some(v)
+    }
+
+    pub(crate) fn u16(&mut self) -> Option<u16> {
+        self.take(2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+    }
+
+    pub(crate) fn u32(&mut self) -> Option<u32> {
+        let b = self.take(4)?;
+        Some(u32::from_le_bytes(<[u8; 4]>::try_from(b).ok()?))
+    }
+
+    pub(crate) fn u64(&mut self) -> Option<u64> {
+        let b = self.take(8)?;
+        Some(u64::from_le_bytes(<[u8; 8]>::try_from(b).ok()?))
+    }
+
+    pub(crate) fn f64(&mut self) -> Option<f64> {
+        self.u64().map(f64::from_bits)
+    }
+
+    /// Reads an unsigned LEB128 varint.
+    pub(crate) fn varint(&mut self) -> Option<u64> {
+        let mut v = 0u64;
+        for shift in (0..64).step_by(7) {
+            let b = self.take(1)?[0];
+            v |= ((b & 0x7f) as u64) << shift;
+            if b & 0x80 == 0 {
+                return Some(v);
+            }
+        }
+        None
+    }
+}
+
+/// The serialised form of a [`PeerTable`] row.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct RawPeer<K> {
+    id: K,
+    vector: Vec<f64>,
+    error: f64,
+    height: f64,
+    #[serde(default)]
+    adjustment: f64,
+    updated: Duration,
+    quarantined: bool,
+}
+
+#[cfg(feature = "serde")]
+impl<K, V, C> serde::Serialize for PeerTable<K, V, C>
+where
+    K: Hash + Eq + Clone + serde::Serialize,
+    V: Vector,
+    C: Clock,
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeSeq;
+
+        let mut seq = serializer.serialize_seq(Some(self.len()))?;
+        for (row, id) in self.ids.iter().enumerate() {
+            seq.serialize_element(&RawPeer {
+                id,
+                vector: self.columns.iter().map(|c| c[row]).collect(),
+                error: self.errors[row],
+                height: self.heights[row],
+                adjustment: self.adjustments[row],
+                updated: self.updated[row],
+                quarantined: self.quarantined[row],
+            })?;
+        }
+        seq.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, K, V, C> serde::Deserialize<'de> for PeerTable<K, V, C>
+where
+    K: Hash + Eq + Clone + serde::Deserialize<'de>,
+    V: Vector,
+    C: Clock + Default,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error as _;
+
+        let rows = Vec::<RawPeer<K>>::deserialize(deserializer)?;
+        let mut table = PeerTable::with_clock(C::default());
+        for row in rows {
+            let vector = V::from_components(&row.vector).ok_or_else(|| {
+                D::Error::custom(Error::DimensionMismatch {
+                    expected: V::default().components().len(),
+                    got: row.vector.len(),
+                })
+            })?;
+            let coord =
+                Coordinate::new(vector, row.error, row.height).with_adjustment(row.adjustment);
+            coord.validate().map_err(D::Error::custom)?;
+            table
+                .insert_row(row.id, &coord, row.updated, row.quarantined)
+                .map_err(D::Error::custom)?;
+        }
+        Ok(table)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use crate::model::estimate_rtt;
+    use crate::vector::{Dimension2, Dimension3};
+
+    fn coord(v: [f64; 3]) -> Coordinate<Dimension3> {
+        Coordinate::new(Dimension3(v), 1.0, 0.1)
+    }
+
+    #[test]
+    fn insert_get_replace() {
+        let mut t = PeerTable::new();
+        assert!(t.is_empty());
+
+        assert!(t.insert("a", &coord([1.0, 2.0, 3.0])).is_none());
+        assert_eq!(t.len(), 1);
+        assert_eq!(t.get(&"a").unwrap().vector(), &Dimension3([1.0, 2.0, 3.0]));
+
+        let old = t.insert("a", &coord([4.0, 5.0, 6.0])).unwrap();
+        assert_eq!(old.vector(), &Dimension3([1.0, 2.0, 3.0]));
+        assert_eq!(t.len(), 1);
+        assert_eq!(t.get(&"a").unwrap().vector(), &Dimension3([4.0, 5.0, 6.0]));
+        assert!(t.get(&"b").is_none());
+    }
+
+    #[test]
+    fn remove_reindexes() {
+        let mut t = PeerTable::new();
+        t.insert("a", &coord([1.0, 0.0, 0.0]));
+        t.insert("b", &coord([2.0, 0.0, 0.0]));
+        t.insert("c", &coord([3.0, 0.0, 0.0]));
+        t.quarantine(&"c");
+
+        let removed = t.remove(&"a").unwrap();
+        assert_eq!(removed.vector(), &Dimension3([1.0, 0.0, 0.0]));
+        assert!(t.remove(&"a").is_none());
+
+        assert_eq!(t.len(), 2);
+        assert_eq!(t.get(&"b").unwrap().vector(), &Dimension3([2.0, 0.0, 0.0]));
+        assert_eq!(t.get(&"c").unwrap().vector(), &Dimension3([3.0, 0.0, 0.0]));
+        assert!(!t.is_quarantined(&"b"));
+        assert!(t.is_quarantined(&"c"));
+    }
+
+    #[test]
+    fn estimate_all_and_nearest() {
+        let local = coord([0.0, 0.0, 0.0]);
+        let mut t = PeerTable::new();
+        t.insert("far", &coord([10.0, 0.0, 0.0]));
+        t.insert("near", &coord([1.0, 0.0, 0.0]));
+        t.insert("mid", &coord([5.0, 0.0, 0.0]));
+
+        for (peer, rtt) in t.estimate_all(&local) {
+            assert_eq!(rtt, estimate_rtt(&local, &t.get(peer).unwrap()));
+        }
+
+        let nearest = t.nearest(&local, 2);
+        assert_eq!(
+            nearest.iter().map(|(p, _)| **p).collect::<Vec<_>>(),
+            vec!["near", "mid"]
+        );
+
+        assert!(t.quarantine(&"near"));
+        assert!(!t.quarantine(&"missing"));
+        let nearest = t.nearest(&local, 2);
+        assert_eq!(
+            nearest.iter().map(|(p, _)| **p).collect::<Vec<_>>(),
+            vec!["mid", "far"]
+        );
+
+        assert!(t.release(&"near"));
+        assert_eq!(*t.nearest(&local, 1)[0].0, "near");
+    }
+
+    #[test]
+    fn percentile_rank() {
+        let local = coord([0.0, 0.0, 0.0]);
+        let mut t = PeerTable::new();
+        assert_eq!(t.percentile_rank(&local, &coord([1.0, 0.0, 0.0])), None);
+
+        for i in 1..=10 {
+            t.insert(i, &coord([i as f64, 0.0, 0.0]));
+        }
+
+        // A peer in the table is not ranked against itself.
+        let rank = |t: &PeerTable<_, _>, x| t.percentile_rank(&local, &coord([x, 0.0, 0.0]));
+        assert_eq!(rank(&t, 0.5), Some(1.0));
+        assert_eq!(rank(&t, 2.0), Some(8.0 / 9.0));
+        assert_eq!(rank(&t, 2.5), Some(0.8));
+        assert_eq!(rank(&t, 10.0), Some(0.0));
+
+        t.quarantine(&1);
+        assert_eq!(rank(&t, 2.0), Some(1.0));
+    }
+
+    #[test]
+    fn percentile_rank_one_peer() {
+        let local = coord([0.0, 0.0, 0.0]);
+        let mut t = PeerTable::new();
+        t.insert("only", &coord([1.0, 0.0, 0.0]));
+
+        let only = t.get(&"only").unwrap();
+        assert_eq!(t.percentile_rank(&local, &only), None);
+        assert_eq!(
+            t.percentile_rank(&local, &coord([2.0, 0.0, 0.0])),
+            Some(0.0)
+        );
+
+        // Another peer at the same coordinate is ranked against.
+        t.insert("same", &only);
+        assert_eq!(t.percentile_rank(&local, &only), Some(0.0));
+    }
+
+    #[test]
+    fn timestamps() {
+        let clock = MockClock::new(Duration::from_secs(10));
+        let mut t = PeerTable::with_clock(clock.clone());
+
+        t.insert("a", &coord([1.0, 0.0, 0.0]));
+        clock.advance(Duration::from_secs(5));
+        t.insert("b", &coord([1.0, 0.0, 0.0]));
+        assert_eq!(t.updated(&"a"), Some(Duration::from_secs(10)));
+        assert_eq!(t.updated(&"b"), Some(Duration::from_secs(15)));
+
+        t.quarantine(&"a");
+        clock.advance(Duration::from_secs(5));
+        t.insert("a", &coord([2.0, 0.0, 0.0]));
+        assert_eq!(t.updated(&"a"), Some(Duration::from_secs(20)));
+        assert!(t.is_quarantined(&"a"));
+        assert_eq!(t.updated(&"c"), None);
+    }
+
+    #[test]
+    fn merge() {
+        let clock = MockClock::new(Duration::from_secs(10));
+        let mut a = PeerTable::with_clock(clock.clone());
+        let mut b = PeerTable::with_clock(clock.clone());
+
+        a.insert("old", &coord([1.0, 0.0, 0.0]));
+        b.insert(
+            "tie",
+            &Coordinate::new(Dimension3([5.0, 0.0, 0.0]), 0.5, 0.1),
+        );
+        a.insert(
+            "tie",
+            &Coordinate::new(Dimension3([6.0, 0.0, 0.0]), 0.7, 0.1),
+        );
+        a.insert("same", &coord([7.0, 0.0, 0.0]));
+        b.insert("same", &coord([8.0, 0.0, 0.0]));
+        a.insert("adjusted", &coord([4.0, 0.0, 0.0]).with_adjustment(0.001));
+        b.insert("adjusted", &coord([4.0, 0.0, 0.0]).with_adjustment(0.003));
+        a.insert("only-a", &coord([2.0, 0.0, 0.0]));
+        a.quarantine(&"old");
+        clock.advance(Duration::from_secs(1));
+        b.insert("old", &coord([3.0, 0.0, 0.0]));
+
+        let (mut ab, mut ba) = (a.clone(), b.clone());
+        assert_eq!(ab.merge(&b), Ok(4));
+        assert_eq!(ba.merge(&a), Ok(1));
+
+        for t in [&ab, &ba] {
+            assert_eq!(t.len(), 5);
+            assert_eq!(
+                t.get(&"old").unwrap().vector(),
+                &Dimension3([3.0, 0.0, 0.0])
+            );
+            assert_eq!(t.updated(&"old"), Some(Duration::from_secs(11)));
+            assert_eq!(t.get(&"tie").unwrap().error(), 0.5);
+            assert_eq!(
+                t.get(&"same").unwrap().vector(),
+                &Dimension3([8.0, 0.0, 0.0])
+            );
+            assert_eq!(t.get(&"adjusted").unwrap().adjustment(), 0.003);
+        }
+        assert!(ab.is_quarantined(&"old"));
+        assert!(!ba.is_quarantined(&"old"));
+
+        // Merging is idempotent.
+        assert_eq!(ab.merge(&ba), Ok(0));
+    }
+
+    fn populated() -> PeerTable<u64, Dimension3, MockClock> {
+        let clock = MockClock::new(Duration::from_secs(10));
+        let mut t = PeerTable::with_clock(clock.clone());
+        t.insert(1, &coord([1.0, 2.0, 3.0]));
+        clock.advance(Duration::from_millis(1500));
+        t.insert(2, &coord([-1.0, 0.5, 0.0]).with_adjustment(0.002));
+        t.quarantine(&2);
+        t
+    }
+
+    fn assert_same<C: Clock>(a: &PeerTable<u64, Dimension3, C>, b: &PeerTable<u64, Dimension3>) {
+        assert_eq!(a.len(), b.len());
+        for (id, c) in a.iter() {
+            assert_eq!(b.get(id), Some(c));
+            assert_eq!(b.updated(id), a.updated(id));
+            assert_eq!(b.is_quarantined(id), a.is_quarantined(id));
+        }
+    }
+
+    #[test]
+    fn bytes_round_trip() {
+        let t = populated();
+        let data = t.to_bytes();
+
+        let got = PeerTable::<u64, Dimension3>::from_bytes(&data).unwrap();
+        assert_same(&t, &got);
+
+        let empty = PeerTable::<String, Dimension3>::new();
+        let got = PeerTable::<String, Dimension3>::from_bytes(&empty.to_bytes()).unwrap();
+        assert!(got.is_empty());
+    }
+
+    #[test]
+    fn bytes_version_1() {
+        let mut t = PeerTable::<u64, Dimension3>::new();
+        t.insert(1, &coord([1.0, 2.0, 3.0]));
+
+        // Re-encode without the adjustment, which follows the height.
+        let data = t.to_bytes();
+        let adjustment = 10 + 2 + 8 + 8 * 3 + 8 + 8;
+        let mut v1 = data[..adjustment].to_vec();
+        v1.extend_from_slice(&data[adjustment + 8..data.len() - 8]);
+        v1[4] = 1;
+        let sum = fnv1a(&v1);
+        v1.extend_from_slice(&sum.to_le_bytes());
+
+        let got = PeerTable::<u64, Dimension3>::from_bytes(&v1).unwrap();
+        assert_eq!(got.get(&1), t.get(&1));
+    }
+
+    #[test]
+    fn bytes_corrupt() {
+        let mut data = populated().to_bytes();
+        assert!(matches!(
+            PeerTable::<u64, Dimension2>::from_bytes(&data),
+            Err(Error::DimensionMismatch {
+                expected: 2,
+                got: 3
+            })
+        ));
+
+        data[12] ^= 0x01;
+        assert!(matches!(
+            PeerTable::<u64, Dimension3>::from_bytes(&data),
+            Err(Error::Serialization(_))
+        ));
+        assert!(matches!(
+            PeerTable::<u64, Dimension3>::from_bytes(&data[..4]),
+            Err(Error::Serialization(_))
+        ));
+    }
+
+    #[test]
+    fn bytes_invalid_coordinate() {
+        for (bad, want) in [
+            (
+                Coordinate::new(Dimension3([f64::NAN, 0.0, 0.0]), 1.0, 0.1),
+                Error::NonFiniteCoordinate,
+            ),
+            (
+                Coordinate::new(Dimension3::default(), 1.0, 1e9),
+                Error::OutOfRange { field: "height" },
+            ),
+        ] {
+            let mut t = populated();
+            t.insert(3, &bad);
+            assert_eq!(
+                PeerTable::<u64, Dimension3>::from_bytes(&t.to_bytes()).err(),
+                Some(want)
+            );
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        let t = populated();
+        let json = serde_json::to_string(&t).unwrap();
+
+        let got: PeerTable<u64, Dimension3> = serde_json::from_str(&json).unwrap();
+        assert_same(&t, &got);
+
+        let err = serde_json::from_str::<PeerTable<u64, Dimension2>>(&json);
+        assert!(err.is_err());
+
+        let mut t = populated();
+        t.insert(3, &Coordinate::new(Dimension3::default(), -1.0, 0.1));
+        let json = serde_json::to_string(&t).unwrap();
+        let err = serde_json::from_str::<PeerTable<u64, Dimension3>>(&json);
+        assert!(err.is_err());
+    }
+}
@@ -0,0 +1,16 @@
+//! A convenience module re-exporting the commonly used types.
+//!
+//! ```
+//! use vivaldi::prelude::*;
+//!
+//! let model = Model::<Dimension3>::new();
+//! let serf = ModelBuilder::serf().build::<DimensionN<8>>();
+//! ```
+
+pub use crate::builder::ModelBuilder;
+pub use crate::coordinate::Coordinate;
+pub use crate::error::Error;
+pub use crate::estimator::LatencyEstimator;
+pub use crate::model::{estimate_rtt, Model};
+pub use crate::peer_table::PeerTable;
+pub use crate::vector::{Dimension1, Dimension2, Dimension3, DimensionN, Vector};
@@ -0,0 +1,141 @@
+use crate::coordinate::Coordinate;
+use crate::vector::Vector;
+use rand::Rng;
+use std::time::Duration;
+
+/// Perturbs coordinates before they are published to peers, obscuring the
+/// position (and therefore approximate geography) of the local node.
+///
+/// The local model continues to use its accurate coordinate, while peers are
+/// sent a coordinate displaced by a random offset of at most the configured
+/// radius:
+///
+/// ```
+/// use std::time::Duration;
+/// use vivaldi::{Model, PrivacyNoise, vector::Dimension3};
+///
+/// let model = Model::<Dimension3>::new();
+/// let noise = PrivacyNoise::new(Duration::from_millis(10), Duration::from_millis(100));
+///
+/// // Send the noised coordinate to peers.
+/// let published = noise.publish(model.get_coordinate());
+/// ```
+///
+/// The offset is drawn once and reused for every published coordinate - were
+/// fresh noise drawn each time, peers could average many published
+/// coordinates to recover the accurate one. Call
+/// [`rerandomise`](PrivacyNoise::rerandomise) to draw a new offset, for
+/// example when the node moves.
+///
+/// Peers estimating their RTT to a published coordinate are wrong by up to the
+/// radius. Relative to the typical RTT of those peers this is an additional
+/// error of `radius / typical_rtt`, which is added to the error estimate of
+/// the published coordinate so peers weight their observations of the local
+/// node accordingly.
+#[derive(Debug, Clone)]
+pub struct PrivacyNoise<V>
+where
+    V: Vector,
+{
+    radius: f64,
+    error_inflation: f64,
+    offset: V,
+}
+
+impl<V> PrivacyNoise<V>
+where
+    V: Vector,
+{
+    /// Initialises a perturbation of at most `radius`, inflating the published
+    /// error estimate relative to `typical_rtt`.
+    pub fn new(radius: Duration, typical_rtt: Duration) -> Self {
+        let radius = radius.as_secs_f64();
+        let error_inflation = radius / typical_rtt.as_secs_f64().max(f64::MIN_POSITIVE);
+        PrivacyNoise {
+            radius,
+            error_inflation,
+            offset: random_offset(radius, &mut rand::thread_rng()),
+        }
+    }
+
+    /// Returns the maximum displacement of a published coordinate.
+    pub fn radius(&self) -> Duration {
+        Duration::from_secs_f64(self.radius)
+    }
+
+    /// Returns the amount added to the error estimate of a published
+    /// coordinate.
+    pub fn error_inflation(&self) -> f64 {
+        self.error_inflation
+    }
+
+    /// Draws a new random offset.
+    pub fn rerandomise(&mut self) {
+        self.offset = random_offset(self.radius, &mut rand::thread_rng());
+    }
+
+    /// Returns `coord` displaced by the offset, with an inflated error
+    /// estimate, for publishing to peers.
+    pub fn publish(&self, coord: &Coordinate<V>) -> Coordinate<V> {
+        Coordinate::new(
+            coord.vector().clone() + self.offset.clone(),
+            coord.error() + self.error_inflation,
+            coord.height(),
+        )
+    }
+}
+
+/// Returns a vector drawn uniformly from within a ball of `radius`, using
+/// `rng`.
+pub(crate) fn random_offset<V, R>(radius: f64, rng: &mut R) -> V
+where
+    V: Vector,
+    R: Rng + ?Sized,
+{
+    // Sample the unit cube centred on the origin until the point falls within
+    // the inscribed ball, so every direction is equally likely.
+    let centre = V::from_components(&vec![0.5; V::default().components().len()])
+        .expect("default vector has the vector dimensionality");
+    loop {
+        let v = V::random(rng) - &centre;
+        if v.magnitude().0 <= 0.5 {
+            return v * (2.0 * radius);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::Dimension3;
+
+    #[test]
+    fn bounded_stable_noise() {
+        let coord = Coordinate::new(Dimension3([0.1, 0.2, 0.3]), 0.2, 0.01);
+        let mut noise = PrivacyNoise::new(Duration::from_millis(10), Duration::from_millis(50));
+        assert_eq!(noise.radius(), Duration::from_millis(10));
+        assert!((noise.error_inflation() - 0.2).abs() < 1e-12);
+
+        let published = noise.publish(&coord);
+        assert!(published.vector().distance(coord.vector()).0 <= 0.010);
+        assert!((published.error() - 0.4).abs() < 1e-12);
+        assert_eq!(published.height(), coord.height());
+
+        // The same offset is applied until rerandomised.
+        assert_eq!(noise.publish(&coord), published);
+        noise.rerandomise();
+        assert_ne!(noise.publish(&coord), published);
+    }
+
+    #[test]
+    fn offsets_span_every_direction() {
+        let offsets = (0..200)
+            .map(|_| random_offset::<Dimension3, _>(1.0, &mut rand::thread_rng()))
+            .collect::<Vec<_>>();
+        assert!(offsets.iter().all(|v| v.magnitude().0 <= 1.0));
+        for axis in 0..3 {
+            assert!(offsets.iter().any(|v| v.0[axis] < 0.0));
+            assert!(offsets.iter().any(|v| v.0[axis] > 0.0));
+        }
+    }
+}
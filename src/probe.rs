@@ -0,0 +1,223 @@
+use crate::clock::Clock;
+use crate::coordinate::Coordinate;
+use crate::force::ForceFunction;
+use crate::model::{estimate_rtt, Model};
+use crate::peer_table::PeerTable;
+use crate::vector::Vector;
+use rand::Rng;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+impl<V, C, F, R> Model<V, C, F, f64, R>
+where
+    V: Vector + std::fmt::Debug,
+    C: Clock,
+    F: ForceFunction,
+    R: Rng,
+{
+    /// Returns the peer in `peers` expected to most reduce the local error
+    /// estimate if probed next, or `None` if no peer is expected to reduce it.
+    ///
+    /// Each observation moves the local error `ei` towards the relative error
+    /// of the sample, weighted by `w = ei / (ei + ej)`. Using the remote error
+    /// `ej` as the expected sample error, the expected reduction from probing
+    /// a peer is proportional to:
+    ///
+    /// ```text
+    ///     w × (ei - ej)
+    /// ```
+    ///
+    /// favouring confident peers while the local node is uncertain. This
+    /// always recommends the same peer for an unchanged table - use a
+    /// [`ProbeRecommender`] to also mix near and far peers and revisit peers
+    /// not recently probed.
+    pub fn recommend_probe<'a, K, PC>(&self, peers: &'a PeerTable<K, V, PC>) -> Option<&'a K>
+    where
+        K: Hash + Eq + Clone,
+        PC: Clock,
+    {
+        let local = self.get_coordinate();
+        peers
+            .iter()
+            .map(|(k, c)| (k, expected_error_reduction(local, &c)))
+            .filter(|(_, v)| *v > 0.0)
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(k, _)| k)
+    }
+}
+
+/// Returns a value proportional to the expected reduction in the local error
+/// estimate from observing `remote`.
+fn expected_error_reduction<V: Vector>(local: &Coordinate<V>, remote: &Coordinate<V>) -> f64 {
+    let (ei, ej) = (local.error(), remote.error());
+    if ei + ej <= 0.0 {
+        return 0.0;
+    }
+    ei / (ei + ej) * (ei - ej)
+}
+
+/// Recommends which peer to probe next, balancing error reduction against
+/// coverage of the network.
+///
+/// The Vivaldi paper shows coordinates converge fastest when nodes observe a
+/// mix of near and far peers, as near peers alone give accurate local
+/// placement but a distorted global view. Each call to
+/// [`recommend`](ProbeRecommender::recommend) alternates between the nearest
+/// and furthest half of the known peers (by estimated RTT), and within that
+/// half picks the peer with the highest score:
+///
+/// ```text
+///     (1 + w × (ei - ej)) × (1 + staleness × rounds since last recommended)
+/// ```
+///
+/// so peers that have not been probed for a while are eventually revisited
+/// even if they are not expected to reduce the error.
+#[derive(Debug, Clone)]
+pub struct ProbeRecommender<K> {
+    staleness: f64,
+    round: u64,
+    far: bool,
+    last_probed: HashMap<K, u64>,
+}
+
+impl<K> ProbeRecommender<K>
+where
+    K: Hash + Eq + Clone,
+{
+    /// Initialises a recommender weighting each round since a peer was last
+    /// recommended by `staleness`.
+    pub fn new(staleness: f64) -> Self {
+        ProbeRecommender {
+            staleness,
+            round: 0,
+            far: false,
+            last_probed: HashMap::new(),
+        }
+    }
+
+    /// Returns the peer to probe next, or `None` if `peers` is empty.
+    pub fn recommend<V, C, F, R, PC>(
+        &mut self,
+        model: &Model<V, C, F, f64, R>,
+        peers: &PeerTable<K, V, PC>,
+    ) -> Option<K>
+    where
+        V: Vector + std::fmt::Debug,
+        C: Clock,
+        F: ForceFunction,
+        R: Rng,
+        PC: Clock,
+    {
+        let local = model.get_coordinate();
+
+        let mut by_rtt = peers
+            .iter()
+            .map(|(k, c)| (k, estimate_rtt(local, &c), c))
+            .collect::<Vec<_>>();
+        if by_rtt.is_empty() {
+            return None;
+        }
+        by_rtt.sort_by_key(|(_, rtt, _)| *rtt);
+
+        // Alternate between the near and far half of the peers.
+        let mid = by_rtt.len().div_ceil(2);
+        let half = if self.far && by_rtt.len() > 1 {
+            &by_rtt[mid..]
+        } else {
+            &by_rtt[..mid]
+        };
+        self.far = !self.far;
+
+        let round = self.round;
+        let peer = half
+            .iter()
+            .map(|(k, _, c)| {
+                // Peers never probed are treated as probed before round 0.
+                let age = match self.last_probed.get(*k) {
+                    Some(r) => round - r,
+                    None => round + 1,
+                };
+                let gain = 1.0 + expected_error_reduction(local, c);
+                (*k, gain * (1.0 + self.staleness * age as f64))
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(k, _)| k.clone())?;
+
+        self.round += 1;
+        self.last_probed.insert(peer.clone(), round);
+        Some(peer)
+    }
+
+    /// Removes the probe history of `peer`, for example when it leaves the
+    /// peer table.
+    pub fn forget(&mut self, peer: &K) {
+        self.last_probed.remove(peer);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::Dimension3;
+
+    fn coord(x: f64, error: f64) -> Coordinate<Dimension3> {
+        Coordinate::new(Dimension3([x, 0.0, 0.0]), error, 0.0)
+    }
+
+    #[test]
+    fn recommend_probe_prefers_confident_peers() {
+        let model = Model::<Dimension3>::new();
+        let mut peers = PeerTable::new();
+        peers.insert("unsure", &coord(1.0, 1.9));
+        peers.insert("confident", &coord(2.0, 0.1));
+        peers.insert("worse", &coord(3.0, 5.0));
+
+        assert_eq!(model.recommend_probe(&peers), Some(&"confident"));
+
+        let mut worse = PeerTable::new();
+        worse.insert("worse", &coord(3.0, 5.0));
+        assert_eq!(model.recommend_probe(&worse), None);
+    }
+
+    #[test]
+    fn recommender_mixes_near_and_far() {
+        let model = Model::<Dimension3>::new();
+        let mut peers = PeerTable::new();
+        peers.insert("near", &coord(0.1, 1.0));
+        peers.insert("far", &coord(10.0, 1.0));
+
+        let mut r = ProbeRecommender::new(0.0);
+        assert_eq!(r.recommend(&model, &peers), Some("near"));
+        assert_eq!(r.recommend(&model, &peers), Some("far"));
+        assert_eq!(r.recommend(&model, &peers), Some("near"));
+    }
+
+    #[test]
+    fn recommender_revisits_stale_peers() {
+        let model = Model::<Dimension3>::new();
+        let mut peers = PeerTable::new();
+        peers.insert("a", &coord(0.1, 0.1));
+        peers.insert("b", &coord(0.2, 1.9));
+        peers.insert("far", &coord(10.0, 1.0));
+
+        let mut r = ProbeRecommender::new(1.0);
+        let mut near = Vec::new();
+        for _ in 0..20 {
+            let p = r.recommend(&model, &peers).unwrap();
+            if p != "far" {
+                near.push(p);
+            }
+        }
+
+        // Both near peers are probed, despite "a" having a higher gain.
+        assert!(near.contains(&"a"));
+        assert!(near.contains(&"b"));
+    }
+
+    #[test]
+    fn recommender_empty() {
+        let model = Model::<Dimension3>::new();
+        let peers = PeerTable::<&str, Dimension3>::new();
+        assert_eq!(ProbeRecommender::new(1.0).recommend(&model, &peers), None);
+    }
+}
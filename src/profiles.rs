@@ -0,0 +1,198 @@
+use crate::clock::{Clock, SystemClock};
+use crate::coordinate::Coordinate;
+use crate::model::Model;
+use crate::vector::Vector;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::Duration;
+
+/// Separate coordinates for each of a set of caller-defined time buckets,
+/// such as the hour of the day, for networks whose latencies shift
+/// predictably over time.
+///
+/// Observations update the profile of the active bucket, and the coordinate
+/// of the active profile is published to peers - so all nodes must use the
+/// same buckets and switch between them at (approximately) the same time,
+/// such as by using the UTC hour:
+///
+/// ```
+/// use std::time::Duration;
+/// use vivaldi::{ProfiledModel, vector::Dimension3};
+///
+/// let mut model = ProfiledModel::<u8, Dimension3>::new(0);
+/// # let remote = *model.get_coordinate();
+///
+/// // At the start of each hour:
+/// # let utc_hour = 17;
+/// model.set_active(utc_hour);
+///
+/// // Observe and publish as a single model.
+/// model.observe(&remote, Duration::from_millis(40));
+/// let coordinate = model.get_coordinate();
+/// ```
+///
+/// Every observation also updates a shared model spanning all buckets, from
+/// which each new profile is bootstrapped the first time its bucket becomes
+/// active - so a profile starts from a converged coordinate rather than the
+/// origin.
+#[derive(Debug, Clone)]
+pub struct ProfiledModel<B, V, C = SystemClock>
+where
+    V: Vector + std::fmt::Debug,
+{
+    shared: Model<V, C>,
+    profiles: HashMap<B, Model<V, C>>,
+    active: B,
+}
+
+impl<B, V> ProfiledModel<B, V>
+where
+    B: Hash + Eq + Clone,
+    V: Vector + std::fmt::Debug,
+{
+    /// Initialises a model with `active` as the active bucket.
+    pub fn new(active: B) -> Self {
+        ProfiledModel::with_clock(active, SystemClock)
+    }
+}
+
+impl<B, V, C> ProfiledModel<B, V, C>
+where
+    B: Hash + Eq + Clone,
+    V: Vector + std::fmt::Debug,
+    C: Clock + Clone,
+{
+    /// Initialises a model with `active` as the active bucket, with every
+    /// profile reading the current time from a clone of `clock`.
+    pub fn with_clock(active: B, clock: C) -> Self {
+        let shared = Model::with_clock(clock);
+        let mut profiles = HashMap::new();
+        profiles.insert(active.clone(), shared.clone());
+        ProfiledModel {
+            shared,
+            profiles,
+            active,
+        }
+    }
+
+    /// Switches the active profile to `bucket`, bootstrapping it from the
+    /// shared model if it has never been active.
+    pub fn set_active(&mut self, bucket: B) {
+        if !self.profiles.contains_key(&bucket) {
+            self.profiles.insert(bucket.clone(), self.shared.clone());
+        }
+        self.active = bucket;
+    }
+
+    /// Returns the active bucket.
+    pub fn active(&self) -> &B {
+        &self.active
+    }
+
+    /// Updates the active profile and the shared model with the `rtt`
+    /// measured to a remote node at `coord`, as [`Model::observe`].
+    pub fn observe(&mut self, coord: &Coordinate<V>, rtt: Duration) {
+        self.shared.observe(coord, rtt);
+        self.active_mut().observe(coord, rtt);
+    }
+
+    /// Returns the coordinate of the active profile, to be sent to peers.
+    pub fn get_coordinate(&self) -> &Coordinate<V> {
+        self.profiles
+            .get(&self.active)
+            .expect("active profile always exists")
+            .get_coordinate()
+    }
+
+    /// Returns the profile of `bucket`, if it has been active.
+    pub fn profile(&self, bucket: &B) -> Option<&Model<V, C>> {
+        self.profiles.get(bucket)
+    }
+
+    /// Returns the model spanning every bucket.
+    pub fn shared(&self) -> &Model<V, C> {
+        &self.shared
+    }
+
+    /// Discards the profile of `bucket`, so it is bootstrapped again from the
+    /// shared model when next active. The active profile is reset
+    /// immediately.
+    pub fn reset(&mut self, bucket: &B) {
+        self.profiles.remove(bucket);
+        if *bucket == self.active {
+            self.profiles
+                .insert(self.active.clone(), self.shared.clone());
+        }
+    }
+
+    fn active_mut(&mut self) -> &mut Model<V, C> {
+        self.profiles
+            .get_mut(&self.active)
+            .expect("active profile always exists")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::estimate_rtt;
+    use crate::vector::Dimension2;
+
+    #[test]
+    fn profiles_track_their_bucket() {
+        let peers = [[0.0, 0.0], [0.05, 0.0], [0.0, 0.05], [0.05, 0.05]]
+            .iter()
+            .map(|p| Coordinate::new(Dimension2(*p), 0.05, 0.0))
+            .collect::<Vec<_>>();
+
+        // The node appears further from every peer at peak times.
+        let off_peak = Coordinate::new(Dimension2([0.02, 0.03]), 0.0, 0.0);
+        let peak = Coordinate::new(Dimension2([0.1, 0.1]), 0.0, 0.0);
+
+        let mut m = ProfiledModel::<&str, Dimension2>::new("off-peak");
+        for _ in 0..20 {
+            for (bucket, truth) in &[("off-peak", off_peak), ("peak", peak)] {
+                m.set_active(*bucket);
+                for _ in 0..20 {
+                    for p in &peers {
+                        m.observe(p, estimate_rtt(truth, p));
+                    }
+                }
+            }
+        }
+
+        for (bucket, truth) in &[("off-peak", off_peak), ("peak", peak)] {
+            m.set_active(*bucket);
+            for p in &peers {
+                let got = estimate_rtt(m.get_coordinate(), p).as_secs_f64();
+                let want = estimate_rtt(truth, p).as_secs_f64();
+                assert!(
+                    (got - want).abs() < 0.005,
+                    "{}: {} vs {}",
+                    bucket,
+                    got,
+                    want
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn bootstraps_from_shared() {
+        let remote = Coordinate::new(Dimension2([0.05, 0.0]), 0.1, 0.0);
+        let mut m = ProfiledModel::<u8, Dimension2>::new(0);
+        for _ in 0..50 {
+            m.observe(&remote, Duration::from_millis(30));
+        }
+        assert!(m.profile(&1).is_none());
+
+        m.set_active(1);
+        assert_eq!(m.active(), &1);
+        assert_eq!(m.get_coordinate(), m.shared().get_coordinate());
+
+        m.reset(&0);
+        assert!(m.profile(&0).is_none());
+        m.reset(&1);
+        assert_eq!(m.get_coordinate(), m.shared().get_coordinate());
+    }
+}
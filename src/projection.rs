@@ -0,0 +1,213 @@
+use crate::coordinate::Coordinate;
+use crate::vector::Vector;
+
+/// Projects `coord` into a coordinate of a different dimensionality, keeping
+/// the error estimate and height.
+///
+/// Projecting to more dimensions appends zero components, and projecting to
+/// fewer dimensions drops the trailing components. This allows a cluster to
+/// migrate between dimensionalities without a flag day - nodes running either
+/// dimensionality can project the coordinates they receive into the one they
+/// use:
+///
+/// ```
+/// use vivaldi::{project, Model, vector::{Dimension2, Dimension3}};
+///
+/// let model = Model::<Dimension2>::new();
+///
+/// // A node running a 3D model receiving a 2D coordinate.
+/// let coord = project::<_, Dimension3>(model.get_coordinate());
+/// ```
+///
+/// # Error Characteristics
+///
+/// Zero-extending preserves the distance between any two coordinates exactly,
+/// so RTT estimates between extended coordinates are unchanged. The extended
+/// dimensions are then refined by subsequent observations.
+///
+/// Dropping components can only shorten the distance between two coordinates,
+/// so estimates between truncated coordinates never exceed the original
+/// estimates, and are lower by at most the distance between the dropped
+/// components of the two coordinates (which is itself at most the sum of their
+/// magnitudes). The [`Model`](crate::Model) typically spreads nodes across all
+/// dimensions, so this error is significant - truncation is best suited to
+/// display, and a migration to fewer dimensions should expect an increase in
+/// error until the model re-converges.
+pub fn project<A, B>(coord: &Coordinate<A>) -> Coordinate<B>
+where
+    A: Vector,
+    B: Vector,
+{
+    convert_components(coord.vector().components(), coord.error(), coord.height()).coordinate
+}
+
+/// The accuracy of a coordinate converted from a different dimensionality by
+/// [`convert`] or [`convert_components`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Accuracy {
+    /// The coordinate had the same or fewer dimensions, and estimates using
+    /// it are unchanged.
+    Exact,
+
+    /// Components were dropped from the coordinate, and estimates using it may
+    /// be lower than those of the original coordinate.
+    Truncated {
+        /// The magnitude (in seconds) of the dropped components. An estimate
+        /// between two converted coordinates is at most the sum of their
+        /// `dropped` values below the estimate between the originals.
+        dropped: f64,
+    },
+}
+
+impl Accuracy {
+    /// Returns true if the conversion preserved all estimates.
+    pub fn is_exact(&self) -> bool {
+        *self == Accuracy::Exact
+    }
+}
+
+/// A coordinate converted to the local dimensionality, and the accuracy of
+/// the conversion.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Converted<V>
+where
+    V: Vector,
+{
+    /// The converted coordinate.
+    pub coordinate: Coordinate<V>,
+
+    /// The accuracy of `coordinate` relative to the received coordinate.
+    pub accuracy: Accuracy,
+}
+
+/// Projects `coord` into a coordinate of a different dimensionality as
+/// [`project`] does, reporting whether the projection lost accuracy.
+pub fn convert<A, B>(coord: &Coordinate<A>) -> Converted<B>
+where
+    A: Vector,
+    B: Vector,
+{
+    convert_components(coord.vector().components(), coord.error(), coord.height())
+}
+
+/// Converts the components of a received coordinate of any dimensionality
+/// into a coordinate of dimensionality `V`, reporting whether the conversion
+/// lost accuracy.
+///
+/// During a migration between dimensionalities, peers send coordinates with a
+/// different number of components to the local model - rather than rejecting
+/// them, they are zero-extended or truncated as described for [`project`]:
+///
+/// ```
+/// use vivaldi::{convert_components, Accuracy, vector::Dimension2};
+///
+/// // A coordinate received from a peer running a 3D model.
+/// let converted = convert_components::<Dimension2>(&[0.010, 0.020, 0.005], 0.2, 0.001);
+///
+/// if let Accuracy::Truncated { dropped } = converted.accuracy {
+///     println!("estimates may be up to {}s low", dropped);
+/// }
+/// ```
+pub fn convert_components<V: Vector>(components: &[f64], error: f64, height: f64) -> Converted<V> {
+    let mut local = V::default().components().to_vec();
+    for (dst, src) in local.iter_mut().zip(components) {
+        *dst = *src;
+    }
+
+    let accuracy = match components.get(local.len()..) {
+        Some(rest) if !rest.is_empty() => Accuracy::Truncated {
+            dropped: rest.iter().map(|v| v * v).sum::<f64>().sqrt(),
+        },
+        _ => Accuracy::Exact,
+    };
+
+    let vector = V::from_components(&local).expect("default vector has the target dimensionality");
+    Converted {
+        coordinate: Coordinate::new(vector, error, height),
+        accuracy,
+    }
+}
+
+/// Returns the number of dimensions of `V`, for advertising the local
+/// dimensionality to peers.
+pub fn dimensions<V: Vector>() -> usize {
+    V::default().components().len()
+}
+
+/// Returns the highest dimensionality supported by both the local node and a
+/// peer, or `None` if they share none.
+///
+/// Nodes migrating between dimensionalities can advertise every
+/// dimensionality they can run (for example, by running a model of each in
+/// parallel) and agree on the best one both support.
+pub fn negotiate_dimensions(local: &[usize], remote: &[usize]) -> Option<usize> {
+    local.iter().filter(|d| remote.contains(d)).max().cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::estimate_rtt;
+    use crate::vector::{Dimension2, Dimension3};
+
+    #[test]
+    fn zero_extend_preserves_estimates() {
+        let a = Coordinate::new(Dimension2([1.0, 2.0]), 0.5, 0.1);
+        let b = Coordinate::new(Dimension2([-3.0, 0.5]), 0.4, 0.2);
+
+        let (pa, pb) = (project::<_, Dimension3>(&a), project::<_, Dimension3>(&b));
+        assert_eq!(pa.vector(), &Dimension3([1.0, 2.0, 0.0]));
+        assert_eq!(pa.error(), 0.5);
+        assert_eq!(pa.height(), 0.1);
+
+        assert_eq!(estimate_rtt(&a, &b), estimate_rtt(&pa, &pb));
+
+        // And back again.
+        assert_eq!(project::<_, Dimension2>(&pa), a);
+    }
+
+    #[test]
+    fn truncation_bound() {
+        let a = Coordinate::new(Dimension3([1.0, 2.0, 3.0]), 0.5, 0.1);
+        let b = Coordinate::new(Dimension3([-3.0, 0.5, -1.0]), 0.4, 0.2);
+
+        let (pa, pb) = (project::<_, Dimension2>(&a), project::<_, Dimension2>(&b));
+        assert_eq!(pa.vector(), &Dimension2([1.0, 2.0]));
+
+        let before = estimate_rtt(&a, &b).as_secs_f64();
+        let after = estimate_rtt(&pa, &pb).as_secs_f64();
+        assert!(after <= before);
+        assert!(before - after <= 4.0 + 1e-9);
+    }
+
+    #[test]
+    fn conversion_accuracy() {
+        let a = Coordinate::new(Dimension3([1.0, 2.0, 3.0]), 0.5, 0.1);
+        let b = Coordinate::new(Dimension3([-3.0, 0.5, -4.0]), 0.4, 0.2);
+
+        let ca = convert::<_, Dimension2>(&a);
+        let cb = convert::<_, Dimension2>(&b);
+        assert_eq!(ca.accuracy, Accuracy::Truncated { dropped: 3.0 });
+        assert_eq!(cb.accuracy, Accuracy::Truncated { dropped: 4.0 });
+
+        let before = estimate_rtt(&a, &b).as_secs_f64();
+        let after = estimate_rtt(&ca.coordinate, &cb.coordinate).as_secs_f64();
+        assert!(before - after <= 3.0 + 4.0);
+
+        let extended = convert::<_, Dimension3>(&ca.coordinate);
+        assert!(extended.accuracy.is_exact());
+
+        let short = convert_components::<Dimension3>(&[1.0], 0.5, 0.1);
+        assert_eq!(short.coordinate.vector(), &Dimension3([1.0, 0.0, 0.0]));
+        assert!(short.accuracy.is_exact());
+    }
+
+    #[test]
+    fn negotiation() {
+        assert_eq!(dimensions::<Dimension2>(), 2);
+        assert_eq!(dimensions::<Dimension3>(), 3);
+        assert_eq!(negotiate_dimensions(&[3, 8], &[2, 3, 8]), Some(8));
+        assert_eq!(negotiate_dimensions(&[3, 8], &[2, 3]), Some(3));
+        assert_eq!(negotiate_dimensions(&[8], &[3]), None);
+    }
+}
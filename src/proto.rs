@@ -0,0 +1,186 @@
+//! Protobuf messages for exchanging coordinates, for gRPC-based services.
+//!
+//! The messages implement the schema in `proto/vivaldi.proto`, distributed
+//! with the crate for generating bindings in other languages, and convert to
+//! and from the types of this crate:
+//!
+//! ```
+//! use std::convert::TryFrom;
+//! use prost::Message;
+//! use vivaldi::{proto, Coordinate, Model, vector::Dimension3};
+//!
+//! let model = Model::<Dimension3>::new();
+//!
+//! let msg = proto::CoordinateExchange::new("node-a", model.get_coordinate());
+//! let buf = msg.encode_to_vec();
+//!
+//! let received = proto::CoordinateExchange::decode(buf.as_slice())?;
+//! let coord = received.to_coordinate::<Dimension3>()?;
+//! assert_eq!(&coord, model.get_coordinate());
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+use crate::error::Error;
+use crate::vector::Vector;
+use std::convert::TryFrom;
+
+/// A point in the Vivaldi model, the `vivaldi.Coordinate` message.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Coordinate {
+    /// The Euclidean components of the coordinate, in seconds.
+    #[prost(double, repeated, tag = "1")]
+    pub vector: Vec<f64>,
+
+    /// The error estimate of the coordinate.
+    #[prost(double, tag = "2")]
+    pub error: f64,
+
+    /// The height of the coordinate above the Euclidean space, in seconds.
+    #[prost(double, tag = "3")]
+    pub height: f64,
+
+    /// The offset added to RTT estimates involving the coordinate, in
+    /// seconds.
+    #[prost(double, tag = "4")]
+    pub adjustment: f64,
+}
+
+/// The coordinate of a node as exchanged between peers, the
+/// `vivaldi.CoordinateExchange` message.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct CoordinateExchange {
+    /// The identifier of the node publishing the coordinate.
+    #[prost(string, tag = "1")]
+    pub node_id: String,
+
+    /// The current coordinate of the node.
+    #[prost(message, optional, tag = "2")]
+    pub coordinate: Option<Coordinate>,
+}
+
+impl CoordinateExchange {
+    /// Initialises a message publishing `coordinate` as the coordinate of
+    /// `node_id`.
+    pub fn new<V>(node_id: impl Into<String>, coordinate: &crate::Coordinate<V>) -> Self
+    where
+        V: Vector,
+    {
+        CoordinateExchange {
+            node_id: node_id.into(),
+            coordinate: Some(coordinate.into()),
+        }
+    }
+
+    /// Converts the published coordinate, returning [`Error::Serialization`]
+    /// if the message does not contain one.
+    ///
+    /// See the [`TryFrom`] conversion of [`Coordinate`] for the other errors.
+    pub fn to_coordinate<V>(&self) -> Result<crate::Coordinate<V>, Error>
+    where
+        V: Vector,
+    {
+        let c = self
+            .coordinate
+            .clone()
+            .ok_or_else(|| Error::Serialization("missing coordinate".to_string()))?;
+        crate::Coordinate::try_from(c)
+    }
+}
+
+impl<V> From<&crate::Coordinate<V>> for Coordinate
+where
+    V: Vector,
+{
+    fn from(c: &crate::Coordinate<V>) -> Self {
+        Coordinate {
+            vector: c.vector().components().to_vec(),
+            error: c.error(),
+            height: c.height(),
+            adjustment: c.adjustment(),
+        }
+    }
+}
+
+/// Converts a decoded message, returning [`Error::DimensionMismatch`] if it
+/// has a different dimensionality than `V`, or an error if any value is
+/// invalid as described by [`crate::Coordinate::try_new`].
+impl<V> TryFrom<Coordinate> for crate::Coordinate<V>
+where
+    V: Vector,
+{
+    type Error = Error;
+
+    fn try_from(c: Coordinate) -> Result<Self, Self::Error> {
+        let vector = V::from_components(&c.vector).ok_or_else(|| Error::DimensionMismatch {
+            expected: V::default().components().len(),
+            got: c.vector.len(),
+        })?;
+
+        let coord = crate::Coordinate::new(vector, c.error, c.height).with_adjustment(c.adjustment);
+        coord.validate()?;
+        Ok(coord)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::{Dimension2, Dimension3};
+    use prost::Message;
+
+    fn coordinate() -> crate::Coordinate<Dimension2> {
+        crate::Coordinate::new(Dimension2([0.5, -0.25]), 1.0, 0.125).with_adjustment(0.0625)
+    }
+
+    #[test]
+    fn round_trip() {
+        let c = coordinate();
+        let msg = CoordinateExchange::new("a", &c);
+
+        let decoded = CoordinateExchange::decode(msg.encode_to_vec().as_slice()).unwrap();
+        assert_eq!(decoded, msg);
+        assert_eq!(decoded.node_id, "a");
+        assert_eq!(decoded.to_coordinate::<Dimension2>(), Ok(c));
+    }
+
+    #[test]
+    fn wire_format() {
+        // Pins the encoding to the field numbers of proto/vivaldi.proto.
+        let mut want = vec![0x0a, 16];
+        want.extend_from_slice(&0.5_f64.to_le_bytes());
+        want.extend_from_slice(&(-0.25_f64).to_le_bytes());
+        for (tag, v) in [(0x11, 1.0_f64), (0x19, 0.125), (0x21, 0.0625)] {
+            want.push(tag);
+            want.extend_from_slice(&v.to_le_bytes());
+        }
+        assert_eq!(Coordinate::from(&coordinate()).encode_to_vec(), want);
+    }
+
+    #[test]
+    fn invalid() {
+        let missing = CoordinateExchange {
+            node_id: "a".to_string(),
+            coordinate: None,
+        };
+        assert!(matches!(
+            missing.to_coordinate::<Dimension2>(),
+            Err(Error::Serialization(_))
+        ));
+
+        let msg = CoordinateExchange::new("a", &coordinate());
+        assert_eq!(
+            msg.to_coordinate::<Dimension3>(),
+            Err(Error::DimensionMismatch {
+                expected: 3,
+                got: 2
+            })
+        );
+
+        let mut nan = Coordinate::from(&coordinate());
+        nan.error = f64::NAN;
+        assert_eq!(
+            crate::Coordinate::<Dimension2>::try_from(nan),
+            Err(Error::NonFiniteCoordinate)
+        );
+    }
+}
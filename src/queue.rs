@@ -0,0 +1,267 @@
+use crate::source::{Observation, RttSource};
+use crate::vector::Vector;
+use crossbeam_queue::ArrayQueue;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// The action taken when an observation is pushed into a full
+/// [`IngestProducer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverflowPolicy {
+    /// Return the observation to the caller, leaving it to retry or back off.
+    Reject,
+
+    /// Discard the observation being pushed.
+    DropNewest,
+
+    /// Discard the oldest queued observation to make room.
+    DropOldest,
+}
+
+#[derive(Debug)]
+struct Shared<K, V>
+where
+    V: Vector,
+{
+    queue: ArrayQueue<Observation<K, V>>,
+    policy: OverflowPolicy,
+    dropped: AtomicU64,
+}
+
+/// Initialises a bounded, lock-free observation queue holding at most
+/// `capacity` observations, returning the producer and consumer halves.
+///
+/// Producers can be cloned and pushed into from any thread or task without
+/// blocking, while the single [`IngestConsumer`] is drained into a [`Model`]
+/// (usually from a timer, or after each batch of probes) so observations are
+/// applied in the order they were pushed without contending on the model.
+///
+/// ```
+/// use std::time::Duration;
+/// use vivaldi::{ingest_queue, Model, Observation, OverflowPolicy, vector::Dimension3};
+///
+/// let mut model = Model::<Dimension3>::new();
+/// let remote = Model::<Dimension3>::new();
+///
+/// let (tx, mut rx) = ingest_queue(1024, OverflowPolicy::DropOldest);
+///
+/// let tx2 = tx.clone();
+/// std::thread::spawn(move || {
+///     let _ = tx2.push(Observation {
+///         peer: "remote",
+///         coordinate: *remote.get_coordinate(),
+///         rtt: Duration::from_millis(10),
+///     });
+/// })
+/// .join()
+/// .unwrap();
+///
+/// assert_eq!(model.drain(&mut rx), 1);
+/// ```
+///
+/// # Panics
+///
+/// Panics if `capacity` is zero.
+///
+/// [`Model`]: crate::Model
+pub fn ingest_queue<K, V>(
+    capacity: usize,
+    policy: OverflowPolicy,
+) -> (IngestProducer<K, V>, IngestConsumer<K, V>)
+where
+    V: Vector,
+{
+    let shared = Arc::new(Shared {
+        queue: ArrayQueue::new(capacity),
+        policy,
+        dropped: AtomicU64::new(0),
+    });
+
+    (
+        IngestProducer {
+            shared: Arc::clone(&shared),
+        },
+        IngestConsumer { shared },
+    )
+}
+
+/// The sending half of an [`ingest_queue`].
+#[derive(Debug)]
+pub struct IngestProducer<K, V>
+where
+    V: Vector,
+{
+    shared: Arc<Shared<K, V>>,
+}
+
+impl<K, V> Clone for IngestProducer<K, V>
+where
+    V: Vector,
+{
+    fn clone(&self) -> Self {
+        IngestProducer {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<K, V> IngestProducer<K, V>
+where
+    V: Vector,
+{
+    /// Pushes `obs` into the queue without blocking.
+    ///
+    /// If the queue is full, the configured [`OverflowPolicy`] is applied - an
+    /// error containing `obs` is returned only for
+    /// [`Reject`](OverflowPolicy::Reject).
+    pub fn push(&self, obs: Observation<K, V>) -> Result<(), Observation<K, V>> {
+        let shared = &self.shared;
+        match shared.policy {
+            OverflowPolicy::Reject => shared.queue.push(obs),
+            OverflowPolicy::DropNewest => {
+                if shared.queue.push(obs).is_err() {
+                    shared.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+                Ok(())
+            }
+            OverflowPolicy::DropOldest => {
+                if shared.queue.force_push(obs).is_some() {
+                    shared.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Returns true if the queue is full.
+    pub fn is_full(&self) -> bool {
+        self.shared.queue.is_full()
+    }
+}
+
+/// The receiving half of an [`ingest_queue`], applied to a model with
+/// [`Model::drain`](crate::Model::drain).
+#[derive(Debug)]
+pub struct IngestConsumer<K, V>
+where
+    V: Vector,
+{
+    shared: Arc<Shared<K, V>>,
+}
+
+impl<K, V> IngestConsumer<K, V>
+where
+    V: Vector,
+{
+    /// Returns the number of queued observations.
+    pub fn len(&self) -> usize {
+        self.shared.queue.len()
+    }
+
+    /// Returns true if no observations are queued.
+    pub fn is_empty(&self) -> bool {
+        self.shared.queue.is_empty()
+    }
+
+    /// Returns the maximum number of queued observations.
+    pub fn capacity(&self) -> usize {
+        self.shared.queue.capacity()
+    }
+
+    /// Returns the total number of observations discarded by the
+    /// [`DropNewest`](OverflowPolicy::DropNewest) and
+    /// [`DropOldest`](OverflowPolicy::DropOldest) policies.
+    pub fn dropped(&self) -> u64 {
+        self.shared.dropped.load(Ordering::Relaxed)
+    }
+}
+
+impl<K, V> RttSource<K, V> for IngestConsumer<K, V>
+where
+    V: Vector,
+{
+    fn next_observation(&mut self) -> Option<Observation<K, V>> {
+        self.shared.queue.pop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coordinate::Coordinate;
+    use crate::model::Model;
+    use crate::vector::Dimension3;
+    use std::time::Duration;
+
+    fn obs(peer: usize) -> Observation<usize, Dimension3> {
+        Observation {
+            peer,
+            coordinate: Coordinate::new(Dimension3([1.0, 0.0, 0.0]), 1.0, 0.1),
+            rtt: Duration::from_millis(10),
+        }
+    }
+
+    fn peers(rx: &mut IngestConsumer<usize, Dimension3>) -> Vec<usize> {
+        std::iter::from_fn(|| rx.next_observation())
+            .map(|o| o.peer)
+            .collect()
+    }
+
+    #[test]
+    fn reject() {
+        let (tx, mut rx) = ingest_queue(2, OverflowPolicy::Reject);
+        assert!(tx.push(obs(1)).is_ok());
+        assert!(tx.push(obs(2)).is_ok());
+        assert!(tx.is_full());
+        assert_eq!(tx.push(obs(3)), Err(obs(3)));
+
+        assert_eq!(rx.dropped(), 0);
+        assert_eq!(peers(&mut rx), vec![1, 2]);
+    }
+
+    #[test]
+    fn drop_newest() {
+        let (tx, mut rx) = ingest_queue(2, OverflowPolicy::DropNewest);
+        for i in 1..=4 {
+            assert!(tx.push(obs(i)).is_ok());
+        }
+
+        assert_eq!(rx.dropped(), 2);
+        assert_eq!(peers(&mut rx), vec![1, 2]);
+    }
+
+    #[test]
+    fn drop_oldest() {
+        let (tx, mut rx) = ingest_queue(2, OverflowPolicy::DropOldest);
+        for i in 1..=4 {
+            assert!(tx.push(obs(i)).is_ok());
+        }
+
+        assert_eq!(rx.dropped(), 2);
+        assert_eq!(peers(&mut rx), vec![3, 4]);
+    }
+
+    #[test]
+    fn concurrent_producers() {
+        let (tx, mut rx) = ingest_queue(4 * 100, OverflowPolicy::Reject);
+
+        let handles = (0..4)
+            .map(|t| {
+                let tx = tx.clone();
+                std::thread::spawn(move || {
+                    for i in 0..100 {
+                        tx.push(obs(t * 100 + i)).unwrap();
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        for h in handles {
+            h.join().unwrap();
+        }
+
+        assert_eq!(rx.len(), 400);
+        let mut model = Model::<Dimension3>::new();
+        assert_eq!(model.drain(&mut rx), 400);
+        assert!(rx.is_empty());
+    }
+}
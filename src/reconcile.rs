@@ -0,0 +1,329 @@
+use crate::clock::Clock;
+use crate::coordinate::Coordinate;
+use crate::force::ForceFunction;
+use crate::model::Model;
+use crate::vector::Vector;
+use rand::Rng;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::Duration;
+
+/// The maximum number of stress majorization sweeps in a reconciliation.
+const MAX_SWEEPS: usize = 200;
+
+/// The relative reduction in stress below which a reconciliation is
+/// considered converged.
+const CONVERGED: f64 = 1.0e-9;
+
+/// The smallest target distance (in seconds) of a measured pair, bounding the
+/// weight of co-located nodes.
+const MIN_TARGET: f64 = 1.0e-6;
+
+/// Gathers direct RTT measurements between the nodes of a cluster, and
+/// globally re-solves their coordinates to counter slow collective drift.
+///
+/// Each node only ever moves its own coordinate in response to its own
+/// measurements, so errors shared by the whole cluster (such as a slow
+/// rotation or compression of the coordinate space) are never corrected by
+/// incremental updates. For modest clusters (up to a few hundred nodes), a
+/// coordinator can periodically gather recent measurements and coordinates
+/// from every node, and [`reconcile`](Reconciler::reconcile) them:
+///
+/// ```
+/// use std::time::Duration;
+/// use vivaldi::{Model, Reconciler, vector::Dimension3};
+///
+/// # let mut models = (0..3).map(|_| Model::<Dimension3>::new()).collect::<Vec<_>>();
+/// let mut reconciler = Reconciler::new();
+/// reconciler.record(0, 1, Duration::from_millis(10));
+/// reconciler.record(1, 2, Duration::from_millis(15));
+/// reconciler.record(0, 2, Duration::from_millis(20));
+///
+/// let reconciled = reconciler.reconcile(
+///     models.iter().enumerate().map(|(i, m)| (i, *m.get_coordinate())),
+/// );
+///
+/// // Distribute the corrected coordinates to each node.
+/// for (i, model) in models.iter_mut().enumerate() {
+///     model.apply_correction(&reconciled.coordinates[&i]);
+/// }
+/// ```
+///
+/// Reconciliation minimises the weighted stress `Σ (‖xi − xj‖ − dij)² / dij²`
+/// over the measured pairs by stress majorization, starting from the current
+/// coordinates so the solution stays aligned with them. Heights are kept, and
+/// subtracted from each measurement to give the target distance `dij` between
+/// the Euclidean components.
+#[derive(Debug, Clone)]
+pub struct Reconciler<K> {
+    /// The most recent RTT (in seconds) measured between each pair of nodes,
+    /// stored in both directions.
+    rtts: HashMap<K, HashMap<K, f64>>,
+}
+
+/// The result of a [`Reconciler::reconcile`] pass.
+#[derive(Debug, Clone)]
+pub struct Reconciled<K, V>
+where
+    V: Vector,
+{
+    /// The corrected coordinate of each node.
+    pub coordinates: HashMap<K, Coordinate<V>>,
+
+    /// The weighted stress of the coordinates before reconciliation.
+    pub stress_before: f64,
+
+    /// The weighted stress of the corrected coordinates.
+    pub stress_after: f64,
+}
+
+impl<K> Default for Reconciler<K>
+where
+    K: Hash + Eq + Clone,
+{
+    fn default() -> Self {
+        Reconciler {
+            rtts: HashMap::new(),
+        }
+    }
+}
+
+impl<K> Reconciler<K>
+where
+    K: Hash + Eq + Clone,
+{
+    /// Initialises a reconciler with no measurements.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records an RTT measured between nodes `a` and `b`, replacing any
+    /// previous measurement of the pair.
+    ///
+    /// Zero and self-measurements are ignored.
+    pub fn record(&mut self, a: K, b: K, rtt: Duration) {
+        if a == b || rtt == Duration::from_secs(0) {
+            return;
+        }
+        let rtt = rtt.as_secs_f64();
+        self.rtts
+            .entry(a.clone())
+            .or_default()
+            .insert(b.clone(), rtt);
+        self.rtts.entry(b).or_default().insert(a, rtt);
+    }
+
+    /// Returns the number of measured pairs.
+    pub fn len(&self) -> usize {
+        self.rtts.values().map(|v| v.len()).sum::<usize>() / 2
+    }
+
+    /// Returns true if no measurements have been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.rtts.is_empty()
+    }
+
+    /// Discards all measurements, such as after a reconciliation so the next
+    /// uses only fresh measurements.
+    pub fn clear(&mut self) {
+        self.rtts.clear();
+    }
+
+    /// Re-solves the coordinates of `nodes` to best fit the recorded
+    /// measurements.
+    ///
+    /// Nodes without measurements to any other node in `nodes` are returned
+    /// unchanged, and measurements involving nodes not in `nodes` are
+    /// ignored. The error estimate of each coordinate is preserved.
+    pub fn reconcile<V, I>(&self, nodes: I) -> Reconciled<K, V>
+    where
+        V: Vector,
+        I: IntoIterator<Item = (K, Coordinate<V>)>,
+    {
+        let nodes = nodes.into_iter().collect::<Vec<_>>();
+        let index = nodes
+            .iter()
+            .enumerate()
+            .map(|(i, (k, _))| (k, i))
+            .collect::<HashMap<_, _>>();
+
+        // The neighbours of each node, their target distance and weight.
+        let edges = nodes
+            .iter()
+            .map(|(k, c)| {
+                self.rtts
+                    .get(k)
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|(other, rtt)| {
+                        let j = *index.get(other)?;
+                        let d = (rtt - c.height() - nodes[j].1.height()).max(MIN_TARGET);
+                        Some((j, d, 1.0 / (d * d)))
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let mut positions = nodes
+            .iter()
+            .map(|(_, c)| c.vector().components().to_vec())
+            .collect::<Vec<_>>();
+
+        let stress_before = stress(&positions, &edges);
+        let mut current = stress_before;
+        let mut next = vec![0.0; positions.first().map_or(0, |p| p.len())];
+        for _ in 0..MAX_SWEEPS {
+            for i in 0..positions.len() {
+                if edges[i].is_empty() {
+                    continue;
+                }
+
+                // The localised majorization update:
+                //
+                //      xi = Σ wij (xj + dij (xi − xj) / ‖xi − xj‖) / Σ wij
+                //
+                next.iter_mut().for_each(|v| *v = 0.0);
+                let mut total = 0.0;
+                for &(j, d, w) in &edges[i] {
+                    let dist = distance(&positions[i], &positions[j]);
+                    let scale = if dist > 0.0 { d / dist } else { 0.0 };
+                    for ((n, xi), xj) in next.iter_mut().zip(&positions[i]).zip(&positions[j]) {
+                        *n += w * (xj + scale * (xi - xj));
+                    }
+                    total += w;
+                }
+                for (p, n) in positions[i].iter_mut().zip(&next) {
+                    *p = n / total;
+                }
+            }
+
+            let updated = stress(&positions, &edges);
+            let converged = current - updated <= current * CONVERGED;
+            current = updated;
+            if converged {
+                break;
+            }
+        }
+
+        let coordinates = nodes
+            .into_iter()
+            .zip(positions)
+            .map(|((k, c), p)| {
+                let vector = V::from_components(&p).expect("positions keep their dimensionality");
+                (k, Coordinate::new(vector, c.error(), c.height()))
+            })
+            .collect();
+
+        Reconciled {
+            coordinates,
+            stress_before,
+            stress_after: current,
+        }
+    }
+}
+
+impl<V, C, F, R> Model<V, C, F, f64, R>
+where
+    V: Vector + std::fmt::Debug,
+    C: Clock,
+    F: ForceFunction,
+    R: Rng,
+{
+    /// Moves the local coordinate to `corrected`, as computed by
+    /// [`Reconciler::reconcile`], keeping the current error estimate.
+    pub fn apply_correction(&mut self, corrected: &Coordinate<V>) {
+        let error = self.get_coordinate().error();
+        self.set_coordinate(Coordinate::new(
+            corrected.vector().clone(),
+            error,
+            corrected.height(),
+        ));
+    }
+}
+
+/// Returns the weighted stress of `positions` over each pair in `edges`.
+fn stress(positions: &[Vec<f64>], edges: &[Vec<(usize, f64, f64)>]) -> f64 {
+    edges
+        .iter()
+        .enumerate()
+        .flat_map(|(i, e)| {
+            e.iter()
+                .filter(move |(j, _, _)| *j > i)
+                .map(move |e| (i, e))
+        })
+        .map(|(i, &(j, d, w))| w * (distance(&positions[i], &positions[j]) - d).powi(2))
+        .sum()
+}
+
+fn distance(a: &[f64], b: &[f64]) -> f64 {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| (a - b) * (a - b))
+        .sum::<f64>()
+        .sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::estimate_rtt;
+    use crate::vector::Dimension2;
+
+    #[test]
+    fn corrects_collective_drift() {
+        // Four nodes on a square, with the coordinates compressed to half the
+        // true scale as by a slow collective drift.
+        let truth = [[0.0, 0.0], [0.1, 0.0], [0.1, 0.1], [0.0, 0.1]];
+        let mut r = Reconciler::new();
+        for i in 0..4 {
+            for j in (i + 1)..4 {
+                let d = distance(&truth[i], &truth[j]) + 2.0 * 1e-5;
+                r.record(i, j, Duration::from_secs_f64(d));
+            }
+        }
+        assert_eq!(r.len(), 6);
+
+        let coord = |p: [f64; 2]| Coordinate::new(Dimension2(p), 0.3, 0.0);
+        let drifted = truth
+            .iter()
+            .map(|p| coord([p[0] * 0.5 + 0.01, p[1] * 0.5]))
+            .collect::<Vec<_>>();
+
+        let out = r.reconcile(drifted.iter().cloned().enumerate());
+        assert!(out.stress_after < out.stress_before * 1e-6, "{:?}", out);
+        for i in 0..4 {
+            for j in (i + 1)..4 {
+                let got = estimate_rtt(&out.coordinates[&i], &out.coordinates[&j]).as_secs_f64();
+                let want = distance(&truth[i], &truth[j]) + 2.0 * 1e-5;
+                assert!((got - want).abs() < 1e-4, "{} vs {}", got, want);
+            }
+            assert_eq!(out.coordinates[&i].error(), 0.3);
+        }
+    }
+
+    #[test]
+    fn unmeasured_nodes_unchanged() {
+        let mut r = Reconciler::new();
+        r.record("a", "b", Duration::from_millis(10));
+        r.record("a", "a", Duration::from_millis(10));
+        r.record("a", "z", Duration::from_millis(10));
+        assert_eq!(r.len(), 2);
+
+        let c = Coordinate::new(Dimension2([0.5, 0.5]), 0.3, 0.1);
+        let out = r.reconcile(vec![("c", c)]);
+        assert_eq!(out.coordinates["c"], c);
+
+        r.clear();
+        assert!(r.is_empty());
+    }
+
+    #[test]
+    fn apply_correction_keeps_error() {
+        let mut m = Model::<Dimension2>::new();
+        let corrected = Coordinate::new(Dimension2([0.5, 0.5]), 0.01, 0.2);
+        m.apply_correction(&corrected);
+        assert_eq!(m.get_coordinate().vector(), corrected.vector());
+        assert_eq!(m.get_coordinate().height(), 0.2);
+        assert_eq!(m.get_coordinate().error(), 2.0);
+    }
+}
@@ -0,0 +1,212 @@
+use crate::clock::Clock;
+use crate::coordinate::Coordinate;
+use crate::peer_table::PeerTable;
+use crate::vector::Vector;
+use std::hash::Hash;
+use std::time::Duration;
+
+/// Known peers organised into exponentially spaced latency rings around the
+/// local node, as used by [Meridian] for closest-node search.
+///
+/// Ring 0 holds peers with an estimated RTT below `base`, and ring `i > 0`
+/// holds peers with an estimated RTT in `[base × factor^(i-1), base ×
+/// factor^i)`. Members of each ring are ordered by ascending RTT.
+///
+/// ```
+/// use std::time::Duration;
+/// use vivaldi::{LatencyRings, Model, PeerTable, vector::Dimension3};
+///
+/// let local = Model::<Dimension3>::new();
+/// let mut peers = PeerTable::new();
+/// peers.insert("remote", Model::<Dimension3>::new().get_coordinate());
+///
+/// let rings = LatencyRings::from_table(
+///     &peers,
+///     local.get_coordinate(),
+///     Duration::from_millis(1),
+///     2.0,
+/// );
+///
+/// // Candidates for forwarding a closest-node query for a target 5ms away.
+/// for (peer, rtt) in rings.within(Duration::from_millis(5), 0.5) {
+///     println!("{}: {:?}", peer, rtt);
+/// }
+/// ```
+///
+/// [Meridian]: https://www.cs.cornell.edu/people/egs/papers/meridian-sigcomm05.pdf
+#[derive(Debug, Clone)]
+pub struct LatencyRings<K> {
+    base: f64,
+    factor: f64,
+    rings: Vec<Vec<(K, Duration)>>,
+}
+
+impl<K> LatencyRings<K> {
+    /// Initialises an empty set of rings with the innermost ring boundary at
+    /// `base`, and each subsequent ring boundary `factor` times further out.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `base` is zero or `factor` is not greater than 1.
+    pub fn new(base: Duration, factor: f64) -> Self {
+        assert!(base > Duration::from_secs(0), "ring base must be non-zero");
+        assert!(factor > 1.0, "ring factor must be greater than 1");
+
+        LatencyRings {
+            base: base.as_secs_f64(),
+            factor,
+            rings: Vec::new(),
+        }
+    }
+
+    /// Initialises a set of rings containing every peer in `table`, placed by
+    /// their estimated RTT from `local`.
+    pub fn from_table<V, C>(
+        table: &PeerTable<K, V, C>,
+        local: &Coordinate<V>,
+        base: Duration,
+        factor: f64,
+    ) -> Self
+    where
+        K: Hash + Eq + Clone,
+        V: Vector,
+        C: Clock,
+    {
+        let mut rings = Self::new(base, factor);
+        for (peer, rtt) in table.estimate_all(local) {
+            rings.insert(peer.clone(), rtt);
+        }
+        rings
+    }
+
+    /// Places `peer` into the ring for `rtt`.
+    ///
+    /// The caller is responsible for not inserting the same peer twice.
+    pub fn insert(&mut self, peer: K, rtt: Duration) {
+        let idx = self.ring_index(rtt);
+        if self.rings.len() <= idx {
+            self.rings.resize_with(idx + 1, Vec::new);
+        }
+
+        let ring = &mut self.rings[idx];
+        let pos = ring.partition_point(|(_, v)| *v <= rtt);
+        ring.insert(pos, (peer, rtt));
+    }
+
+    /// Returns the index of the ring a peer with the given RTT belongs to.
+    pub fn ring_index(&self, rtt: Duration) -> usize {
+        let rtt = rtt.as_secs_f64();
+        if rtt < self.base {
+            return 0;
+        }
+        (rtt / self.base).log(self.factor).floor() as usize + 1
+    }
+
+    /// Returns the number of rings, including any empty inner rings.
+    pub fn len(&self) -> usize {
+        self.rings.len()
+    }
+
+    /// Returns true if no peers have been placed into the rings.
+    pub fn is_empty(&self) -> bool {
+        self.rings.iter().all(|r| r.is_empty())
+    }
+
+    /// Returns the members of ring `idx`, ordered by ascending RTT.
+    pub fn ring(&self, idx: usize) -> &[(K, Duration)] {
+        self.rings.get(idx).map(|v| v.as_slice()).unwrap_or(&[])
+    }
+
+    /// Returns the peers with an RTT within `[(1 - beta) × rtt, (1 + beta) ×
+    /// rtt]`, ordered by ascending RTT.
+    ///
+    /// These are the peers a Meridian node forwards a closest-node query to
+    /// when the target is `rtt` away.
+    pub fn within(&self, rtt: Duration, beta: f64) -> impl Iterator<Item = (&K, Duration)> {
+        let target = rtt.as_secs_f64();
+        let lo = Duration::from_secs_f64((target * (1.0 - beta)).max(0.0));
+        let hi = Duration::from_secs_f64(target * (1.0 + beta));
+
+        let first = self.ring_index(lo);
+        let last = self.ring_index(hi);
+        self.rings
+            .iter()
+            .skip(first)
+            .take(last + 1 - first)
+            .flatten()
+            .filter(move |(_, v)| *v >= lo && *v <= hi)
+            .map(|(k, v)| (k, *v))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::Dimension3;
+
+    fn ms(v: u64) -> Duration {
+        Duration::from_millis(v)
+    }
+
+    #[test]
+    fn ring_index() {
+        let rings = LatencyRings::<()>::new(ms(1), 2.0);
+
+        assert_eq!(rings.ring_index(ms(0)), 0);
+        assert_eq!(rings.ring_index(Duration::from_micros(999)), 0);
+        assert_eq!(rings.ring_index(ms(1)), 1);
+        assert_eq!(rings.ring_index(ms(3)), 2);
+        assert_eq!(rings.ring_index(ms(4)), 3);
+        assert_eq!(rings.ring_index(ms(100)), 7);
+    }
+
+    #[test]
+    fn insert_and_query() {
+        let mut rings = LatencyRings::new(ms(1), 2.0);
+        assert!(rings.is_empty());
+
+        rings.insert("c", ms(7));
+        rings.insert("a", ms(5));
+        rings.insert("b", ms(6));
+        rings.insert("far", ms(100));
+
+        assert!(!rings.is_empty());
+        assert_eq!(rings.len(), 8);
+        assert_eq!(rings.ring(3), &[("a", ms(5)), ("b", ms(6)), ("c", ms(7))]);
+        assert!(rings.ring(1).is_empty());
+        assert!(rings.ring(42).is_empty());
+
+        let got = rings
+            .within(ms(6), 0.1)
+            .map(|(k, _)| *k)
+            .collect::<Vec<_>>();
+        assert_eq!(got, vec!["b"]);
+
+        let got = rings
+            .within(ms(6), 0.5)
+            .map(|(k, _)| *k)
+            .collect::<Vec<_>>();
+        assert_eq!(got, vec!["a", "b", "c"]);
+    }
+
+    #[test]
+    fn from_table() {
+        let local = Coordinate::new(Dimension3::default(), 1.0, 0.0);
+        let mut table = PeerTable::new();
+        table.insert(
+            "near",
+            &Coordinate::new(Dimension3([0.002, 0.0, 0.0]), 1.0, 0.0),
+        );
+        table.insert(
+            "far",
+            &Coordinate::new(Dimension3([0.050, 0.0, 0.0]), 1.0, 0.0),
+        );
+
+        let rings = LatencyRings::from_table(&table, &local, ms(1), 2.0);
+        let near = rings.ring_index(ms(2));
+        let far = rings.ring_index(ms(50));
+        assert_eq!(rings.ring(near).len(), 1);
+        assert_eq!(rings.ring(near)[0].0, "near");
+        assert_eq!(rings.ring(far)[0].0, "far");
+    }
+}
@@ -0,0 +1,28 @@
+use rand::RngCore;
+
+/// A random number generator drawing from the thread-local generator of
+/// [`rand::thread_rng`], used by a [`Model`](crate::Model) to generate random
+/// unit vectors unless [seeded](crate::Model::with_seed).
+///
+/// Unlike the `ThreadRng` handle it delegates to, it is `Send` and `Sync`,
+/// so models using it can be moved between threads.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ThreadLocalRng;
+
+impl RngCore for ThreadLocalRng {
+    fn next_u32(&mut self) -> u32 {
+        rand::thread_rng().next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        rand::thread_rng().next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        rand::thread_rng().fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        rand::thread_rng().try_fill_bytes(dest)
+    }
+}
@@ -0,0 +1,153 @@
+use crate::coordinate::Coordinate;
+use crate::model::estimate_rtt;
+use crate::vector::Vector;
+use std::time::Duration;
+
+/// The time a sender waits before retransmitting a lost request.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RetransmitTimeout {
+    /// A fixed timeout, regardless of the path RTT.
+    Fixed(Duration),
+
+    /// A timeout proportional to the RTT of the path, such as the `3 × RTT`
+    /// commonly used by application level retry logic.
+    RttMultiple(f64),
+}
+
+/// Scores peers by their effective latency under packet loss.
+///
+/// Selecting peers by RTT alone routinely picks lossy paths - a request sent
+/// over a path with loss rate `p` is lost (and retransmitted after a timeout)
+/// with probability `p` on each attempt, so the expected time to complete a
+/// request is:
+///
+/// ```text
+///     rtt + timeout × p / (1 - p)
+/// ```
+///
+/// The loss rate of each path is supplied by the caller, typically measured
+/// from the same probes used to update the model.
+///
+/// ```
+/// use std::time::Duration;
+/// use vivaldi::{LossAwareScorer, RetransmitTimeout};
+///
+/// let scorer = LossAwareScorer::new(RetransmitTimeout::Fixed(Duration::from_millis(200)));
+///
+/// // A 20ms path with 10% loss is worse than a lossless 40ms path.
+/// let lossy = scorer.score(Duration::from_millis(20), 0.1);
+/// let clean = scorer.score(Duration::from_millis(40), 0.0);
+/// assert!(lossy > clean);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LossAwareScorer {
+    timeout: RetransmitTimeout,
+}
+
+impl LossAwareScorer {
+    /// Initialises a scorer using the given retransmission timeout.
+    pub fn new(timeout: RetransmitTimeout) -> Self {
+        LossAwareScorer { timeout }
+    }
+
+    /// Returns the expected time to complete a request over a path with the
+    /// given RTT and loss rate (between 0 and 1).
+    ///
+    /// A loss rate of 1 or more returns [`Duration::MAX`].
+    pub fn score(&self, rtt: Duration, loss: f64) -> Duration {
+        let loss = if loss.is_nan() { 0.0 } else { loss.max(0.0) };
+        if loss >= 1.0 {
+            return Duration::MAX;
+        }
+
+        let timeout = match self.timeout {
+            RetransmitTimeout::Fixed(v) => v.as_secs_f64(),
+            RetransmitTimeout::RttMultiple(m) => rtt.as_secs_f64() * m,
+        };
+
+        let penalty = timeout * loss / (1.0 - loss);
+        Duration::try_from_secs_f64(rtt.as_secs_f64() + penalty).unwrap_or(Duration::MAX)
+    }
+
+    /// Returns the expected time to complete a request between `local` and
+    /// `remote`, using the estimated RTT between the two coordinates and the
+    /// given loss rate.
+    pub fn score_coordinates<V: Vector>(
+        &self,
+        local: &Coordinate<V>,
+        remote: &Coordinate<V>,
+        loss: f64,
+    ) -> Duration {
+        self.score(estimate_rtt(local, remote), loss)
+    }
+
+    /// Returns the given peers ordered by ascending score (best first), along
+    /// with their score.
+    ///
+    /// Each peer is given as an identifier, its coordinate and the loss rate
+    /// observed on the path to it.
+    pub fn rank<'a, K, V, I>(&self, local: &Coordinate<V>, peers: I) -> Vec<(K, Duration)>
+    where
+        V: Vector + 'a,
+        I: IntoIterator<Item = (K, &'a Coordinate<V>, f64)>,
+    {
+        let mut scored = peers
+            .into_iter()
+            .map(|(k, c, loss)| (k, self.score_coordinates(local, c, loss)))
+            .collect::<Vec<_>>();
+        scored.sort_by_key(|(_, score)| *score);
+        scored
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::Dimension3;
+
+    #[test]
+    fn score() {
+        let scorer = LossAwareScorer::new(RetransmitTimeout::Fixed(Duration::from_secs(1)));
+
+        assert_eq!(
+            scorer.score(Duration::from_secs(1), 0.0),
+            Duration::from_secs(1)
+        );
+        assert_eq!(
+            scorer.score(Duration::from_secs(1), 0.5),
+            Duration::from_secs(2)
+        );
+        assert_eq!(scorer.score(Duration::from_secs(1), 1.0), Duration::MAX);
+        assert_eq!(
+            scorer.score(Duration::from_secs(1), -1.0),
+            Duration::from_secs(1)
+        );
+        assert_eq!(
+            scorer.score(Duration::from_secs(1), f64::NAN),
+            Duration::from_secs(1)
+        );
+    }
+
+    #[test]
+    fn score_rtt_multiple() {
+        let scorer = LossAwareScorer::new(RetransmitTimeout::RttMultiple(3.0));
+        assert_eq!(
+            scorer.score(Duration::from_secs(2), 0.5),
+            Duration::from_secs(8)
+        );
+    }
+
+    #[test]
+    fn rank_prefers_clean_paths() {
+        let scorer = LossAwareScorer::new(RetransmitTimeout::Fixed(Duration::from_secs(1)));
+        let local = Coordinate::new(Dimension3::default(), 1.0, 0.0);
+        let near = Coordinate::new(Dimension3([0.1, 0.0, 0.0]), 1.0, 0.0);
+        let far = Coordinate::new(Dimension3([0.2, 0.0, 0.0]), 1.0, 0.0);
+
+        let ranked = scorer.rank(&local, vec![("near", &near, 0.2), ("far", &far, 0.0)]);
+        assert_eq!(
+            ranked.iter().map(|(k, _)| *k).collect::<Vec<_>>(),
+            vec!["far", "near"]
+        );
+    }
+}
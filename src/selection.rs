@@ -0,0 +1,263 @@
+use crate::coordinate::Coordinate;
+use crate::model::estimate_rtt;
+use crate::vector::Vector;
+use std::time::Duration;
+
+/// Orders resolved endpoints (such as the targets of DNS SRV records) by their
+/// estimated RTT from the local node, with configurable stickiness.
+///
+/// Endpoint coordinates are typically published alongside the endpoint
+/// address, for example in a TXT record or by a discovery service.
+///
+/// Without stickiness, small fluctuations in the estimates cause clients to
+/// flap between endpoints of similar latency, losing connection reuse and
+/// warm caches. With a stickiness of `s`, the previously preferred endpoint
+/// remains first unless another endpoint is estimated to be faster by more
+/// than a fraction `s` of its RTT.
+///
+/// ```
+/// use vivaldi::{EndpointSelector, Model, vector::Dimension3};
+///
+/// let local = Model::<Dimension3>::new();
+/// let a = Model::<Dimension3>::new();
+/// let b = Model::<Dimension3>::new();
+///
+/// // Only switch endpoint for a 20% improvement.
+/// let mut selector = EndpointSelector::new(0.2);
+///
+/// let ordered = selector.order(
+///     local.get_coordinate(),
+///     vec![
+///         ("10.0.0.1:443", a.get_coordinate()),
+///         ("10.0.0.2:443", b.get_coordinate()),
+///     ],
+/// );
+/// let (best, rtt) = ordered[0];
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct EndpointSelector<E> {
+    stickiness: f64,
+    preferred: Option<E>,
+}
+
+impl<E> EndpointSelector<E>
+where
+    E: Clone + PartialEq,
+{
+    /// Initialises a selector that switches preferred endpoint only when
+    /// another endpoint is faster by more than the fraction `stickiness` (0
+    /// always picks the fastest).
+    pub fn new(stickiness: f64) -> Self {
+        EndpointSelector {
+            stickiness: stickiness.max(0.0),
+            preferred: None,
+        }
+    }
+
+    /// Returns the endpoint placed first by the last call to
+    /// [`order`](EndpointSelector::order), if any.
+    pub fn preferred(&self) -> Option<&E> {
+        self.preferred.as_ref()
+    }
+
+    /// Returns `endpoints` ordered by ascending estimated RTT from `local`,
+    /// with the previously preferred endpoint kept first if it is within the
+    /// stickiness margin of the fastest.
+    pub fn order<'a, V, I>(&mut self, local: &Coordinate<V>, endpoints: I) -> Vec<(E, Duration)>
+    where
+        V: Vector + 'a,
+        I: IntoIterator<Item = (E, &'a Coordinate<V>)>,
+    {
+        let mut ordered = endpoints
+            .into_iter()
+            .map(|(e, c)| (e, estimate_rtt(local, c)))
+            .collect::<Vec<_>>();
+        ordered.sort_by_key(|(_, rtt)| *rtt);
+
+        let sticky = self
+            .preferred
+            .as_ref()
+            .and_then(|p| ordered.iter().position(|(e, _)| e == p));
+
+        if let (Some(idx), Some((_, fastest))) = (sticky, ordered.first()) {
+            let current = ordered[idx].1.as_secs_f64();
+            if fastest.as_secs_f64() >= current * (1.0 - self.stickiness) {
+                let v = ordered.remove(idx);
+                ordered.insert(0, v);
+            }
+        }
+
+        self.preferred = ordered.first().map(|(e, _)| e.clone());
+        ordered
+    }
+}
+
+/// A point of presence (such as a CDN edge site) to which clients are steered
+/// by [`select_pop`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pop<K, V>
+where
+    V: Vector,
+{
+    /// The identifier of the PoP.
+    pub id: K,
+
+    /// The coordinate of the PoP.
+    pub coordinate: Coordinate<V>,
+
+    /// The maximum number of clients to assign to the PoP, or `None` if
+    /// unlimited.
+    pub capacity: Option<usize>,
+}
+
+/// Assigns each of `clients` to one of `pops`, minimising the estimated RTT
+/// between clients and their assigned PoP subject to the capacity of each PoP,
+/// for DNS or anycast steering.
+///
+/// Returns the index into `pops` of the PoP assigned to each client, in the
+/// order of `clients`:
+///
+/// ```
+/// use vivaldi::{select_pop, Model, Pop, vector::Dimension3};
+///
+/// # let edge = |_| *Model::<Dimension3>::new().get_coordinate();
+/// let pops = vec![
+///     Pop { id: "lhr", coordinate: edge("lhr"), capacity: Some(1000) },
+///     Pop { id: "iad", coordinate: edge("iad"), capacity: None },
+/// ];
+/// let clients = vec![*Model::<Dimension3>::new().get_coordinate()];
+///
+/// for (client, pop) in clients.iter().zip(select_pop(&clients, &pops)) {
+///     println!("steer to {}", pops[pop.unwrap()].id);
+/// }
+/// ```
+///
+/// Client-PoP pairs are assigned greedily in order of ascending estimated RTT,
+/// so each client receives the nearest PoP with remaining capacity. Once every
+/// PoP is at capacity, the remaining clients are assigned to their nearest PoP
+/// regardless of capacity, as a client must always be steered somewhere.
+/// Returns `None` for every client if `pops` is empty.
+pub fn select_pop<K, V>(clients: &[Coordinate<V>], pops: &[Pop<K, V>]) -> Vec<Option<usize>>
+where
+    V: Vector,
+{
+    let mut pairs = clients
+        .iter()
+        .enumerate()
+        .flat_map(|(c, client)| {
+            pops.iter()
+                .enumerate()
+                .map(move |(p, pop)| (estimate_rtt(client, &pop.coordinate), c, p))
+        })
+        .collect::<Vec<_>>();
+    pairs.sort_unstable();
+
+    let mut remaining = pops.iter().map(|p| p.capacity).collect::<Vec<_>>();
+    let mut assigned = vec![None; clients.len()];
+    for (_, c, p) in &pairs {
+        if assigned[*c].is_some() {
+            continue;
+        }
+        match &mut remaining[*p] {
+            Some(0) => continue,
+            Some(n) => *n -= 1,
+            None => {}
+        }
+        assigned[*c] = Some(*p);
+    }
+
+    // Clients left over once all PoPs are full go to their nearest PoP.
+    for (_, c, p) in &pairs {
+        if assigned[*c].is_none() {
+            assigned[*c] = Some(*p);
+        }
+    }
+
+    assigned
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::Dimension3;
+
+    fn coord(x: f64) -> Coordinate<Dimension3> {
+        Coordinate::new(Dimension3([x, 0.0, 0.0]), 1.0, 0.0)
+    }
+
+    fn names(v: &[(&'static str, Duration)]) -> Vec<&'static str> {
+        v.iter().map(|(e, _)| *e).collect()
+    }
+
+    #[test]
+    fn orders_by_rtt() {
+        let local = coord(0.0);
+        let (a, b, c) = (coord(0.3), coord(0.1), coord(0.2));
+
+        let mut s = EndpointSelector::new(0.0);
+        let got = s.order(&local, vec![("a", &a), ("b", &b), ("c", &c)]);
+        assert_eq!(names(&got), vec!["b", "c", "a"]);
+        assert_eq!(s.preferred(), Some(&"b"));
+    }
+
+    #[test]
+    fn stickiness() {
+        let local = coord(0.0);
+        let mut s = EndpointSelector::new(0.2);
+
+        let got = s.order(&local, vec![("a", &coord(1.0)), ("b", &coord(2.0))]);
+        assert_eq!(names(&got), vec!["a", "b"]);
+
+        // b is now 10% faster than a - within the stickiness margin.
+        let got = s.order(&local, vec![("a", &coord(1.0)), ("b", &coord(0.9))]);
+        assert_eq!(names(&got), vec!["a", "b"]);
+
+        // b is now 50% faster than a.
+        let got = s.order(&local, vec![("a", &coord(1.0)), ("b", &coord(0.5))]);
+        assert_eq!(names(&got), vec!["b", "a"]);
+        assert_eq!(s.preferred(), Some(&"b"));
+    }
+
+    #[test]
+    fn preferred_endpoint_removed() {
+        let local = coord(0.0);
+        let mut s = EndpointSelector::new(0.5);
+
+        s.order(&local, vec![("a", &coord(1.0))]);
+        let got = s.order(&local, vec![("b", &coord(2.0)), ("c", &coord(1.5))]);
+        assert_eq!(names(&got), vec!["c", "b"]);
+    }
+
+    fn pop(id: &'static str, x: f64, capacity: Option<usize>) -> Pop<&'static str, Dimension3> {
+        Pop {
+            id,
+            coordinate: coord(x),
+            capacity,
+        }
+    }
+
+    #[test]
+    fn select_pop_nearest() {
+        let pops = vec![pop("a", 0.0, None), pop("b", 1.0, None)];
+        let clients = vec![coord(0.1), coord(0.9), coord(0.4)];
+        assert_eq!(select_pop(&clients, &pops), vec![Some(0), Some(1), Some(0)]);
+
+        assert_eq!(select_pop::<&str, _>(&clients, &[]), vec![None, None, None]);
+    }
+
+    #[test]
+    fn select_pop_capacity() {
+        let pops = vec![pop("a", 0.0, Some(1)), pop("b", 1.0, Some(1))];
+
+        // The client nearest a is assigned first, spilling the other to b.
+        let clients = vec![coord(0.2), coord(0.1)];
+        assert_eq!(select_pop(&clients, &pops), vec![Some(1), Some(0)]);
+
+        // Over capacity, the remaining clients go to their nearest PoP.
+        let clients = vec![coord(0.2), coord(0.1), coord(0.9), coord(0.3)];
+        assert_eq!(
+            select_pop(&clients, &pops),
+            vec![Some(0), Some(0), Some(1), Some(0)]
+        );
+    }
+}
@@ -0,0 +1,167 @@
+use crate::coordinate::Coordinate;
+use crate::error::Error;
+use crate::vector::Vector;
+use std::convert::TryFrom;
+
+/// A coordinate in the JSON representation of HashiCorp's
+/// `coordinate.Coordinate`, as exchanged by Serf and served by Consul's
+/// `/v1/coordinate` endpoints:
+///
+/// ```text
+///     {"Vec":[...],"Error":1.5,"Adjustment":0,"Height":0.00001}
+/// ```
+///
+/// Together with a model built with [`ModelBuilder::serf`] over an
+/// 8 dimensional vector, this allows Rust nodes to participate in an existing
+/// Serf or Consul cluster:
+///
+/// ```
+/// use std::convert::TryFrom;
+/// use vivaldi::{Coordinate, ModelBuilder, SerfCoordinate, vector::DimensionN};
+///
+/// let model = ModelBuilder::serf().build::<DimensionN<8>>();
+///
+/// // Publish the local coordinate to the cluster.
+/// let json = serde_json::to_string(&SerfCoordinate::from(model.get_coordinate()))?;
+///
+/// // And read the coordinate of a Serf node.
+/// let remote = serde_json::from_str::<SerfCoordinate>(&json)?;
+/// let remote = Coordinate::<DimensionN<8>>::try_from(remote)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// [`ModelBuilder::serf`]: crate::ModelBuilder::serf
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SerfCoordinate {
+    /// The Euclidean components of the coordinate, in seconds.
+    #[serde(rename = "Vec")]
+    pub vec: Vec<f64>,
+
+    /// The error estimate of the coordinate.
+    #[serde(rename = "Error")]
+    pub error: f64,
+
+    /// The adjustment added to RTT estimates, in seconds.
+    #[serde(rename = "Adjustment")]
+    pub adjustment: f64,
+
+    /// The height of the coordinate, in seconds.
+    #[serde(rename = "Height")]
+    pub height: f64,
+}
+
+impl<V> From<&Coordinate<V>> for SerfCoordinate
+where
+    V: Vector,
+{
+    fn from(c: &Coordinate<V>) -> Self {
+        SerfCoordinate {
+            vec: c.vector().components().to_vec(),
+            error: c.error(),
+            adjustment: c.adjustment(),
+            height: c.height(),
+        }
+    }
+}
+
+/// Converts a Serf coordinate, returning [`Error::DimensionMismatch`] if it
+/// has a different dimensionality than `V` (Serf defaults to 8 dimensions),
+/// or an error if any value is invalid as described by
+/// [`Coordinate::try_new`].
+impl<V> TryFrom<SerfCoordinate> for Coordinate<V>
+where
+    V: Vector,
+{
+    type Error = Error;
+
+    fn try_from(c: SerfCoordinate) -> Result<Self, Self::Error> {
+        let vector = V::from_components(&c.vec).ok_or_else(|| Error::DimensionMismatch {
+            expected: V::default().components().len(),
+            got: c.vec.len(),
+        })?;
+
+        let coord = Coordinate::new(vector, c.error, c.height).with_adjustment(c.adjustment);
+        coord.validate()?;
+        Ok(coord)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::ModelBuilder;
+    use crate::model::estimate_rtt;
+    use crate::vector::{Dimension3, DimensionN};
+    use std::time::Duration;
+
+    type Serf = DimensionN<8>;
+
+    /// Coordinates in the form encoded by Go's `encoding/json`.
+    const NEW: &str = r#"{"Vec":[0,0,0,0,0,0,0,0],"Error":1.5,"Adjustment":0,"Height":0.00001}"#;
+    const CONVERGED: &str = r#"{"Vec":[0.0008592451527066446,-0.0004375418349541405,0.0011209735019384516,0.00025370813541064,-0.0006826193471307574,0.0003035946013062745,-0.00011584009473264523,0.0004736590476428716],"Error":0.1741237519062114,"Adjustment":-0.00003571430581587133,"Height":0.000012880914815208627}"#;
+
+    fn parse(json: &str) -> SerfCoordinate {
+        serde_json::from_str(json).unwrap()
+    }
+
+    fn decode(json: &str) -> Coordinate<Serf> {
+        Coordinate::try_from(parse(json)).unwrap()
+    }
+
+    #[test]
+    fn round_trip() {
+        for json in [NEW, CONVERGED] {
+            let go = parse(json);
+            let c = Coordinate::<Serf>::try_from(go.clone()).unwrap();
+            assert_eq!(SerfCoordinate::from(&c), go);
+
+            let encoded = serde_json::to_string(&SerfCoordinate::from(&c)).unwrap();
+            assert_eq!(parse(&encoded), go);
+        }
+
+        let c = decode(CONVERGED);
+        assert_eq!(c.vector().0[2], 0.0011209735019384516);
+        assert_eq!(c.error(), 0.1741237519062114);
+        assert_eq!(c.adjustment(), -0.00003571430581587133);
+        assert_eq!(c.height(), 0.000012880914815208627);
+    }
+
+    #[test]
+    fn new_coordinate_matches_serf() {
+        let model = ModelBuilder::serf().build::<Serf>();
+        assert_eq!(model.get_coordinate(), &decode(NEW));
+        assert_eq!(SerfCoordinate::from(model.get_coordinate()), parse(NEW));
+    }
+
+    #[test]
+    fn observe_serf_node() {
+        let remote = decode(CONVERGED);
+        let mut model = ModelBuilder::serf().build::<Serf>();
+        for _ in 0..50 {
+            model.observe_peer("serf-node", &remote, Duration::from_millis(5));
+        }
+
+        let rtt = estimate_rtt(model.get_coordinate(), &remote);
+        assert!((rtt.as_secs_f64() - 0.005).abs() < 0.0005, "{:?}", rtt);
+        assert!(model.get_coordinate().error() <= 1.5);
+    }
+
+    #[test]
+    fn invalid() {
+        let c = parse(CONVERGED);
+        assert_eq!(
+            Coordinate::<Dimension3>::try_from(c.clone()),
+            Err(Error::DimensionMismatch {
+                expected: 3,
+                got: 8
+            })
+        );
+
+        let mut nan = c;
+        nan.height = f64::NAN;
+        assert_eq!(
+            Coordinate::<Serf>::try_from(nan),
+            Err(Error::NonFiniteCoordinate)
+        );
+    }
+}
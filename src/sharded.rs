@@ -0,0 +1,286 @@
+use crate::clock::{Clock, SystemClock};
+use crate::coordinate::Coordinate;
+use crate::force::{ForceFunction, LinearSpring};
+use crate::model::Model;
+use crate::rng::ThreadLocalRng;
+use crate::vector::{Scalar, Vector};
+use rand::Rng;
+
+/// Several worker-local [`Model`] shards absorbing observations in parallel,
+/// periodically merged into a single published coordinate.
+///
+/// A single [`Model`] processes observations one at a time. On nodes
+/// aggregating measurements from many peers, each worker thread can instead
+/// own a shard, observing into it without synchronisation, while the
+/// published coordinate is refreshed by a periodic [`merge`](ShardedModel::merge):
+///
+/// ```
+/// use std::time::Duration;
+/// use vivaldi::{ShardedModel, vector::Dimension3};
+///
+/// let mut sharded = ShardedModel::<Dimension3>::new(4);
+/// # let remote = *sharded.published();
+///
+/// std::thread::scope(|s| {
+///     for shard in sharded.shards_mut() {
+///         s.spawn(move || {
+///             // Each worker observes its share of the responses.
+///             # let (remote, rtt) = (remote, Duration::from_millis(20));
+///             shard.observe(&remote, rtt);
+///         });
+///     }
+/// });
+///
+/// sharded.merge();
+/// let published = sharded.published();
+/// ```
+///
+/// Each shard starts from the published coordinate of the previous merge, and
+/// the movements of every shard since then are summed into the new published
+/// coordinate - approximating the same observations applied to a single model
+/// in turn. The error estimate and adjustment of the merged coordinate are the
+/// means of the shards, and the merged coordinate is bounded by the shard
+/// configuration as an observation would be. Observations made between merges
+/// see a slightly stale local coordinate, so merge often relative to the rate
+/// the coordinate moves.
+///
+/// Shards using a custom force function, scalar type or RNG are built
+/// individually and combined with [`from_models`](ShardedModel::from_models).
+#[derive(Debug, Clone)]
+pub struct ShardedModel<V, C = SystemClock, F = LinearSpring, T = f64, R = ThreadLocalRng>
+where
+    V: Vector<T> + std::fmt::Debug,
+    T: Scalar,
+{
+    published: Coordinate<V, T>,
+    shards: Vec<Model<V, C, F, T, R>>,
+}
+
+impl<V> ShardedModel<V>
+where
+    V: Vector + std::fmt::Debug,
+{
+    /// Initialises `shards` new models.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `shards` is zero.
+    pub fn new(shards: usize) -> Self {
+        ShardedModel::with_clock(shards, SystemClock)
+    }
+}
+
+impl<V, C> ShardedModel<V, C>
+where
+    V: Vector + std::fmt::Debug,
+    C: Clock + Clone,
+{
+    /// Initialises `shards` new models, each reading the current time from a
+    /// clone of `clock`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `shards` is zero.
+    pub fn with_clock(shards: usize, clock: C) -> Self {
+        assert!(shards > 0, "sharded model requires at least one shard");
+        let shards = (0..shards)
+            .map(|_| Model::with_clock(clock.clone()))
+            .collect::<Vec<_>>();
+        ShardedModel::from_models(shards)
+    }
+}
+
+impl<V, C, F, T, R> ShardedModel<V, C, F, T, R>
+where
+    V: Vector<T> + std::fmt::Debug,
+    C: Clock,
+    F: ForceFunction,
+    T: Scalar,
+    R: Rng,
+{
+    /// Combines `shards` built with the same configuration, such as with a
+    /// [`ModelBuilder`](crate::ModelBuilder) and a custom
+    /// [force function](Model::with_force_function), into a sharded model.
+    ///
+    /// Every shard is reset to the coordinate of the first.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `shards` is empty.
+    pub fn from_models(mut shards: Vec<Model<V, C, F, T, R>>) -> Self {
+        assert!(
+            !shards.is_empty(),
+            "sharded model requires at least one shard"
+        );
+        let published = shards[0].get_coordinate().clone();
+        for shard in shards.iter_mut().skip(1) {
+            shard.set_coordinate(published.clone());
+        }
+        ShardedModel { published, shards }
+    }
+
+    /// Returns the coordinate produced by the most recent
+    /// [`merge`](ShardedModel::merge), to be sent to peers.
+    pub fn published(&self) -> &Coordinate<V, T> {
+        &self.published
+    }
+
+    /// Returns the shard models, for distributing between workers.
+    pub fn shards_mut(&mut self) -> &mut [Model<V, C, F, T, R>] {
+        &mut self.shards
+    }
+
+    /// Returns the shard models.
+    pub fn shards(&self) -> &[Model<V, C, F, T, R>] {
+        &self.shards
+    }
+
+    /// Merges the movement of every shard since the last merge into the
+    /// published coordinate, and resets each shard to it.
+    pub fn merge(&mut self) -> &Coordinate<V, T> {
+        let base = self.published.vector().clone();
+        let base_height = self.published.height().into_f64();
+        let mut vector = base.clone();
+        let mut height = base_height;
+        let mut error = 0.0;
+        let mut adjustment = 0.0;
+        for shard in &self.shards {
+            let c = shard.get_coordinate();
+            vector = vector + (c.vector().clone() - &base);
+            height += c.height().into_f64() - base_height;
+            error += c.error().into_f64();
+            adjustment += c.adjustment().into_f64();
+        }
+
+        // The shards share a configuration, which bounds the merged
+        // coordinate as it would the result of an observation.
+        let n = self.shards.len() as f64;
+        self.published = self.shards[0].config().normalise(
+            Coordinate::new(vector, T::from_f64(error / n), T::from_f64(height))
+                .with_adjustment(T::from_f64(adjustment / n)),
+        );
+        for shard in self.shards.iter_mut() {
+            shard.set_coordinate(self.published.clone());
+        }
+        &self.published
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::builder::ModelBuilder;
+    use crate::force::LogarithmicSpring;
+    use crate::model::estimate_rtt;
+    use crate::vector::{Dimension2, DimensionN, Spherical};
+    use rand::{rngs::StdRng, SeedableRng};
+    use std::time::Duration;
+
+    #[test]
+    fn merge_sums_movement() {
+        let mut m = ShardedModel::<Dimension2>::new(2);
+        let start = *m.published();
+
+        let moved = |x: f64, y: f64, error: f64, height: f64, adjustment: f64| {
+            Coordinate::new(
+                *start.vector() + Dimension2([x, y]),
+                error,
+                start.height() + height,
+            )
+            .with_adjustment(adjustment)
+        };
+        m.shards_mut()[0].set_coordinate(moved(0.1, 0.0, 1.0, 0.01, 0.002));
+        m.shards_mut()[1].set_coordinate(moved(0.0, 0.2, 0.5, 0.02, 0.004));
+
+        let merged = *m.merge();
+        assert_eq!(merged.vector(), &(*start.vector() + Dimension2([0.1, 0.2])));
+        assert_eq!(merged.error(), 0.75);
+        assert!((merged.adjustment() - 0.003).abs() < 1e-12);
+        assert!((merged.height() - (start.height() + 0.03)).abs() < 1e-12);
+        assert!(m.shards().iter().all(|s| *s.get_coordinate() == merged));
+    }
+
+    #[test]
+    fn merge_applies_config() {
+        fn shards<V: Vector + std::fmt::Debug>(builder: ModelBuilder) -> Vec<Model<V>> {
+            (0..2).map(|_| builder.build()).collect()
+        }
+
+        // The height floor and maximum error of the shards bound the merge.
+        let mut m = ShardedModel::<Dimension2>::from_models(shards(
+            ModelBuilder::new()
+                .max_error(0.5)
+                .min_height(Duration::from_millis(5)),
+        ));
+        m.shards_mut()[0].set_coordinate(Coordinate::new(Dimension2([0.1, 0.0]), 1.0, 0.0));
+        let merged = *m.merge();
+        assert_eq!(merged.error(), 0.5);
+        assert_eq!(merged.height(), 0.005);
+
+        // Euclidean shards merge without a height.
+        let mut m =
+            ShardedModel::<Dimension2>::from_models(shards(ModelBuilder::new().euclidean()));
+        m.shards_mut()[0].set_coordinate(Coordinate::new(Dimension2([0.1, 0.0]), 1.0, 0.01));
+        assert_eq!(m.merge().height(), 0.0);
+
+        // The summed movement is projected back onto the sphere.
+        let mut m = ShardedModel::<Spherical>::from_models(shards(ModelBuilder::new()));
+        let start = *m.published().vector();
+        for (shard, step) in m.shards_mut().iter_mut().zip(&[0.01, -0.01]) {
+            let moved = start + Spherical([*step, 0.02, 0.0]);
+            shard.set_coordinate(Coordinate::new(moved.project(), 1.0, 0.001));
+        }
+        let radius = m.merge().vector().magnitude().0;
+        assert!((radius - Spherical::RADIUS).abs() < 1e-12, "{}", radius);
+    }
+
+    #[test]
+    fn parallel_ingestion_converges() {
+        let peers = [[0.0, 0.0], [0.05, 0.0], [0.0, 0.05], [0.05, 0.05]]
+            .iter()
+            .map(|p| Coordinate::new(Dimension2(*p), 0.05, 0.0))
+            .collect::<Vec<_>>();
+        let truth = Coordinate::new(Dimension2([0.02, 0.03]), 0.0, 0.0);
+
+        let mut m = ShardedModel::<Dimension2>::new(4);
+        for _ in 0..50 {
+            std::thread::scope(|s| {
+                for (shard, peer) in m.shards_mut().iter_mut().zip(&peers) {
+                    s.spawn(move || {
+                        for _ in 0..4 {
+                            shard.observe(peer, estimate_rtt(&truth, peer));
+                        }
+                    });
+                }
+            });
+            m.merge();
+        }
+
+        for peer in &peers {
+            let got = estimate_rtt(m.published(), peer).as_secs_f64();
+            let want = estimate_rtt(&truth, peer).as_secs_f64();
+            assert!((got - want).abs() < 0.005, "{} vs {}", got, want);
+        }
+        assert!(m.published().error() < 0.5, "{:?}", m.published());
+    }
+
+    #[test]
+    fn from_models() {
+        let shards = (0..2)
+            .map(|seed| {
+                Model::<DimensionN<2, f32>, _, _, f32>::with_clock(SystemClock)
+                    .with_force_function(LogarithmicSpring)
+                    .with_rng(StdRng::seed_from_u64(seed))
+            })
+            .collect::<Vec<_>>();
+        let mut m = ShardedModel::from_models(shards);
+
+        let remote = Coordinate::new(DimensionN([0.05_f32, 0.0]), 0.1, 0.001);
+        for shard in m.shards_mut() {
+            shard.observe(&remote, std::time::Duration::from_millis(20));
+        }
+        let merged = *m.merge();
+        assert_ne!(merged.vector(), &DimensionN::default());
+        assert!(m.shards().iter().all(|s| *s.get_coordinate() == merged));
+    }
+}
@@ -0,0 +1,466 @@
+use crate::clock::SystemClock;
+use crate::coordinate::Coordinate;
+use crate::force::LinearSpring;
+use crate::model::{estimate_rtt, Model};
+use crate::vector::Vector;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use std::time::Duration;
+
+/// How a [`Simulation`] treats probes that are lost.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProbeLoss {
+    /// Lost probes produce no observation.
+    Drop,
+
+    /// Lost probes are observed as taking the timeout. Probes with an RTT
+    /// above the timeout are also treated as lost, as a real prober would
+    /// give up waiting for them.
+    Timeout(Duration),
+}
+
+/// The largest component (in seconds) of the initial position of each node.
+const INITIAL_SPREAD: f64 = 1.0e-3;
+
+/// The error estimate reported by attackers, claiming high confidence so
+/// victims weight their lies heavily.
+const ATTACKER_ERROR: f64 = 0.01;
+
+/// The behaviour of a malicious node in a [`Simulation`].
+///
+/// Attackers do not update their own coordinates - they only lie to the nodes
+/// probing them, as described in [Kaafar et al.].
+///
+/// [Kaafar et al.]: https://www-sop.inria.fr/members/Chadi.Barakat/cnsm06.pdf
+#[derive(Debug, Clone, PartialEq)]
+pub enum Attack<V>
+where
+    V: Vector,
+{
+    /// Reports the attacker coordinate scaled by `factor`, pushing victims
+    /// away from (or, below 1, towards) the origin.
+    Inflation {
+        /// The scale applied to the reported coordinate.
+        factor: f64,
+    },
+
+    /// Reports a new random coordinate within `radius` of the origin in every
+    /// response, preventing victims from converging.
+    Oscillation {
+        /// The maximum distance of a reported component from the origin.
+        radius: Duration,
+    },
+
+    /// Reports `position`, delaying responses so the measured RTT agrees with
+    /// the distance from `position` to the victim. Attackers sharing a
+    /// position collude, presenting a consistent lie that is hard to detect.
+    Collusion {
+        /// The fake position reported by the colluding attackers.
+        position: V,
+    },
+}
+
+/// Simulates a network of nodes with known pairwise RTTs, each running a
+/// [`Model`], to evaluate convergence under different conditions.
+///
+/// Each round, every node probes one other node chosen at random and observes
+/// the RTT between them - all probes of a round see the coordinates as they
+/// were at the start of the round. Probes can be [lost](Simulation::set_loss)
+/// to reflect lossy real networks:
+///
+/// ```
+/// use std::time::Duration;
+/// use vivaldi::{ProbeLoss, Simulation, vector::Dimension3};
+///
+/// # let rtts = vec![vec![Duration::from_millis(10); 4]; 4];
+/// let mut sim = Simulation::<Dimension3>::with_seed(rtts, 42);
+/// sim.set_loss(0.05, ProbeLoss::Timeout(Duration::from_secs(1)));
+/// sim.run(100);
+///
+/// println!("median error: {:.1}%", sim.median_error() * 100.0);
+/// ```
+///
+/// Nodes can be made [attackers](Simulation::set_attacker) to quantify the
+/// effect of malicious peers, and of any defences configured on the
+/// [models](Simulation::models_mut) of the honest nodes.
+///
+/// With the `rayon` feature enabled, the observations of each round and the
+/// [evaluation](Simulation::relative_errors) of every pair of nodes are
+/// spread across threads. Probes are still chosen on a single thread, and
+/// each model [seeded](Model::with_seed) from the simulation, so a
+/// simulation [seeded](Simulation::with_seed) with the same value produces
+/// the same results with or without the feature.
+#[derive(Debug)]
+pub struct Simulation<V>
+where
+    V: Vector + std::fmt::Debug,
+{
+    rtts: Vec<Vec<Duration>>,
+    models: Vec<Model<V, SystemClock, LinearSpring, f64, StdRng>>,
+    loss: f64,
+    on_loss: ProbeLoss,
+    rng: StdRng,
+    lost: u64,
+    attackers: Vec<Option<Attack<V>>>,
+}
+
+impl<V> Simulation<V>
+where
+    V: Vector + std::fmt::Debug + Send + Sync,
+{
+    /// Initialises a simulation of `rtts.len()` nodes, where `rtts[i][j]` is
+    /// the RTT between node `i` and node `j`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `rtts` is not square.
+    pub fn new(rtts: Vec<Vec<Duration>>) -> Self {
+        Simulation::with_rng(rtts, StdRng::from_entropy())
+    }
+
+    /// Initialises a simulation as [`new`](Simulation::new), choosing probes
+    /// and losses from a random number generator seeded with `seed`.
+    pub fn with_seed(rtts: Vec<Vec<Duration>>, seed: u64) -> Self {
+        Simulation::with_rng(rtts, StdRng::seed_from_u64(seed))
+    }
+
+    fn with_rng(rtts: Vec<Vec<Duration>>, mut rng: StdRng) -> Self {
+        let n = rtts.len();
+        assert!(
+            rtts.iter().all(|r| r.len() == n),
+            "rtt matrix must be square"
+        );
+
+        // Scatter the nodes around the origin so no two models start at the
+        // same position, where they would push each other apart in a random
+        // direction.
+        let models = (0..n)
+            .map(|_| {
+                let mut model = Model::new().with_seed(rng.gen());
+                let mut components = V::default().components().to_vec();
+                for v in components.iter_mut() {
+                    *v = rng.gen_range(-INITIAL_SPREAD..=INITIAL_SPREAD);
+                }
+                let vector = V::from_components(&components)
+                    .expect("default vector has the simulation dimensionality");
+                let initial = model.get_coordinate();
+                model.set_coordinate(Coordinate::new(vector, initial.error(), initial.height()));
+                model
+            })
+            .collect();
+
+        Simulation {
+            rtts,
+            models,
+            loss: 0.0,
+            on_loss: ProbeLoss::Drop,
+            rng,
+            lost: 0,
+            attackers: vec![None; n],
+        }
+    }
+
+    /// Sets the probability (0 to 1) of each probe being lost, and how lost
+    /// probes are treated.
+    ///
+    /// By default no probes are lost.
+    pub fn set_loss(&mut self, probability: f64, on_loss: ProbeLoss) {
+        self.loss = probability.clamp(0.0, 1.0);
+        self.on_loss = on_loss;
+    }
+
+    /// Makes `node` an attacker behaving as `attack`, or honest again if
+    /// `attack` is `None`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `node` is out of range.
+    pub fn set_attacker(&mut self, node: usize, attack: Option<Attack<V>>) {
+        self.attackers[node] = attack;
+    }
+
+    /// Runs `rounds` rounds of probes.
+    pub fn run(&mut self, rounds: usize) {
+        let n = self.models.len();
+        if n < 2 {
+            return;
+        }
+
+        let mut observations = Vec::with_capacity(n);
+        for _ in 0..rounds {
+            observations.clear();
+            for i in 0..n {
+                observations.push(self.choose_probe(i));
+            }
+
+            let models = &mut self.models;
+            #[cfg(feature = "rayon")]
+            let models = models.par_iter_mut();
+            #[cfg(not(feature = "rayon"))]
+            let models = models.iter_mut();
+
+            models.zip(&observations).for_each(|(model, observation)| {
+                if let Some((remote, rtt)) = observation {
+                    model.observe(remote, *rtt);
+                }
+            });
+        }
+    }
+
+    /// Chooses the node probed by node `i`, returning the coordinate and RTT
+    /// observed, or `None` if `i` makes no observation.
+    fn choose_probe(&mut self, i: usize) -> Option<(Coordinate<V>, Duration)> {
+        if self.attackers[i].is_some() {
+            return None;
+        }
+
+        // Choose any node other than i.
+        let n = self.models.len();
+        let j = (i + self.rng.gen_range(1..n)) % n;
+        let rtt = self.probe(self.rtts[i][j])?;
+        Some(self.respond(i, j, rtt))
+    }
+
+    /// Returns the coordinate reported by node `j` when probed by node `i`,
+    /// and the RTT measured by `i`.
+    fn respond(&mut self, i: usize, j: usize, rtt: Duration) -> (Coordinate<V>, Duration) {
+        let honest = self.models[j].get_coordinate();
+        match &self.attackers[j] {
+            None => (honest.clone(), rtt),
+            Some(Attack::Inflation { factor }) => {
+                let vector = honest.vector().clone() * *factor;
+                (
+                    Coordinate::new(vector, ATTACKER_ERROR, honest.height()),
+                    rtt,
+                )
+            }
+            Some(Attack::Oscillation { radius }) => {
+                let radius = radius.as_secs_f64();
+                let mut components = V::default().components().to_vec();
+                for v in components.iter_mut() {
+                    *v = self.rng.gen_range(-radius..=radius);
+                }
+                let vector = V::from_components(&components)
+                    .expect("default vector has the simulation dimensionality");
+                (Coordinate::new(vector, ATTACKER_ERROR, 0.0), rtt)
+            }
+            Some(Attack::Collusion { position }) => {
+                let fake = Coordinate::new(position.clone(), ATTACKER_ERROR, 0.0);
+                let claimed = estimate_rtt(&fake, self.models[i].get_coordinate());
+                (fake, rtt.max(claimed))
+            }
+        }
+    }
+
+    /// Returns the RTT observed by a probe of a path with a true RTT of `rtt`,
+    /// or `None` if no observation is made.
+    fn probe(&mut self, rtt: Duration) -> Option<Duration> {
+        let lost = match self.on_loss {
+            ProbeLoss::Drop => self.rng.gen_bool(self.loss),
+            ProbeLoss::Timeout(timeout) => self.rng.gen_bool(self.loss) || rtt > timeout,
+        };
+        if lost {
+            self.lost += 1;
+        }
+
+        let observed = match self.on_loss {
+            ProbeLoss::Drop if lost => return None,
+            ProbeLoss::Timeout(timeout) if lost => timeout,
+            _ => rtt,
+        };
+        Some(observed).filter(|v| *v > Duration::from_secs(0))
+    }
+
+    /// Returns the number of probes lost or timed out.
+    pub fn lost(&self) -> u64 {
+        self.lost
+    }
+
+    /// Returns the model of each node.
+    pub fn models(&self) -> &[Model<V, SystemClock, LinearSpring, f64, StdRng>] {
+        &self.models
+    }
+
+    /// Returns the model of each node, for configuring defences such as
+    /// [strict mode](Model::set_strict) or
+    /// [weight limits](Model::set_weight_limits).
+    pub fn models_mut(&mut self) -> &mut [Model<V, SystemClock, LinearSpring, f64, StdRng>] {
+        &mut self.models
+    }
+
+    /// Returns the relative error of the estimated RTT between every pair of
+    /// honest nodes with a non-zero RTT.
+    pub fn relative_errors(&self) -> Vec<f64> {
+        let n = self.models.len();
+        let pair_error = |i: usize, j: usize| {
+            let want = self.rtts[i][j].as_secs_f64();
+            if want <= 0.0 || self.attackers[i].is_some() || self.attackers[j].is_some() {
+                return None;
+            }
+            let got = estimate_rtt(
+                self.models[i].get_coordinate(),
+                self.models[j].get_coordinate(),
+            )
+            .as_secs_f64();
+            Some((got - want).abs() / want)
+        };
+
+        let pairs = |i: usize| ((i + 1)..n).filter_map(move |j| pair_error(i, j));
+
+        #[cfg(feature = "rayon")]
+        return (0..n).into_par_iter().flat_map_iter(pairs).collect();
+        #[cfg(not(feature = "rayon"))]
+        return (0..n).flat_map(pairs).collect();
+    }
+
+    /// Returns the median of the [relative errors](Simulation::relative_errors),
+    /// or zero if there are none.
+    pub fn median_error(&self) -> f64 {
+        let mut errors = self.relative_errors();
+        if errors.is_empty() {
+            return 0.0;
+        }
+        errors.sort_by(|a, b| a.total_cmp(b));
+        errors[errors.len() / 2]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::Dimension3;
+
+    /// Returns the RTTs between nodes on a 2D grid, 10ms apart.
+    fn grid(side: usize) -> Vec<Vec<Duration>> {
+        let pos = (0..side * side)
+            .map(|i| ((i % side) as f64, (i / side) as f64))
+            .collect::<Vec<_>>();
+        pos.iter()
+            .map(|a| {
+                pos.iter()
+                    .map(|b| {
+                        let d = ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt();
+                        Duration::from_secs_f64(0.010 * d)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn converges() {
+        let mut sim = Simulation::<Dimension3>::with_seed(grid(4), 1);
+        sim.run(200);
+        assert_eq!(sim.lost(), 0);
+        assert_eq!(sim.relative_errors().len(), 16 * 15 / 2);
+        assert!(sim.median_error() < 0.15, "{}", sim.median_error());
+    }
+
+    #[test]
+    fn dropped_probes() {
+        let mut sim = Simulation::<Dimension3>::with_seed(grid(4), 1);
+        sim.set_loss(0.5, ProbeLoss::Drop);
+        sim.run(200);
+
+        let probes = 200 * 16;
+        let lost = sim.lost() as f64 / probes as f64;
+        assert!((lost - 0.5).abs() < 0.05, "{}", lost);
+        assert!(sim.median_error() < 0.2, "{}", sim.median_error());
+
+        sim.set_loss(1.0, ProbeLoss::Drop);
+        let before = sim.models().to_vec();
+        sim.run(10);
+        assert_eq!(sim.models(), &before[..]);
+    }
+
+    #[test]
+    fn timeouts_degrade_accuracy() {
+        let mut sim = Simulation::<Dimension3>::with_seed(grid(4), 1);
+        sim.set_loss(0.2, ProbeLoss::Timeout(Duration::from_millis(200)));
+        sim.run(200);
+        assert!(sim.median_error() > 0.15, "{}", sim.median_error());
+
+        // Probes slower than the timeout are capped.
+        let mut sim = Simulation::<Dimension3>::with_seed(grid(4), 1);
+        sim.set_loss(0.0, ProbeLoss::Timeout(Duration::from_millis(15)));
+        sim.run(1);
+        assert!(sim.lost() > 0);
+    }
+
+    #[test]
+    fn attacks_degrade_honest_nodes() {
+        use crate::vector::Dimension2;
+
+        let baseline = |seed| {
+            let mut sim = Simulation::<Dimension3>::with_seed(grid(5), seed);
+            sim.run(300);
+            sim.median_error()
+        };
+
+        let attacks = vec![
+            Attack::Inflation { factor: 10.0 },
+            Attack::Oscillation {
+                radius: Duration::from_secs(1),
+            },
+        ];
+        for attack in attacks {
+            let mut sim = Simulation::<Dimension3>::with_seed(grid(5), 1);
+            for node in 0..5 {
+                sim.set_attacker(node, Some(attack.clone()));
+            }
+            sim.run(300);
+
+            // Pairs involving attackers are excluded.
+            assert_eq!(sim.relative_errors().len(), 20 * 19 / 2);
+            assert!(
+                sim.median_error() > 2.0 * baseline(1),
+                "{:?}: {}",
+                attack,
+                sim.median_error()
+            );
+        }
+
+        // Colluders appear consistently distant, despite being within 60ms of
+        // every honest node.
+        let position = Dimension3([1.0, 1.0, 1.0]);
+        let mut sim = Simulation::<Dimension3>::with_seed(grid(5), 1);
+        for node in 0..5 {
+            sim.set_attacker(node, Some(Attack::Collusion { position }));
+        }
+        sim.run(300);
+        let fake = Coordinate::new(position, ATTACKER_ERROR, 0.0);
+        for victim in &sim.models()[5..] {
+            let rtt = estimate_rtt(victim.get_coordinate(), &fake).as_secs_f64();
+            assert!(rtt > 1.0, "{}", rtt);
+        }
+
+        // Attackers can be made honest again.
+        let mut sim = Simulation::<Dimension2>::with_seed(grid(3), 1);
+        sim.set_attacker(0, Some(Attack::Inflation { factor: 2.0 }));
+        sim.set_attacker(0, None);
+        sim.run(1);
+        assert_eq!(sim.relative_errors().len(), 9 * 8 / 2);
+        assert_eq!(sim.models_mut().len(), 9);
+    }
+
+    #[test]
+    fn deterministic_under_seed() {
+        let run = |seed| {
+            let mut sim = Simulation::<Dimension3>::with_seed(grid(4), seed);
+            sim.set_loss(0.1, ProbeLoss::Drop);
+            sim.set_attacker(
+                0,
+                Some(Attack::Oscillation {
+                    radius: Duration::from_millis(100),
+                }),
+            );
+            sim.run(50);
+            (sim.models().to_vec(), sim.lost(), sim.relative_errors())
+        };
+
+        assert_eq!(run(7), run(7));
+        assert_ne!(run(7).0, run(8).0);
+    }
+}
@@ -0,0 +1,182 @@
+use crate::clock::{Clock, SystemClock};
+use crate::coordinate::Coordinate;
+use crate::vector::Vector;
+use std::time::Duration;
+
+/// Smooths the output of a [`Model`] by linearly interpolating from the
+/// previously exposed coordinate to each new coordinate over a fixed period.
+///
+/// Each observation can move the model coordinate by a large step, especially
+/// while the error is high. Consumers that render coordinates, or feed
+/// estimates into EWMA-sensitive systems (such as timeouts), see these steps as
+/// discontinuous jumps. The smoother exposes a coordinate that glides towards
+/// the latest model coordinate instead, without affecting the model itself.
+///
+/// ```
+/// use std::time::Duration;
+/// use vivaldi::{CoordinateSmoother, Model, vector::Dimension3};
+///
+/// let model = Model::<Dimension3>::new();
+/// let mut smoother = CoordinateSmoother::new(Duration::from_secs(5));
+///
+/// // After each observation:
+/// smoother.update(model.get_coordinate());
+///
+/// // When reading:
+/// let coord = smoother.coordinate().unwrap();
+/// ```
+///
+/// [`Model`]: crate::Model
+#[derive(Debug, Clone, PartialEq)]
+pub struct CoordinateSmoother<V, C = SystemClock>
+where
+    V: Vector,
+{
+    period: Duration,
+    clock: C,
+    state: Option<State<V>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct State<V>
+where
+    V: Vector,
+{
+    from: Coordinate<V>,
+    to: Coordinate<V>,
+    start: Duration,
+}
+
+impl<V> CoordinateSmoother<V>
+where
+    V: Vector,
+{
+    /// Initialises a smoother that reaches each new coordinate `period` after
+    /// it is passed to [`update`](CoordinateSmoother::update).
+    pub fn new(period: Duration) -> Self {
+        CoordinateSmoother::with_clock(period, SystemClock)
+    }
+}
+
+impl<V, C> CoordinateSmoother<V, C>
+where
+    V: Vector,
+    C: Clock,
+{
+    /// Initialises a smoother reading the current time from `clock`.
+    pub fn with_clock(period: Duration, clock: C) -> Self {
+        CoordinateSmoother {
+            period,
+            clock,
+            state: None,
+        }
+    }
+
+    /// Begins interpolating from the currently exposed coordinate to `target`.
+    ///
+    /// The first update is exposed immediately.
+    pub fn update(&mut self, target: &Coordinate<V>) {
+        let now = self.clock.now();
+        let from = match self.coordinate() {
+            Some(v) => v,
+            None => target.clone(),
+        };
+
+        self.state = Some(State {
+            from,
+            to: target.clone(),
+            start: now,
+        });
+    }
+
+    /// Returns the current smoothed coordinate, or `None` if
+    /// [`update`](CoordinateSmoother::update) has not been called.
+    pub fn coordinate(&self) -> Option<Coordinate<V>> {
+        let state = self.state.as_ref()?;
+
+        let elapsed = self.clock.now().saturating_sub(state.start);
+        if self.period.as_secs_f64() <= 0.0 || elapsed >= self.period {
+            return Some(state.to.clone());
+        }
+
+        let f = elapsed.as_secs_f64() / self.period.as_secs_f64();
+        Some(lerp(&state.from, &state.to, f))
+    }
+}
+
+/// Linearly interpolates each part of the coordinate from `a` (at `f = 0`) to
+/// `b` (at `f = 1`).
+fn lerp<V: Vector>(a: &Coordinate<V>, b: &Coordinate<V>, f: f64) -> Coordinate<V> {
+    let vector = (b.vector().clone() - a.vector()) * f + a.vector().clone();
+    Coordinate::new(
+        vector,
+        a.error() + (b.error() - a.error()) * f,
+        a.height() + (b.height() - a.height()) * f,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use crate::vector::Dimension3;
+
+    fn coord(x: f64, error: f64, height: f64) -> Coordinate<Dimension3> {
+        Coordinate::new(Dimension3([x, 0.0, 0.0]), error, height)
+    }
+
+    #[test]
+    fn interpolates() {
+        let clock = MockClock::default();
+        let mut s = CoordinateSmoother::with_clock(Duration::from_secs(10), clock.clone());
+        assert_eq!(s.coordinate(), None);
+
+        s.update(&coord(0.0, 1.0, 0.1));
+        assert_eq!(s.coordinate(), Some(coord(0.0, 1.0, 0.1)));
+
+        s.update(&coord(10.0, 0.5, 0.2));
+        clock.advance(Duration::from_secs(5));
+        let mid = s.coordinate().unwrap();
+        assert!((mid.vector().0[0] - 5.0).abs() < 1e-9);
+        assert!((mid.error() - 0.75).abs() < 1e-9);
+        assert!((mid.height() - 0.15).abs() < 1e-9);
+
+        clock.advance(Duration::from_secs(15));
+        assert_eq!(s.coordinate(), Some(coord(10.0, 0.5, 0.2)));
+    }
+
+    #[test]
+    fn retargets_from_current_position() {
+        let clock = MockClock::default();
+        let mut s = CoordinateSmoother::with_clock(Duration::from_secs(10), clock.clone());
+        s.update(&coord(0.0, 1.0, 0.1));
+        s.update(&coord(10.0, 1.0, 0.1));
+
+        // Halfway there, retarget back to the origin.
+        clock.advance(Duration::from_secs(5));
+        s.update(&coord(0.0, 1.0, 0.1));
+        assert!((s.coordinate().unwrap().vector().0[0] - 5.0).abs() < 1e-9);
+
+        clock.advance(Duration::from_secs(5));
+        assert!((s.coordinate().unwrap().vector().0[0] - 2.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn clock_moving_backwards() {
+        let clock = MockClock::new(Duration::from_secs(100));
+        let mut s = CoordinateSmoother::with_clock(Duration::from_secs(10), clock.clone());
+        s.update(&coord(0.0, 1.0, 0.1));
+        s.update(&coord(10.0, 1.0, 0.1));
+
+        clock.set(Duration::from_secs(50));
+        assert_eq!(s.coordinate(), Some(coord(0.0, 1.0, 0.1)));
+    }
+
+    #[test]
+    fn zero_period() {
+        let mut s = CoordinateSmoother::new(Duration::from_secs(0));
+        s.update(&coord(0.0, 1.0, 0.1));
+        s.update(&coord(10.0, 1.0, 0.1));
+        assert_eq!(s.coordinate(), Some(coord(10.0, 1.0, 0.1)));
+    }
+}
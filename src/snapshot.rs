@@ -0,0 +1,115 @@
+use crate::clock::Clock;
+use crate::coordinate::Coordinate;
+use crate::force::ForceFunction;
+use crate::model::Model;
+use crate::vector::Vector;
+use rand::Rng;
+
+/// A copy of the coordinate of a [`Model`], tagged with the epoch at which it
+/// was taken.
+///
+/// The epoch of a model increases each time its coordinate changes, so
+/// readers holding a snapshot can cheaply check whether the coordinate has
+/// changed since, without comparing floating point values:
+///
+/// ```
+/// use std::time::Duration;
+/// use vivaldi::{Model, vector::Dimension3};
+///
+/// let mut model = Model::<Dimension3>::new();
+/// # let remote = Model::<Dimension3>::new();
+/// let snapshot = model.snapshot();
+/// assert!(!snapshot.is_stale(&model));
+///
+/// model.observe(remote.get_coordinate(), Duration::from_millis(10));
+/// assert!(snapshot.is_stale(&model));
+/// assert!(model.snapshot().epoch() > snapshot.epoch());
+/// ```
+///
+/// Epochs are only comparable between snapshots of the same model.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CoordinateSnapshot<V>
+where
+    V: Vector,
+{
+    coordinate: Coordinate<V>,
+    epoch: u64,
+}
+
+impl<V> CoordinateSnapshot<V>
+where
+    V: Vector,
+{
+    /// Returns the coordinate at the time the snapshot was taken.
+    pub fn coordinate(&self) -> &Coordinate<V> {
+        &self.coordinate
+    }
+
+    /// Returns the epoch of the model at the time the snapshot was taken.
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
+    /// Returns true if the coordinate of `model` has changed since the
+    /// snapshot was taken.
+    pub fn is_stale<C, F, R>(&self, model: &Model<V, C, F, f64, R>) -> bool
+    where
+        V: std::fmt::Debug,
+        C: Clock,
+        F: ForceFunction,
+        R: Rng,
+    {
+        model.epoch() != self.epoch
+    }
+
+    /// Consumes the snapshot, returning the coordinate.
+    pub fn into_coordinate(self) -> Coordinate<V> {
+        self.coordinate
+    }
+}
+
+impl<V, C, F, R> Model<V, C, F, f64, R>
+where
+    V: Vector + std::fmt::Debug,
+    C: Clock,
+    F: ForceFunction,
+    R: Rng,
+{
+    /// Returns a [`CoordinateSnapshot`] of the current coordinate.
+    pub fn snapshot(&self) -> CoordinateSnapshot<V> {
+        CoordinateSnapshot {
+            coordinate: self.get_coordinate().clone(),
+            epoch: self.epoch(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::Dimension3;
+    use std::time::Duration;
+
+    #[test]
+    fn epochs() {
+        let mut model = Model::<Dimension3>::new();
+        let first = model.snapshot();
+        assert_eq!(first.epoch(), 0);
+        assert_eq!(first.coordinate(), model.get_coordinate());
+
+        // Rejected observations leave the epoch unchanged.
+        let remote = Coordinate::new(Dimension3([1.0, 0.0, 0.0]), 1.0, 0.1);
+        assert!(model.try_observe(&remote, Duration::from_secs(0)).is_err());
+        assert!(!first.is_stale(&model));
+
+        model.observe(&remote, Duration::from_millis(10));
+        let second = model.snapshot();
+        assert!(first.is_stale(&model));
+        assert!(!second.is_stale(&model));
+        assert_eq!(second.epoch(), 1);
+
+        model.set_coordinate(remote);
+        assert!(second.is_stale(&model));
+        assert_eq!(model.snapshot().into_coordinate(), remote);
+    }
+}
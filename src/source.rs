@@ -0,0 +1,406 @@
+use crate::clock::Clock;
+use crate::coordinate::Coordinate;
+#[cfg(feature = "async")]
+use crate::error::Error;
+use crate::force::ForceFunction;
+use crate::model::Model;
+use crate::vector::Vector;
+use rand::Rng;
+use std::collections::VecDeque;
+#[cfg(feature = "async")]
+use std::ops::ControlFlow;
+use std::pin::Pin;
+use std::sync::mpsc::Receiver;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+/// A single RTT measurement to a peer, along with the coordinate the peer
+/// reported.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Observation<K, V>
+where
+    V: Vector,
+{
+    /// The identifier of the measured peer.
+    pub peer: K,
+
+    /// The coordinate reported by the peer.
+    pub coordinate: Coordinate<V>,
+
+    /// The measured round-trip time.
+    pub rtt: Duration,
+}
+
+/// A source of RTT observations, decoupling how measurements are taken from
+/// how they are applied to a [`Model`].
+///
+/// This is the synchronous flavour - see [`AsyncRttSource`] for the
+/// asynchronous equivalent.
+pub trait RttSource<K, V>
+where
+    V: Vector,
+{
+    /// Returns the next available observation, or `None` if no observation is
+    /// currently available.
+    ///
+    /// Implementations should not block - a `None` return does not imply the
+    /// source is exhausted, and may be followed by further observations.
+    fn next_observation(&mut self) -> Option<Observation<K, V>>;
+}
+
+/// An asynchronous source of RTT observations.
+///
+/// With the `async` feature enabled this is implemented for any
+/// [`Stream`](futures_core::Stream) of [`Observation`] values.
+pub trait AsyncRttSource<K, V>
+where
+    V: Vector,
+{
+    /// Attempts to pull the next observation, registering the current task
+    /// for wakeup if none is available.
+    ///
+    /// Returns `Poll::Ready(None)` once the source is exhausted.
+    fn poll_observation(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Observation<K, V>>>;
+}
+
+#[cfg(feature = "async")]
+impl<K, V, S> AsyncRttSource<K, V> for S
+where
+    V: Vector,
+    S: futures_core::Stream<Item = Observation<K, V>>,
+{
+    fn poll_observation(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Observation<K, V>>> {
+        self.poll_next(cx)
+    }
+}
+
+/// An [`RttSource`] fed manually by the application.
+///
+/// ```
+/// use std::time::Duration;
+/// use vivaldi::{ManualSource, Model, Observation, vector::Dimension3};
+///
+/// let mut model = Model::<Dimension3>::new();
+/// let remote = Model::<Dimension3>::new();
+///
+/// let mut source = ManualSource::default();
+/// source.push(Observation {
+///     peer: "remote",
+///     coordinate: *remote.get_coordinate(),
+///     rtt: Duration::from_millis(10),
+/// });
+///
+/// assert_eq!(model.drain(&mut source), 1);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ManualSource<K, V>
+where
+    V: Vector,
+{
+    queue: VecDeque<Observation<K, V>>,
+}
+
+impl<K, V> ManualSource<K, V>
+where
+    V: Vector,
+{
+    /// Queues an observation to be returned by the source.
+    pub fn push(&mut self, obs: Observation<K, V>) {
+        self.queue.push_back(obs)
+    }
+
+    /// Returns the number of queued observations.
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Returns true if no observations are queued.
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+}
+
+impl<K, V> RttSource<K, V> for ManualSource<K, V>
+where
+    V: Vector,
+{
+    fn next_observation(&mut self) -> Option<Observation<K, V>> {
+        self.queue.pop_front()
+    }
+}
+
+/// An [`RttSource`] adapter yielding the observations of an iterator.
+#[derive(Debug, Clone)]
+pub struct IterSource<I>(pub I);
+
+impl<K, V, I> RttSource<K, V> for IterSource<I>
+where
+    V: Vector,
+    I: Iterator<Item = Observation<K, V>>,
+{
+    fn next_observation(&mut self) -> Option<Observation<K, V>> {
+        self.0.next()
+    }
+}
+
+/// Observations sent from any thread can be received from the channel
+/// without blocking.
+impl<K, V> RttSource<K, V> for Receiver<Observation<K, V>>
+where
+    V: Vector,
+{
+    fn next_observation(&mut self) -> Option<Observation<K, V>> {
+        self.try_recv().ok()
+    }
+}
+
+impl<V, C, F, R> Model<V, C, F, f64, R>
+where
+    V: Vector + std::fmt::Debug,
+    C: Clock,
+    F: ForceFunction,
+    R: Rng,
+{
+    /// Applies every observation currently available from `source`, returning
+    /// the number applied.
+    pub fn drain<K, S>(&mut self, source: &mut S) -> usize
+    where
+        S: RttSource<K, V> + ?Sized,
+    {
+        let mut n = 0;
+        while let Some(obs) = source.next_observation() {
+            self.observe(&obs.coordinate, obs.rtt);
+            n += 1;
+        }
+        n
+    }
+
+    /// Applies observations from `source` as they arrive, until the source is
+    /// exhausted or `outcome` stops the drive.
+    ///
+    /// Each observation is applied with [`try_observe`](Model::try_observe),
+    /// and the result passed to `outcome` along with the observation.
+    /// Returning [`ControlFlow::Break`] from `outcome` stops the drive, for
+    /// example on shutdown or after too many rejections:
+    ///
+    /// ```
+    /// use std::ops::ControlFlow;
+    /// use vivaldi::{Model, Observation, vector::Dimension3};
+    ///
+    /// async fn run(
+    ///     model: &mut Model<Dimension3>,
+    ///     observations: impl futures_core::Stream<Item = Observation<String, Dimension3>>,
+    /// ) {
+    ///     let summary = model
+    ///         .drive(observations, |obs, result| {
+    ///             if let Err(e) = result {
+    ///                 eprintln!("rejected observation of {}: {}", obs.peer, e);
+    ///             }
+    ///             ControlFlow::Continue(())
+    ///         })
+    ///         .await;
+    ///
+    ///     println!("applied {} observations", summary.applied);
+    /// }
+    /// ```
+    ///
+    /// The returned future is also cancelled by dropping it - observations are
+    /// applied synchronously once received, so no observation is partially
+    /// applied.
+    #[cfg(feature = "async")]
+    pub async fn drive<K, S, O>(&mut self, source: S, mut outcome: O) -> DriveSummary
+    where
+        S: AsyncRttSource<K, V>,
+        O: FnMut(&Observation<K, V>, Result<(), Error>) -> ControlFlow<()>,
+    {
+        let mut source = std::pin::pin!(source);
+        let mut summary = DriveSummary::default();
+
+        while let Some(obs) = std::future::poll_fn(|cx| source.as_mut().poll_observation(cx)).await
+        {
+            let result = self.try_observe(&obs.coordinate, obs.rtt);
+            match result {
+                Ok(()) => summary.applied += 1,
+                Err(_) => summary.rejected += 1,
+            }
+            if outcome(&obs, result).is_break() {
+                summary.cancelled = true;
+                break;
+            }
+        }
+
+        summary
+    }
+}
+
+/// The outcome of a [`Model::drive`] call.
+#[cfg(feature = "async")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DriveSummary {
+    /// The number of observations applied to the model.
+    pub applied: usize,
+
+    /// The number of observations rejected by
+    /// [`try_observe`](Model::try_observe).
+    pub rejected: usize,
+
+    /// True if the drive was stopped before the source was exhausted.
+    pub cancelled: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector::Dimension3;
+    use std::sync::mpsc::channel;
+
+    fn obs(peer: &'static str) -> Observation<&'static str, Dimension3> {
+        Observation {
+            peer,
+            coordinate: Coordinate::new(Dimension3([1.0, 0.0, 0.0]), 1.0, 0.1),
+            rtt: Duration::from_millis(10),
+        }
+    }
+
+    #[test]
+    fn manual() {
+        let mut s = ManualSource::default();
+        assert!(s.is_empty());
+        s.push(obs("a"));
+        s.push(obs("b"));
+        assert_eq!(s.len(), 2);
+
+        assert_eq!(s.next_observation().unwrap().peer, "a");
+        assert_eq!(s.next_observation().unwrap().peer, "b");
+        assert!(s.next_observation().is_none());
+    }
+
+    #[test]
+    fn drain_channel() {
+        let (tx, mut rx) = channel();
+        let handle = std::thread::spawn(move || {
+            tx.send(obs("a")).unwrap();
+            tx.send(obs("b")).unwrap();
+        });
+        handle.join().unwrap();
+
+        let mut model = Model::<Dimension3>::new();
+        let before = model.clone();
+        assert_eq!(model.drain(&mut rx), 2);
+        assert_ne!(model, before);
+        assert_eq!(model.drain(&mut rx), 0);
+    }
+
+    #[test]
+    fn drain_iter() {
+        let mut model = Model::<Dimension3>::new();
+        let mut s = IterSource(vec![obs("a"), obs("b"), obs("c")].into_iter());
+        assert_eq!(model.drain(&mut s), 3);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn async_stream() {
+        struct Queue(VecDeque<Observation<&'static str, Dimension3>>);
+
+        impl futures_core::Stream for Queue {
+            type Item = Observation<&'static str, Dimension3>;
+
+            fn poll_next(
+                mut self: Pin<&mut Self>,
+                _cx: &mut Context<'_>,
+            ) -> Poll<Option<Self::Item>> {
+                Poll::Ready(self.0.pop_front())
+            }
+        }
+
+        let mut q = Queue(vec![obs("a")].into());
+        let mut cx = Context::from_waker(std::task::Waker::noop());
+
+        match Pin::new(&mut q).poll_observation(&mut cx) {
+            Poll::Ready(Some(o)) => assert_eq!(o.peer, "a"),
+            v => panic!("unexpected {:?}", v),
+        }
+        assert_eq!(
+            Pin::new(&mut q).poll_observation(&mut cx),
+            Poll::Ready(None)
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn drive() {
+        use std::future::Future;
+
+        struct Queue(VecDeque<Observation<&'static str, Dimension3>>);
+
+        impl futures_core::Stream for Queue {
+            type Item = Observation<&'static str, Dimension3>;
+
+            fn poll_next(
+                mut self: Pin<&mut Self>,
+                _cx: &mut Context<'_>,
+            ) -> Poll<Option<Self::Item>> {
+                Poll::Ready(self.0.pop_front())
+            }
+        }
+
+        fn block_on<F: Future>(f: F) -> F::Output {
+            let mut f = std::pin::pin!(f);
+            let mut cx = Context::from_waker(std::task::Waker::noop());
+            loop {
+                if let Poll::Ready(v) = f.as_mut().poll(&mut cx) {
+                    return v;
+                }
+            }
+        }
+
+        let mut invalid = obs("b");
+        invalid.rtt = Duration::from_secs(0);
+        let queue = || Queue(vec![obs("a"), invalid.clone(), obs("c"), obs("d")].into());
+
+        let mut model = Model::<Dimension3>::new();
+        let mut seen = Vec::new();
+        let summary = block_on(model.drive(queue(), |o, r| {
+            seen.push((o.peer, r));
+            ControlFlow::Continue(())
+        }));
+        assert_eq!(
+            summary,
+            DriveSummary {
+                applied: 3,
+                rejected: 1,
+                cancelled: false,
+            }
+        );
+        assert_eq!(
+            seen,
+            vec![
+                ("a", Ok(())),
+                ("b", Err(Error::InvalidRtt)),
+                ("c", Ok(())),
+                ("d", Ok(())),
+            ]
+        );
+
+        // Stop after the first rejection.
+        let summary = block_on(model.drive(queue(), |_, r| match r {
+            Ok(()) => ControlFlow::Continue(()),
+            Err(_) => ControlFlow::Break(()),
+        }));
+        assert_eq!(
+            summary,
+            DriveSummary {
+                applied: 1,
+                rejected: 1,
+                cancelled: true,
+            }
+        );
+    }
+}
@@ -0,0 +1,151 @@
+//! Emits model telemetry through the [`metrics`] facade when the `metrics`
+//! feature is enabled, and compiles to nothing otherwise.
+//!
+//! The emitted names are documented in the crate root.
+
+use crate::anomaly::AnomalyKind;
+use crate::error::Error;
+use crate::outlier::OutlierPolicy;
+
+/// Records a successful observation leaving the local coordinate with
+/// `error` and `height`, having moved it `displacement` seconds.
+#[inline]
+pub(crate) fn observed(error: f64, height: f64, displacement: f64) {
+    #[cfg(feature = "metrics")]
+    {
+        metrics::counter!("vivaldi_observations_total").increment(1);
+        metrics::gauge!("vivaldi_coordinate_error").set(error);
+        metrics::gauge!("vivaldi_coordinate_height_seconds").set(height);
+        metrics::histogram!("vivaldi_displacement_seconds").record(displacement);
+    }
+    #[cfg(not(feature = "metrics"))]
+    let _ = (error, height, displacement);
+}
+
+/// Records an observation rejected with `err`.
+#[inline]
+pub(crate) fn rejected(err: &Error) {
+    #[cfg(feature = "metrics")]
+    {
+        let reason = match err {
+            Error::InvalidRtt => "invalid_rtt",
+            Error::NonFiniteCoordinate => "non_finite",
+            Error::BelowFloor { .. } => "below_floor",
+            Error::Strict(_) => "strict",
+            _ => "other",
+        };
+        metrics::counter!("vivaldi_observations_rejected_total", "reason" => reason).increment(1);
+    }
+    #[cfg(not(feature = "metrics"))]
+    let _ = err;
+}
+
+/// Records an outlying observation treated according to `policy`.
+#[inline]
+pub(crate) fn outlier(policy: &OutlierPolicy) {
+    #[cfg(feature = "metrics")]
+    {
+        let action = match policy {
+            OutlierPolicy::Discard => "discarded",
+            OutlierPolicy::DownWeight(_) => "down_weighted",
+        };
+        metrics::counter!("vivaldi_outliers_total", "action" => action).increment(1);
+    }
+    #[cfg(not(feature = "metrics"))]
+    let _ = policy;
+}
+
+/// Records an anomaly of `kind` reported by an
+/// [`AnomalyDetector`](crate::AnomalyDetector).
+#[inline]
+pub(crate) fn anomaly(kind: &AnomalyKind) {
+    #[cfg(feature = "metrics")]
+    {
+        let kind = match kind {
+            AnomalyKind::CoordinateShift { .. } => "coordinate_shift",
+            AnomalyKind::ErrorShift { .. } => "error_shift",
+        };
+        metrics::counter!("vivaldi_anomalies_total", "kind" => kind).increment(1);
+    }
+    #[cfg(not(feature = "metrics"))]
+    let _ = kind;
+}
+
+#[cfg(all(test, feature = "metrics"))]
+mod tests {
+    use super::*;
+    use crate::model::Model;
+    use crate::vector::Dimension3;
+    use metrics::{
+        Counter, Gauge, Histogram, Key, KeyName, Metadata, Recorder, SharedString, Unit,
+    };
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    /// Records the name and labels of every registered metric.
+    #[derive(Default)]
+    struct Names(Arc<Mutex<Vec<String>>>);
+
+    impl Names {
+        fn push(&self, key: &Key) {
+            let labels = key
+                .labels()
+                .map(|l| format!("{}={}", l.key(), l.value()))
+                .collect::<Vec<_>>();
+            self.0
+                .lock()
+                .unwrap()
+                .push(format!("{}{:?}", key.name(), labels));
+        }
+    }
+
+    impl Recorder for Names {
+        fn describe_counter(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_gauge(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+        fn describe_histogram(&self, _: KeyName, _: Option<Unit>, _: SharedString) {}
+
+        fn register_counter(&self, key: &Key, _: &Metadata<'_>) -> Counter {
+            self.push(key);
+            Counter::noop()
+        }
+
+        fn register_gauge(&self, key: &Key, _: &Metadata<'_>) -> Gauge {
+            self.push(key);
+            Gauge::noop()
+        }
+
+        fn register_histogram(&self, key: &Key, _: &Metadata<'_>) -> Histogram {
+            self.push(key);
+            Histogram::noop()
+        }
+    }
+
+    #[test]
+    fn emitted_names() {
+        let recorder = Names::default();
+        let names = Arc::clone(&recorder.0);
+
+        metrics::with_local_recorder(&recorder, || {
+            let mut a = Model::<Dimension3>::new();
+            let b = Model::<Dimension3>::new();
+            a.observe(b.get_coordinate(), Duration::from_millis(10));
+            let _ = a.try_observe(b.get_coordinate(), Duration::from_secs(0));
+            anomaly(&AnomalyKind::ErrorShift {
+                baseline: 0.0,
+                current: 1.0,
+            });
+        });
+
+        assert_eq!(
+            *names.lock().unwrap(),
+            vec![
+                "vivaldi_observations_total[]",
+                "vivaldi_coordinate_error[]",
+                "vivaldi_coordinate_height_seconds[]",
+                "vivaldi_displacement_seconds[]",
+                "vivaldi_observations_rejected_total[\"reason=invalid_rtt\"]",
+                "vivaldi_anomalies_total[\"kind=error_shift\"]",
+            ]
+        );
+    }
+}
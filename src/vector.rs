@@ -1,31 +1,148 @@
+use rand::Rng;
 use std::ops::Add;
 use std::ops::Div;
 use std::ops::Mul;
 use std::ops::Sub;
 
+mod dimension_1;
+pub use dimension_1::Dimension1;
+
 mod dimension_2;
 pub use dimension_2::Dimension2;
 
 mod dimension_3;
 pub use dimension_3::Dimension3;
 
-/// An trait to allow the [`Model`](crate::model::Model) to operate in N dimensional Euclidean space.
-pub trait Vector:
+mod dimension_n;
+pub use dimension_n::DimensionN;
+
+mod hyperbolic;
+pub use hyperbolic::Hyperbolic2;
+
+mod spherical;
+pub use spherical::Spherical;
+
+mod simd;
+
+#[cfg(feature = "nalgebra")]
+mod nalgebra;
+
+#[cfg(feature = "glam")]
+mod glam;
+
+#[cfg(feature = "postcard")]
+mod postcard;
+
+/// An trait to allow the [`Model`](crate::model::Model) to operate in N dimensional space.
+///
+/// The arithmetic operators are always Euclidean, while the model measures
+/// distances with [`distance`](Vector::distance) and moves coordinates along
+/// [`displacement`](Vector::displacement) - overriding both embeds the model
+/// in a non-Euclidean space, such as [`Hyperbolic2`] or [`Spherical`].
+///
+/// Vectors are generic over the [`Scalar`] type of their components, which
+/// defaults to `f64`.
+///
+/// With the `nalgebra` feature enabled, `nalgebra`'s statically sized
+/// `SVector<f64, N>` types implement `Vector`, as do `glam`'s `DVec2` and
+/// `DVec3` with the `glam` feature.
+pub trait Vector<T: Scalar = f64>:
     Add<Output = Self>
-    + Add<f64, Output = Self>
     + Sub<Output = Self>
-    + Mul<f64, Output = Self>
-    + Div<f64, Output = Self>
+    + for<'a> Sub<&'a Self, Output = Self>
+    + Mul<T, Output = Self>
+    + Div<T, Output = Self>
     + Clone
     + Default
 {
     /// Returns the magnitude of the vector.
-    fn magnitude(&self) -> Magnitude;
+    fn magnitude(&self) -> Magnitude<T>;
+
+    /// Returns the components of the vector, one per dimension.
+    fn components(&self) -> &[T];
+
+    /// Constructs a vector from its components, returning `None` if the number
+    /// of components does not match the dimensionality of the vector.
+    fn from_components(components: &[T]) -> Option<Self>;
+
+    /// Returns the distance between `self` and `other`, Euclidean unless
+    /// overridden.
+    ///
+    /// The default implementation clones `self` to compute the difference
+    /// vector - implementations should override it to compute the distance by
+    /// reference.
+    fn distance(&self, other: &Self) -> Magnitude<T> {
+        (self.clone() - other).magnitude()
+    }
+
+    /// Returns the vector pointing from `other` towards `self` with a
+    /// magnitude of the [`distance`](Vector::distance) between them.
+    ///
+    /// The default implementation returns `self - other`, and must be
+    /// overridden alongside `distance`.
+    fn displacement(&self, other: &Self) -> Self {
+        self.clone() - other
+    }
 
-    /// Returns a random vector.
-    fn random() -> Self;
+    /// Returns the nearest vector to `self` in the space, applied by the model
+    /// after moving a coordinate.
+    ///
+    /// The default implementation returns `self` unchanged - spaces
+    /// constraining their vectors, such as [`Spherical`], override it.
+    fn project(self) -> Self {
+        self
+    }
+
+    /// Returns a random vector with components drawn from `rng`.
+    fn random<R: Rng + ?Sized>(rng: &mut R) -> Self;
 }
 
 /// Magnitude is a newtype alias holding the magnitude value of a vector.
 #[derive(PartialEq, Debug)]
-pub struct Magnitude(pub f64);
+pub struct Magnitude<T = f64>(pub T);
+
+/// The floating point type of the components of a [`Vector`], and of the
+/// [`Coordinate`](crate::Coordinate) and [`Model`](crate::Model) using it.
+///
+/// Implemented for `f64`, the default, and `f32` - halving the size of
+/// coordinates for embedded and high-volume users where memory and bandwidth
+/// matter more than precision:
+///
+/// ```
+/// use vivaldi::{Model, SystemClock, LinearSpring, vector::DimensionN};
+///
+/// let model = Model::<DimensionN<3, f32>, SystemClock, LinearSpring, f32>::new();
+/// let height: f32 = model.get_coordinate().height();
+/// ```
+///
+/// With the `simd` feature enabled, the arithmetic of [`Dimension3`] and
+/// [`DimensionN`] vectors with `f64` components is vectorised on x86_64.
+pub trait Scalar:
+    simd::Lanes + Default + std::fmt::Debug + std::iter::Sum + Send + Sync + 'static
+{
+    /// Converts `v` to the nearest representable value.
+    fn from_f64(v: f64) -> Self;
+
+    /// Converts `self` to an `f64`.
+    fn into_f64(self) -> f64;
+}
+
+impl Scalar for f64 {
+    fn from_f64(v: f64) -> Self {
+        v
+    }
+
+    fn into_f64(self) -> f64 {
+        self
+    }
+}
+
+impl Scalar for f32 {
+    fn from_f64(v: f64) -> Self {
+        v as f32
+    }
+
+    fn into_f64(self) -> f64 {
+        self as f64
+    }
+}
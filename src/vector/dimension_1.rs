@@ -0,0 +1,187 @@
+use super::*;
+use rand::Rng;
+use std::ops::Div;
+
+/// A 1 dimensional Euclidean vector.
+///
+/// Embeds nodes along a line, which is cheap and sufficient for line
+/// topologies or experiments with a single metric.
+#[derive(PartialEq, Debug, Copy, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
+pub struct Dimension1(pub [f64; 1]);
+
+impl Vector for Dimension1 {
+    fn magnitude(&self) -> Magnitude {
+        Magnitude(self.0[0].abs())
+    }
+
+    fn components(&self) -> &[f64] {
+        &self.0
+    }
+
+    fn from_components(components: &[f64]) -> Option<Self> {
+        match components.len() {
+            1 => Some(Dimension1([components[0]])),
+            _ => None,
+        }
+    }
+
+    fn distance(&self, other: &Self) -> Magnitude {
+        Magnitude((self.0[0] - other.0[0]).abs())
+    }
+
+    fn random<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        Dimension1([rng.gen::<f64>()])
+    }
+}
+
+impl From<[f64; 1]> for Dimension1 {
+    fn from(v: [f64; 1]) -> Self {
+        Self(v)
+    }
+}
+
+impl From<Dimension1> for [f64; 1] {
+    fn from(v: Dimension1) -> Self {
+        v.0
+    }
+}
+
+impl Add for Dimension1 {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self::Output {
+        Self([self.0[0] + other.0[0]])
+    }
+}
+
+impl Add<f64> for Dimension1 {
+    type Output = Self;
+
+    fn add(self, other: f64) -> Self::Output {
+        Self([self.0[0] + other])
+    }
+}
+
+impl Sub for Dimension1 {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self::Output {
+        Self([self.0[0] - other.0[0]])
+    }
+}
+
+/// Subtract a borrowed vector, avoiding a copy of the right hand side.
+impl Sub<&Dimension1> for Dimension1 {
+    type Output = Self;
+
+    fn sub(self, other: &Self) -> Self::Output {
+        Self([self.0[0] - other.0[0]])
+    }
+}
+
+/// Divide a vector by a constant amount.
+impl Div<f64> for Dimension1 {
+    type Output = Self;
+
+    fn div(self, other: f64) -> Self::Output {
+        Self([self.0[0] / other])
+    }
+}
+
+impl Mul<f64> for Dimension1 {
+    type Output = Self;
+
+    fn mul(self, other: f64) -> Self::Output {
+        Self([self.0[0] * other])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add() {
+        let a = Dimension1([1.0]);
+        let b = Dimension1([0.1]);
+
+        assert_eq!(a + b, Dimension1([1.1]));
+    }
+
+    #[test]
+    fn add_f64_constant() {
+        assert_eq!(Dimension1([1.0]) + 42.0, Dimension1([43.0]));
+    }
+
+    #[test]
+    fn sub() {
+        let a = Dimension1([1.1]);
+        let b = Dimension1([0.1]);
+
+        assert_eq!(a - b, Dimension1([1.0]));
+    }
+
+    #[test]
+    #[allow(clippy::op_ref)]
+    fn sub_ref() {
+        let a = Dimension1([1.1]);
+        let b = Dimension1([0.1]);
+
+        assert_eq!(a - &b, Dimension1([1.0]));
+    }
+
+    #[test]
+    fn mul_f64_constant() {
+        let a = Dimension1([1.5]);
+
+        assert_eq!(a * 2.0, Dimension1([3.0]));
+    }
+
+    #[test]
+    fn div_f64_constant() {
+        assert_eq!(Dimension1([1.0]) / 2.0, Dimension1([0.5]));
+    }
+
+    #[test]
+    fn magnitude() {
+        assert_eq!(Dimension1([0.0]).magnitude(), Magnitude(0.0));
+
+        // Non-zero magnitude
+        assert_eq!(Dimension1([2.5]).magnitude(), Magnitude(2.5));
+
+        // Direction plays no part
+        assert_eq!(Dimension1([-2.5]).magnitude(), Magnitude(2.5));
+    }
+
+    #[test]
+    fn distance() {
+        let a = Dimension1([1.0]);
+        let b = Dimension1([-1.5]);
+
+        assert_eq!(a.distance(&b), Magnitude(2.5));
+        assert_eq!(b.distance(&a), Magnitude(2.5));
+        assert_eq!(a.distance(&a), Magnitude(0.0));
+    }
+
+    #[test]
+    fn array_conversion() {
+        let a = Dimension1::from([1.0]);
+
+        assert_eq!(a, Dimension1([1.0]));
+        assert_eq!(<[f64; 1]>::from(a), [1.0]);
+    }
+
+    #[test]
+    fn components_round_trip() {
+        let a = Dimension1([1.0]);
+
+        assert_eq!(Dimension1::from_components(a.components()), Some(a));
+        assert_eq!(Dimension1::from_components(&[]), None);
+        assert_eq!(Dimension1::from_components(&[1.0; 2]), None);
+    }
+}
@@ -5,6 +5,10 @@ use std::ops::Div;
 /// A 2 dimensional Euclidean vector.
 #[derive(PartialEq, Debug, Copy, Clone, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
 pub struct Dimension2(pub [f64; 2]);
 
 impl Vector for Dimension2 {
@@ -14,11 +18,42 @@ impl Vector for Dimension2 {
         Magnitude(m)
     }
 
-    fn random() -> Self {
-        Dimension2([
-            rand::thread_rng().gen::<f64>(),
-            rand::thread_rng().gen::<f64>(),
-        ])
+    fn components(&self) -> &[f64] {
+        &self.0
+    }
+
+    fn from_components(components: &[f64]) -> Option<Self> {
+        match components.len() {
+            2 => Some(Dimension2([components[0], components[1]])),
+            _ => None,
+        }
+    }
+
+    fn distance(&self, other: &Self) -> Magnitude {
+        let m = self
+            .0
+            .iter()
+            .zip(other.0.iter())
+            .fold(0.0, |acc, (a, b)| acc + (a - b) * (a - b))
+            .sqrt();
+
+        Magnitude(m)
+    }
+
+    fn random<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        Dimension2([rng.gen::<f64>(), rng.gen::<f64>()])
+    }
+}
+
+impl From<[f64; 2]> for Dimension2 {
+    fn from(v: [f64; 2]) -> Self {
+        Self(v)
+    }
+}
+
+impl From<Dimension2> for [f64; 2] {
+    fn from(v: Dimension2) -> Self {
+        v.0
     }
 }
 
@@ -46,6 +81,15 @@ impl Sub for Dimension2 {
     }
 }
 
+/// Subtract a borrowed vector, avoiding a copy of the right hand side.
+impl Sub<&Dimension2> for Dimension2 {
+    type Output = Self;
+
+    fn sub(self, other: &Self) -> Self::Output {
+        Self([self.0[0] - other.0[0], self.0[1] - other.0[1]])
+    }
+}
+
 /// Divide a vector by a constant amount.
 impl Div<f64> for Dimension2 {
     type Output = Self;
@@ -88,6 +132,15 @@ mod tests {
         assert_eq!(a - b, Dimension2([1.0, 2.0]));
     }
 
+    #[test]
+    #[allow(clippy::op_ref)]
+    fn sub_ref() {
+        let a = Dimension2([1.1, 2.2]);
+        let b = Dimension2([0.1, 0.2]);
+
+        assert_eq!(a - &b, Dimension2([1.0, 2.0]));
+    }
+
     #[test]
     fn mul_f64_constant() {
         let a = Dimension2([1.0, 2.0]);
@@ -116,4 +169,31 @@ mod tests {
             Magnitude(2.23606797749979)
         );
     }
+
+    #[test]
+    fn distance() {
+        let a = Dimension2([1.0, 2.0]);
+        let b = Dimension2([0.0, 0.0]);
+
+        assert_eq!(a.distance(&b), Magnitude(2.23606797749979));
+        assert_eq!(b.distance(&a), Magnitude(2.23606797749979));
+        assert_eq!(a.distance(&a), Magnitude(0.0));
+    }
+
+    #[test]
+    fn array_conversion() {
+        let a = Dimension2::from([1.0, 2.0]);
+
+        assert_eq!(a, Dimension2([1.0, 2.0]));
+        assert_eq!(<[f64; 2]>::from(a), [1.0, 2.0]);
+    }
+
+    #[test]
+    fn components_round_trip() {
+        let a = Dimension2([1.0, 2.0]);
+
+        assert_eq!(Dimension2::from_components(a.components()), Some(a));
+        assert_eq!(Dimension2::from_components(&[]), None);
+        assert_eq!(Dimension2::from_components(&[1.0; 4]), None);
+    }
 }
@@ -1,3 +1,4 @@
+use super::simd::Lanes;
 use super::*;
 use rand::Rng;
 use std::ops::Div;
@@ -5,33 +6,55 @@ use std::ops::Div;
 /// A 3 dimensional Euclidean vector.
 #[derive(PartialEq, Debug, Copy, Clone, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
 pub struct Dimension3(pub [f64; 3]);
 
 impl Vector for Dimension3 {
     fn magnitude(&self) -> Magnitude {
-        let m = self.0.iter().fold(0.0, |acc, v| acc + (v * v)).sqrt();
+        Magnitude(f64::sum_squares(&self.0).sqrt())
+    }
+
+    fn components(&self) -> &[f64] {
+        &self.0
+    }
+
+    fn from_components(components: &[f64]) -> Option<Self> {
+        match components.len() {
+            3 => Some(Dimension3([components[0], components[1], components[2]])),
+            _ => None,
+        }
+    }
+
+    fn distance(&self, other: &Self) -> Magnitude {
+        Magnitude(f64::sum_squared_differences(&self.0, &other.0).sqrt())
+    }
+
+    fn random<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        Dimension3([rng.gen::<f64>(), rng.gen::<f64>(), rng.gen::<f64>()])
+    }
+}
 
-        Magnitude(m)
+impl From<[f64; 3]> for Dimension3 {
+    fn from(v: [f64; 3]) -> Self {
+        Self(v)
     }
+}
 
-    fn random() -> Self {
-        Dimension3([
-            rand::thread_rng().gen::<f64>(),
-            rand::thread_rng().gen::<f64>(),
-            rand::thread_rng().gen::<f64>(),
-        ])
+impl From<Dimension3> for [f64; 3] {
+    fn from(v: Dimension3) -> Self {
+        v.0
     }
 }
 
 impl Add for Dimension3 {
     type Output = Self;
 
-    fn add(self, other: Self) -> Self::Output {
-        Self([
-            self.0[0] + other.0[0],
-            self.0[1] + other.0[1],
-            self.0[2] + other.0[2],
-        ])
+    fn add(mut self, other: Self) -> Self::Output {
+        f64::add_lanes(&mut self.0, &other.0);
+        self
     }
 }
 
@@ -46,12 +69,19 @@ impl Add<f64> for Dimension3 {
 impl Sub for Dimension3 {
     type Output = Self;
 
-    fn sub(self, other: Self) -> Self::Output {
-        Self([
-            self.0[0] - other.0[0],
-            self.0[1] - other.0[1],
-            self.0[2] - other.0[2],
-        ])
+    fn sub(mut self, other: Self) -> Self::Output {
+        f64::sub_lanes(&mut self.0, &other.0);
+        self
+    }
+}
+
+/// Subtract a borrowed vector, avoiding a copy of the right hand side.
+impl Sub<&Dimension3> for Dimension3 {
+    type Output = Self;
+
+    fn sub(mut self, other: &Self) -> Self::Output {
+        f64::sub_lanes(&mut self.0, &other.0);
+        self
     }
 }
 
@@ -59,16 +89,18 @@ impl Sub for Dimension3 {
 impl Div<f64> for Dimension3 {
     type Output = Self;
 
-    fn div(self, other: f64) -> Self::Output {
-        Self([self.0[0] / other, self.0[1] / other, self.0[2] / other])
+    fn div(mut self, other: f64) -> Self::Output {
+        f64::div_lanes(&mut self.0, other);
+        self
     }
 }
 
 impl Mul<f64> for Dimension3 {
     type Output = Self;
 
-    fn mul(self, other: f64) -> Self::Output {
-        Self([self.0[0] * other, self.0[1] * other, self.0[2] * other])
+    fn mul(mut self, other: f64) -> Self::Output {
+        f64::mul_lanes(&mut self.0, other);
+        self
     }
 }
 
@@ -100,6 +132,15 @@ mod tests {
         assert_eq!(a - b, Dimension3([1.0, 2.0, 3.0]));
     }
 
+    #[test]
+    #[allow(clippy::op_ref)]
+    fn sub_ref() {
+        let a = Dimension3([1.1, 2.2, 3.3]);
+        let b = Dimension3([0.1, 0.2, 0.3]);
+
+        assert_eq!(a - &b, Dimension3([1.0, 2.0, 3.0]));
+    }
+
     #[test]
     fn mul_f64_constant() {
         let a = Dimension3([1.0, 2.0, 3.0]);
@@ -131,4 +172,31 @@ mod tests {
             Magnitude(3.7416573867739413)
         );
     }
+
+    #[test]
+    fn distance() {
+        let a = Dimension3([1.0, 2.0, 3.0]);
+        let b = Dimension3([0.0, 0.0, 0.0]);
+
+        assert_eq!(a.distance(&b), Magnitude(3.7416573867739413));
+        assert_eq!(b.distance(&a), Magnitude(3.7416573867739413));
+        assert_eq!(a.distance(&a), Magnitude(0.0));
+    }
+
+    #[test]
+    fn array_conversion() {
+        let a = Dimension3::from([1.0, 2.0, 3.0]);
+
+        assert_eq!(a, Dimension3([1.0, 2.0, 3.0]));
+        assert_eq!(<[f64; 3]>::from(a), [1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn components_round_trip() {
+        let a = Dimension3([1.0, 2.0, 3.0]);
+
+        assert_eq!(Dimension3::from_components(a.components()), Some(a));
+        assert_eq!(Dimension3::from_components(&[]), None);
+        assert_eq!(Dimension3::from_components(&[1.0; 4]), None);
+    }
 }
@@ -0,0 +1,363 @@
+use super::*;
+use rand::Rng;
+use std::convert::TryInto;
+use std::ops::Div;
+
+/// An N dimensional Euclidean vector.
+///
+/// [Dabek et al.] found accuracy improves little beyond 2 or 3 dimensions
+/// with a height, but higher dimensionality can be evaluated by choosing `N`:
+///
+/// ```
+/// use vivaldi::{Model, vector::DimensionN};
+///
+/// let model = Model::<DimensionN<5>>::new();
+/// ```
+///
+/// The components are `f64` unless another [`Scalar`], such as `f32`, is
+/// chosen with `T`.
+///
+/// Arrays of components, [`Dimension1`], [`Dimension2`] and [`Dimension3`]
+/// all convert to and from the equivalent `DimensionN`, so applications
+/// already exchanging coordinates as `[f64; N]` arrays need no conversion
+/// code of their own:
+///
+/// ```
+/// use vivaldi::{Model, vector::DimensionN};
+///
+/// let model = Model::<DimensionN<5>>::new();
+/// let sent: [f64; 5] = (*model.get_coordinate().vector()).into();
+///
+/// let received = DimensionN::from(sent);
+/// ```
+///
+/// [`Dimension1`], [`Dimension2`] and [`Dimension3`] are distinct types
+/// rather than aliases of `DimensionN`, as a tuple struct cannot be
+/// constructed or matched through a type alias - aliasing them would break
+/// every `Dimension3([x, y, z])` expression written against them.
+///
+/// [Dabek et al.]: https://pdos.csail.mit.edu/papers/vivaldi:sigcomm/paper.pdf
+#[derive(PartialEq, Debug, Copy, Clone)]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
+pub struct DimensionN<const N: usize, T = f64>(pub [T; N]);
+
+impl<const N: usize, T: Scalar> Default for DimensionN<N, T> {
+    fn default() -> Self {
+        Self([T::zero(); N])
+    }
+}
+
+impl<const N: usize, T: Scalar> Vector<T> for DimensionN<N, T> {
+    fn magnitude(&self) -> Magnitude<T> {
+        Magnitude(T::sum_squares(&self.0).sqrt())
+    }
+
+    fn components(&self) -> &[T] {
+        &self.0
+    }
+
+    fn from_components(components: &[T]) -> Option<Self> {
+        components.try_into().ok().map(Self)
+    }
+
+    fn distance(&self, other: &Self) -> Magnitude<T> {
+        Magnitude(T::sum_squared_differences(&self.0, &other.0).sqrt())
+    }
+
+    fn random<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        Self(std::array::from_fn(|_| T::from_f64(rng.gen::<f64>())))
+    }
+}
+
+impl<const N: usize, T: Scalar> Add for DimensionN<N, T> {
+    type Output = Self;
+
+    fn add(mut self, other: Self) -> Self::Output {
+        T::add_lanes(&mut self.0, &other.0);
+        self
+    }
+}
+
+impl<const N: usize, T: Scalar> Add<T> for DimensionN<N, T> {
+    type Output = Self;
+
+    fn add(self, other: T) -> Self::Output {
+        Self(self.0.map(|v| v + other))
+    }
+}
+
+impl<const N: usize, T: Scalar> Sub for DimensionN<N, T> {
+    type Output = Self;
+
+    fn sub(mut self, other: Self) -> Self::Output {
+        T::sub_lanes(&mut self.0, &other.0);
+        self
+    }
+}
+
+/// Subtract a borrowed vector, avoiding a copy of the right hand side.
+impl<const N: usize, T: Scalar> Sub<&DimensionN<N, T>> for DimensionN<N, T> {
+    type Output = Self;
+
+    fn sub(mut self, other: &Self) -> Self::Output {
+        T::sub_lanes(&mut self.0, &other.0);
+        self
+    }
+}
+
+/// Divide a vector by a constant amount.
+impl<const N: usize, T: Scalar> Div<T> for DimensionN<N, T> {
+    type Output = Self;
+
+    fn div(mut self, other: T) -> Self::Output {
+        T::div_lanes(&mut self.0, other);
+        self
+    }
+}
+
+impl<const N: usize, T: Scalar> Mul<T> for DimensionN<N, T> {
+    type Output = Self;
+
+    fn mul(mut self, other: T) -> Self::Output {
+        T::mul_lanes(&mut self.0, other);
+        self
+    }
+}
+
+impl<const N: usize, T> From<[T; N]> for DimensionN<N, T> {
+    fn from(v: [T; N]) -> Self {
+        Self(v)
+    }
+}
+
+impl<const N: usize, T> From<DimensionN<N, T>> for [T; N] {
+    fn from(v: DimensionN<N, T>) -> Self {
+        v.0
+    }
+}
+
+impl From<Dimension1> for DimensionN<1> {
+    fn from(v: Dimension1) -> Self {
+        Self(v.0)
+    }
+}
+
+impl From<DimensionN<1>> for Dimension1 {
+    fn from(v: DimensionN<1>) -> Self {
+        Self(v.0)
+    }
+}
+
+impl From<Dimension2> for DimensionN<2> {
+    fn from(v: Dimension2) -> Self {
+        Self(v.0)
+    }
+}
+
+impl From<DimensionN<2>> for Dimension2 {
+    fn from(v: DimensionN<2>) -> Self {
+        Self(v.0)
+    }
+}
+
+impl From<Dimension3> for DimensionN<3> {
+    fn from(v: Dimension3) -> Self {
+        Self(v.0)
+    }
+}
+
+impl From<DimensionN<3>> for Dimension3 {
+    fn from(v: DimensionN<3>) -> Self {
+        Self(v.0)
+    }
+}
+
+/// Serialises the components as a tuple, as serde derives for fixed size
+/// arrays.
+#[cfg(feature = "serde")]
+impl<const N: usize, T: serde::Serialize> serde::Serialize for DimensionN<N, T> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeTuple;
+
+        let mut tuple = serializer.serialize_tuple(N)?;
+        for v in &self.0 {
+            tuple.serialize_element(v)?;
+        }
+        tuple.end()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, const N: usize, T> serde::Deserialize<'de> for DimensionN<N, T>
+where
+    T: Scalar + serde::Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct Components<const N: usize, T>(std::marker::PhantomData<T>);
+
+        impl<'de, const N: usize, T> serde::de::Visitor<'de> for Components<N, T>
+        where
+            T: Scalar + serde::Deserialize<'de>,
+        {
+            type Value = DimensionN<N, T>;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                write!(f, "{} vector components", N)
+            }
+
+            fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+            where
+                A: serde::de::SeqAccess<'de>,
+            {
+                let mut v = [T::zero(); N];
+                for (i, c) in v.iter_mut().enumerate() {
+                    *c = seq
+                        .next_element()?
+                        .ok_or_else(|| serde::de::Error::invalid_length(i, &self))?;
+                }
+                Ok(DimensionN(v))
+            }
+        }
+
+        deserializer.deserialize_tuple(N, Components(std::marker::PhantomData))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add() {
+        let a = DimensionN([1.0, 2.0, 3.0, 4.0]);
+        let b = DimensionN([0.1, 0.2, 0.3, 0.4]);
+
+        assert_eq!(a + b, DimensionN([1.1, 2.2, 3.3, 4.4]));
+    }
+
+    #[test]
+    fn add_f64_constant() {
+        assert_eq!(
+            DimensionN([1.0, 2.0, 3.0, 4.0]) + 42.0,
+            DimensionN([43.0, 44.0, 45.0, 46.0])
+        );
+    }
+
+    #[test]
+    fn sub() {
+        let a = DimensionN([1.1, 2.2, 3.3, 4.4]);
+        let b = DimensionN([0.1, 0.2, 0.3, 0.4]);
+
+        assert_eq!(a - b, DimensionN([1.0, 2.0, 3.0, 4.0]));
+    }
+
+    #[test]
+    #[allow(clippy::op_ref)]
+    fn sub_ref() {
+        let a = DimensionN([1.1, 2.2, 3.3, 4.4]);
+        let b = DimensionN([0.1, 0.2, 0.3, 0.4]);
+
+        assert_eq!(a - &b, DimensionN([1.0, 2.0, 3.0, 4.0]));
+    }
+
+    #[test]
+    fn mul_f64_constant() {
+        let a = DimensionN([1.0, 2.0, 3.0, 4.0]);
+
+        assert_eq!(a * 2.0, DimensionN([2.0, 4.0, 6.0, 8.0]));
+    }
+
+    #[test]
+    fn div_f64_constant() {
+        assert_eq!(
+            DimensionN([1.0, 2.0, 3.0, 4.0]) / 2.0,
+            DimensionN([0.5, 1.0, 1.5, 2.0])
+        );
+    }
+
+    #[test]
+    fn magnitude() {
+        assert_eq!(DimensionN::<4>::default().magnitude(), Magnitude(0.0));
+
+        // Non-zero magnitude
+        assert_eq!(DimensionN([1.0, 2.0, 2.0, 4.0]).magnitude(), Magnitude(5.0));
+
+        // Direction plays no part
+        assert_eq!(
+            DimensionN([-1.0, -2.0, -2.0, -4.0]).magnitude(),
+            Magnitude(5.0)
+        );
+    }
+
+    #[test]
+    fn distance() {
+        let a = DimensionN([1.0, 2.0, 2.0, 4.0]);
+        let b = DimensionN::default();
+
+        assert_eq!(a.distance(&b), Magnitude(5.0));
+        assert_eq!(b.distance(&a), Magnitude(5.0));
+        assert_eq!(a.distance(&a), Magnitude(0.0));
+    }
+
+    #[test]
+    fn components_round_trip() {
+        let a = DimensionN([1.0, 2.0, 3.0, 4.0]);
+
+        assert_eq!(DimensionN::<4>::from_components(a.components()), Some(a));
+        assert_eq!(DimensionN::<4>::from_components(&[]), None);
+        assert_eq!(DimensionN::<4>::from_components(&[1.0; 5]), None);
+    }
+
+    #[test]
+    fn f32_components() {
+        let a = DimensionN([3.0_f32, 4.0]);
+
+        assert_eq!(a.magnitude(), Magnitude(5.0_f32));
+        assert_eq!(a.distance(&DimensionN::default()), Magnitude(5.0_f32));
+        assert_eq!(a * 2.0 - a, a);
+        assert_eq!(std::mem::size_of::<DimensionN<4, f32>>(), 16);
+    }
+
+    #[test]
+    fn array_conversion() {
+        let a = DimensionN::from([1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(a, DimensionN([1.0, 2.0, 3.0, 4.0]));
+        assert_eq!(<[f64; 4]>::from(a), [1.0, 2.0, 3.0, 4.0]);
+    }
+
+    #[test]
+    fn matches_fixed_dimensions() {
+        let a = Dimension3([1.0, 2.0, 3.0]);
+        let b = Dimension3([0.5, -1.0, 0.0]);
+
+        let (an, bn) = (DimensionN::from(a), DimensionN::from(b));
+        assert_eq!(an.distance(&bn), a.distance(&b));
+        assert_eq!(Dimension3::from(an + bn), a + b);
+        assert_eq!(
+            Dimension2::from(DimensionN([1.0, 2.0])),
+            Dimension2([1.0, 2.0])
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        let a = DimensionN([1.0, 2.0, 3.0, 4.0]);
+
+        // Encoded as the fixed dimension types are.
+        let json = serde_json::to_string(&a).unwrap();
+        assert_eq!(json, serde_json::to_string(&[1.0, 2.0, 3.0, 4.0]).unwrap());
+        assert_eq!(serde_json::from_str::<DimensionN<4>>(&json).unwrap(), a);
+
+        assert!(serde_json::from_str::<DimensionN<4>>("[1.0,2.0]").is_err());
+    }
+}
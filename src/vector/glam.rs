@@ -0,0 +1,138 @@
+use super::*;
+use ::glam::{DVec2, DVec3};
+use rand::Rng;
+
+/// Embeds the model in [`glam`](https://docs.rs/glam)'s 2 dimensional
+/// vector, so game and netcode applications can keep their existing math
+/// types:
+///
+/// ```
+/// use glam::DVec2;
+/// use vivaldi::Model;
+///
+/// let model = Model::<DVec2>::new();
+/// let v: &DVec2 = model.get_coordinate().vector();
+/// ```
+impl Vector for DVec2 {
+    fn magnitude(&self) -> Magnitude {
+        Magnitude(self.length())
+    }
+
+    fn components(&self) -> &[f64] {
+        AsRef::<[f64; 2]>::as_ref(self)
+    }
+
+    fn from_components(components: &[f64]) -> Option<Self> {
+        match components.len() {
+            2 => Some(DVec2::from_slice(components)),
+            _ => None,
+        }
+    }
+
+    fn distance(&self, other: &Self) -> Magnitude {
+        Magnitude(DVec2::distance(*self, *other))
+    }
+
+    fn random<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        DVec2::new(rng.gen::<f64>(), rng.gen::<f64>())
+    }
+}
+
+/// Embeds the model in [`glam`](https://docs.rs/glam)'s 3 dimensional
+/// vector.
+impl Vector for DVec3 {
+    fn magnitude(&self) -> Magnitude {
+        Magnitude(self.length())
+    }
+
+    fn components(&self) -> &[f64] {
+        AsRef::<[f64; 3]>::as_ref(self)
+    }
+
+    fn from_components(components: &[f64]) -> Option<Self> {
+        match components.len() {
+            3 => Some(DVec3::from_slice(components)),
+            _ => None,
+        }
+    }
+
+    fn distance(&self, other: &Self) -> Magnitude {
+        Magnitude(DVec3::distance(*self, *other))
+    }
+
+    fn random<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        DVec3::new(rng.gen::<f64>(), rng.gen::<f64>(), rng.gen::<f64>())
+    }
+}
+
+impl From<Dimension2> for DVec2 {
+    fn from(v: Dimension2) -> Self {
+        DVec2::from_array(v.0)
+    }
+}
+
+impl From<DVec2> for Dimension2 {
+    fn from(v: DVec2) -> Self {
+        Dimension2(v.to_array())
+    }
+}
+
+impl From<Dimension3> for DVec3 {
+    fn from(v: Dimension3) -> Self {
+        DVec3::from_array(v.0)
+    }
+}
+
+impl From<DVec3> for Dimension3 {
+    fn from(v: DVec3) -> Self {
+        Dimension3(v.to_array())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn matches_fixed_dimensions() {
+        let (a, b) = (Dimension2([1.0, 2.0]), Dimension2([0.5, -1.0]));
+        let (ga, gb) = (DVec2::from(a), DVec2::from(b));
+        assert_eq!(Vector::distance(&ga, &gb), a.distance(&b));
+        assert_eq!(Vector::magnitude(&ga), a.magnitude());
+        assert_eq!(Dimension2::from(ga - gb), a - b);
+
+        let (a, b) = (Dimension3([1.0, 2.0, 3.0]), Dimension3([0.5, -1.0, 0.0]));
+        let (ga, gb) = (DVec3::from(a), DVec3::from(b));
+        assert_eq!(Vector::distance(&ga, &gb), a.distance(&b));
+        assert_eq!(Vector::magnitude(&ga), a.magnitude());
+        assert_eq!(Dimension3::from(ga - gb), a - b);
+    }
+
+    #[test]
+    fn components_round_trip() {
+        let a = DVec2::new(1.0, 2.0);
+        assert_eq!(a.components(), &[1.0, 2.0]);
+        assert_eq!(DVec2::from_components(a.components()), Some(a));
+        assert_eq!(DVec2::from_components(&[1.0]), None);
+
+        let a = DVec3::new(1.0, 2.0, 3.0);
+        assert_eq!(a.components(), &[1.0, 2.0, 3.0]);
+        assert_eq!(DVec3::from_components(a.components()), Some(a));
+        assert_eq!(DVec3::from_components(&[1.0, 2.0]), None);
+    }
+
+    #[test]
+    fn model() {
+        let mut a = crate::Model::<DVec3>::new();
+        let mut b = crate::Model::<DVec3>::new();
+
+        for _ in 0..100 {
+            a.observe(b.get_coordinate(), Duration::from_millis(10));
+            b.observe(a.get_coordinate(), Duration::from_millis(10));
+        }
+
+        let rtt = crate::estimate_rtt(a.get_coordinate(), b.get_coordinate());
+        assert!((rtt.as_secs_f64() - 0.01).abs() < 0.001, "{:?}", rtt);
+    }
+}
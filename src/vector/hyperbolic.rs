@@ -0,0 +1,242 @@
+use super::*;
+use rand::Rng;
+use std::ops::Div;
+
+/// A point in 2 dimensional hyperbolic space.
+///
+/// Internet topologies are largely tree-like - paths between distant nodes
+/// converge on a few backbone links - which Euclidean space embeds poorly.
+/// Hyperbolic space grows exponentially away from the origin, like a tree,
+/// and so embeds these topologies with less distortion, as described in
+/// [Lumezanu and Spring].
+///
+/// The point is represented using the hyperboloid model: the components are
+/// the coordinates of the point projected onto the plane, with the distance
+/// between two points being the hyperbolic distance between them on a
+/// hyperboloid with a radius of curvature of
+/// [`CURVATURE_RADIUS`](Hyperbolic2::CURVATURE_RADIUS). Points close to the
+/// origin, relative to the radius, behave as Euclidean vectors.
+///
+/// ```
+/// use vivaldi::{Model, vector::Hyperbolic2};
+///
+/// let model = Model::<Hyperbolic2>::new();
+/// ```
+///
+/// [Lumezanu and Spring]: https://www.cs.umd.edu/~lume/papers/TR-4843.pdf
+#[derive(PartialEq, Debug, Copy, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
+pub struct Hyperbolic2(pub [f64; 2]);
+
+impl Hyperbolic2 {
+    /// The radius of curvature of the space, in seconds.
+    ///
+    /// Distances well below the radius are approximately Euclidean, while
+    /// distances above it become increasingly tree-like. Embeddings of RTTs
+    /// far above the radius converge slowly.
+    pub const CURVATURE_RADIUS: f64 = 0.01;
+}
+
+impl Vector for Hyperbolic2 {
+    fn magnitude(&self) -> Magnitude {
+        let m = self.0.iter().fold(0.0, |acc, v| acc + (v * v)).sqrt();
+
+        Magnitude(m)
+    }
+
+    fn components(&self) -> &[f64] {
+        &self.0
+    }
+
+    fn from_components(components: &[f64]) -> Option<Self> {
+        match components.len() {
+            2 => Some(Hyperbolic2([components[0], components[1]])),
+            _ => None,
+        }
+    }
+
+    fn distance(&self, other: &Self) -> Magnitude {
+        let r = Self::CURVATURE_RADIUS;
+        let (a, b) = (self.0, other.0);
+
+        // The height of each point above the plane on the unit hyperboloid,
+        // and the difference between them.
+        let a2 = (a[0] * a[0] + a[1] * a[1]) / (r * r);
+        let b2 = (b[0] * b[0] + b[1] * b[1]) / (r * r);
+        let (a0, b0) = ((1.0 + a2).sqrt(), (1.0 + b2).sqrt());
+        let height = (a2 - b2) / (a0 + b0);
+
+        // The distance is arcosh(1 + t), with t half the squared Minkowski
+        // norm of the difference between the points - computed from the
+        // planar difference to remain accurate for nearby points.
+        let planar = ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2)) / (r * r);
+        let t = ((planar - height * height) / 2.0).max(0.0);
+
+        Magnitude(r * (t + (t * (t + 2.0)).sqrt()).ln_1p())
+    }
+
+    fn displacement(&self, other: &Self) -> Self {
+        let diff = *self - other;
+        let planar = diff.magnitude().0;
+        if planar == 0.0 {
+            return diff;
+        }
+        diff * (self.distance(other).0 / planar)
+    }
+
+    fn random<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        Hyperbolic2([rng.gen::<f64>(), rng.gen::<f64>()])
+    }
+}
+
+impl Add for Hyperbolic2 {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self::Output {
+        Self([self.0[0] + other.0[0], self.0[1] + other.0[1]])
+    }
+}
+
+impl Add<f64> for Hyperbolic2 {
+    type Output = Self;
+
+    fn add(self, other: f64) -> Self::Output {
+        Self([self.0[0] + other, self.0[1] + other])
+    }
+}
+
+impl Sub for Hyperbolic2 {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self::Output {
+        Self([self.0[0] - other.0[0], self.0[1] - other.0[1]])
+    }
+}
+
+/// Subtract a borrowed vector, avoiding a copy of the right hand side.
+impl Sub<&Hyperbolic2> for Hyperbolic2 {
+    type Output = Self;
+
+    fn sub(self, other: &Self) -> Self::Output {
+        Self([self.0[0] - other.0[0], self.0[1] - other.0[1]])
+    }
+}
+
+/// Divide a vector by a constant amount.
+impl Div<f64> for Hyperbolic2 {
+    type Output = Self;
+
+    fn div(self, other: f64) -> Self::Output {
+        Self([self.0[0] / other, self.0[1] / other])
+    }
+}
+
+impl Mul<f64> for Hyperbolic2 {
+    type Output = Self;
+
+    fn mul(self, other: f64) -> Self::Output {
+        Self([self.0[0] * other, self.0[1] * other])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::Simulation;
+    use crate::vector::Dimension2;
+    use std::time::Duration;
+
+    const R: f64 = Hyperbolic2::CURVATURE_RADIUS;
+
+    #[test]
+    fn distance() {
+        let a = Hyperbolic2([0.0, 0.0]);
+        let b = Hyperbolic2([R, 0.0]);
+
+        // The distance from the origin is r·arsinh(||x|| / r).
+        let want = R * 1.0_f64.asinh();
+        assert!((a.distance(&b).0 - want).abs() < 1e-12);
+        assert!((b.distance(&a).0 - want).abs() < 1e-12);
+        assert_eq!(a.distance(&a), Magnitude(0.0));
+        assert_eq!(b.distance(&b), Magnitude(0.0));
+
+        // Nearby points are approximately Euclidean.
+        let c = Hyperbolic2([R * 1e-4, 0.0]);
+        assert!((a.distance(&c).0 - R * 1e-4).abs() < 1e-12);
+
+        // Paths between distant points bend towards the origin, as in a
+        // tree, so are longer relative to their distance from it than in
+        // Euclidean space.
+        let d = Hyperbolic2([0.0, R]);
+        let via_origin = a.distance(&b).0 + a.distance(&d).0;
+        assert!(b.distance(&d).0 / via_origin > 2.0_f64.sqrt() / 2.0);
+    }
+
+    #[test]
+    fn displacement() {
+        let a = Hyperbolic2([R, R]);
+        let b = Hyperbolic2([-R, 0.0]);
+
+        let d = a.displacement(&b);
+        assert!((d.magnitude().0 - a.distance(&b).0).abs() < 1e-12);
+        assert!((d.0[1] / d.0[0] - 0.5).abs() < 1e-12);
+        assert_eq!(a.displacement(&a), Hyperbolic2([0.0, 0.0]));
+    }
+
+    #[test]
+    fn components_round_trip() {
+        let a = Hyperbolic2([1.0, 2.0]);
+
+        assert_eq!(Hyperbolic2::from_components(a.components()), Some(a));
+        assert_eq!(Hyperbolic2::from_components(&[]), None);
+    }
+
+    /// Returns the RTTs between the leaves of a complete binary tree of the
+    /// given depth, with a 5ms delay on each link.
+    fn tree_rtts(depth: u32) -> Vec<Vec<Duration>> {
+        let leaves = 1_usize << depth;
+        (0..leaves)
+            .map(|a| {
+                (0..leaves)
+                    .map(|b| {
+                        // The number of links from each leaf up to the
+                        // closest common ancestor.
+                        let hops = usize::BITS - (a ^ b).leading_zeros();
+                        Duration::from_millis(2 * 5 * hops as u64)
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Returns the median error of a simulation of `rtts` embedded in `V`,
+    /// averaged over several seeds.
+    fn mean_error<V>(rtts: &[Vec<Duration>]) -> f64
+    where
+        V: Vector + std::fmt::Debug + Send + Sync,
+    {
+        const SEEDS: u64 = 4;
+        (0..SEEDS)
+            .map(|seed| {
+                let mut sim = Simulation::<V>::with_seed(rtts.to_vec(), seed);
+                sim.run(2000);
+                sim.median_error()
+            })
+            .sum::<f64>()
+            / SEEDS as f64
+    }
+
+    #[test]
+    fn embeds_trees_more_accurately() {
+        let rtts = tree_rtts(4);
+
+        // Than a Euclidean embedding of the same dimensionality.
+        let h = mean_error::<Hyperbolic2>(&rtts);
+        let e2 = mean_error::<Dimension2>(&rtts);
+        assert!(h < e2, "hyperbolic {} vs Dimension2 {}", h, e2);
+    }
+}
@@ -0,0 +1,142 @@
+use super::*;
+use crate::Coordinate;
+use ::nalgebra::SVector;
+use rand::Rng;
+
+/// Embeds the model in the statically sized vectors of
+/// [`nalgebra`](https://docs.rs/nalgebra), so applications already using
+/// them need no conversions of their own (for up to 32 dimensions, for which
+/// the vectors implement `Default`):
+///
+/// ```
+/// use nalgebra::Vector3;
+/// use vivaldi::Model;
+///
+/// let model = Model::<Vector3<f64>>::new();
+/// let v: &Vector3<f64> = model.get_coordinate().vector();
+/// ```
+impl<const N: usize> Vector for SVector<f64, N>
+where
+    Self: Default,
+{
+    fn magnitude(&self) -> Magnitude {
+        Magnitude(self.norm())
+    }
+
+    fn components(&self) -> &[f64] {
+        self.as_slice()
+    }
+
+    fn from_components(components: &[f64]) -> Option<Self> {
+        match components.len() {
+            n if n == N => Some(Self::from_column_slice(components)),
+            _ => None,
+        }
+    }
+
+    fn distance(&self, other: &Self) -> Magnitude {
+        Magnitude(self.metric_distance(other))
+    }
+
+    fn random<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        Self::from_fn(|_, _| rng.gen::<f64>())
+    }
+}
+
+impl<const N: usize> From<DimensionN<N>> for SVector<f64, N> {
+    fn from(v: DimensionN<N>) -> Self {
+        Self::from(v.0)
+    }
+}
+
+impl<const N: usize> From<SVector<f64, N>> for DimensionN<N> {
+    fn from(v: SVector<f64, N>) -> Self {
+        Self(v.into())
+    }
+}
+
+/// Converts a coordinate to the equivalent `nalgebra` vector, such as one
+/// received from a peer using [`DimensionN`].
+impl<const N: usize> From<Coordinate<DimensionN<N>>> for Coordinate<SVector<f64, N>>
+where
+    SVector<f64, N>: Default,
+{
+    fn from(c: Coordinate<DimensionN<N>>) -> Self {
+        Coordinate::new(c.vector().0.into(), c.error(), c.height()).with_adjustment(c.adjustment())
+    }
+}
+
+impl<const N: usize> From<Coordinate<SVector<f64, N>>> for Coordinate<DimensionN<N>>
+where
+    SVector<f64, N>: Default,
+{
+    fn from(c: Coordinate<SVector<f64, N>>) -> Self {
+        Coordinate::new((*c.vector()).into(), c.error(), c.height()).with_adjustment(c.adjustment())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::nalgebra::{Vector2, Vector3};
+
+    #[test]
+    fn magnitude() {
+        // Not the inherent nalgebra method of the same name.
+        assert_eq!(
+            Vector::magnitude(&Vector3::<f64>::default()),
+            Magnitude(0.0)
+        );
+        assert_eq!(Vector::magnitude(&Vector2::new(3.0, 4.0)), Magnitude(5.0));
+    }
+
+    #[test]
+    fn distance() {
+        let a = Vector3::new(1.0, 2.0, 3.0);
+        let b = Vector3::new(0.5, -1.0, 0.0);
+
+        let want = Dimension3([1.0, 2.0, 3.0]).distance(&Dimension3([0.5, -1.0, 0.0]));
+        assert_eq!(a.distance(&b), want);
+    }
+
+    #[test]
+    fn components_round_trip() {
+        let a = Vector3::new(1.0, 2.0, 3.0);
+
+        assert_eq!(a.components(), &[1.0, 2.0, 3.0]);
+        assert_eq!(Vector3::from_components(a.components()), Some(a));
+        assert_eq!(Vector3::<f64>::from_components(&[1.0, 2.0]), None);
+    }
+
+    #[test]
+    fn coordinate_conversion() {
+        let c = Coordinate::new(DimensionN([1.0, 2.0, 3.0]), 0.5, 0.1).with_adjustment(0.01);
+
+        let v: Coordinate<Vector3<f64>> = c.into();
+        assert_eq!(v.vector(), &Vector3::new(1.0, 2.0, 3.0));
+        assert_eq!(v.magnitude(), c.magnitude());
+        assert_eq!((v.error(), v.height(), v.adjustment()), (0.5, 0.1, 0.01));
+
+        assert_eq!(Coordinate::<DimensionN<3>>::from(v), c);
+    }
+
+    #[test]
+    fn model() {
+        let mut a = crate::Model::<Vector3<f64>>::new();
+        let mut b = crate::Model::<DimensionN<3>>::new();
+
+        for _ in 0..100 {
+            a.observe(
+                &(*b.get_coordinate()).into(),
+                std::time::Duration::from_millis(10),
+            );
+            b.observe(
+                &(*a.get_coordinate()).into(),
+                std::time::Duration::from_millis(10),
+            );
+        }
+
+        let rtt = crate::estimate_rtt(a.get_coordinate(), &(*b.get_coordinate()).into());
+        assert!((rtt.as_secs_f64() - 0.01).abs() < 0.001, "{:?}", rtt);
+    }
+}
@@ -0,0 +1,115 @@
+use super::*;
+use crate::Coordinate;
+use ::postcard::experimental::max_size::MaxSize;
+
+impl MaxSize for Dimension1 {
+    const POSTCARD_MAX_SIZE: usize = f64::POSTCARD_MAX_SIZE;
+}
+
+impl MaxSize for Dimension2 {
+    const POSTCARD_MAX_SIZE: usize = 2 * f64::POSTCARD_MAX_SIZE;
+}
+
+impl MaxSize for Dimension3 {
+    const POSTCARD_MAX_SIZE: usize = 3 * f64::POSTCARD_MAX_SIZE;
+}
+
+impl<const N: usize, T> MaxSize for DimensionN<N, T>
+where
+    T: MaxSize,
+{
+    const POSTCARD_MAX_SIZE: usize = N * T::POSTCARD_MAX_SIZE;
+}
+
+impl MaxSize for Hyperbolic2 {
+    const POSTCARD_MAX_SIZE: usize = 2 * f64::POSTCARD_MAX_SIZE;
+}
+
+impl MaxSize for Spherical {
+    const POSTCARD_MAX_SIZE: usize = 3 * f64::POSTCARD_MAX_SIZE;
+}
+
+/// The largest [`postcard`](https://docs.rs/postcard) encoding of a
+/// coordinate, for budgeting packet space on constrained devices.
+///
+/// Postcard encodes each `f64` as 8 bytes and the fixed-size vectors without
+/// a length prefix, so the encoding of a coordinate is always exactly this
+/// size:
+///
+/// | Vector                                 | Bytes |
+/// |----------------------------------------|-------|
+/// | [`Dimension1`]                         | 32    |
+/// | [`Dimension2`] and [`Hyperbolic2`]     | 40    |
+/// | [`Dimension3`] and [`Spherical`]       | 48    |
+/// | [`DimensionN<N>`](DimensionN)          | 8 × (N + 3) |
+///
+/// Serialise into a buffer of this size without allocating:
+///
+/// ```
+/// use postcard::experimental::max_size::MaxSize;
+/// use vivaldi::{Coordinate, Model, vector::Dimension3};
+///
+/// let model = Model::<Dimension3>::new();
+///
+/// let mut buf = [0; Coordinate::<Dimension3>::POSTCARD_MAX_SIZE];
+/// let packet = postcard::to_slice(model.get_coordinate(), &mut buf)?;
+///
+/// let coord: Coordinate<Dimension3> = postcard::from_bytes(packet)?;
+/// # Ok::<(), postcard::Error>(())
+/// ```
+impl<V, T> MaxSize for Coordinate<V, T>
+where
+    V: Vector<T> + MaxSize,
+    T: Scalar + MaxSize,
+{
+    // The vector, error, height and adjustment.
+    const POSTCARD_MAX_SIZE: usize = V::POSTCARD_MAX_SIZE + 3 * T::POSTCARD_MAX_SIZE;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip<V>(vector: V)
+    where
+        V: Vector
+            + MaxSize
+            + PartialEq
+            + serde::Serialize
+            + serde::de::DeserializeOwned
+            + std::fmt::Debug,
+    {
+        let c = Coordinate::new(vector, 0.5, 0.1).with_adjustment(-0.01);
+        let mut buf = [0; 1024];
+        let encoded = ::postcard::to_slice(&c, &mut buf).unwrap();
+        assert_eq!(encoded.len(), Coordinate::<V>::POSTCARD_MAX_SIZE);
+
+        let decoded: Coordinate<V> = ::postcard::from_bytes(encoded).unwrap();
+        assert_eq!(decoded, c);
+        assert_eq!(decoded.magnitude(), c.magnitude());
+    }
+
+    #[test]
+    fn encoded_size() {
+        round_trip(Dimension1([1.0]));
+        round_trip(Dimension2([1.0, -2.0]));
+        round_trip(Dimension3([1.0, -2.0, 3.0]));
+        round_trip(DimensionN([1.0, -2.0, 3.0, -4.0, 5.0, -6.0, 7.0, -8.0]));
+        round_trip(Hyperbolic2([0.1, -0.2]));
+        round_trip(Spherical([0.1, -0.2, 0.3]));
+
+        assert_eq!(Coordinate::<Dimension1>::POSTCARD_MAX_SIZE, 32);
+        assert_eq!(Coordinate::<Dimension2>::POSTCARD_MAX_SIZE, 40);
+        assert_eq!(Coordinate::<Dimension3>::POSTCARD_MAX_SIZE, 48);
+        assert_eq!(Coordinate::<DimensionN<8>>::POSTCARD_MAX_SIZE, 88);
+    }
+
+    #[test]
+    fn truncated() {
+        let c = Coordinate::new(Dimension3([1.0, -2.0, 3.0]), 0.5, 0.1);
+        let mut buf = [0; Coordinate::<Dimension3>::POSTCARD_MAX_SIZE];
+        let encoded = ::postcard::to_slice(&c, &mut buf).unwrap();
+
+        assert!(::postcard::from_bytes::<Coordinate<Dimension3>>(&encoded[..40]).is_err());
+    }
+}
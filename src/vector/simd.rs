@@ -0,0 +1,210 @@
+/// Arithmetic over the components of a vector, vectorised with SIMD
+/// instructions for `f64` components when the `simd` feature is enabled.
+///
+/// This trait is sealed as a supertrait of [`Scalar`](super::Scalar), so is
+/// implemented only for the scalar types of this crate.
+pub trait Lanes: num_traits::Float {
+    /// Returns the sum of the squares of `v`.
+    fn sum_squares(v: &[Self]) -> Self {
+        v.iter().fold(Self::zero(), |acc, &x| acc + x * x)
+    }
+
+    /// Returns the sum of the squares of the differences between `a` and `b`.
+    fn sum_squared_differences(a: &[Self], b: &[Self]) -> Self {
+        a.iter()
+            .zip(b)
+            .fold(Self::zero(), |acc, (&a, &b)| acc + (a - b) * (a - b))
+    }
+
+    /// Adds each of `b` to `a`.
+    fn add_lanes(a: &mut [Self], b: &[Self]) {
+        a.iter_mut().zip(b).for_each(|(a, &b)| *a = *a + b);
+    }
+
+    /// Subtracts each of `b` from `a`.
+    fn sub_lanes(a: &mut [Self], b: &[Self]) {
+        a.iter_mut().zip(b).for_each(|(a, &b)| *a = *a - b);
+    }
+
+    /// Multiplies each of `a` by `s`.
+    fn mul_lanes(a: &mut [Self], s: Self) {
+        a.iter_mut().for_each(|a| *a = *a * s);
+    }
+
+    /// Divides each of `a` by `s`.
+    fn div_lanes(a: &mut [Self], s: Self) {
+        a.iter_mut().for_each(|a| *a = *a / s);
+    }
+}
+
+impl Lanes for f32 {}
+
+#[cfg(not(all(feature = "simd", target_arch = "x86_64")))]
+impl Lanes for f64 {}
+
+/// Processes pairs of components in 128-bit SSE2 registers, with any odd
+/// component processed as a scalar.
+///
+/// SSE2 is part of the x86_64 baseline, so every CPU of the target
+/// architecture supports the intrinsics called in the `unsafe` blocks below.
+/// Each method bounds the number of paired components by the length of every
+/// slice once, so the loads and stores within the loops are unchecked.
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+impl Lanes for f64 {
+    #[inline]
+    fn sum_squares(v: &[Self]) -> Self {
+        let pairs = v.len() - v.len() % 2;
+        let p = v.as_ptr();
+        // SAFETY: i + 1 < pairs <= v.len(), so both loaded values are in
+        // bounds, and the loads are unaligned.
+        let acc = unsafe {
+            (0..pairs).step_by(2).fold(_mm_setzero_pd(), |acc, i| {
+                let x = _mm_loadu_pd(p.add(i));
+                _mm_add_pd(acc, _mm_mul_pd(x, x))
+            })
+        };
+        sum(acc) + v[pairs..].iter().map(|x| x * x).sum::<f64>()
+    }
+
+    #[inline]
+    fn sum_squared_differences(a: &[Self], b: &[Self]) -> Self {
+        let n = a.len().min(b.len());
+        let pairs = n - n % 2;
+        let (pa, pb) = (a.as_ptr(), b.as_ptr());
+        // SAFETY: i + 1 < pairs <= n, which is no longer than either slice.
+        let acc = unsafe {
+            (0..pairs).step_by(2).fold(_mm_setzero_pd(), |acc, i| {
+                let d = _mm_sub_pd(_mm_loadu_pd(pa.add(i)), _mm_loadu_pd(pb.add(i)));
+                _mm_add_pd(acc, _mm_mul_pd(d, d))
+            })
+        };
+        let rem = a[pairs..n].iter().zip(&b[pairs..n]);
+        sum(acc) + rem.map(|(x, y)| (x - y) * (x - y)).sum::<f64>()
+    }
+
+    #[inline]
+    fn add_lanes(a: &mut [Self], b: &[Self]) {
+        let n = a.len().min(b.len());
+        let pairs = n - n % 2;
+        let (pa, pb) = (a.as_mut_ptr(), b.as_ptr());
+        for i in (0..pairs).step_by(2) {
+            // SAFETY: i + 1 < pairs <= n, which is no longer than either
+            // slice, and the slices cannot overlap as `a` is borrowed
+            // mutably.
+            unsafe {
+                _mm_storeu_pd(
+                    pa.add(i),
+                    _mm_add_pd(_mm_loadu_pd(pa.add(i)), _mm_loadu_pd(pb.add(i))),
+                )
+            };
+        }
+        a[pairs..n]
+            .iter_mut()
+            .zip(&b[pairs..n])
+            .for_each(|(x, y)| *x += y);
+    }
+
+    #[inline]
+    fn sub_lanes(a: &mut [Self], b: &[Self]) {
+        let n = a.len().min(b.len());
+        let pairs = n - n % 2;
+        let (pa, pb) = (a.as_mut_ptr(), b.as_ptr());
+        for i in (0..pairs).step_by(2) {
+            // SAFETY: i + 1 < pairs <= n, which is no longer than either
+            // slice, and the slices cannot overlap as `a` is borrowed
+            // mutably.
+            unsafe {
+                _mm_storeu_pd(
+                    pa.add(i),
+                    _mm_sub_pd(_mm_loadu_pd(pa.add(i)), _mm_loadu_pd(pb.add(i))),
+                )
+            };
+        }
+        a[pairs..n]
+            .iter_mut()
+            .zip(&b[pairs..n])
+            .for_each(|(x, y)| *x -= y);
+    }
+
+    #[inline]
+    fn mul_lanes(a: &mut [Self], s: Self) {
+        let pairs = a.len() - a.len() % 2;
+        let p = a.as_mut_ptr();
+        // SAFETY: setting both lanes of a register has no preconditions.
+        let s2 = unsafe { _mm_set1_pd(s) };
+        for i in (0..pairs).step_by(2) {
+            // SAFETY: i + 1 < pairs <= a.len(), so both values are in bounds.
+            unsafe { _mm_storeu_pd(p.add(i), _mm_mul_pd(_mm_loadu_pd(p.add(i)), s2)) };
+        }
+        a[pairs..].iter_mut().for_each(|x| *x *= s);
+    }
+
+    #[inline]
+    fn div_lanes(a: &mut [Self], s: Self) {
+        let pairs = a.len() - a.len() % 2;
+        let p = a.as_mut_ptr();
+        // SAFETY: setting both lanes of a register has no preconditions.
+        let s2 = unsafe { _mm_set1_pd(s) };
+        for i in (0..pairs).step_by(2) {
+            // SAFETY: i + 1 < pairs <= a.len(), so both values are in bounds.
+            unsafe { _mm_storeu_pd(p.add(i), _mm_div_pd(_mm_loadu_pd(p.add(i)), s2)) };
+        }
+        a[pairs..].iter_mut().for_each(|x| *x /= s);
+    }
+}
+
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+use std::arch::x86_64::*;
+
+/// Returns the sum of both lanes of `x`.
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+#[inline]
+fn sum(x: __m128d) -> f64 {
+    let mut lanes = [0.0; 2];
+    // SAFETY: `lanes` holds two values, and the store is unaligned.
+    unsafe { _mm_storeu_pd(lanes.as_mut_ptr(), x) };
+    lanes[0] + lanes[1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scalar_sum_squares(v: &[f64]) -> f64 {
+        v.iter().fold(0.0, |acc, &x| acc + x * x)
+    }
+
+    #[test]
+    fn matches_scalar() {
+        for len in 0..8 {
+            let a = (0..len).map(|i| i as f64 * 0.5 - 1.0).collect::<Vec<_>>();
+            let b = (0..len).map(|i| 2.0 - i as f64 * 0.25).collect::<Vec<_>>();
+
+            assert_eq!(f64::sum_squares(&a), scalar_sum_squares(&a));
+            let diff = a.iter().zip(&b).map(|(a, b)| a - b).collect::<Vec<_>>();
+            assert_eq!(
+                f64::sum_squared_differences(&a, &b),
+                scalar_sum_squares(&diff)
+            );
+
+            let mut v = a.clone();
+            f64::add_lanes(&mut v, &b);
+            assert!(v
+                .iter()
+                .zip(a.iter().zip(&b))
+                .all(|(v, (a, b))| *v == a + b));
+
+            let mut v = a.clone();
+            f64::sub_lanes(&mut v, &b);
+            assert_eq!(v, diff);
+
+            let mut v = a.clone();
+            f64::mul_lanes(&mut v, 3.0);
+            assert!(v.iter().zip(&a).all(|(v, a)| *v == a * 3.0));
+
+            let mut v = a.clone();
+            f64::div_lanes(&mut v, 3.0);
+            assert!(v.iter().zip(&a).all(|(v, a)| *v == a / 3.0));
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use super::*;
+use rand::Rng;
+use std::ops::Div;
+
+/// A point on the surface of a sphere.
+///
+/// Nodes spread across the globe are separated by the great-circle distance
+/// between them, which a flat embedding can only approximate - paths around
+/// the far side of a sphere have no Euclidean equivalent. A spherical
+/// embedding often converges better for globe-spanning deployments.
+///
+/// The point is stored as a 3 dimensional vector from the centre of a sphere
+/// of radius [`RADIUS`](Spherical::RADIUS), with the distance between two
+/// points being the length of the great-circle arc between them. The
+/// [`Model`](crate::Model) moves coordinates along the surface, projecting
+/// each update back onto the sphere:
+///
+/// ```
+/// use vivaldi::{Model, vector::Spherical};
+///
+/// let model = Model::<Spherical>::new();
+/// ```
+///
+/// A model starts at the centre of the sphere (zero distance from every
+/// point) until its first observation places it on the surface.
+#[derive(PartialEq, Debug, Copy, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "rkyv",
+    derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
+pub struct Spherical(pub [f64; 3]);
+
+impl Spherical {
+    /// The radius of the sphere, in seconds, placing antipodal points 200ms
+    /// apart - about the RTT between opposite sides of the Earth.
+    pub const RADIUS: f64 = 0.2 / std::f64::consts::PI;
+
+    fn dot(&self, other: &Self) -> f64 {
+        self.0.iter().zip(other.0.iter()).map(|(a, b)| a * b).sum()
+    }
+
+    fn cross(&self, other: &Self) -> Self {
+        let (a, b) = (self.0, other.0);
+        Spherical([
+            a[1] * b[2] - a[2] * b[1],
+            a[2] * b[0] - a[0] * b[2],
+            a[0] * b[1] - a[1] * b[0],
+        ])
+    }
+}
+
+impl Vector for Spherical {
+    fn magnitude(&self) -> Magnitude {
+        let m = self.0.iter().fold(0.0, |acc, v| acc + (v * v)).sqrt();
+
+        Magnitude(m)
+    }
+
+    fn components(&self) -> &[f64] {
+        &self.0
+    }
+
+    fn from_components(components: &[f64]) -> Option<Self> {
+        match components.len() {
+            3 => Some(Spherical([components[0], components[1], components[2]])),
+            _ => None,
+        }
+    }
+
+    fn distance(&self, other: &Self) -> Magnitude {
+        // The angle between the points, accurate for both nearby and
+        // antipodal points.
+        let angle = self.cross(other).magnitude().0.atan2(self.dot(other));
+
+        Magnitude(Self::RADIUS * angle)
+    }
+
+    fn displacement(&self, other: &Self) -> Self {
+        let (a, b) = (self.magnitude().0, other.magnitude().0);
+        if a == 0.0 || b == 0.0 {
+            return Spherical::default();
+        }
+
+        // The direction along the surface at `self` leading away from
+        // `other`, undefined for coincident and antipodal points.
+        let (a, b) = (*self / a, *other / b);
+        let tangent = a * a.dot(&b) - b;
+        let len = tangent.magnitude().0;
+        if len == 0.0 {
+            return Spherical::default();
+        }
+        tangent * (self.distance(other).0 / len)
+    }
+
+    fn project(self) -> Self {
+        let m = self.magnitude().0;
+        if m == 0.0 {
+            return self;
+        }
+        self * (Self::RADIUS / m)
+    }
+
+    fn random<R: Rng + ?Sized>(rng: &mut R) -> Self {
+        Spherical([rng.gen::<f64>(), rng.gen::<f64>(), rng.gen::<f64>()])
+    }
+}
+
+impl Add for Spherical {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self::Output {
+        Self([
+            self.0[0] + other.0[0],
+            self.0[1] + other.0[1],
+            self.0[2] + other.0[2],
+        ])
+    }
+}
+
+impl Add<f64> for Spherical {
+    type Output = Self;
+
+    fn add(self, other: f64) -> Self::Output {
+        Self([self.0[0] + other, self.0[1] + other, self.0[2] + other])
+    }
+}
+
+impl Sub for Spherical {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self::Output {
+        Self([
+            self.0[0] - other.0[0],
+            self.0[1] - other.0[1],
+            self.0[2] - other.0[2],
+        ])
+    }
+}
+
+/// Subtract a borrowed vector, avoiding a copy of the right hand side.
+impl Sub<&Spherical> for Spherical {
+    type Output = Self;
+
+    fn sub(self, other: &Self) -> Self::Output {
+        Self([
+            self.0[0] - other.0[0],
+            self.0[1] - other.0[1],
+            self.0[2] - other.0[2],
+        ])
+    }
+}
+
+/// Divide a vector by a constant amount.
+impl Div<f64> for Spherical {
+    type Output = Self;
+
+    fn div(self, other: f64) -> Self::Output {
+        Self([self.0[0] / other, self.0[1] / other, self.0[2] / other])
+    }
+}
+
+impl Mul<f64> for Spherical {
+    type Output = Self;
+
+    fn mul(self, other: f64) -> Self::Output {
+        Self([self.0[0] * other, self.0[1] * other, self.0[2] * other])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::Simulation;
+    use crate::vector::{Dimension2, Dimension3};
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::f64::consts::PI;
+    use std::time::Duration;
+
+    const R: f64 = Spherical::RADIUS;
+
+    #[test]
+    fn distance() {
+        let a = Spherical([R, 0.0, 0.0]);
+        let b = Spherical([0.0, R, 0.0]);
+
+        assert!((a.distance(&b).0 - R * PI / 2.0).abs() < 1e-12);
+        assert!((b.distance(&a).0 - R * PI / 2.0).abs() < 1e-12);
+        assert_eq!(a.distance(&a), Magnitude(0.0));
+
+        // Antipodal points are half the circumference apart.
+        let c = Spherical([-R, 0.0, 0.0]);
+        assert!((a.distance(&c).0 - 0.2).abs() < 1e-12);
+
+        // Only the direction of a vector places it on the sphere.
+        assert_eq!(a.distance(&b), (a * 2.0).distance(&b));
+    }
+
+    #[test]
+    fn displacement() {
+        let a = Spherical([R, 0.0, 0.0]);
+        let b = Spherical([0.0, R, 0.0]);
+
+        // Tangent to the sphere at `a`, leading away from `b`.
+        let d = a.displacement(&b);
+        assert!((d.magnitude().0 - a.distance(&b).0).abs() < 1e-12);
+        assert!(d.0[0].abs() < 1e-12);
+        assert!(d.0[1] < 0.0);
+
+        assert_eq!(a.displacement(&a), Spherical::default());
+        assert_eq!(a.displacement(&(a * -1.0)), Spherical::default());
+        assert_eq!(a.displacement(&Spherical::default()), Spherical::default());
+    }
+
+    #[test]
+    fn project() {
+        let v = Spherical([1.0, 2.0, 2.0]).project();
+        assert!((v.magnitude().0 - R).abs() < 1e-12);
+        assert!((v.0[1] / v.0[0] - 2.0).abs() < 1e-12);
+
+        assert_eq!(Spherical::default().project(), Spherical::default());
+    }
+
+    #[test]
+    fn components_round_trip() {
+        let a = Spherical([1.0, 2.0, 3.0]);
+
+        assert_eq!(Spherical::from_components(a.components()), Some(a));
+        assert_eq!(Spherical::from_components(&[1.0, 2.0]), None);
+    }
+
+    #[test]
+    fn embeds_globe_more_accurately() {
+        // Nodes spread uniformly over the globe, separated by the
+        // great-circle distance between them.
+        let mut rng = StdRng::seed_from_u64(42);
+        let nodes = (0..32)
+            .map(|_| {
+                let z = rng.gen_range(-1.0..1.0_f64);
+                let theta = rng.gen_range(0.0..2.0 * PI);
+                let r = (1.0 - z * z).sqrt();
+                Spherical([r * theta.cos(), r * theta.sin(), z]).project()
+            })
+            .collect::<Vec<_>>();
+        let rtts = nodes
+            .iter()
+            .map(|a| {
+                nodes
+                    .iter()
+                    .map(|b| Duration::from_secs_f64(a.distance(b).0))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+
+        let mut spherical = Simulation::<Spherical>::with_seed(rtts.clone(), 42);
+        spherical.run(2000);
+        let mut d2 = Simulation::<Dimension2>::with_seed(rtts.clone(), 42);
+        d2.run(2000);
+        let mut d3 = Simulation::<Dimension3>::with_seed(rtts, 42);
+        d3.run(2000);
+
+        let s = spherical.median_error();
+        let (e2, e3) = (d2.median_error(), d3.median_error());
+        assert!(s < e2, "spherical {} vs Dimension2 {}", s, e2);
+        assert!(s < e3, "spherical {} vs Dimension3 {}", s, e3);
+    }
+}
@@ -0,0 +1,247 @@
+use crate::clock::Clock;
+use crate::error::Error;
+use crate::force::ForceFunction;
+use crate::model::Model;
+use crate::peer_table::PeerTable;
+use crate::vector::Vector;
+use rand::Rng;
+use std::hash::Hash;
+use std::io::BufRead;
+use std::str::FromStr;
+use std::time::Duration;
+
+/// A single historical RTT measurement to a peer, as exported by a monitoring
+/// system.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoricalRecord<K> {
+    /// The measured peer.
+    pub peer: K,
+
+    /// The measured round-trip time.
+    pub rtt: Duration,
+
+    /// The time of the measurement, as the duration since the UNIX epoch.
+    pub timestamp: Duration,
+}
+
+/// The result of [`warm_start`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WarmStart {
+    /// The number of records applied to the model.
+    pub applied: usize,
+
+    /// The number of records skipped, either because the coordinate of the
+    /// peer is unknown or the model rejected the measurement.
+    pub skipped: usize,
+}
+
+/// Parses historical RTT records from CSV.
+///
+/// Each line holds `peer,rtt,timestamp`, where `rtt` is in milliseconds and
+/// `timestamp` is in seconds since the UNIX epoch - both may be fractional.
+/// Blank lines are ignored, as is a header line if present.
+///
+/// ```
+/// use vivaldi::parse_history;
+///
+/// let csv = "peer,rtt_ms,timestamp\nnode-a,12.5,1700000000\nnode-b,40,1700000001.5\n";
+/// let records = parse_history::<String, _>(csv.as_bytes())?;
+/// assert_eq!(records.len(), 2);
+/// # Ok::<(), vivaldi::Error>(())
+/// ```
+pub fn parse_history<K, R>(reader: R) -> Result<Vec<HistoricalRecord<K>>, Error>
+where
+    K: FromStr,
+    R: BufRead,
+{
+    let mut records = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line.map_err(|e| Error::Storage(e.to_string()))?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let invalid = |reason: &str| {
+            Error::Serialization(format!("invalid record on line {}: {}", i + 1, reason))
+        };
+
+        let fields = line.split(',').map(str::trim).collect::<Vec<_>>();
+        if fields.len() != 3 {
+            return Err(invalid("expected 3 fields"));
+        }
+
+        let rtt = match fields[1].parse::<f64>() {
+            Ok(v) => v,
+            // A header names the columns rather than holding values.
+            Err(_) if i == 0 => continue,
+            Err(_) => return Err(invalid("rtt is not a number")),
+        };
+        let timestamp = fields[2]
+            .parse::<f64>()
+            .map_err(|_| invalid("timestamp is not a number"))?;
+
+        let rtt = Duration::try_from_secs_f64(rtt / 1000.0)
+            .map_err(|_| invalid("rtt is out of range"))?;
+        let timestamp = Duration::try_from_secs_f64(timestamp)
+            .map_err(|_| invalid("timestamp is out of range"))?;
+
+        let peer = fields[0]
+            .parse::<K>()
+            .map_err(|_| invalid("peer is invalid"))?;
+
+        records.push(HistoricalRecord {
+            peer,
+            rtt,
+            timestamp,
+        });
+    }
+
+    Ok(records)
+}
+
+/// Replays historical RTT `records` through `model`, producing a warm
+/// coordinate before the node takes real traffic.
+///
+/// The model is typically freshly built with the configuration the node will
+/// run with, but may be an existing model restored from a checkpoint.
+///
+/// Records are replayed in timestamp order as fast as they can be applied,
+/// rather than at the rate they were measured. The coordinate of each peer is
+/// read from `peers`, typically loaded from a checkpoint or fetched from the
+/// peers themselves - records of peers without a known coordinate are
+/// skipped.
+///
+/// ```
+/// use std::time::Duration;
+/// use vivaldi::{parse_history, warm_start, Model, ModelBuilder, PeerTable, vector::Dimension3};
+///
+/// # let csv = "node-a,12.5,1700000000\n";
+/// let mut peers = PeerTable::<String, Dimension3>::new();
+/// peers.insert("node-a".to_string(), Model::new().get_coordinate());
+///
+/// let mut model = ModelBuilder::new().build::<Dimension3>();
+///
+/// let records = parse_history(csv.as_bytes())?;
+/// let warm = warm_start(&mut model, records, &peers);
+/// assert_eq!(warm.applied, 1);
+/// # Ok::<(), vivaldi::Error>(())
+/// ```
+pub fn warm_start<K, V, C, MC, F, R>(
+    model: &mut Model<V, MC, F, f64, R>,
+    mut records: Vec<HistoricalRecord<K>>,
+    peers: &PeerTable<K, V, C>,
+) -> WarmStart
+where
+    K: Hash + Eq + Clone,
+    V: Vector + std::fmt::Debug,
+    C: Clock,
+    MC: Clock,
+    F: ForceFunction,
+    R: Rng,
+{
+    records.sort_by_key(|r| r.timestamp);
+
+    let mut applied = 0;
+    for record in &records {
+        let applies = peers
+            .get(&record.peer)
+            .is_some_and(|coord| model.try_observe(&coord, record.rtt).is_ok());
+        if applies {
+            applied += 1;
+        }
+    }
+
+    WarmStart {
+        applied,
+        skipped: records.len() - applied,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coordinate::Coordinate;
+    use crate::model::estimate_rtt;
+    use crate::vector::Dimension2;
+
+    #[test]
+    fn parse() {
+        let csv = "peer, rtt, timestamp\n\n1, 10.5, 100\n2,0.25,100.5\n";
+        let records = parse_history::<u32, _>(csv.as_bytes()).unwrap();
+        assert_eq!(
+            records,
+            vec![
+                HistoricalRecord {
+                    peer: 1,
+                    rtt: Duration::from_micros(10_500),
+                    timestamp: Duration::from_secs(100),
+                },
+                HistoricalRecord {
+                    peer: 2,
+                    rtt: Duration::from_micros(250),
+                    timestamp: Duration::from_millis(100_500),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_invalid() {
+        for csv in &[
+            "1,10\n",
+            "1,10,100\n2,ten,100\n",
+            "1,10,yesterday\n",
+            "1,-10,100\n",
+            "x,10,100\n",
+            "1,NaN,100\n",
+            "1,1e300,5\n",
+            "1,10,1e300\n",
+        ] {
+            match parse_history::<u32, _>(csv.as_bytes()) {
+                Err(Error::Serialization(_)) => {}
+                v => panic!("{:?} parsed as {:?}", csv, v),
+            }
+        }
+    }
+
+    #[test]
+    fn warm_start_converges() {
+        let positions = [[0.0, 0.0], [0.05, 0.0], [0.0, 0.05], [0.05, 0.05]];
+        let truth = Coordinate::new(Dimension2([0.02, 0.03]), 0.0, 0.0);
+
+        let mut peers = PeerTable::new();
+        for (i, p) in positions.iter().enumerate() {
+            peers.insert(i, &Coordinate::new(Dimension2(*p), 0.05, 0.0));
+        }
+
+        // Records arrive out of order, and include an unknown peer.
+        let mut records = (0..400)
+            .map(|n| HistoricalRecord {
+                peer: n % 5,
+                rtt: peers
+                    .get(&(n % 5))
+                    .map_or(Duration::from_millis(10), |c| estimate_rtt(&truth, &c)),
+                timestamp: Duration::from_secs(400 - n as u64),
+            })
+            .collect::<Vec<_>>();
+        records.push(HistoricalRecord {
+            peer: 0,
+            rtt: Duration::from_secs(0),
+            timestamp: Duration::from_secs(0),
+        });
+
+        let mut model = Model::new();
+        let warm = warm_start(&mut model, records, &peers);
+        assert_eq!(warm.applied, 320);
+        assert_eq!(warm.skipped, 81);
+
+        for i in 0..4 {
+            let peer = peers.get(&i).unwrap();
+            let got = estimate_rtt(model.get_coordinate(), &peer).as_secs_f64();
+            let want = estimate_rtt(&truth, &peer).as_secs_f64();
+            assert!((got - want).abs() < 0.005, "{} vs {}", got, want);
+        }
+        assert!(model.get_coordinate().error() < 0.5);
+    }
+}
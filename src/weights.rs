@@ -0,0 +1,267 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::Duration;
+
+/// How estimated RTTs are converted into routing weights by
+/// [`WeightExporter`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum WeightCurve {
+    /// Weight proportional to `1 / rtt`.
+    Inverse,
+
+    /// Weight proportional to `1 / rtt²`, favouring nearby backends more
+    /// strongly.
+    InverseSquare,
+
+    /// Weight proportional to `exp(-rtt / scale)` - each additional `scale`
+    /// of RTT reduces the weight of a backend by a factor of `e`.
+    Exponential {
+        /// The RTT scale of the decay.
+        scale: Duration,
+    },
+}
+
+impl WeightCurve {
+    fn weight(&self, rtt: Duration) -> f64 {
+        // Floor the RTT to avoid infinite weights for co-located backends.
+        let rtt = rtt.as_secs_f64().max(1e-6);
+        match self {
+            WeightCurve::Inverse => 1.0 / rtt,
+            WeightCurve::InverseSquare => 1.0 / (rtt * rtt),
+            WeightCurve::Exponential { scale } => {
+                (-rtt / scale.as_secs_f64().max(f64::MIN_POSITIVE)).exp()
+            }
+        }
+    }
+}
+
+/// The share of traffic routed to a single backend.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BackendWeight<K> {
+    /// The backend identifier.
+    pub backend: K,
+
+    /// The fraction (0 to 1) of traffic routed to the backend. The shares of
+    /// all backends sum to 1.
+    pub share: f64,
+}
+
+/// A set of routing weights produced by [`WeightExporter`], serialisable with
+/// the `serde` feature for consumption by proxies.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RoutingWeights<K> {
+    /// The weight of each backend.
+    pub backends: Vec<BackendWeight<K>>,
+}
+
+/// Converts estimated RTTs to a set of backends into normalised routing
+/// weights for a load balancer.
+///
+/// ```
+/// use std::time::Duration;
+/// use vivaldi::{Model, PeerTable, WeightCurve, WeightExporter, vector::Dimension3};
+///
+/// let local = Model::<Dimension3>::new();
+/// # let backend = Model::<Dimension3>::new();
+/// let mut backends = PeerTable::new();
+/// backends.insert("backend-1", backend.get_coordinate());
+///
+/// let mut exporter = WeightExporter::new(WeightCurve::Inverse);
+/// exporter.set_min_share(0.05);
+/// exporter.set_hysteresis(0.02);
+///
+/// let weights = exporter.update(
+///     backends
+///         .estimate_all(local.get_coordinate())
+///         .map(|(id, rtt)| (*id, rtt)),
+/// );
+/// for b in &weights.backends {
+///     println!("{}: {:.1}%", b.backend, b.share * 100.0);
+/// }
+/// ```
+///
+/// Every backend receives at least the [minimum share](WeightExporter::set_min_share)
+/// of traffic (keeping distant backends warm), with the remainder divided
+/// according to the [`WeightCurve`]. To prevent small coordinate movements
+/// from constantly reshuffling traffic, the previous weights are kept until
+/// the share of some backend moves by more than the
+/// [hysteresis](WeightExporter::set_hysteresis), or the set of backends
+/// changes.
+#[derive(Debug, Clone)]
+pub struct WeightExporter<K> {
+    curve: WeightCurve,
+    min_share: f64,
+    hysteresis: f64,
+    current: RoutingWeights<K>,
+}
+
+impl<K> WeightExporter<K>
+where
+    K: Hash + Eq + Clone,
+{
+    /// Initialises an exporter converting RTTs to weights with `curve`, with
+    /// no minimum share or hysteresis.
+    pub fn new(curve: WeightCurve) -> Self {
+        WeightExporter {
+            curve,
+            min_share: 0.0,
+            hysteresis: 0.0,
+            current: RoutingWeights {
+                backends: Vec::new(),
+            },
+        }
+    }
+
+    /// Sets the minimum fraction of traffic (0 to 1) routed to each backend.
+    ///
+    /// If the minimum shares of all backends sum to more than 1, traffic is
+    /// divided evenly.
+    pub fn set_min_share(&mut self, min_share: f64) {
+        self.min_share = min_share.max(0.0);
+    }
+
+    /// Sets the change in the share of any one backend (0 to 1) required
+    /// before new weights are published.
+    pub fn set_hysteresis(&mut self, hysteresis: f64) {
+        self.hysteresis = hysteresis.max(0.0);
+    }
+
+    /// Returns the current weights.
+    pub fn weights(&self) -> &RoutingWeights<K> {
+        &self.current
+    }
+
+    /// Computes weights from the estimated RTT to each backend, returning the
+    /// weights to publish.
+    pub fn update<I>(&mut self, rtts: I) -> &RoutingWeights<K>
+    where
+        I: IntoIterator<Item = (K, Duration)>,
+    {
+        let raw = rtts
+            .into_iter()
+            .map(|(k, rtt)| (k, self.curve.weight(rtt)))
+            .collect::<Vec<_>>();
+
+        let n = raw.len() as f64;
+        let total = raw.iter().map(|(_, w)| w).sum::<f64>();
+        let spare = 1.0 - self.min_share * n;
+        let even = spare <= 0.0 || total <= 0.0 || !total.is_finite();
+        let backends = raw
+            .into_iter()
+            .map(|(backend, w)| {
+                let share = if even {
+                    1.0 / n
+                } else {
+                    self.min_share + spare * w / total
+                };
+                BackendWeight { backend, share }
+            })
+            .collect::<Vec<_>>();
+
+        let previous = self
+            .current
+            .backends
+            .iter()
+            .map(|b| (&b.backend, b.share))
+            .collect::<HashMap<_, _>>();
+        let changed = backends.len() != previous.len()
+            || backends.iter().any(|b| match previous.get(&b.backend) {
+                Some(old) => (b.share - old).abs() > self.hysteresis,
+                None => true,
+            });
+
+        if changed {
+            self.current = RoutingWeights { backends };
+        }
+        &self.current
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ms(v: u64) -> Duration {
+        Duration::from_millis(v)
+    }
+
+    fn shares(w: &RoutingWeights<&str>) -> Vec<f64> {
+        w.backends.iter().map(|b| b.share).collect()
+    }
+
+    fn assert_shares(got: Vec<f64>, want: &[f64]) {
+        assert_eq!(got.len(), want.len());
+        for (g, w) in got.iter().zip(want) {
+            assert!((g - w).abs() < 1e-9, "{:?} vs {:?}", got, want);
+        }
+    }
+
+    #[test]
+    fn curves() {
+        let rtts = vec![("a", ms(10)), ("b", ms(30))];
+
+        let mut e = WeightExporter::new(WeightCurve::Inverse);
+        assert_shares(shares(e.update(rtts.clone())), &[0.75, 0.25]);
+
+        let mut e = WeightExporter::new(WeightCurve::InverseSquare);
+        assert_shares(shares(e.update(rtts.clone())), &[0.9, 0.1]);
+
+        let mut e = WeightExporter::new(WeightCurve::Exponential { scale: ms(20) });
+        let ratio = (-0.5_f64).exp() / (-1.5_f64).exp();
+        assert_shares(
+            shares(e.update(rtts)),
+            &[ratio / (ratio + 1.0), 1.0 / (ratio + 1.0)],
+        );
+    }
+
+    #[test]
+    fn min_share() {
+        let mut e = WeightExporter::new(WeightCurve::InverseSquare);
+        e.set_min_share(0.2);
+        assert_shares(
+            shares(e.update(vec![("a", ms(10)), ("b", ms(30))])),
+            &[0.2 + 0.6 * 0.9, 0.2 + 0.6 * 0.1],
+        );
+
+        e.set_min_share(0.5);
+        assert_shares(
+            shares(e.update(vec![("a", ms(10)), ("b", ms(30)), ("c", ms(1))])),
+            &[1.0 / 3.0; 3],
+        );
+    }
+
+    #[test]
+    fn hysteresis() {
+        let mut e = WeightExporter::new(WeightCurve::Inverse);
+        e.set_hysteresis(0.05);
+        e.update(vec![("a", ms(10)), ("b", ms(10))]);
+        assert_shares(shares(e.weights()), &[0.5, 0.5]);
+
+        // A small change is held.
+        e.update(vec![("a", ms(10)), ("b", ms(11))]);
+        assert_shares(shares(e.weights()), &[0.5, 0.5]);
+
+        // A large change is published.
+        e.update(vec![("a", ms(10)), ("b", ms(30))]);
+        assert_shares(shares(e.weights()), &[0.75, 0.25]);
+
+        // As is a change in the set of backends.
+        e.update(vec![("a", ms(10)), ("c", ms(30))]);
+        assert_eq!(e.weights().backends[1].backend, "c");
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn serde() {
+        let mut e = WeightExporter::new(WeightCurve::Inverse);
+        let w = e.update(vec![("a".to_string(), ms(10))]).clone();
+        let json = serde_json::to_string(&w).unwrap();
+        assert_eq!(json, r#"{"backends":[{"backend":"a","share":1.0}]}"#);
+        assert_eq!(
+            serde_json::from_str::<RoutingWeights<String>>(&json).unwrap(),
+            w
+        );
+    }
+}
@@ -0,0 +1,103 @@
+//! Asserts the core model paths perform no heap allocations.
+//!
+//! This lives in its own test binary as it installs a counting global
+//! allocator.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::time::Duration;
+use vivaldi::{estimate_rtt, vector::Dimension3, Coordinate, Error, Model};
+
+struct CountingAllocator;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let _ = ALLOCATIONS.try_with(|c| c.set(c.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+/// Returns the number of allocations made by the current thread while
+/// executing `f`.
+fn count_allocations<F: FnOnce()>(f: F) -> usize {
+    let before = ALLOCATIONS.with(|c| c.get());
+    f();
+    ALLOCATIONS.with(|c| c.get()) - before
+}
+
+#[test]
+fn observe_and_estimate_do_not_allocate() {
+    let mut a = Model::<Dimension3>::new();
+    let mut b = Model::<Dimension3>::new();
+    let rtt = Duration::from_millis(10);
+
+    // The thread-local RNG lazily allocates its state the first time it is
+    // used on a thread - observing between two nodes at the origin forces
+    // a random unit vector, warming it up.
+    a.observe(b.get_coordinate(), rtt);
+
+    // Includes observations between colocated nodes, which generate a random
+    // unit vector.
+    let mut c = Model::<Dimension3>::new();
+    let mut d = Model::<Dimension3>::new();
+    let n = count_allocations(|| {
+        c.observe(d.get_coordinate(), rtt);
+        d.observe(c.get_coordinate(), rtt);
+        for _ in 0..100 {
+            a.observe(b.get_coordinate(), rtt);
+            b.observe(a.get_coordinate(), rtt);
+        }
+    });
+    assert_eq!(n, 0, "observe allocated");
+
+    let n = count_allocations(|| {
+        for _ in 0..100 {
+            let _ = estimate_rtt(a.get_coordinate(), b.get_coordinate());
+        }
+    });
+    assert_eq!(n, 0, "estimate_rtt allocated");
+}
+
+#[test]
+fn parse_untrusted_does_not_allocate() {
+    let valid = Model::<Dimension3>::new().get_coordinate().to_bytes();
+    let mut non_finite = valid.clone();
+    non_finite[2..10].copy_from_slice(&f64::NAN.to_le_bytes());
+
+    let n = count_allocations(|| {
+        assert!(Coordinate::<Dimension3>::parse_untrusted(&valid).is_ok());
+        assert_eq!(
+            Coordinate::<Dimension3>::parse_untrusted(&non_finite),
+            Err(Error::NonFiniteCoordinate)
+        );
+    });
+    assert_eq!(n, 0, "parse_untrusted allocated");
+}
+
+#[cfg(feature = "heapless")]
+#[test]
+fn fixed_filters_do_not_allocate() {
+    use vivaldi::filters::{FixedMovingMedian, FixedPercentileWindow, LatencyFilter};
+
+    let mut median = FixedMovingMedian::<8>::new();
+    let mut percentile = FixedPercentileWindow::<8>::new(0.1);
+    let n = count_allocations(|| {
+        for i in 0..100 {
+            let rtt = Duration::from_millis(10 + i % 7);
+            median.push(rtt);
+            percentile.push(rtt);
+        }
+    });
+    assert_eq!(n, 0, "fixed filters allocated");
+}